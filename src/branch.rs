@@ -1,4 +1,11 @@
+use crate::errno::{EINVAL, EROFS};
+use crate::policy::utils::DiskSpace;
+use parking_lot::{Condvar, Mutex, RwLock};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BranchMode {
@@ -7,19 +14,115 @@ pub enum BranchMode {
     NoCreate,  // Branch can be read and modified but not used for new file creation
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct Branch {
     pub path: PathBuf,
     pub mode: BranchMode,
+    // Multiplier applied to free space by the pfrd policy (`/disk=RW,weight=3`)
+    pub weight: u64,
+    // Last sample taken by a DiskSpaceRefresher, if one is running
+    cached_space: RwLock<Option<DiskSpace>>,
+    // Bytes written to the branch through the pool since mount or the last reset
+    bytes_written: AtomicU64,
+    // When a write to this writable branch last failed with EROFS, or a
+    // re-probe found it still read-only; creates skip it while set
+    remounted_readonly: Mutex<Option<Instant>>,
+    // Set while the branch directory itself is gone, so the loss is logged once
+    missing: AtomicBool,
+}
+
+/// How long a branch found remounted read-only is skipped before checking it again
+pub const READONLY_REPROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+impl PartialEq for Branch {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path && self.mode == other.mode && self.weight == other.weight
+    }
 }
 
 impl Branch {
     pub fn new(path: PathBuf, mode: BranchMode) -> Self {
-        Self { path, mode }
+        Self {
+            path,
+            mode,
+            weight: 1,
+            cached_space: RwLock::new(None),
+            bytes_written: AtomicU64::new(0),
+            remounted_readonly: Mutex::new(None),
+            missing: AtomicBool::new(false),
+        }
+    }
+    
+    pub fn with_weight(mut self, weight: u64) -> Self {
+        self.weight = weight;
+        self
     }
 
+    /// Whether new files may go to this branch
+    ///
+    /// False for RO and NC branches, for RW branches the kernel has
+    /// remounted read-only until a re-probe finds them writable again, and
+    /// for branches whose directory has disappeared.
     pub fn allows_create(&self) -> bool {
-        matches!(self.mode, BranchMode::ReadWrite)
+        matches!(self.mode, BranchMode::ReadWrite) && self.is_online() && !self.is_remounted_readonly()
+    }
+    
+    /// Whether the branch directory is still there
+    ///
+    /// A branch removed at runtime is treated as offline until it comes
+    /// back: nothing is created on it and the other branches keep serving
+    /// the pool. Each change is logged once rather than on every operation.
+    pub fn is_online(&self) -> bool {
+        let online = self.path.is_dir();
+        if self.missing.swap(!online, Ordering::Relaxed) == online {
+            if online {
+                tracing::info!("Branch {} is back online", self.path.display());
+            } else {
+                tracing::warn!("Branch {} has disappeared, serving from the remaining branches", self.path.display());
+            }
+        }
+        online
+    }
+    
+    /// Note an I/O error from this branch, returning whether it revealed a read-only remount
+    ///
+    /// EROFS from a branch not configured RO means its filesystem went
+    /// read-only underneath us, typically after errors.
+    pub fn record_io_error(&self, error: &std::io::Error) -> bool {
+        if error.raw_os_error() != Some(EROFS) || self.is_readonly() {
+            return false;
+        }
+        if self.remounted_readonly.lock().replace(Instant::now()).is_none() {
+            tracing::warn!("Branch {} returned EROFS, treating it as read-only for creates", self.path.display());
+        }
+        true
+    }
+    
+    /// Whether the branch is currently treated as remounted read-only
+    ///
+    /// Once READONLY_REPROBE_INTERVAL has passed since it was last found
+    /// read-only the filesystem is checked again.
+    pub fn is_remounted_readonly(&self) -> bool {
+        match *self.remounted_readonly.lock() {
+            None => return false,
+            Some(since) if since.elapsed() < READONLY_REPROBE_INTERVAL => return true,
+            Some(_) => {}
+        }
+        self.probe_readonly()
+    }
+    
+    /// Check the branch's mount flags now, clearing the read-only mark if it is writable again
+    pub fn probe_readonly(&self) -> bool {
+        use nix::sys::statvfs::{statvfs, FsFlags};
+        
+        let readonly = statvfs(&self.path).map_or(true, |stat| stat.flags().contains(FsFlags::ST_RDONLY));
+        let mut marked = self.remounted_readonly.lock();
+        if readonly {
+            *marked = Some(Instant::now());
+        } else if marked.take().is_some() {
+            tracing::info!("Branch {} is writable again", self.path.display());
+        }
+        readonly
     }
     
     pub fn is_readonly(&self) -> bool {
@@ -34,6 +137,20 @@ impl Branch {
         matches!(self.mode, BranchMode::ReadOnly | BranchMode::NoCreate)
     }
 
+    /// Count `bytes` written to this branch through the pool
+    pub fn record_write(&self, bytes: u64) {
+        self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+    
+    /// Bytes written to this branch through the pool since mount or the last reset
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written.load(Ordering::Relaxed)
+    }
+    
+    pub fn reset_bytes_written(&self) {
+        self.bytes_written.store(0, Ordering::Relaxed);
+    }
+
     pub fn full_path(&self, relative_path: &Path) -> PathBuf {
         self.path.join(relative_path.strip_prefix("/").unwrap_or(relative_path))
     }
@@ -41,6 +158,10 @@ impl Branch {
     pub fn free_space(&self) -> Result<u64, std::io::Error> {
         use nix::sys::statvfs::statvfs;
         
+        if let Some(space) = self.cached_space.read().as_ref() {
+            return Ok(space.available);
+        }
+        
         let stat = statvfs(&self.path).map_err(|e| {
             std::io::Error::new(std::io::ErrorKind::Other, e)
         })?;
@@ -50,8 +171,219 @@ impl Branch {
         let free_bytes = stat.blocks_available() as u64 * stat.fragment_size() as u64;
         Ok(free_bytes)
     }
+    
+    /// Space on the branch's filesystem, served from the refresher's cache when one is running
+    pub fn disk_space(&self) -> Result<DiskSpace, std::io::Error> {
+        if let Some(space) = self.cached_space.read().clone() {
+            return Ok(space);
+        }
+        DiskSpace::for_path(&self.path)
+    }
+    
+    /// Sample the branch's disk space into the cache read by `disk_space` and `free_space`
+    pub fn refresh_disk_space(&self) -> Result<(), std::io::Error> {
+        let space = DiskSpace::for_path(&self.path)?;
+        *self.cached_space.write() = Some(space);
+        Ok(())
+    }
+    
+    /// Drop the cached sample so queries go back to statvfs
+    pub fn clear_disk_space(&self) {
+        *self.cached_space.write() = None;
+    }
+}
+
+/// How often a disabled refresher checks whether it has been turned on
+const DISK_SPACE_DISABLED_POLL: Duration = Duration::from_secs(1);
+
+/// Background thread keeping each branch's cached disk space fresh
+///
+/// Without it the space-based create policies (mfs, lfs, lus, pfrd and the
+/// existing-path variants) statvfs every branch on each create. The interval
+/// is asked for again on every cycle so it can change at runtime; a zero
+/// interval clears the caches and policies go back to live queries. Dropping
+/// the refresher stops and joins the thread.
+pub struct DiskSpaceRefresher {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DiskSpaceRefresher {
+    pub fn start<F>(branches: Vec<Arc<Branch>>, interval: F) -> Self
+    where
+        F: Fn() -> Duration + Send + 'static,
+    {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_stop = stop.clone();
+        let thread = std::thread::Builder::new()
+            .name("diskspace".to_string())
+            .spawn(move || {
+                let (stopped, wakeup) = &*thread_stop;
+                loop {
+                    let interval = interval();
+                    for branch in &branches {
+                        if interval.is_zero() || !branch.is_online() {
+                            branch.clear_disk_space();
+                        } else if let Err(e) = branch.refresh_disk_space() {
+                            tracing::warn!("Failed to refresh disk space for {}: {}", branch.path.display(), e);
+                            branch.clear_disk_space();
+                        }
+                    }
+                    
+                    let mut stopped = stopped.lock();
+                    if !*stopped {
+                        let wait = if interval.is_zero() { DISK_SPACE_DISABLED_POLL } else { interval };
+                        wakeup.wait_for(&mut stopped, wait);
+                    }
+                    if *stopped {
+                        break;
+                    }
+                }
+                for branch in &branches {
+                    branch.clear_disk_space();
+                }
+            })
+            .expect("failed to spawn disk space refresher");
+        
+        Self { stop, thread: Some(thread) }
+    }
+}
+
+impl Drop for DiskSpaceRefresher {
+    fn drop(&mut self) {
+        let (stopped, wakeup) = &*self.stop;
+        *stopped.lock() = true;
+        wakeup.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
 }
 
+/// The pool's branches in priority order, shared by the managers
+///
+/// Policies walk branches in this order, and it can be changed at runtime with
+/// `reorder`. Readers take a snapshot, so an operation sees one consistent
+/// order even if the branches are reordered while it runs.
+#[derive(Debug, Clone, Default)]
+pub struct BranchList {
+    current: Arc<RwLock<Arc<Vec<Arc<Branch>>>>>,
+}
+
+impl BranchList {
+    pub fn new(branches: Vec<Arc<Branch>>) -> Self {
+        Self { current: Arc::new(RwLock::new(Arc::new(branches))) }
+    }
+    
+    /// The branches in their current order
+    pub fn snapshot(&self) -> Arc<Vec<Arc<Branch>>> {
+        self.current.read().clone()
+    }
+    
+    /// Put the branches in the order of `paths`, which must name each branch exactly once
+    ///
+    /// Returns the new position of every branch, indexed by its old one, so
+    /// callers can remap stored branch indices. Fails with EINVAL, leaving the
+    /// order unchanged, if `paths` is not a permutation of the branch paths.
+    pub fn reorder(&self, paths: &[PathBuf]) -> std::io::Result<Vec<usize>> {
+        let mut current = self.current.write();
+        let invalid = || std::io::Error::from_raw_os_error(EINVAL);
+        if paths.len() != current.len() {
+            return Err(invalid());
+        }
+        
+        let mut new_index = vec![usize::MAX; current.len()];
+        for (new_idx, path) in paths.iter().enumerate() {
+            let old_idx = current.iter().position(|b| &b.path == path).ok_or_else(invalid)?;
+            if new_index[old_idx] != usize::MAX {
+                return Err(invalid());
+            }
+            new_index[old_idx] = new_idx;
+        }
+        
+        let mut reordered = current.as_ref().clone();
+        for (old_idx, branch) in current.iter().enumerate() {
+            reordered[new_index[old_idx]] = branch.clone();
+        }
+        tracing::info!("Branch order is now {:?}", paths);
+        *current = Arc::new(reordered);
+        Ok(new_index)
+    }
+}
+
+impl From<Vec<Arc<Branch>>> for BranchList {
+    fn from(branches: Vec<Arc<Branch>>) -> Self {
+        Self::new(branches)
+    }
+}
+
+/// Drop branches whose path was already listed, keeping the first occurrence
+///
+/// Paths are compared as given, so they should be canonicalized first. A
+/// branch listed twice would otherwise have its free space counted twice by
+/// statfs and every metadata change applied to it once per listing.
+pub fn dedup_branches(branches: Vec<Branch>) -> Vec<Branch> {
+    let mut seen = std::collections::HashSet::new();
+    branches
+        .into_iter()
+        .filter(|branch| {
+            let first = seen.insert(branch.path.clone());
+            if !first {
+                tracing::warn!("Branch {} is listed more than once, ignoring the duplicate", branch.path.display());
+            }
+            first
+        })
+        .collect()
+}
+
+/// Check whether a branch path looks ready to be used
+///
+/// A branch is ready once it is a mountpoint (its device differs from its
+/// parent's) or, for plain directories, once it exists and is non-empty.
+pub fn branch_ready(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    
+    let metadata = match std::fs::metadata(path) {
+        Ok(m) if m.is_dir() => m,
+        _ => return false,
+    };
+    
+    let is_mountpoint = match path.parent().map(std::fs::metadata) {
+        Some(Ok(parent)) => parent.dev() != metadata.dev(),
+        _ => true, // "/" is always a mountpoint
+    };
+    
+    is_mountpoint || std::fs::read_dir(path).is_ok_and(|mut d| d.next().is_some())
+}
+
+/// Wait up to `timeout` for every branch path to become ready
+///
+/// Returns the paths that were still not ready when the timeout expired.
+pub fn wait_for_branches(paths: &[PathBuf], timeout: Duration, interval: Duration) -> Vec<PathBuf> {
+    let deadline = Instant::now() + timeout;
+    let mut pending: Vec<PathBuf> = paths.to_vec();
+    
+    loop {
+        pending.retain(|path| {
+            if branch_ready(path) {
+                tracing::info!("Branch {} is ready", path.display());
+                false
+            } else {
+                true
+            }
+        });
+        
+        if pending.is_empty() || Instant::now() >= deadline {
+            break;
+        }
+        std::thread::sleep(interval.min(deadline.saturating_duration_since(Instant::now())));
+    }
+    
+    for path in &pending {
+        tracing::warn!("Branch {} not ready after {:?}", path.display(), timeout);
+    }
+    pending
+}
 
 #[cfg(test)]
 mod tests {
@@ -75,6 +407,21 @@ mod tests {
         assert!(!branch.allows_create());
     }
 
+    #[test]
+    fn test_dedup_branches_keeps_first_listing() {
+        let a = TempDir::new().unwrap();
+        let b = TempDir::new().unwrap();
+        let branches = dedup_branches(vec![
+            Branch::new(a.path().to_path_buf(), BranchMode::ReadWrite),
+            Branch::new(b.path().to_path_buf(), BranchMode::ReadWrite),
+            Branch::new(a.path().to_path_buf(), BranchMode::ReadOnly),
+        ]);
+        
+        let paths: Vec<&Path> = branches.iter().map(|branch| branch.path.as_path()).collect();
+        assert_eq!(paths, [a.path(), b.path()]);
+        assert_eq!(branches[0].mode, BranchMode::ReadWrite);
+    }
+
     #[test]
     fn test_full_path() {
         let temp_dir = TempDir::new().unwrap();
@@ -86,4 +433,76 @@ mod tests {
         let full_path_abs = branch.full_path(Path::new("/test.txt"));
         assert_eq!(full_path_abs, temp_dir.path().join("test.txt"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_disk_space_refresher_feeds_policies() {
+        use crate::policy::{CreatePolicy, MostFreeSpaceCreatePolicy};
+        use crate::test_utils::SpacePolicyTestSetup;
+        
+        let setup = SpacePolicyTestSetup::new(10, 50, 30);
+        setup.setup_space();
+        let branches = setup.get_branches();
+        let marker = |idx: usize, mb: u64| {
+            std::fs::write(branches[idx].path.join(".space_marker"), mb.to_string()).unwrap();
+        };
+        let mfs = MostFreeSpaceCreatePolicy::new();
+        
+        // A cached sample is what policies see, even once it goes stale
+        for branch in &branches {
+            branch.refresh_disk_space().unwrap();
+        }
+        marker(0, 90);
+        assert_eq!(mfs.select_branch(&branches, Path::new("a")).unwrap().path, branches[1].path);
+        assert_eq!(branches[0].free_space().unwrap(), 10 * 1024 * 1024);
+        
+        // The refresher picks up the change on its next cycle
+        let refresher = DiskSpaceRefresher::start(branches.clone(), || Duration::from_millis(10));
+        let start = Instant::now();
+        while branches[0].disk_space().unwrap().available != 90 * 1024 * 1024 {
+            assert!(start.elapsed() < Duration::from_secs(5), "cache never refreshed");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(mfs.select_branch(&branches, Path::new("a")).unwrap().path, branches[0].path);
+        
+        // Stopping the refresher joins the thread and drops the cached samples
+        drop(refresher);
+        marker(2, 95);
+        assert_eq!(mfs.select_branch(&branches, Path::new("a")).unwrap().path, branches[2].path);
+    }
+
+    #[test]
+    fn test_wait_for_branch_that_appears_late() {
+        let root = TempDir::new().unwrap();
+        let ready = root.path().join("ready");
+        let late = root.path().join("late");
+        std::fs::create_dir(&ready).unwrap();
+        std::fs::write(ready.join("data"), b"x").unwrap();
+        
+        // Empty directories on the same device are not ready yet
+        std::fs::create_dir(&late).unwrap();
+        assert!(!branch_ready(&late));
+        
+        let late_clone = late.clone();
+        let populate = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            std::fs::write(late_clone.join("data"), b"x").unwrap();
+        });
+        
+        let paths = vec![ready.clone(), late.clone()];
+        let pending = wait_for_branches(&paths, Duration::from_secs(5), Duration::from_millis(10));
+        populate.join().unwrap();
+        assert!(pending.is_empty());
+        assert!(branch_ready(&late));
+    }
+
+    #[test]
+    fn test_wait_for_branches_times_out() {
+        let root = TempDir::new().unwrap();
+        let missing = root.path().join("missing");
+        
+        let start = Instant::now();
+        let pending = wait_for_branches(std::slice::from_ref(&missing), Duration::from_millis(50), Duration::from_millis(10));
+        assert_eq!(pending, vec![missing]);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -1,7 +1,9 @@
+use nix::sys::statvfs::FsFlags;
 use std::path::PathBuf;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use crate::inode::InodeCalc;
+use crate::policy::TieBreak;
 
 pub type ConfigRef = Arc<RwLock<Config>>;
 
@@ -9,6 +11,7 @@ pub type ConfigRef = Arc<RwLock<Config>>;
 pub enum StatFSMode {
     Base,  // Use base branch paths
     Full,  // Use full path (branch + fuse path)
+    Largest, // Report only the branch with the most free space
 }
 
 impl Default for StatFSMode {
@@ -43,6 +46,15 @@ impl Default for RenameEXDEV {
     }
 }
 
+/// What link() does when the hard link would have to cross branches
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LinkEXDEV {
+    #[default]
+    Passthrough, // Return EXDEV error to caller
+    RelSymlink,  // Create a symlink relative to the link's directory
+    AbsSymlink,  // Create a symlink to the source's absolute path in the pool
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CacheFiles {
     Libfuse,    // Use libfuse default (always cache)
@@ -78,14 +90,42 @@ impl Default for MoveOnENOSPC {
 pub struct Config {
     pub statfs_mode: StatFSMode,
     pub statfs_ignore: StatFSIgnore,
+    pub statfs_total: Option<u64>,  // Bytes statfs reports as the filesystem size, overriding the branches
+    pub statfs_available: Option<u64>,  // Bytes statfs reports as available, overriding the branches
     pub mountpoint: PathBuf,
+    pub mount_flags: FsFlags,  // ST_RDONLY/ST_NOSUID/ST_NODEV from the ro, nosuid and nodev mount options
     pub ignore_path_preserving_on_rename: bool,
     pub rename_exdev: RenameEXDEV,
+    pub link_exdev: LinkEXDEV,
     pub moveonenospc: MoveOnENOSPC,
+    pub minfreespace: u64,  // Bytes a branch must keep free to receive data
+    pub tiebreak: TieBreak,  // Which of several equally free branches mfs/lfs pick
     pub cache_files: CacheFiles,
+    pub cache_files_process_names: Vec<String>,  // Processes allowed page caching under per-process
     pub direct_io_allow_mmap: bool,
     pub parallel_direct_writes: bool,
     pub inodecalc: InodeCalc,
+    pub link_cow: bool,
+    pub dropcacheonclose: bool,
+    pub sync_on_close: bool,  // fsync files opened for writing when their handle is released
+    pub create_durable: bool,  // fsync the parent directory after creating a file
+    pub writeback: bool,  // Buffer small contiguous writes per handle until flush/fsync/release
+    pub write_verify: bool,  // Read every write back from the branch and fail with EIO on mismatch
+    pub readahead: u64,  // KiB to prefetch for files opened read-only (0 disables)
+    pub cache_diskspace: u64,  // Seconds between background branch space samples (0 = query per create)
+    pub cache_symlinks: bool,
+    pub cache_readdir: u64,  // Seconds a directory's union listing is reused (0 = list branches every readdir)
+    pub max_inodes: u64,  // Cached inodes kept before least recently used ones are evicted (0 = unbounded)
+    pub follow_symlinks: bool,  // chmod/chown/utimens change a symlink's target instead of the link
+    pub xattr_union: bool,  // listxattr merges the names from every copy instead of reading one
+    pub mergerfs_control: bool, // Serve the /.mergerfs control file and its xattr interface
+    pub whiteout: bool,
+    pub hide_patterns: Vec<String>,  // Name patterns hidden from directory listings
+    pub default_mode: Option<u32>,  // Permissions for creates whose client mode is 0
+    pub force_mode: Option<u32>,  // Permissions for every create, overriding the client mode
+    pub umask: Option<u32>,  // Bits cleared from every client create mode on top of the client's umask
+    pub uid_squash: Option<u32>,  // Owner reported for every file; chown leaves the owner alone
+    pub gid_squash: Option<u32>,  // Group reported for every file; chown leaves the group alone
 }
 
 impl Default for Config {
@@ -93,14 +133,42 @@ impl Default for Config {
         Self {
             statfs_mode: StatFSMode::default(),
             statfs_ignore: StatFSIgnore::default(),
+            statfs_total: None,
+            statfs_available: None,
             mountpoint: PathBuf::from("/mnt/mergerfs"),
+            mount_flags: FsFlags::empty(),
             ignore_path_preserving_on_rename: false,
             rename_exdev: RenameEXDEV::default(),
+            link_exdev: LinkEXDEV::default(),
             moveonenospc: MoveOnENOSPC::default(),
+            minfreespace: 0,
+            tiebreak: TieBreak::default(),
             cache_files: CacheFiles::default(),
+            cache_files_process_names: vec!["rtorrent".to_string(), "qbittorrent-nox".to_string()],
             direct_io_allow_mmap: false,
             parallel_direct_writes: false,
             inodecalc: InodeCalc::default(),
+            link_cow: false,
+            dropcacheonclose: false,
+            sync_on_close: false,
+            create_durable: false,
+            writeback: false,
+            write_verify: false,
+            readahead: 0,
+            cache_diskspace: 0,
+            cache_symlinks: false,
+            cache_readdir: 0,
+            max_inodes: 0,
+            follow_symlinks: false,
+            xattr_union: false,
+            mergerfs_control: true,
+            whiteout: false,
+            hide_patterns: crate::file_ops::DEFAULT_HIDE_PATTERNS.iter().map(|p| p.to_string()).collect(),
+            default_mode: None,
+            force_mode: None,
+            umask: None,
+            uid_squash: None,
+            gid_squash: None,
         }
     }
 }
@@ -111,6 +179,33 @@ impl Config {
         matches!(self.cache_files, CacheFiles::Off)
     }
     
+    /// Determine if direct I/O should be used for a request from the given process.
+    /// Under per-process caching only the listed process names get the page cache.
+    pub fn should_use_direct_io_for(&self, process_name: Option<&str>) -> bool {
+        match self.cache_files {
+            CacheFiles::Off => true,
+            CacheFiles::PerProcess => !process_name
+                .map(|name| self.cache_files_process_names.iter().any(|p| p == name))
+                .unwrap_or(false),
+            _ => false,
+        }
+    }
+    
+    /// Permission bits for a new file or directory created with `mode` and `umask`
+    ///
+    /// The umask option is cleared from the client's mode along with its own
+    /// umask. force_mode wins over the client; default_mode only fills in
+    /// when the client asked for no permissions at all.
+    pub fn create_mode(&self, mode: u32, umask: u32) -> u32 {
+        let umask = umask | self.umask.unwrap_or(0);
+        let requested = mode & !umask & 0o7777;
+        match (self.force_mode, self.default_mode) {
+            (Some(forced), _) => forced,
+            (None, Some(default)) if requested == 0 => default,
+            _ => requested,
+        }
+    }
+    
     /// Determine if kernel cache should be enabled
     pub fn should_enable_kernel_cache(&self) -> bool {
         matches!(self.cache_files, CacheFiles::Full | CacheFiles::AutoFull | CacheFiles::PerProcess)
@@ -119,4 +214,28 @@ impl Config {
 
 pub fn create_config() -> ConfigRef {
     Arc::new(RwLock::new(Config::default()))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_use_direct_io_for() {
+        let mut config = Config {
+            cache_files: CacheFiles::PerProcess,
+            cache_files_process_names: vec!["rtorrent".to_string()],
+            ..Config::default()
+        };
+        
+        // Listed processes keep the page cache, everyone else goes direct
+        assert!(!config.should_use_direct_io_for(Some("rtorrent")));
+        assert!(config.should_use_direct_io_for(Some("cp")));
+        assert!(config.should_use_direct_io_for(None));
+        
+        config.cache_files = CacheFiles::Off;
+        assert!(config.should_use_direct_io_for(Some("rtorrent")));
+        config.cache_files = CacheFiles::Libfuse;
+        assert!(!config.should_use_direct_io_for(Some("cp")));
+    }
+}
@@ -1,10 +1,16 @@
-use crate::config::ConfigRef;
+use crate::config::{Config, ConfigRef};
 use crate::file_ops::FileManager;
-use crate::policy::create_policy_from_name;
+use crate::metadata_ops::{MetadataManager, MetadataOp};
+use crate::moveonenospc::MoveOnENOSPCStats;
+use crate::policy::{AllSearchPolicy, CreatePolicy, LeastFreeSpaceCreatePolicy, MostFreeSpaceCreatePolicy, PolicyError, RandomFreeSpaceCreatePolicy, SearchPolicy, TieBreak, action_policy_from_name, create_policy_from_name, mkdir_policy_from_name, search_policy_from_name};
 use std::collections::HashMap;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
 use std::sync::{Arc, Weak};
 use std::any::Any;
 use parking_lot::RwLock;
+use serde::Serialize;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -17,17 +23,8 @@ pub enum ConfigError {
     ReadOnly,
     #[error("Operation not supported")]
     NotSupported,
-}
-
-impl ConfigError {
-    pub fn errno(&self) -> i32 {
-        match self {
-            ConfigError::NotFound => 61,      // ENOATTR
-            ConfigError::InvalidValue(_) => 22, // EINVAL
-            ConfigError::ReadOnly => 30,       // EROFS
-            ConfigError::NotSupported => 95,   // ENOTSUP
-        }
-    }
+    #[error(transparent)]
+    Policy(#[from] PolicyError),
 }
 
 /// Trait for configuration options that can be get/set at runtime
@@ -50,12 +47,57 @@ pub trait ConfigOption: Send + Sync + Any {
     fn help(&self) -> &str;
 }
 
+/// Name of the read-only attribute holding the JSON dump of all options
+pub const CONFIG_DUMP_NAME: &str = "config";
+
+/// Prefix of the read-only query listing the branches that hold a path,
+/// e.g. "find./dir/file.txt"
+pub const FIND_PREFIX: &str = "find.";
+
+/// Prefix of the read-only query returning an option's help text,
+/// e.g. "help.func.create"
+pub const HELP_PREFIX: &str = "help.";
+
+/// Write-only control file option that zeroes the per-branch write counters
+pub const BRANCH_STATS_RESET: &str = "stats.branch.reset";
+
+/// Decode `%XX` escapes, returning None for a truncated or non-hex escape
+fn percent_decode(value: &str) -> Option<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            decoded.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Snapshot of a single option as serialized in the JSON config dump
+#[derive(Debug, Serialize)]
+pub struct OptionInfo {
+    pub name: String,
+    pub value: String,
+    pub readonly: bool,
+    pub help: String,
+}
+
 /// Manages runtime configuration through xattr interface
 pub struct ConfigManager {
     options: Arc<RwLock<HashMap<String, Box<dyn ConfigOption>>>>,
     #[allow(dead_code)]
     config: ConfigRef,
     file_manager: Weak<FileManager>,
+    metadata_manager: Weak<MetadataManager>,
 }
 
 impl ConfigManager {
@@ -73,11 +115,48 @@ impl ConfigManager {
             Box::new(CreatePolicyOption::new(config.clone())),
         );
         
+        options.insert(
+            "func.mkdir".to_string(),
+            Box::new(MkdirPolicyOption::new()),
+        );
+        
+        options.insert(
+            "func.open".to_string(),
+            Box::new(SearchPolicyOption::new("func.open")),
+        );
+        
+        options.insert(
+            "func.getattr".to_string(),
+            Box::new(SearchPolicyOption::new("func.getattr")),
+        );
+        
+        for op in MetadataOp::ALL {
+            options.insert(
+                op.option_name().to_string(),
+                Box::new(ActionPolicyOption::new(op.option_name())),
+            );
+        }
+        
+        options.insert(
+            "func.unlink".to_string(),
+            Box::new(ActionPolicyOption::new("func.unlink")),
+        );
+        
+        options.insert(
+            "func.truncate".to_string(),
+            Box::new(ActionPolicyOption::new("func.truncate")),
+        );
+        
         options.insert(
             "moveonenospc".to_string(),
             Box::new(MoveOnENOSPCOption::new(config.clone())),
         );
         
+        options.insert(
+            "minfreespace".to_string(),
+            Box::new(MinFreeSpaceOption::new(config.clone())),
+        );
+        
         options.insert(
             "direct_io".to_string(),
             Box::new(BooleanOption::new(
@@ -93,6 +172,16 @@ impl ConfigManager {
             Box::new(CacheFilesOption::new(config.clone())),
         );
         
+        options.insert(
+            "hide_patterns".to_string(),
+            Box::new(HidePatternsOption::new(config.clone())),
+        );
+        
+        options.insert(
+            "cache.files.process-names".to_string(),
+            Box::new(CacheFilesProcessNamesOption::new(config.clone())),
+        );
+        
         options.insert(
             "inodecalc".to_string(),
             Box::new(InodeCalcOption::new(config.clone())),
@@ -108,6 +197,269 @@ impl ConfigManager {
             Box::new(StatFSIgnoreOption::new(config.clone())),
         );
         
+        options.insert(
+            "statfs.total".to_string(),
+            Box::new(ConfigSizeOption::new(
+                "statfs.total",
+                "Size statfs reports for the pool instead of the branch totals (bytes, or with K/M/G/T suffix; empty to unset)",
+                |c| c.statfs_total,
+                |c, v| c.statfs_total = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "statfs.available".to_string(),
+            Box::new(ConfigSizeOption::new(
+                "statfs.available",
+                "Free space statfs reports for the pool instead of the branch totals (bytes, or with K/M/G/T suffix; empty to unset)",
+                |c| c.statfs_available,
+                |c, v| c.statfs_available = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "link-exdev".to_string(),
+            Box::new(LinkEXDEVOption::new(config.clone())),
+        );
+        
+        options.insert(
+            "tiebreak".to_string(),
+            Box::new(TieBreakOption::new(config.clone())),
+        );
+        
+        options.insert(
+            "link-cow".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "link-cow",
+                "Break hard links by copying the file before it is written to or truncated (true|false)",
+                |c| c.link_cow,
+                |c, v| c.link_cow = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "ignorepponrename".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "ignorepponrename",
+                "Ignore path preservation on rename, always using the create-path strategy (true|false)",
+                |c| c.ignore_path_preserving_on_rename,
+                |c, v| c.ignore_path_preserving_on_rename = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "dropcacheonclose".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "dropcacheonclose",
+                "Drop the page cache of files opened for reading when they are closed (true|false)",
+                |c| c.dropcacheonclose,
+                |c, v| c.dropcacheonclose = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "sync_on_close".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "sync_on_close",
+                "Sync files opened for writing to their branch when they are closed (true|false)",
+                |c| c.sync_on_close,
+                |c, v| c.sync_on_close = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "create_durable".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "create_durable",
+                "Sync the parent directory of new files so they survive a crash (true|false)",
+                |c| c.create_durable,
+                |c, v| c.create_durable = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "writeback".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "writeback",
+                "Buffer small contiguous writes per file handle, writing them out on flush, fsync and release (true|false)",
+                |c| c.writeback,
+                |c, v| c.writeback = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "write_verify".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "write_verify",
+                "Read each write back from the branch and fail it with EIO if the data differs; slow (true|false)",
+                |c| c.write_verify,
+                |c, v| c.write_verify = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "readahead".to_string(),
+            Box::new(ConfigNumberOption::new(
+                "readahead",
+                "KiB to read ahead, with sequential access advice, for files opened read-only (0 disables)",
+                |c| c.readahead,
+                |c, v| c.readahead = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "default_mode".to_string(),
+            Box::new(ConfigModeOption::new(
+                "default_mode",
+                "Octal permissions for files and directories created with mode 0 (empty to unset)",
+                |c| c.default_mode,
+                |c, v| c.default_mode = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "umask".to_string(),
+            Box::new(ConfigModeOption::new(
+                "umask",
+                "Octal bits removed from the mode of every created file, directory and special file, in addition to the client's umask (empty to unset)",
+                |c| c.umask,
+                |c, v| c.umask = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "force_mode".to_string(),
+            Box::new(ConfigModeOption::new(
+                "force_mode",
+                "Octal permissions applied to every created file and directory, overriding the client (empty to unset)",
+                |c| c.force_mode,
+                |c, v| c.force_mode = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "uid_squash".to_string(),
+            Box::new(ConfigIdOption::new(
+                "uid_squash",
+                "User ID reported as the owner of every file; chown then keeps the backing owner (empty to unset)",
+                |c| c.uid_squash,
+                |c, v| c.uid_squash = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "gid_squash".to_string(),
+            Box::new(ConfigIdOption::new(
+                "gid_squash",
+                "Group ID reported as the group of every file; chown then keeps the backing group (empty to unset)",
+                |c| c.gid_squash,
+                |c, v| c.gid_squash = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "cache.diskspace".to_string(),
+            Box::new(ConfigNumberOption::new(
+                "cache.diskspace",
+                "Seconds between background refreshes of branch free space used by create policies (0 queries on every create)",
+                |c| c.cache_diskspace,
+                |c, v| c.cache_diskspace = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "max_inodes".to_string(),
+            Box::new(ConfigNumberOption::new(
+                "max_inodes",
+                "Inodes kept cached before the least recently used unopened ones are evicted and recomputed on lookup (0 is unbounded)",
+                |c| c.max_inodes,
+                |c, v| c.max_inodes = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "cache.symlinks".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "cache.symlinks",
+                "Cache readlink results per inode until the link is changed (true|false)",
+                |c| c.cache_symlinks,
+                |c, v| c.cache_symlinks = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "cache.readdir".to_string(),
+            Box::new(ConfigNumberOption::new(
+                "cache.readdir",
+                "Seconds a directory listing is reused until an entry is added, removed or renamed in it (0 lists the branches on every readdir)",
+                |c| c.cache_readdir,
+                |c, v| c.cache_readdir = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "mergerfs_control".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "mergerfs_control",
+                "Serve the /.mergerfs control file; once off it can only be turned back on by remounting (true|false)",
+                |c| c.mergerfs_control,
+                |c, v| c.mergerfs_control = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "follow_symlinks".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "follow_symlinks",
+                "Apply chmod, chown and utimens to a symlink's target instead of the link (true|false)",
+                |c| c.follow_symlinks,
+                |c, v| c.follow_symlinks = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "xattr.union".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "xattr.union",
+                "List the extended attributes of every copy of a file, not just the first found (true|false)",
+                |c| c.xattr_union,
+                |c, v| c.xattr_union = v,
+                config.clone(),
+            )),
+        );
+        
+        options.insert(
+            "whiteout".to_string(),
+            Box::new(ConfigFlagOption::new(
+                "whiteout",
+                "Hide files left on read-only branches after unlink with .wh.<name> markers (true|false)",
+                |c| c.whiteout,
+                |c, v| c.whiteout = v,
+                config.clone(),
+            )),
+        );
+        
         // Read-only options
         options.insert(
             "version".to_string(),
@@ -131,6 +483,7 @@ impl ConfigManager {
             options: Arc::new(RwLock::new(options)),
             config,
             file_manager: Weak::new(),
+            metadata_manager: Weak::new(),
         }
     }
     
@@ -144,16 +497,75 @@ impl ConfigManager {
             // Update the stored value to match the FileManager's current policy
             let _ = create_option.set_value(&current_policy_name);
         }
+        if let Some(mkdir_option) = self.options.write().get_mut("func.mkdir") {
+            let _ = mkdir_option.set_value(&file_manager.get_mkdir_policy_name());
+        }
+        if let Some(open_option) = self.options.write().get_mut("func.open") {
+            let _ = open_option.set_value(&file_manager.get_open_policy_name());
+        }
+        if let Some(getattr_option) = self.options.write().get_mut("func.getattr") {
+            let _ = getattr_option.set_value(&file_manager.get_getattr_policy_name());
+        }
+        if let Some(unlink_option) = self.options.write().get_mut("func.unlink") {
+            let _ = unlink_option.set_value(&file_manager.get_unlink_policy_name());
+        }
+        if let Some(truncate_option) = self.options.write().get_mut("func.truncate") {
+            let _ = truncate_option.set_value(&file_manager.get_truncate_policy_name());
+        }
+        file_manager.set_whiteout(self.config.read().whiteout);
+        file_manager.set_create_durable(self.config.read().create_durable);
+        file_manager.set_hide_patterns(self.config.read().hide_patterns.clone());
         
         tracing::info!("ConfigManager initialized with FileManager, current policy: {}", current_policy_name);
     }
     
+    /// Set the metadata manager reference for runtime action policy updates
+    pub fn set_metadata_manager(&mut self, metadata_manager: &Arc<MetadataManager>) {
+        self.metadata_manager = Arc::downgrade(metadata_manager);
+        
+        // Sync the stored values with the MetadataManager's current policies
+        let mut options = self.options.write();
+        for op in MetadataOp::ALL {
+            if let Some(option) = options.get_mut(op.option_name()) {
+                let _ = option.set_value(&metadata_manager.get_action_policy_name(op));
+            }
+        }
+        metadata_manager.set_follow_symlinks(self.config.read().follow_symlinks);
+    }
+    
+    /// Register the read-only moveonenospc counters under "stats.moveonenospc.*"
+    pub fn register_moveonenospc_stats(&mut self, stats: Arc<MoveOnENOSPCStats>) {
+        type Counter = (&'static str, fn(&MoveOnENOSPCStats) -> u64, &'static str);
+        let counters: [Counter; 4] = [
+            ("attempted", MoveOnENOSPCStats::attempted, "Number of moveonenospc migrations attempted"),
+            ("success", MoveOnENOSPCStats::succeeded, "Number of moveonenospc migrations that succeeded"),
+            ("failed", MoveOnENOSPCStats::failed, "Number of moveonenospc migrations that failed"),
+            ("bytes_moved", MoveOnENOSPCStats::bytes_moved, "Bytes copied between branches by moveonenospc"),
+        ];
+        
+        for (suffix, read, help) in counters {
+            let stats = stats.clone();
+            self.register_stat(&format!("moveonenospc.{}", suffix), help, move || read(&stats));
+        }
+    }
+    
+    /// Register a read-only counter under "stats.<name>" whose value is sampled on each read
+    pub fn register_stat<F>(&mut self, name: &str, help: &str, read: F)
+    where
+        F: Fn() -> u64 + Send + Sync + 'static,
+    {
+        let name = format!("stats.{}", name);
+        let option = ReadOnlyOption::sampled(&name, help, move || read().to_string());
+        self.options.write().insert(name, Box::new(option));
+    }
+    
     /// Get all available option names with "user.mergerfs." prefix
     pub fn list_options(&self) -> Vec<String> {
         let options = self.options.read();
         options
             .keys()
             .map(|k| format!("user.mergerfs.{}", k))
+            .chain(std::iter::once(format!("user.mergerfs.{}", CONFIG_DUMP_NAME)))
             .collect()
     }
     
@@ -162,6 +574,22 @@ impl ConfigManager {
         // Remove "user.mergerfs." prefix if present
         let name = name.strip_prefix("user.mergerfs.").unwrap_or(name);
         
+        // Synthesized snapshot of every option
+        if name == CONFIG_DUMP_NAME {
+            return Ok(self.to_json());
+        }
+        
+        // Placement query for the path encoded in the name
+        if let Some(encoded) = name.strip_prefix(FIND_PREFIX) {
+            return self.find(encoded);
+        }
+        
+        if let Some(option) = name.strip_prefix(HELP_PREFIX) {
+            return self.options.read().get(option)
+                .map(|option| option.help().to_string())
+                .ok_or(ConfigError::NotFound);
+        }
+        
         let options = self.options.read();
         match options.get(name) {
             Some(option) => Ok(option.get_value()),
@@ -169,15 +597,108 @@ impl ConfigManager {
         }
     }
     
+    /// Snapshot all options, sorted by name
+    pub fn option_infos(&self) -> Vec<OptionInfo> {
+        let options = self.options.read();
+        let mut infos: Vec<OptionInfo> = options
+            .iter()
+            .map(|(name, option)| OptionInfo {
+                name: name.clone(),
+                value: option.get_value(),
+                readonly: option.is_readonly(),
+                help: option.help().to_string(),
+            })
+            .collect();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+    
+    /// Serialize all options (name, value, readonly, help) as a JSON array
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.option_infos()).unwrap_or_else(|_| "[]".to_string())
+    }
+    
+    /// Get the file manager, if it is still alive
+    pub fn file_manager(&self) -> Option<Arc<FileManager>> {
+        self.file_manager.upgrade()
+    }
+    
     /// Set a specific option value
     pub fn set_option(&self, name: &str, value: &str) -> Result<(), ConfigError> {
         // Remove "user.mergerfs." prefix if present
         let name = name.strip_prefix("user.mergerfs.").unwrap_or(name);
         
+        if name == CONFIG_DUMP_NAME || name.starts_with(HELP_PREFIX) {
+            return Err(ConfigError::ReadOnly);
+        }
+        
         // Special handling for create policy
         if name == "func.create" {
             return self.set_create_policy(value);
         }
+        if name == "func.mkdir" {
+            return self.set_mkdir_policy(value);
+        }
+        
+        // Special handling for open and getattr search policies
+        if name == "func.open" {
+            return self.set_open_policy(value);
+        }
+        if name == "func.getattr" {
+            return self.set_getattr_policy(value);
+        }
+        
+        // Special handling for unlink and truncate policies
+        if name == "func.unlink" {
+            return self.set_unlink_policy(value);
+        }
+        if name == "func.truncate" {
+            return self.set_truncate_policy(value);
+        }
+        
+        // Symlink following lives in the MetadataManager
+        if name == "follow_symlinks" {
+            return self.set_follow_symlinks(value);
+        }
+        
+        // Whiteout handling lives in the FileManager
+        if name == "whiteout" {
+            return self.set_whiteout(value);
+        }
+        
+        // Create syncing lives in the FileManager
+        if name == "create_durable" {
+            return self.set_create_durable(value);
+        }
+        
+        // Listing filters live in the FileManager
+        if name == "hide_patterns" {
+            return self.set_hide_patterns(value);
+        }
+        
+        // Write-only command: prune duplicate copies of a path
+        if name == "prune" {
+            return self.prune(value);
+        }
+        
+        // Write-only command: zero the per-branch write counters
+        if name == BRANCH_STATS_RESET {
+            return self.reset_branch_stats(value);
+        }
+        
+        // msprand holds its own copy of minfreespace, mfs and lfs of tiebreak
+        if name == "minfreespace" || name == "tiebreak" {
+            self.options.write().get_mut(name).ok_or(ConfigError::NotFound)?.set_value(value)?;
+            let create_policy = self.get_option("func.create")?;
+            self.set_create_policy(&create_policy)?;
+            let mkdir_policy = self.get_option("func.mkdir")?;
+            return self.set_mkdir_policy(&mkdir_policy);
+        }
+        
+        // Special handling for metadata action policies
+        if let Some(op) = MetadataOp::from_option_name(name) {
+            return self.set_action_policy(op, value);
+        }
         
         let mut options = self.options.write();
         match options.get_mut(name) {
@@ -192,12 +713,77 @@ impl ConfigManager {
         }
     }
     
+    /// Zero the `stats.branch.<i>.written` counter of one branch, or of all
+    fn reset_branch_stats(&self, value: &str) -> Result<(), ConfigError> {
+        let file_manager = self.file_manager.upgrade().ok_or(ConfigError::NotSupported)?;
+        let value = value.trim_end_matches('\0').trim();
+        if value.is_empty() || value == "all" {
+            file_manager.branches().iter().for_each(|branch| branch.reset_bytes_written());
+            return Ok(());
+        }
+        
+        let branch = value.parse::<usize>().ok()
+            .and_then(|idx| file_manager.branches().get(idx).cloned())
+            .ok_or_else(|| ConfigError::InvalidValue(format!("{} expects a branch index or \"all\", got: {}", BRANCH_STATS_RESET, value)))?;
+        branch.reset_bytes_written();
+        Ok(())
+    }
+    
+    /// Branch roots holding the path encoded in a find query, one per line
+    ///
+    /// The path travels in the attribute name, which cannot carry NUL or
+    /// non-UTF-8 bytes, so `%XX` escapes are decoded first (a literal '%'
+    /// must be sent as "%25"). Names are limited to 255 bytes by the kernel.
+    fn find(&self, encoded: &str) -> Result<String, ConfigError> {
+        let decoded = percent_decode(encoded)
+            .ok_or_else(|| ConfigError::InvalidValue(format!("Malformed escape in find path: {}", encoded)))?;
+        let path = PathBuf::from(OsString::from_vec(decoded));
+        let escapes = path.components().any(|c| matches!(c, std::path::Component::ParentDir));
+        if !path.has_root() || escapes {
+            return Err(ConfigError::InvalidValue(format!("find expects an absolute path, got: {}", path.display())));
+        }
+        
+        let file_manager = self.file_manager.upgrade().ok_or(ConfigError::NotSupported)?;
+        let branches = AllSearchPolicy::new().search_branches(&file_manager.branches(), &path)?;
+        Ok(branches.iter()
+            .map(|branch| branch.path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+    
+    /// Remove duplicated copies of `value` (a pool-relative path), keeping the newest
+    fn prune(&self, value: &str) -> Result<(), ConfigError> {
+        let path = std::path::Path::new(value.trim());
+        let escapes = path.components().any(|c| matches!(c, std::path::Component::ParentDir));
+        if !path.has_root() || escapes {
+            return Err(ConfigError::InvalidValue(format!("prune expects an absolute path, got: {}", value)));
+        }
+        
+        let file_manager = self.file_manager.upgrade().ok_or(ConfigError::NotSupported)?;
+        file_manager.prune_duplicates(path)?;
+        Ok(())
+    }
+    
+    /// Build the named policy with the settings it takes from the config
+    ///
+    /// msprand uses minfreespace as its threshold and mfs/lfs break ties as
+    /// tiebreak says; other names go through `from_name`.
+    fn configured_create_policy(&self, value: &str, from_name: fn(&str) -> Option<Box<dyn CreatePolicy>>) -> Option<Box<dyn CreatePolicy>> {
+        let config = self.config.read();
+        match value {
+            "msprand" => Some(Box::new(RandomFreeSpaceCreatePolicy::with_min_free_space(config.minfreespace))),
+            "mfs" => Some(Box::new(MostFreeSpaceCreatePolicy::with_tiebreak(config.tiebreak))),
+            "lfs" => Some(Box::new(LeastFreeSpaceCreatePolicy::with_tiebreak(config.tiebreak))),
+            _ => from_name(value),
+        }
+    }
+    
     /// Set create policy with file manager update
     fn set_create_policy(&self, value: &str) -> Result<(), ConfigError> {
         // Validate policy name and create the policy
-        let policy = create_policy_from_name(value)
+        let policy = self.configured_create_policy(value, create_policy_from_name)
             .ok_or_else(|| ConfigError::InvalidValue(format!(
-                "Unknown create policy: {}. Valid options: ff, mfs, lfs, lus, rand, epff, epmfs, eplfs, pfrd",
+                "Unknown create policy: {}. Valid options: ff, mfs, lfs, lus, rand, epff, epff-writable, epmfs, epmfs-or-mfs, eplfs, pfrd, msprand",
                 value
             )))?;
         
@@ -213,165 +799,925 @@ impl ConfigManager {
             tracing::warn!("FileManager not available for policy update");
         }
         
-        // Update the stored value in the config option
-        let mut options = self.options.write();
-        if let Some(option) = options.get_mut("func.create") {
-            option.set_value(value)?;
+        // Update the stored value in the config option
+        let mut options = self.options.write();
+        if let Some(option) = options.get_mut("func.create") {
+            option.set_value(value)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Set the mkdir policy with file manager update
+    fn set_mkdir_policy(&self, value: &str) -> Result<(), ConfigError> {
+        let policy = self.configured_create_policy(value, mkdir_policy_from_name)
+            .ok_or_else(|| ConfigError::InvalidValue(format!(
+                "Unknown mkdir policy: {}. Valid options: all or any create policy",
+                value
+            )))?;
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_mkdir_policy(policy);
+            tracing::info!("Updated mkdir policy to: {}", value);
+        } else {
+            tracing::warn!("FileManager not available for policy update");
+        }
+        
+        let mut options = self.options.write();
+        if let Some(option) = options.get_mut("func.mkdir") {
+            option.set_value(value)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Set open search policy with file manager update
+    fn set_open_policy(&self, value: &str) -> Result<(), ConfigError> {
+        let policy = search_policy_from_name(value)
+            .ok_or_else(|| ConfigError::InvalidValue(format!(
+                "Unknown open policy: {}. Valid options: ff, newest, epnewest, rand",
+                value
+            )))?;
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_open_policy(policy);
+            tracing::info!("Updated open policy to: {}", value);
+        } else {
+            tracing::warn!("FileManager not available for policy update");
+        }
+        
+        let mut options = self.options.write();
+        if let Some(option) = options.get_mut("func.open") {
+            option.set_value(value)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Set getattr search policy with file manager update
+    fn set_getattr_policy(&self, value: &str) -> Result<(), ConfigError> {
+        let policy = search_policy_from_name(value)
+            .ok_or_else(|| ConfigError::InvalidValue(format!(
+                "Unknown getattr policy: {}. Valid options: ff, newest, epnewest, rand",
+                value
+            )))?;
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_getattr_policy(policy);
+            tracing::info!("Updated getattr policy to: {}", value);
+        } else {
+            tracing::warn!("FileManager not available for policy update");
+        }
+        
+        let mut options = self.options.write();
+        if let Some(option) = options.get_mut("func.getattr") {
+            option.set_value(value)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Set the unlink action policy with file manager update
+    fn set_unlink_policy(&self, value: &str) -> Result<(), ConfigError> {
+        let policy = action_policy_from_name(value)
+            .ok_or_else(|| ConfigError::InvalidValue(format!(
+                "Unknown action policy: {}. Valid options: all, epall, epff",
+                value
+            )))?;
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_unlink_policy(policy);
+            tracing::info!("Updated unlink policy to: {}", value);
+        } else {
+            tracing::warn!("FileManager not available for policy update");
+        }
+        
+        let mut options = self.options.write();
+        if let Some(option) = options.get_mut("func.unlink") {
+            option.set_value(value)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Set the truncate action policy with file manager update
+    fn set_truncate_policy(&self, value: &str) -> Result<(), ConfigError> {
+        let policy = action_policy_from_name(value)
+            .ok_or_else(|| ConfigError::InvalidValue(format!(
+                "Unknown action policy: {}. Valid options: all, epall, epff",
+                value
+            )))?;
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_truncate_policy(policy);
+            tracing::info!("Updated truncate policy to: {}", value);
+        } else {
+            tracing::warn!("FileManager not available for policy update");
+        }
+        
+        let mut options = self.options.write();
+        if let Some(option) = options.get_mut("func.truncate") {
+            option.set_value(value)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Toggle symlink following for metadata changes in the config and the metadata manager
+    fn set_follow_symlinks(&self, value: &str) -> Result<(), ConfigError> {
+        if let Some(option) = self.options.write().get_mut("follow_symlinks") {
+            option.set_value(value)?;
+        }
+        
+        if let Some(metadata_manager) = self.metadata_manager.upgrade() {
+            metadata_manager.set_follow_symlinks(self.config.read().follow_symlinks);
+        }
+        
+        Ok(())
+    }
+    
+    /// Toggle whiteout markers in the config and the file manager
+    fn set_whiteout(&self, value: &str) -> Result<(), ConfigError> {
+        if let Some(option) = self.options.write().get_mut("whiteout") {
+            option.set_value(value)?;
+        }
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_whiteout(self.config.read().whiteout);
+        }
+        
+        Ok(())
+    }
+    
+    /// Toggle parent directory syncing on create in the config and the file manager
+    fn set_create_durable(&self, value: &str) -> Result<(), ConfigError> {
+        if let Some(option) = self.options.write().get_mut("create_durable") {
+            option.set_value(value)?;
+        }
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_create_durable(self.config.read().create_durable);
+        }
+        
+        Ok(())
+    }
+    
+    /// Update the hidden listing patterns in the config and the file manager
+    fn set_hide_patterns(&self, value: &str) -> Result<(), ConfigError> {
+        if let Some(option) = self.options.write().get_mut("hide_patterns") {
+            option.set_value(value)?;
+        }
+        
+        if let Some(file_manager) = self.file_manager.upgrade() {
+            file_manager.set_hide_patterns(self.config.read().hide_patterns.clone());
+        }
+        
+        Ok(())
+    }
+    
+    /// Set a metadata action policy with metadata manager update
+    fn set_action_policy(&self, op: MetadataOp, value: &str) -> Result<(), ConfigError> {
+        let policy = action_policy_from_name(value)
+            .ok_or_else(|| ConfigError::InvalidValue(format!(
+                "Unknown action policy: {}. Valid options: all, epall, epff",
+                value
+            )))?;
+        
+        if let Some(metadata_manager) = self.metadata_manager.upgrade() {
+            metadata_manager.set_action_policy(op, policy);
+            tracing::info!("Updated {} policy to: {}", op.option_name(), value);
+        } else {
+            tracing::warn!("MetadataManager not available for policy update");
+        }
+        
+        let mut options = self.options.write();
+        if let Some(option) = options.get_mut(op.option_name()) {
+            option.set_value(value)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Get access to the underlying config
+    pub fn config(&self) -> &ConfigRef {
+        &self.config
+    }
+}
+
+/// Option for create policy configuration
+struct CreatePolicyOption {
+    #[allow(dead_code)]
+    config: ConfigRef,
+    current_value: RwLock<String>,
+}
+
+impl CreatePolicyOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { 
+            config,
+            current_value: RwLock::new("ff".to_string()),
+        }
+    }
+}
+
+impl ConfigOption for CreatePolicyOption {
+    fn name(&self) -> &str {
+        "func.create"
+    }
+    
+    fn get_value(&self) -> String {
+        self.current_value.read().clone()
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        // Just validate and store the value - actual policy update is handled by ConfigManager
+        match value {
+            "ff" | "mfs" | "lfs" | "lus" | "rand" | "epff" | "epff-writable" | "epmfs" | "epmfs-or-mfs" | "eplfs" | "pfrd" | "msprand" => {
+                *self.current_value.write() = value.to_string();
+                Ok(())
+            }
+            _ => Err(ConfigError::InvalidValue(format!(
+                "Unknown create policy: {}. Valid options: ff, mfs, lfs, lus, rand, epff, epff-writable, epmfs, epmfs-or-mfs, eplfs, pfrd, msprand",
+                value
+            ))),
+        }
+    }
+    
+    fn help(&self) -> &str {
+        "Create policy: ff (first found), mfs (most free space), lfs (least free space), lus (least used space), rand (random), epff-writable (first branch whose existing parent can be written), epmfs (existing path most free space), epmfs-or-mfs (epmfs, else mfs when no branch has the parent), eplfs (existing path least free space), pfrd (proportional fill random distribution), msprand (random among branches above minfreespace)"
+    }
+}
+
+/// Option for the policy choosing where directories are created
+struct MkdirPolicyOption {
+    current_value: RwLock<String>,
+}
+
+impl MkdirPolicyOption {
+    fn new() -> Self {
+        Self {
+            current_value: RwLock::new("all".to_string()),
+        }
+    }
+}
+
+impl ConfigOption for MkdirPolicyOption {
+    fn name(&self) -> &str {
+        "func.mkdir"
+    }
+    
+    fn get_value(&self) -> String {
+        self.current_value.read().clone()
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        // Just validate and store the value - actual policy update is handled by ConfigManager
+        if mkdir_policy_from_name(value).is_none() {
+            return Err(ConfigError::InvalidValue(format!(
+                "Unknown mkdir policy: {}. Valid options: all or any create policy",
+                value
+            )));
+        }
+        *self.current_value.write() = value.to_string();
+        Ok(())
+    }
+    
+    fn help(&self) -> &str {
+        "Mkdir policy: all (every writable branch, the default) or any func.create policy to place directories like files"
+    }
+}
+
+/// Option for a search policy (func.open, func.getattr)
+struct SearchPolicyOption {
+    name: String,
+    current_value: RwLock<String>,
+}
+
+impl SearchPolicyOption {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            current_value: RwLock::new("ff".to_string()),
+        }
+    }
+}
+
+impl ConfigOption for SearchPolicyOption {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn get_value(&self) -> String {
+        self.current_value.read().clone()
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        // Just validate and store the value - actual policy update is handled by ConfigManager
+        match value {
+            "ff" | "newest" | "epnewest" | "rand" => {
+                *self.current_value.write() = value.to_string();
+                Ok(())
+            }
+            _ => Err(ConfigError::InvalidValue(format!(
+                "Unknown search policy: {}. Valid options: ff, newest, epnewest, rand",
+                value
+            ))),
+        }
+    }
+    
+    fn help(&self) -> &str {
+        "Search policy: ff (first found), newest (most recently modified), epnewest (newest of the existing copies, symlinks included), rand (random branch containing the file)"
+    }
+}
+
+/// Option for an action policy (func.chmod, func.chown, func.utimens, func.unlink, func.truncate)
+struct ActionPolicyOption {
+    name: String,
+    current_value: RwLock<String>,
+}
+
+impl ActionPolicyOption {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            current_value: RwLock::new("epall".to_string()),
+        }
+    }
+}
+
+impl ConfigOption for ActionPolicyOption {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn get_value(&self) -> String {
+        self.current_value.read().clone()
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        // Just validate and store the value - actual policy update is handled by ConfigManager
+        match value {
+            "all" | "epall" | "epff" => {
+                *self.current_value.write() = value.to_string();
+                Ok(())
+            }
+            _ => Err(ConfigError::InvalidValue(format!(
+                "Unknown action policy: {}. Valid options: all, epall, epff",
+                value
+            ))),
+        }
+    }
+    
+    fn help(&self) -> &str {
+        "Action policy: all (all writable branches where the path exists), epall (existing path on branches that allow creates), epff (first such branch only)"
+    }
+}
+
+/// Option for moveonenospc configuration
+struct MoveOnENOSPCOption {
+    config: ConfigRef,
+}
+
+impl MoveOnENOSPCOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for MoveOnENOSPCOption {
+    fn name(&self) -> &str {
+        "moveonenospc"
+    }
+    
+    fn get_value(&self) -> String {
+        let config = self.config.read();
+        if config.moveonenospc.enabled {
+            config.moveonenospc.policy_name.clone()
+        } else {
+            "false".to_string()
+        }
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        let mut config = self.config.write();
+        
+        match value.to_lowercase().as_str() {
+            "false" | "0" | "no" | "off" => {
+                config.moveonenospc.enabled = false;
+                Ok(())
+            }
+            "true" | "1" | "yes" | "on" => {
+                config.moveonenospc.enabled = true;
+                config.moveonenospc.policy_name = "pfrd".to_string(); // Default policy
+                Ok(())
+            }
+            // Check if it's a valid policy name
+            "ff" | "mfs" | "lfs" | "lus" | "rand" | "epff" | "epff-writable" | "epmfs" | "epmfs-or-mfs" | "eplfs" | "pfrd" | "msprand" => {
+                config.moveonenospc.enabled = true;
+                config.moveonenospc.policy_name = value.to_string();
+                Ok(())
+            }
+            _ => Err(ConfigError::InvalidValue(format!(
+                "Invalid moveonenospc value: {}. Use 'true', 'false', or a valid create policy name",
+                value
+            ))),
+        }
+    }
+    
+    fn help(&self) -> &str {
+        "Move files to another branch on ENOSPC. Values: true, false, or a create policy name (ff, mfs, lfs, lus, rand, epff-writable, epmfs, epmfs-or-mfs, eplfs, pfrd, msprand)"
+    }
+}
+
+/// Parse a byte count with an optional K, M, G or T suffix (powers of 1024)
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.chars().last()?.to_ascii_uppercase() {
+        'K' => (&value[..value.len() - 1], 1u64 << 10),
+        'M' => (&value[..value.len() - 1], 1u64 << 20),
+        'G' => (&value[..value.len() - 1], 1u64 << 30),
+        'T' => (&value[..value.len() - 1], 1u64 << 40),
+        _ => (value, 1),
+    };
+    digits.parse::<u64>().ok()?.checked_mul(multiplier)
+}
+
+/// Option for the minimum free space a branch must keep (minfreespace)
+struct MinFreeSpaceOption {
+    config: ConfigRef,
+}
+
+impl MinFreeSpaceOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for MinFreeSpaceOption {
+    fn name(&self) -> &str {
+        "minfreespace"
+    }
+    
+    fn get_value(&self) -> String {
+        self.config.read().minfreespace.to_string()
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        let bytes = parse_size(value).ok_or_else(|| ConfigError::InvalidValue(format!(
+            "Invalid minfreespace value: {}. Use a byte count with optional K, M, G or T suffix",
+            value
+        )))?;
+        self.config.write().minfreespace = bytes;
+        Ok(())
+    }
+    
+    fn help(&self) -> &str {
+        "Minimum free space a branch must keep to receive files moved by moveonenospc (bytes, or with K/M/G/T suffix)"
+    }
+}
+
+/// Generic boolean option
+struct BooleanOption {
+    name: String,
+    value: Arc<RwLock<bool>>,
+    help: String,
+    #[allow(dead_code)]
+    config: ConfigRef,
+}
+
+impl BooleanOption {
+    fn new(name: &str, default: bool, help: &str, config: ConfigRef) -> Self {
+        Self {
+            name: name.to_string(),
+            value: Arc::new(RwLock::new(default)),
+            help: help.to_string(),
+            config,
+        }
+    }
+}
+
+impl ConfigOption for BooleanOption {
+    fn name(&self) -> &str {
+        &self.name
+    }
+    
+    fn get_value(&self) -> String {
+        self.value.read().to_string()
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        match value.to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => {
+                *self.value.write() = true;
+                Ok(())
+            }
+            "false" | "0" | "no" | "off" => {
+                *self.value.write() = false;
+                Ok(())
+            }
+            _ => Err(ConfigError::InvalidValue(format!(
+                "Invalid boolean value: {}. Use true/false, 1/0, yes/no, or on/off",
+                value
+            ))),
+        }
+    }
+    
+    fn help(&self) -> &str {
+        &self.help
+    }
+}
+
+/// Cache files configuration option
+struct CacheFilesOption {
+    config: ConfigRef,
+}
+
+impl CacheFilesOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for CacheFilesOption {
+    fn name(&self) -> &str {
+        "cache.files"
+    }
+    
+    fn get_value(&self) -> String {
+        use crate::config::CacheFiles;
+        match self.config.read().cache_files {
+            CacheFiles::Libfuse => "libfuse".to_string(),
+            CacheFiles::Off => "off".to_string(),
+            CacheFiles::Partial => "partial".to_string(),
+            CacheFiles::Full => "full".to_string(),
+            CacheFiles::AutoFull => "auto-full".to_string(),
+            CacheFiles::PerProcess => "per-process".to_string(),
+        }
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        use crate::config::CacheFiles;
+        let cache_mode = match value.to_lowercase().as_str() {
+            "libfuse" => CacheFiles::Libfuse,
+            "off" => CacheFiles::Off,
+            "partial" => CacheFiles::Partial,
+            "full" => CacheFiles::Full,
+            "auto-full" => CacheFiles::AutoFull,
+            "per-process" => CacheFiles::PerProcess,
+            _ => return Err(ConfigError::InvalidValue(format!("Invalid cache.files value: {}", value))),
+        };
+        
+        self.config.write().cache_files = cache_mode;
+        Ok(())
+    }
+    
+    fn is_readonly(&self) -> bool {
+        false
+    }
+    
+    fn help(&self) -> &str {
+        "File caching behavior (libfuse|off|partial|full|auto-full|per-process)"
+    }
+}
+
+/// link-exdev configuration option
+struct LinkEXDEVOption {
+    config: ConfigRef,
+}
+
+impl LinkEXDEVOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for LinkEXDEVOption {
+    fn name(&self) -> &str {
+        "link-exdev"
+    }
+    
+    fn get_value(&self) -> String {
+        use crate::config::LinkEXDEV;
+        match self.config.read().link_exdev {
+            LinkEXDEV::Passthrough => "passthrough".to_string(),
+            LinkEXDEV::RelSymlink => "rel-symlink".to_string(),
+            LinkEXDEV::AbsSymlink => "abs-symlink".to_string(),
+        }
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        use crate::config::LinkEXDEV;
+        let mode = match value.to_lowercase().as_str() {
+            "passthrough" => LinkEXDEV::Passthrough,
+            "rel-symlink" => LinkEXDEV::RelSymlink,
+            "abs-symlink" => LinkEXDEV::AbsSymlink,
+            _ => return Err(ConfigError::InvalidValue(format!("Invalid link-exdev value: {}", value))),
+        };
+        
+        self.config.write().link_exdev = mode;
+        Ok(())
+    }
+    
+    fn help(&self) -> &str {
+        "What link() does when the hard link would cross branches (passthrough|rel-symlink|abs-symlink)"
+    }
+}
+
+/// How mfs and lfs choose among branches with equal free space
+struct TieBreakOption {
+    config: ConfigRef,
+}
+
+impl TieBreakOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for TieBreakOption {
+    fn name(&self) -> &str {
+        "tiebreak"
+    }
+    
+    fn get_value(&self) -> String {
+        match self.config.read().tiebreak {
+            TieBreak::First => "first".to_string(),
+            TieBreak::Random => "random".to_string(),
+        }
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        let tiebreak = match value.to_lowercase().as_str() {
+            "first" => TieBreak::First,
+            "random" => TieBreak::Random,
+            _ => return Err(ConfigError::InvalidValue(format!("Invalid tiebreak value: {}", value))),
+        };
+        
+        self.config.write().tiebreak = tiebreak;
+        Ok(())
+    }
+    
+    fn help(&self) -> &str {
+        "How mfs and lfs choose among branches with equal free space (first|random)"
+    }
+}
+
+/// Name patterns hidden from directory listings
+struct HidePatternsOption {
+    config: ConfigRef,
+}
+
+impl HidePatternsOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for HidePatternsOption {
+    fn name(&self) -> &str {
+        "hide_patterns"
+    }
+    
+    fn get_value(&self) -> String {
+        self.config.read().hide_patterns.join("|")
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        let patterns: Vec<String> = value
+            .split('|')
+            .map(|pattern| pattern.trim())
+            .filter(|pattern| !pattern.is_empty())
+            .map(|pattern| pattern.to_string())
+            .collect();
+        self.config.write().hide_patterns = patterns;
+        Ok(())
+    }
+    
+    fn help(&self) -> &str {
+        "'|' separated name patterns (* and ? wildcards) hidden from directory listings"
+    }
+}
+
+/// Process names given the page cache when cache.files=per-process
+struct CacheFilesProcessNamesOption {
+    config: ConfigRef,
+}
+
+impl CacheFilesProcessNamesOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for CacheFilesProcessNamesOption {
+    fn name(&self) -> &str {
+        "cache.files.process-names"
+    }
+    
+    fn get_value(&self) -> String {
+        self.config.read().cache_files_process_names.join("|")
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        let names: Vec<String> = value
+            .split('|')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect();
+        self.config.write().cache_files_process_names = names;
+        Ok(())
+    }
+    
+    fn help(&self) -> &str {
+        "'|' separated process names that keep the page cache when cache.files=per-process"
+    }
+}
+
+/// Inode calculation algorithm configuration option
+struct InodeCalcOption {
+    config: ConfigRef,
+}
+
+impl InodeCalcOption {
+    fn new(config: ConfigRef) -> Self {
+        Self { config }
+    }
+}
+
+impl ConfigOption for InodeCalcOption {
+    fn name(&self) -> &str {
+        "inodecalc"
+    }
+    
+    fn get_value(&self) -> String {
+        self.config.read().inodecalc.to_string().to_string()
+    }
+    
+    fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
+        use crate::inode::InodeCalc;
+        
+        // Choosing path-hash-salted again must not pick a new salt
+        if self.config.read().inodecalc.to_string() == value {
+            return Ok(());
+        }
+        match InodeCalc::from_str(value) {
+            Ok(mode) => {
+                self.config.write().inodecalc = mode;
+                Ok(())
+            }
+            Err(e) => Err(ConfigError::InvalidValue(e)),
         }
-        
-        Ok(())
     }
     
-    /// Get access to the underlying config
-    pub fn config(&self) -> &ConfigRef {
-        &self.config
+    fn help(&self) -> &str {
+        "Inode calculation algorithm (passthrough|path-hash|path-hash32|path-hash-salted|devino-hash|devino-hash32|hybrid-hash|hybrid-hash32)"
     }
 }
 
-/// Option for create policy configuration
-struct CreatePolicyOption {
-    #[allow(dead_code)]
+/// Boolean option backed by a field of the shared config
+struct ConfigFlagOption {
+    name: String,
+    help: String,
+    get: fn(&Config) -> bool,
+    set: fn(&mut Config, bool),
     config: ConfigRef,
-    current_value: RwLock<String>,
 }
 
-impl CreatePolicyOption {
-    fn new(config: ConfigRef) -> Self {
-        Self { 
+impl ConfigFlagOption {
+    fn new(name: &str, help: &str, get: fn(&Config) -> bool, set: fn(&mut Config, bool), config: ConfigRef) -> Self {
+        Self {
+            name: name.to_string(),
+            help: help.to_string(),
+            get,
+            set,
             config,
-            current_value: RwLock::new("ff".to_string()),
         }
     }
 }
 
-impl ConfigOption for CreatePolicyOption {
+impl ConfigOption for ConfigFlagOption {
     fn name(&self) -> &str {
-        "func.create"
+        &self.name
     }
     
     fn get_value(&self) -> String {
-        self.current_value.read().clone()
+        (self.get)(&self.config.read()).to_string()
     }
     
     fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
-        // Just validate and store the value - actual policy update is handled by ConfigManager
-        match value {
-            "ff" | "mfs" | "lfs" | "lus" | "rand" | "epff" | "epmfs" | "eplfs" | "pfrd" => {
-                *self.current_value.write() = value.to_string();
-                Ok(())
-            }
-            _ => Err(ConfigError::InvalidValue(format!(
-                "Unknown create policy: {}. Valid options: ff, mfs, lfs, lus, rand, epff, epmfs, eplfs, pfrd",
+        let enabled = match value.to_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => true,
+            "false" | "0" | "no" | "off" => false,
+            _ => return Err(ConfigError::InvalidValue(format!(
+                "Invalid boolean value: {}. Use true/false, 1/0, yes/no, or on/off",
                 value
             ))),
-        }
+        };
+        
+        (self.set)(&mut self.config.write(), enabled);
+        Ok(())
     }
     
     fn help(&self) -> &str {
-        "Create policy: ff (first found), mfs (most free space), lfs (least free space), lus (least used space), rand (random), epmfs (existing path most free space), eplfs (existing path least free space), pfrd (proportional fill random distribution)"
+        &self.help
     }
 }
 
-/// Option for moveonenospc configuration
-struct MoveOnENOSPCOption {
+/// Unsigned integer option backed by a field of the shared config
+struct ConfigNumberOption {
+    name: String,
+    help: String,
+    get: fn(&Config) -> u64,
+    set: fn(&mut Config, u64),
     config: ConfigRef,
 }
 
-impl MoveOnENOSPCOption {
-    fn new(config: ConfigRef) -> Self {
-        Self { config }
+impl ConfigNumberOption {
+    fn new(name: &str, help: &str, get: fn(&Config) -> u64, set: fn(&mut Config, u64), config: ConfigRef) -> Self {
+        Self {
+            name: name.to_string(),
+            help: help.to_string(),
+            get,
+            set,
+            config,
+        }
     }
 }
 
-impl ConfigOption for MoveOnENOSPCOption {
+impl ConfigOption for ConfigNumberOption {
     fn name(&self) -> &str {
-        "moveonenospc"
+        &self.name
     }
     
     fn get_value(&self) -> String {
-        let config = self.config.read();
-        if config.moveonenospc.enabled {
-            config.moveonenospc.policy_name.clone()
-        } else {
-            "false".to_string()
-        }
+        (self.get)(&self.config.read()).to_string()
     }
     
     fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
-        let mut config = self.config.write();
+        let number = value.trim().parse::<u64>().map_err(|_| ConfigError::InvalidValue(format!(
+            "Invalid value for {}: {}. Expected a non-negative integer",
+            self.name, value
+        )))?;
         
-        match value.to_lowercase().as_str() {
-            "false" | "0" | "no" | "off" => {
-                config.moveonenospc.enabled = false;
-                Ok(())
-            }
-            "true" | "1" | "yes" | "on" => {
-                config.moveonenospc.enabled = true;
-                config.moveonenospc.policy_name = "pfrd".to_string(); // Default policy
-                Ok(())
-            }
-            // Check if it's a valid policy name
-            "ff" | "mfs" | "lfs" | "lus" | "rand" | "epff" | "epmfs" | "eplfs" | "pfrd" => {
-                config.moveonenospc.enabled = true;
-                config.moveonenospc.policy_name = value.to_string();
-                Ok(())
-            }
-            _ => Err(ConfigError::InvalidValue(format!(
-                "Invalid moveonenospc value: {}. Use 'true', 'false', or a valid create policy name",
-                value
-            ))),
-        }
+        (self.set)(&mut self.config.write(), number);
+        Ok(())
     }
     
     fn help(&self) -> &str {
-        "Move files to another branch on ENOSPC. Values: true, false, or a create policy name (ff, mfs, lfs, lus, rand, epmfs, eplfs, pfrd)"
+        &self.help
     }
 }
 
-/// Generic boolean option
-struct BooleanOption {
+/// Generic option for optional octal permission bits stored in `Config`
+struct ConfigModeOption {
     name: String,
-    value: Arc<RwLock<bool>>,
     help: String,
-    #[allow(dead_code)]
+    get: fn(&Config) -> Option<u32>,
+    set: fn(&mut Config, Option<u32>),
     config: ConfigRef,
 }
 
-impl BooleanOption {
-    fn new(name: &str, default: bool, help: &str, config: ConfigRef) -> Self {
+impl ConfigModeOption {
+    fn new(name: &str, help: &str, get: fn(&Config) -> Option<u32>, set: fn(&mut Config, Option<u32>), config: ConfigRef) -> Self {
         Self {
             name: name.to_string(),
-            value: Arc::new(RwLock::new(default)),
             help: help.to_string(),
+            get,
+            set,
             config,
         }
     }
 }
 
-impl ConfigOption for BooleanOption {
+impl ConfigOption for ConfigModeOption {
     fn name(&self) -> &str {
         &self.name
     }
     
     fn get_value(&self) -> String {
-        self.value.read().to_string()
+        match (self.get)(&self.config.read()) {
+            Some(mode) => format!("{:04o}", mode),
+            None => String::new(),
+        }
     }
     
     fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
-        match value.to_lowercase().as_str() {
-            "true" | "1" | "yes" | "on" => {
-                *self.value.write() = true;
-                Ok(())
-            }
-            "false" | "0" | "no" | "off" => {
-                *self.value.write() = false;
-                Ok(())
+        let value = value.trim();
+        let mode = if value.is_empty() {
+            None
+        } else {
+            match u32::from_str_radix(value, 8) {
+                Ok(mode) if mode <= 0o7777 => Some(mode),
+                _ => return Err(ConfigError::InvalidValue(format!(
+                    "Invalid value for {}: {}. Expected octal permissions such as 0664",
+                    self.name, value
+                ))),
             }
-            _ => Err(ConfigError::InvalidValue(format!(
-                "Invalid boolean value: {}. Use true/false, 1/0, yes/no, or on/off",
-                value
-            ))),
-        }
+        };
+        
+        (self.set)(&mut self.config.write(), mode);
+        Ok(())
     }
     
     fn help(&self) -> &str {
@@ -379,108 +1725,130 @@ impl ConfigOption for BooleanOption {
     }
 }
 
-/// Cache files configuration option
-struct CacheFilesOption {
+/// Optional user or group ID stored in `Config`
+struct ConfigIdOption {
+    name: String,
+    help: String,
+    get: fn(&Config) -> Option<u32>,
+    set: fn(&mut Config, Option<u32>),
     config: ConfigRef,
 }
 
-impl CacheFilesOption {
-    fn new(config: ConfigRef) -> Self {
-        Self { config }
+impl ConfigIdOption {
+    fn new(name: &str, help: &str, get: fn(&Config) -> Option<u32>, set: fn(&mut Config, Option<u32>), config: ConfigRef) -> Self {
+        Self {
+            name: name.to_string(),
+            help: help.to_string(),
+            get,
+            set,
+            config,
+        }
     }
 }
 
-impl ConfigOption for CacheFilesOption {
+impl ConfigOption for ConfigIdOption {
     fn name(&self) -> &str {
-        "cache.files"
+        &self.name
     }
     
     fn get_value(&self) -> String {
-        use crate::config::CacheFiles;
-        match self.config.read().cache_files {
-            CacheFiles::Libfuse => "libfuse".to_string(),
-            CacheFiles::Off => "off".to_string(),
-            CacheFiles::Partial => "partial".to_string(),
-            CacheFiles::Full => "full".to_string(),
-            CacheFiles::AutoFull => "auto-full".to_string(),
-            CacheFiles::PerProcess => "per-process".to_string(),
-        }
+        (self.get)(&self.config.read()).map(|id| id.to_string()).unwrap_or_default()
     }
     
     fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
-        use crate::config::CacheFiles;
-        let cache_mode = match value.to_lowercase().as_str() {
-            "libfuse" => CacheFiles::Libfuse,
-            "off" => CacheFiles::Off,
-            "partial" => CacheFiles::Partial,
-            "full" => CacheFiles::Full,
-            "auto-full" => CacheFiles::AutoFull,
-            "per-process" => CacheFiles::PerProcess,
-            _ => return Err(ConfigError::InvalidValue(format!("Invalid cache.files value: {}", value))),
+        let value = value.trim();
+        let id = if value.is_empty() {
+            None
+        } else {
+            // u32::MAX is chown's "leave unchanged" marker, not a usable ID
+            match value.parse::<u32>() {
+                Ok(id) if id != u32::MAX => Some(id),
+                _ => return Err(ConfigError::InvalidValue(format!(
+                    "Invalid value for {}: {}. Expected a numeric ID",
+                    self.name, value
+                ))),
+            }
         };
         
-        self.config.write().cache_files = cache_mode;
+        (self.set)(&mut self.config.write(), id);
         Ok(())
     }
     
-    fn is_readonly(&self) -> bool {
-        false
-    }
-    
     fn help(&self) -> &str {
-        "File caching behavior (libfuse|off|partial|full|auto-full|per-process)"
+        &self.help
     }
 }
 
-/// Inode calculation algorithm configuration option
-struct InodeCalcOption {
+/// Optional byte count with a K, M, G or T suffix; an empty value unsets it
+struct ConfigSizeOption {
+    name: String,
+    help: String,
+    get: fn(&Config) -> Option<u64>,
+    set: fn(&mut Config, Option<u64>),
     config: ConfigRef,
 }
 
-impl InodeCalcOption {
-    fn new(config: ConfigRef) -> Self {
-        Self { config }
+impl ConfigSizeOption {
+    fn new(name: &str, help: &str, get: fn(&Config) -> Option<u64>, set: fn(&mut Config, Option<u64>), config: ConfigRef) -> Self {
+        Self {
+            name: name.to_string(),
+            help: help.to_string(),
+            get,
+            set,
+            config,
+        }
     }
 }
 
-impl ConfigOption for InodeCalcOption {
+impl ConfigOption for ConfigSizeOption {
     fn name(&self) -> &str {
-        "inodecalc"
+        &self.name
     }
     
     fn get_value(&self) -> String {
-        self.config.read().inodecalc.to_string().to_string()
+        (self.get)(&self.config.read()).map(|bytes| bytes.to_string()).unwrap_or_default()
     }
     
     fn set_value(&mut self, value: &str) -> Result<(), ConfigError> {
-        use crate::inode::InodeCalc;
+        let value = value.trim();
+        let bytes = if value.is_empty() {
+            None
+        } else {
+            Some(parse_size(value).ok_or_else(|| ConfigError::InvalidValue(format!(
+                "Invalid value for {}: {}. Use a byte count with optional K, M, G or T suffix",
+                self.name, value
+            )))?)
+        };
         
-        match InodeCalc::from_str(value) {
-            Ok(mode) => {
-                self.config.write().inodecalc = mode;
-                Ok(())
-            }
-            Err(e) => Err(ConfigError::InvalidValue(e)),
-        }
+        (self.set)(&mut self.config.write(), bytes);
+        Ok(())
     }
     
     fn help(&self) -> &str {
-        "Inode calculation algorithm (passthrough|path-hash|path-hash32|devino-hash|devino-hash32|hybrid-hash|hybrid-hash32)"
+        &self.help
     }
 }
 
-/// Read-only option that returns a fixed value
+/// Read-only option returning a fixed value, or one sampled on each read
 struct ReadOnlyOption {
     name: String,
-    value: String,
+    read: Box<dyn Fn() -> String + Send + Sync>,
     help: String,
 }
 
 impl ReadOnlyOption {
     fn new(name: &str, value: &str, help: &str) -> Self {
+        let value = value.to_string();
+        Self::sampled(name, help, move || value.clone())
+    }
+    
+    fn sampled<F>(name: &str, help: &str, read: F) -> Self
+    where
+        F: Fn() -> String + Send + Sync + 'static,
+    {
         Self {
             name: name.to_string(),
-            value: value.to_string(),
+            read: Box::new(read),
             help: help.to_string(),
         }
     }
@@ -492,7 +1860,7 @@ impl ConfigOption for ReadOnlyOption {
     }
     
     fn get_value(&self) -> String {
-        self.value.clone()
+        (self.read)()
     }
     
     fn set_value(&mut self, _value: &str) -> Result<(), ConfigError> {
@@ -615,10 +1983,161 @@ mod tests {
         assert!(manager.set_option("func.create", "lfs").is_ok());
         assert!(manager.set_option("func.create", "rand").is_ok());
         assert!(manager.set_option("func.create", "epmfs").is_ok());
+        assert!(manager.set_option("func.create", "epmfs-or-mfs").is_ok());
+        assert!(manager.set_option("func.create", "epff-writable").is_ok());
+        assert!(manager.set_option("func.create", "msprand").is_ok());
         
         // Test invalid policy
         assert!(manager.set_option("func.create", "invalid").is_err());
     }
+    
+    #[test]
+    fn test_link_cow_option() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("link-cow").unwrap(), "false");
+        
+        assert!(manager.set_option("link-cow", "true").is_ok());
+        assert!(config.read().link_cow);
+        assert_eq!(manager.get_option("user.mergerfs.link-cow").unwrap(), "true");
+        
+        assert!(manager.set_option("link-cow", "off").is_ok());
+        assert!(!config.read().link_cow);
+        
+        assert!(manager.set_option("link-cow", "maybe").is_err());
+    }
+    
+    #[test]
+    fn test_dropcacheonclose_option() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("dropcacheonclose").unwrap(), "false");
+        assert!(manager.set_option("dropcacheonclose", "true").is_ok());
+        assert!(config.read().dropcacheonclose);
+        assert!(!config.read().link_cow);
+    }
+    
+    #[test]
+    fn test_sync_on_close_option() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("sync_on_close").unwrap(), "false");
+        manager.set_option("sync_on_close", "true").unwrap();
+        assert!(config.read().sync_on_close);
+    }
+    
+    #[test]
+    fn test_xattr_union_option() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("xattr.union").unwrap(), "false");
+        manager.set_option("xattr.union", "true").unwrap();
+        assert!(config.read().xattr_union);
+    }
+    
+    #[test]
+    fn test_readahead_option() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("readahead").unwrap(), "0");
+        manager.set_option("readahead", "1024").unwrap();
+        assert_eq!(config.read().readahead, 1024);
+        assert!(manager.set_option("readahead", "-1").is_err());
+    }
+    
+    #[test]
+    fn test_create_mode_options() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("default_mode").unwrap(), "");
+        assert_eq!(config.read().create_mode(0o100666, 0o022), 0o644);
+        
+        // default_mode only fills in for a client mode of 0
+        manager.set_option("default_mode", "0664").unwrap();
+        assert_eq!(manager.get_option("default_mode").unwrap(), "0664");
+        assert_eq!(config.read().create_mode(0, 0o022), 0o664);
+        assert_eq!(config.read().create_mode(0o100600, 0o022), 0o600);
+        
+        // force_mode overrides whatever the client asked for
+        manager.set_option("force_mode", "775").unwrap();
+        assert_eq!(config.read().force_mode, Some(0o775));
+        assert_eq!(config.read().create_mode(0o100600, 0o022), 0o775);
+        assert_eq!(config.read().create_mode(0, 0), 0o775);
+        
+        manager.set_option("force_mode", "").unwrap();
+        assert_eq!(config.read().force_mode, None);
+        assert!(manager.set_option("force_mode", "0888").is_err());
+        assert!(manager.set_option("default_mode", "17777").is_err());
+    }
+    
+    #[test]
+    fn test_minfreespace_option() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("minfreespace").unwrap(), "0");
+        manager.set_option("minfreespace", "4G").unwrap();
+        assert_eq!(config.read().minfreespace, 4 << 30);
+        manager.set_option("minfreespace", "512").unwrap();
+        assert_eq!(manager.get_option("minfreespace").unwrap(), "512");
+        assert!(manager.set_option("minfreespace", "lots").is_err());
+    }
+    
+    #[test]
+    fn test_cache_files_process_names_option() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config.clone());
+        
+        assert_eq!(manager.get_option("cache.files.process-names").unwrap(), "rtorrent|qbittorrent-nox");
+        manager.set_option("cache.files.process-names", "rsync| mpv").unwrap();
+        assert_eq!(config.read().cache_files_process_names, vec!["rsync", "mpv"]);
+        assert_eq!(manager.get_option("cache.files.process-names").unwrap(), "rsync|mpv");
+    }
+    
+    #[test]
+    fn test_config_json_dump() {
+        let config = config::create_config();
+        let manager = ConfigManager::new(config);
+        manager.set_option("func.create", "mfs").unwrap();
+        
+        assert!(manager.list_options().contains(&"user.mergerfs.config".to_string()));
+        
+        let json = manager.get_option("user.mergerfs.config").unwrap();
+        let dump: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let options = dump.as_array().unwrap();
+        
+        let create = options.iter().find(|o| o["name"] == "func.create").unwrap();
+        assert_eq!(create["value"], "mfs");
+        assert_eq!(create["readonly"], false);
+        assert!(!create["help"].as_str().unwrap().is_empty());
+        
+        let version = options.iter().find(|o| o["name"] == "version").unwrap();
+        assert_eq!(version["readonly"], true);
+        
+        // The dump itself is not an option and can't be written
+        assert!(options.iter().all(|o| o["name"] != "config"));
+        assert!(matches!(manager.set_option("config", "{}"), Err(ConfigError::ReadOnly)));
+    }
+    
+    #[test]
+    fn test_option_help_query() {
+        let manager = ConfigManager::new(config::create_config());
+        let info = manager.option_infos().into_iter().find(|o| o.name == "func.create").unwrap();
+        
+        let help = manager.get_option("user.mergerfs.help.func.create").unwrap();
+        assert_eq!(help, info.help);
+        assert!(help.contains("epmfs-or-mfs"));
+        
+        assert!(matches!(manager.get_option("help.no.such.option"), Err(ConfigError::NotFound)));
+        assert!(matches!(manager.set_option("help.func.create", "x"), Err(ConfigError::ReadOnly)));
+        assert_eq!(manager.get_option("func.create").unwrap(), "ff");
+    }
 }
 
 /// StatFS mode configuration option
@@ -642,6 +2161,7 @@ impl ConfigOption for StatFSModeOption {
         match self.config.read().statfs_mode {
             StatFSMode::Base => "base".to_string(),
             StatFSMode::Full => "full".to_string(),
+            StatFSMode::Largest => "largest".to_string(),
         }
     }
     
@@ -650,6 +2170,7 @@ impl ConfigOption for StatFSModeOption {
         let mode = match value.to_lowercase().as_str() {
             "base" => StatFSMode::Base,
             "full" => StatFSMode::Full,
+            "largest" => StatFSMode::Largest,
             _ => return Err(ConfigError::InvalidValue(format!("Invalid statfs mode: {}", value))),
         };
         
@@ -658,7 +2179,7 @@ impl ConfigOption for StatFSModeOption {
     }
     
     fn help(&self) -> &str {
-        "StatFS mode (base|full) - controls how filesystem statistics are reported"
+        "StatFS mode (base|full|largest) - controls how filesystem statistics are reported"
     }
 }
 
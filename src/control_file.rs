@@ -1,5 +1,8 @@
-use crate::config_manager::ConfigManager;
+use crate::branch::BranchMode;
+use crate::config_manager::{ConfigError, ConfigManager};
+use crate::errno::{ToErrno, EACCES, EINVAL, ENOTSUP, ENOTTY, ERANGE};
 use fuser::{FileAttr, FileType, ReplyAttr, ReplyData, ReplyEmpty, ReplyXattr};
+use serde::Serialize;
 use std::ffi::OsStr;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
@@ -7,10 +10,28 @@ use std::time::{Duration, SystemTime};
 // Constants
 pub const CONTROL_FILE_INO: u64 = u64::MAX; // Special inode for /.mergerfs
 const TTL: Duration = Duration::from_secs(1);
-const EINVAL: i32 = 22;
-const ENOTSUP: i32 = 95;
-const EACCES: i32 = 13;
-const ERANGE: i32 = 34;
+
+// ioctl commands on the control file
+//
+// Commands are encoded like the kernel's _IOR('M', nr, buf): the caller passes a
+// buffer of IOCTL_BUF_SIZE bytes and the reply is UTF-8 JSON (not NUL terminated)
+// whose length is returned as the ioctl result. ERANGE is returned if the JSON
+// doesn't fit the caller's buffer and ENOTTY for unknown commands.
+pub const IOCTL_BUF_SIZE: u32 = 0x3FFF; // Largest size the 14-bit field can encode
+const fn ior(nr: u32) -> u32 {
+    (2 << 30) | (IOCTL_BUF_SIZE << 16) | ((b'M' as u32) << 8) | nr
+}
+/// Dump all config options as a JSON array of {name, value, readonly, help}
+pub const IOCTL_GET_CONFIG_JSON: u32 = ior(1);
+/// List branches as a JSON array of {path, mode, free_space}
+pub const IOCTL_GET_BRANCHES_JSON: u32 = ior(2);
+
+#[derive(Serialize)]
+struct BranchInfo {
+    path: String,
+    mode: &'static str,
+    free_space: Option<u64>,
+}
 
 /// Handles all operations related to the .mergerfs control file
 pub struct ControlFileHandler {
@@ -94,9 +115,12 @@ impl ControlFileHandler {
                         reply.data(value_bytes);
                     }
                 }
-                Err(_) => {
+                Err(ConfigError::NotFound) => {
                     reply.error(ENOTSUP);
                 }
+                Err(e) => {
+                    reply.error(e.errno());
+                }
             }
         } else {
             reply.error(ENOTSUP);
@@ -167,6 +191,36 @@ impl ControlFileHandler {
         reply.error(ENOTSUP);
     }
     
+    /// Handle ioctl for control file, returning the reply payload or an errno
+    pub fn handle_ioctl(&self, cmd: u32, out_size: u32) -> Result<Vec<u8>, i32> {
+        let json = match cmd {
+            IOCTL_GET_CONFIG_JSON => self.config_manager.to_json(),
+            IOCTL_GET_BRANCHES_JSON => self.branches_json()?,
+            _ => return Err(ENOTTY),
+        };
+        
+        if json.len() > out_size as usize {
+            return Err(ERANGE);
+        }
+        Ok(json.into_bytes())
+    }
+    
+    fn branches_json(&self) -> Result<String, i32> {
+        let file_manager = self.config_manager.file_manager().ok_or(ENOTSUP)?;
+        let branches: Vec<BranchInfo> = file_manager.branches().iter()
+            .map(|branch| BranchInfo {
+                path: branch.path.display().to_string(),
+                mode: match branch.mode {
+                    BranchMode::ReadWrite => "RW",
+                    BranchMode::ReadOnly => "RO",
+                    BranchMode::NoCreate => "NC",
+                },
+                free_space: branch.free_space().ok(),
+            })
+            .collect();
+        serde_json::to_string(&branches).map_err(|_| EINVAL)
+    }
+    
     /// Handle access for control file
     pub fn handle_access(&self, mask: i32, reply: ReplyEmpty) {
         // Control file is readable for all
@@ -206,4 +260,41 @@ mod tests {
         assert_eq!(attr.uid, 0);
         assert_eq!(attr.gid, 0);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_ioctl_config_and_branches_json() {
+        use crate::branch::Branch;
+        use crate::file_ops::FileManager;
+        use crate::policy::FirstFoundCreatePolicy;
+        use tempfile::TempDir;
+        
+        let temp1 = TempDir::new().unwrap();
+        let temp2 = TempDir::new().unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(temp1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(temp2.path().to_path_buf(), BranchMode::ReadOnly)),
+        ];
+        let file_manager = Arc::new(FileManager::new(branches, Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(config::create_config());
+        config_manager.set_file_manager(&file_manager);
+        let handler = ControlFileHandler::new(Arc::new(config_manager));
+        
+        let data = handler.handle_ioctl(IOCTL_GET_CONFIG_JSON, IOCTL_BUF_SIZE).unwrap();
+        let options: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        let create = options.as_array().unwrap().iter()
+            .find(|o| o["name"] == "func.create")
+            .unwrap();
+        assert_eq!(create["value"], "ff");
+        assert_eq!(create["readonly"], false);
+        
+        let data = handler.handle_ioctl(IOCTL_GET_BRANCHES_JSON, IOCTL_BUF_SIZE).unwrap();
+        let branches: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(branches[0]["path"], temp1.path().display().to_string());
+        assert_eq!(branches[0]["mode"], "RW");
+        assert_eq!(branches[1]["mode"], "RO");
+        assert!(branches[0]["free_space"].as_u64().is_some());
+        
+        assert_eq!(handler.handle_ioctl(IOCTL_GET_CONFIG_JSON, 8), Err(ERANGE));
+        assert_eq!(handler.handle_ioctl(0x1234, IOCTL_BUF_SIZE), Err(ENOTTY));
+    }
+}
@@ -35,7 +35,7 @@ mod directory_ops_tests {
 
     #[test]
     #[serial]
-    fn test_mkdir_creates_directory_in_first_writable_branch() {
+    fn test_mkdir_creates_directory_in_all_writable_branches() {
         let (temp_dirs, file_manager) = setup_test_dirs();
 
         // Test creating a directory
@@ -43,18 +43,28 @@ mod directory_ops_tests {
         let result = file_manager.create_directory(dir_path);
         assert!(result.is_ok(), "Should be able to create directory: {:?}", result);
 
-        // Verify directory was created in first writable branch (branch1)
-        let expected_path = temp_dirs[0].path().join("test_directory");
-        assert!(expected_path.exists(), "Directory should exist in first branch");
-        assert!(expected_path.is_dir(), "Path should be a directory");
-
-        // Verify directory was NOT created in other branches
-        let path2 = temp_dirs[1].path().join("test_directory");
+        // The default mkdir policy places it on every writable branch
+        for temp_dir in &temp_dirs[..2] {
+            let expected_path = temp_dir.path().join("test_directory");
+            assert!(expected_path.is_dir(), "Directory should exist in {:?}", temp_dir.path());
+        }
         let path3 = temp_dirs[2].path().join("test_directory");
-        assert!(!path2.exists(), "Directory should NOT exist in second branch");
         assert!(!path3.exists(), "Directory should NOT exist in readonly branch");
     }
 
+    #[test]
+    #[serial]
+    fn test_mkdir_with_create_policy_uses_first_writable_branch() {
+        let (temp_dirs, file_manager) = setup_test_dirs();
+        file_manager.set_mkdir_policy(Box::new(FirstFoundCreatePolicy));
+
+        file_manager.create_directory(Path::new("test_directory")).unwrap();
+
+        assert!(temp_dirs[0].path().join("test_directory").is_dir());
+        assert!(!temp_dirs[1].path().join("test_directory").exists());
+        assert!(!temp_dirs[2].path().join("test_directory").exists());
+    }
+
     #[test]
     #[serial]
     fn test_mkdir_nested_directories() {
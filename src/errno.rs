@@ -0,0 +1,184 @@
+//! errno values and the error-to-errno mapping used by every FUSE reply
+//!
+//! Values are hardcoded (Linux x86_64/aarch64) instead of coming from libc
+//! for MUSL compatibility.
+
+use std::io;
+
+use crate::config_manager::ConfigError;
+use crate::permissions::AccessError;
+use crate::policy::PolicyError;
+use crate::rename_ops::RenameError;
+use crate::xattr::XattrError;
+
+pub const EPERM: i32 = 1;         // Operation not permitted
+pub const ENOENT: i32 = 2;        // No such file or directory
+pub const EIO: i32 = 5;           // I/O error
+pub const E2BIG: i32 = 7;         // Argument list too long
+pub const EBADF: i32 = 9;         // Bad file descriptor
+pub const EACCES: i32 = 13;       // Permission denied
+pub const EEXIST: i32 = 17;       // File exists
+pub const EXDEV: i32 = 18;        // Cross-device link
+pub const ENOTDIR: i32 = 20;      // Not a directory
+pub const EISDIR: i32 = 21;       // Is a directory
+pub const EINVAL: i32 = 22;       // Invalid argument
+pub const ENOTTY: i32 = 25;       // Inappropriate ioctl for device
+pub const ENOSPC: i32 = 28;       // No space left on device
+pub const EROFS: i32 = 30;        // Read-only file system
+pub const ERANGE: i32 = 34;       // Result too large
+pub const ENAMETOOLONG: i32 = 36; // File name too long
+pub const ENOSYS: i32 = 38;       // Function not implemented
+pub const ENOTEMPTY: i32 = 39;    // Directory not empty
+pub const ENODATA: i32 = 61;      // No data available
+pub const ENOATTR: i32 = ENODATA; // No such attribute (Linux alias of ENODATA)
+pub const ENOTSUP: i32 = 95;      // Operation not supported
+pub const EDQUOT: i32 = 122;      // Disk quota exceeded
+
+/// Errors that can be answered to the kernel as an errno
+pub trait ToErrno {
+    fn errno(&self) -> i32;
+}
+
+impl ToErrno for io::Error {
+    fn errno(&self) -> i32 {
+        // Errors built from a kind rather than a syscall carry no raw code
+        self.raw_os_error().unwrap_or(match self.kind() {
+            io::ErrorKind::NotFound => ENOENT,
+            io::ErrorKind::PermissionDenied => EACCES,
+            io::ErrorKind::AlreadyExists => EEXIST,
+            io::ErrorKind::CrossesDevices => EXDEV,
+            io::ErrorKind::InvalidInput => EINVAL,
+            _ => EIO,
+        })
+    }
+}
+
+impl ToErrno for PolicyError {
+    fn errno(&self) -> i32 {
+        match self {
+            PolicyError::NoBranchesAvailable => ENOENT,
+            PolicyError::ReadOnlyFilesystem => EROFS,
+            PolicyError::PermissionDenied => EACCES,
+            PolicyError::PathNotFound => ENOENT,
+            PolicyError::NoSpace => ENOSPC,
+            PolicyError::IoError(e) => e.errno(),
+        }
+    }
+}
+
+impl ToErrno for RenameError {
+    fn errno(&self) -> i32 {
+        match self {
+            RenameError::NotFound => ENOENT,
+            RenameError::PermissionDenied => EACCES,
+            RenameError::ReadOnly => EROFS,
+            RenameError::NoSpace => ENOSPC,
+            RenameError::CrossDevice => EXDEV,
+            RenameError::DestinationExists => EEXIST,
+            RenameError::InvalidPath => EINVAL,
+            RenameError::IsDirectory => EISDIR,
+            RenameError::NotDirectory => ENOTDIR,
+            RenameError::NotEmpty => ENOTEMPTY,
+            RenameError::Io(e) => e.errno(),
+            RenameError::Policy(e) => e.errno(),
+        }
+    }
+}
+
+impl ToErrno for XattrError {
+    fn errno(&self) -> i32 {
+        match self {
+            XattrError::NotFound => ENOATTR,
+            XattrError::PermissionDenied => EPERM,
+            XattrError::NameTooLong => ENAMETOOLONG,
+            XattrError::ValueTooLarge => E2BIG,
+            XattrError::NotSupported => ENOTSUP,
+            XattrError::InvalidArgument => EINVAL,
+            XattrError::Io(e) => e.errno(),
+        }
+    }
+}
+
+impl ToErrno for ConfigError {
+    fn errno(&self) -> i32 {
+        match self {
+            ConfigError::NotFound => ENOATTR,
+            ConfigError::InvalidValue(_) => EINVAL,
+            ConfigError::ReadOnly => EROFS,
+            ConfigError::NotSupported => ENOTSUP,
+            ConfigError::Policy(e) => e.errno(),
+        }
+    }
+}
+
+impl ToErrno for AccessError {
+    fn errno(&self) -> i32 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_error_mapping() {
+        assert_eq!(io::Error::from_raw_os_error(ENOTEMPTY).errno(), ENOTEMPTY);
+        assert_eq!(io::Error::from(io::ErrorKind::NotFound).errno(), ENOENT);
+        assert_eq!(io::Error::from(io::ErrorKind::PermissionDenied).errno(), EACCES);
+        assert_eq!(io::Error::from(io::ErrorKind::AlreadyExists).errno(), EEXIST);
+        assert_eq!(io::Error::other("seek failed").errno(), EIO);
+    }
+
+    #[test]
+    fn test_policy_error_mapping() {
+        assert_eq!(PolicyError::NoBranchesAvailable.errno(), ENOENT);
+        assert_eq!(PolicyError::ReadOnlyFilesystem.errno(), EROFS);
+        assert_eq!(PolicyError::PermissionDenied.errno(), EACCES);
+        assert_eq!(PolicyError::PathNotFound.errno(), ENOENT);
+        assert_eq!(PolicyError::NoSpace.errno(), ENOSPC);
+        assert_eq!(PolicyError::IoError(io::Error::from_raw_os_error(EDQUOT)).errno(), EDQUOT);
+    }
+
+    #[test]
+    fn test_rename_error_mapping() {
+        assert_eq!(RenameError::NotFound.errno(), ENOENT);
+        assert_eq!(RenameError::PermissionDenied.errno(), EACCES);
+        assert_eq!(RenameError::ReadOnly.errno(), EROFS);
+        assert_eq!(RenameError::NoSpace.errno(), ENOSPC);
+        assert_eq!(RenameError::CrossDevice.errno(), EXDEV);
+        assert_eq!(RenameError::DestinationExists.errno(), EEXIST);
+        assert_eq!(RenameError::InvalidPath.errno(), EINVAL);
+        assert_eq!(RenameError::IsDirectory.errno(), EISDIR);
+        assert_eq!(RenameError::NotDirectory.errno(), ENOTDIR);
+        assert_eq!(RenameError::NotEmpty.errno(), ENOTEMPTY);
+        assert_eq!(RenameError::Io(io::Error::from_raw_os_error(EXDEV)).errno(), EXDEV);
+        // Policy failures keep their own code instead of collapsing to EIO
+        assert_eq!(RenameError::Policy(PolicyError::ReadOnlyFilesystem).errno(), EROFS);
+    }
+
+    #[test]
+    fn test_xattr_error_mapping() {
+        assert_eq!(XattrError::NotFound.errno(), ENOATTR);
+        assert_eq!(XattrError::PermissionDenied.errno(), EPERM);
+        assert_eq!(XattrError::NameTooLong.errno(), ENAMETOOLONG);
+        assert_eq!(XattrError::ValueTooLarge.errno(), E2BIG);
+        assert_eq!(XattrError::NotSupported.errno(), ENOTSUP);
+        assert_eq!(XattrError::InvalidArgument.errno(), EINVAL);
+        assert_eq!(XattrError::Io(io::Error::from_raw_os_error(ERANGE)).errno(), ERANGE);
+    }
+
+    #[test]
+    fn test_config_error_mapping() {
+        assert_eq!(ConfigError::NotFound.errno(), ENOATTR);
+        assert_eq!(ConfigError::InvalidValue("x".to_string()).errno(), EINVAL);
+        assert_eq!(ConfigError::ReadOnly.errno(), EROFS);
+        assert_eq!(ConfigError::NotSupported.errno(), ENOTSUP);
+        assert_eq!(ConfigError::Policy(PolicyError::NoSpace).errno(), ENOSPC);
+    }
+
+    #[test]
+    fn test_access_error_mapping() {
+        assert_eq!(AccessError(EACCES).errno(), EACCES);
+    }
+}
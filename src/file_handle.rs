@@ -1,7 +1,16 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
-use parking_lot::RwLock;
+use nix::sys::uio::{pread, pwrite};
+use parking_lot::{Mutex, RwLock};
+use crate::errno::EIO;
+
+/// Largest amount of data a handle's write buffer holds before it is written out
+pub const WRITE_BUFFER_CAPACITY: usize = 128 * 1024;
 
 #[derive(Debug, Clone)]
 pub struct FileHandle {
@@ -10,6 +19,247 @@ pub struct FileHandle {
     pub flags: i32,
     pub branch_idx: Option<usize>,  // Which branch the file was opened from
     pub direct_io: bool,
+    pub file: Option<Arc<File>>,    // Underlying branch file, kept open until release
+    pub write_buffer: Arc<Mutex<WriteBuffer>>,  // Pending writes when writeback is enabled
+}
+
+/// Contiguous small writes waiting to be written to a handle's file as one pwrite
+#[derive(Debug, Default)]
+pub struct WriteBuffer {
+    offset: i64,
+    data: Vec<u8>,
+    flushes: u64,
+}
+
+impl WriteBuffer {
+    /// Queue `data` at `offset`, first writing out pending data it does not continue
+    ///
+    /// Writes of at least `WRITE_BUFFER_CAPACITY` bytes bypass the buffer, and
+    /// the buffer is written out as soon as it reaches that size.
+    pub fn write(&mut self, file: &File, data: &[u8], offset: i64) -> io::Result<usize> {
+        if !self.data.is_empty() && offset != self.offset + self.data.len() as i64 {
+            self.flush(file)?;
+        }
+        
+        if data.len() >= WRITE_BUFFER_CAPACITY {
+            self.flush(file)?;
+            return pwrite_all(file, data, offset);
+        }
+        
+        if self.data.is_empty() {
+            self.offset = offset;
+        }
+        self.data.extend_from_slice(data);
+        
+        if self.data.len() >= WRITE_BUFFER_CAPACITY {
+            self.flush(file)?;
+        }
+        Ok(data.len())
+    }
+    
+    /// Write out pending data; it is kept for a later attempt if the write fails
+    pub fn flush(&mut self, file: &File) -> io::Result<()> {
+        if self.data.is_empty() {
+            return Ok(());
+        }
+        
+        pwrite_all(file, &self.data, self.offset)?;
+        self.data.clear();
+        self.flushes += 1;
+        Ok(())
+    }
+    
+    /// Number of bytes waiting to be written
+    pub fn pending(&self) -> usize {
+        self.data.len()
+    }
+    
+    /// Number of times buffered data has been written to the file
+    pub fn flush_count(&self) -> u64 {
+        self.flushes
+    }
+}
+
+/// Write out a handle's buffered data, if it has an open file
+pub fn flush_write_buffer(handle: &FileHandle) -> io::Result<()> {
+    match &handle.file {
+        Some(file) => handle.write_buffer.lock().flush(file),
+        None => Ok(()),
+    }
+}
+
+/// O_DIRECT open flag for the target architecture
+///
+/// Taken from libc rather than hardcoded: the value differs between
+/// architectures (0o40000 on x86, but O_DIRECTORY on aarch64, where
+/// O_DIRECT is 0o200000).
+pub const O_DIRECT: i32 = nix::libc::O_DIRECT;
+
+/// Whether the client asked for O_DIRECT
+///
+/// O_DIRECT is emulated rather than passed to the branch: FUSE hands us
+/// requests with arbitrary offsets, lengths and buffers, which real O_DIRECT
+/// would reject with EINVAL. The branch file is opened buffered and the
+/// handle is served with direct_io instead, so the kernel page cache is
+/// bypassed for the client as it asked.
+pub fn wants_direct_io(flags: i32) -> bool {
+    flags & O_DIRECT != 0
+}
+
+/// Open a branch file using the access mode from the FUSE open flags
+///
+/// Creation and truncation flags are ignored since the kernel has already
+/// handled them (or they are applied by `create`), and O_DIRECT is emulated
+/// (see `wants_direct_io`).
+pub fn open_branch_file(full_path: &Path, flags: i32) -> io::Result<File> {
+    // Use hardcoded constants for MUSL compatibility
+    const O_ACCMODE: i32 = 3;
+    const O_RDONLY: i32 = 0;
+    const O_WRONLY: i32 = 1;
+    
+    let access = flags & O_ACCMODE;
+    OpenOptions::new()
+        .read(access != O_WRONLY)
+        .write(access != O_RDONLY)
+        .open(full_path)
+}
+
+/// Read into `buf` at `offset`, retrying short reads until EOF or the buffer is full
+pub fn pread_full(file: &File, buf: &mut [u8], offset: i64) -> io::Result<usize> {
+    read_full_at(|buf, offset| pread(file, buf, offset).map_err(io::Error::from), buf, offset)
+}
+
+/// Fill `buf` from `offset` with repeated `read_at` calls
+///
+/// A read returning fewer bytes than asked is legal before EOF, so only a
+/// zero-length read ends the loop early. Interrupted reads are retried.
+pub fn read_full_at<F>(mut read_at: F, buf: &mut [u8], offset: i64) -> io::Result<usize>
+where
+    F: FnMut(&mut [u8], i64) -> io::Result<usize>,
+{
+    let mut total = 0;
+    while total < buf.len() {
+        match read_at(&mut buf[total..], offset + total as i64) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+/// Read back `data.len()` bytes at `offset` from `full_path` and compare them with `data`
+///
+/// A short read or any differing byte is reported as EIO.
+pub fn verify_written(full_path: &Path, data: &[u8], offset: i64) -> io::Result<()> {
+    let file = File::open(full_path)?;
+    let mut readback = vec![0u8; data.len()];
+    let read = pread_full(&file, &mut readback, offset)?;
+    if read != data.len() || readback != data {
+        tracing::error!("write verification failed for {:?} at offset {} ({} bytes)", full_path, offset, data.len());
+        return Err(io::Error::from_raw_os_error(EIO));
+    }
+    Ok(())
+}
+
+/// Write all of `data` at `offset`
+pub fn pwrite_all(file: &File, data: &[u8], offset: i64) -> io::Result<usize> {
+    let mut total = 0;
+    while total < data.len() {
+        match pwrite(file, &data[total..], offset + total as i64) {
+            Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "pwrite wrote 0 bytes")),
+            Ok(n) => total += n,
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(e) => return Err(io::Error::from(e)),
+        }
+    }
+    Ok(total)
+}
+
+/// Ask the kernel to drop cached pages for the whole file
+pub fn fadvise_dontneed(fd: RawFd) -> nix::Result<()> {
+    use nix::fcntl::{posix_fadvise, PosixFadviseAdvice};
+    posix_fadvise(fd, 0, 0, PosixFadviseAdvice::POSIX_FADV_DONTNEED)
+}
+
+/// Advise sequential access and prefetch the first `len` bytes of the file
+pub fn fadvise_readahead(fd: RawFd, len: i64) -> nix::Result<()> {
+    use nix::fcntl::{posix_fadvise, PosixFadviseAdvice};
+    posix_fadvise(fd, 0, 0, PosixFadviseAdvice::POSIX_FADV_SEQUENTIAL)?;
+    posix_fadvise(fd, 0, len, PosixFadviseAdvice::POSIX_FADV_WILLNEED)
+}
+
+/// Apply the readahead setting to a file just opened with `flags`
+///
+/// Only read-only opens are advised, with a window of `readahead_kib` KiB.
+/// As with `drop_cache_on_close` the advice function is passed in.
+/// Returns true if the advice was issued.
+pub fn advise_readahead<F>(file: &File, flags: i32, readahead_kib: u64, fadvise: F) -> bool
+where
+    F: FnOnce(RawFd, i64) -> nix::Result<()>,
+{
+    const O_ACCMODE: i32 = 3;
+    const O_RDONLY: i32 = 0;
+    
+    if readahead_kib == 0 || flags & O_ACCMODE != O_RDONLY {
+        return false;
+    }
+    
+    let len = readahead_kib.saturating_mul(1024).min(i64::MAX as u64) as i64;
+    if let Err(e) = fadvise(file.as_raw_fd(), len) {
+        tracing::warn!("posix_fadvise(SEQUENTIAL/WILLNEED) failed: {:?}", e);
+    }
+    true
+}
+
+/// Drop the page cache for a handle being released (dropcacheonclose)
+///
+/// Only handles opened for reading with an open branch file are affected.
+/// The advice function is passed in so callers (and tests) can substitute it.
+/// Returns true if the advice was issued.
+pub fn drop_cache_on_close<F>(handle: &FileHandle, enabled: bool, fadvise: F) -> bool
+where
+    F: FnOnce(RawFd) -> nix::Result<()>,
+{
+    const O_ACCMODE: i32 = 3;
+    const O_WRONLY: i32 = 1;
+    
+    if !enabled || handle.flags & O_ACCMODE == O_WRONLY {
+        return false;
+    }
+    
+    match &handle.file {
+        Some(file) => {
+            if let Err(e) = fadvise(file.as_raw_fd()) {
+                tracing::warn!("posix_fadvise(DONTNEED) failed for {:?}: {:?}", handle.path, e);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Sync a handle being released to its branch (sync_on_close)
+///
+/// Only handles opened for writing with an open branch file are synced.
+/// As with `drop_cache_on_close` the sync function is passed in.
+/// Returns true if the sync was issued.
+pub fn sync_on_close<F>(handle: &FileHandle, enabled: bool, sync: F) -> io::Result<bool>
+where
+    F: FnOnce(&File) -> io::Result<()>,
+{
+    const O_ACCMODE: i32 = 3;
+    const O_RDONLY: i32 = 0;
+    
+    if !enabled || handle.flags & O_ACCMODE == O_RDONLY {
+        return Ok(false);
+    }
+    
+    match &handle.file {
+        Some(file) => sync(file).map(|()| true),
+        None => Ok(false),
+    }
 }
 
 pub struct FileHandleManager {
@@ -26,6 +276,11 @@ impl FileHandleManager {
     }
 
     pub fn create_handle(&self, ino: u64, path: PathBuf, flags: i32, branch_idx: Option<usize>, direct_io: bool) -> u64 {
+        self.create_handle_with_file(ino, path, flags, branch_idx, direct_io, None)
+    }
+
+    /// Create a handle that keeps the underlying branch file open for reuse by read/write
+    pub fn create_handle_with_file(&self, ino: u64, path: PathBuf, flags: i32, branch_idx: Option<usize>, direct_io: bool, file: Option<File>) -> u64 {
         let fh = self.next_handle.fetch_add(1, Ordering::SeqCst);
         
         let handle = FileHandle {
@@ -34,6 +289,8 @@ impl FileHandleManager {
             flags,
             branch_idx,
             direct_io,
+            file: file.map(Arc::new),
+            write_buffer: Arc::new(Mutex::new(WriteBuffer::default())),
         };
         
         self.handles.write().insert(fh, handle);
@@ -44,6 +301,77 @@ impl FileHandleManager {
         self.handles.read().get(&fh).cloned()
     }
 
+    /// Find an open handle for `ino` that is bound to a specific branch
+    pub fn find_handle_for_inode(&self, ino: u64) -> Option<FileHandle> {
+        self.handles.read()
+            .values()
+            .find(|handle| handle.ino == ino && handle.branch_idx.is_some())
+            .cloned()
+    }
+
+    /// Every open handle for `ino`
+    pub fn handles_for_inode(&self, ino: u64) -> Vec<FileHandle> {
+        self.handles.read()
+            .values()
+            .filter(|handle| handle.ino == ino)
+            .cloned()
+            .collect()
+    }
+
+    /// Inode numbers with at least one open handle
+    pub fn open_inodes(&self) -> HashSet<u64> {
+        self.handles.read().values().map(|handle| handle.ino).collect()
+    }
+
+    /// Follow a branch reorder, where `new_index[old]` is each branch's new position
+    pub fn remap_branches(&self, new_index: &[usize]) {
+        for handle in self.handles.write().values_mut() {
+            handle.branch_idx = handle.branch_idx.and_then(|idx| new_index.get(idx).copied());
+        }
+    }
+
+    /// Write out the buffered data of every handle open on `ino`
+    pub fn flush_inode_buffers(&self, ino: u64) -> io::Result<()> {
+        for handle in &self.handles_for_inode(ino) {
+            flush_write_buffer(handle)?;
+        }
+        Ok(())
+    }
+
+    /// Write out the buffered data of every open handle
+    ///
+    /// Every handle is attempted; the last failure, if any, is returned.
+    pub fn flush_all_buffers(&self) -> io::Result<()> {
+        let handles: Vec<FileHandle> = self.handles.read().values().cloned().collect();
+        
+        let mut result = Ok(());
+        for handle in &handles {
+            if let Err(e) = flush_write_buffer(handle) {
+                tracing::warn!("Failed to flush write buffer for {:?}: {}", handle.path, e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// Point handles open on `old_path`, or anything below it, at the renamed path
+    ///
+    /// Returns the number of handles updated.
+    pub fn rename_paths(&self, old_path: &Path, new_path: &Path) -> usize {
+        let mut updated = 0;
+        for handle in self.handles.write().values_mut() {
+            if let Ok(relative) = handle.path.strip_prefix(old_path) {
+                handle.path = if relative.as_os_str().is_empty() {
+                    new_path.to_path_buf()
+                } else {
+                    new_path.join(relative)
+                };
+                updated += 1;
+            }
+        }
+        updated
+    }
+
     pub fn remove_handle(&self, fh: u64) -> Option<FileHandle> {
         self.handles.write().remove(&fh)
     }
@@ -160,6 +488,280 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_reads_reuse_open_file() {
+        use tempfile::TempDir;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let full_path = temp_dir.path().join("data.bin");
+        let content: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&full_path, &content).unwrap();
+        
+        const READS: usize = 1000;
+        const CHUNK: usize = 4096;
+        
+        // A single open at handle creation, then pread
+        let manager = FileHandleManager::new();
+        let file = open_branch_file(&full_path, 0).unwrap();
+        let fh = manager.create_handle_with_file(1, PathBuf::from("/data.bin"), 0, Some(0), false, Some(file));
+        
+        // Removing the path proves reads never reopen it
+        std::fs::remove_file(&full_path).unwrap();
+        
+        let handle = manager.get_handle(fh).unwrap();
+        let file = handle.file.as_ref().unwrap();
+        for i in 0..READS {
+            let offset = (i * CHUNK) % content.len();
+            let mut buf = vec![0u8; CHUNK];
+            let n = pread_full(file, &mut buf, offset as i64).unwrap();
+            assert_eq!(n, CHUNK);
+            assert_eq!(&buf[..], &content[offset..offset + CHUNK]);
+        }
+    }
+
+    #[test]
+    fn test_o_direct_open_is_emulated() {
+        use tempfile::TempDir;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let full_path = temp_dir.path().join("video.bin");
+        let content: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&full_path, &content).unwrap();
+        
+        const O_RDONLY: i32 = 0;
+        let flags = O_RDONLY | O_DIRECT;
+        assert!(wants_direct_io(flags));
+        assert!(!wants_direct_io(O_RDONLY));
+        
+        // Unaligned offset, length and buffer, as FUSE requests arrive
+        let file = open_branch_file(&full_path, flags).unwrap();
+        let mut buf = vec![0u8; 1001];
+        let n = pread_full(&file, &mut buf, 3).unwrap();
+        assert_eq!(n, 1001);
+        assert_eq!(&buf[..], &content[3..1004]);
+    }
+
+    #[test]
+    fn test_pwrite_all_and_short_read() {
+        use tempfile::TempDir;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let full_path = temp_dir.path().join("rw.txt");
+        std::fs::write(&full_path, b"hello").unwrap();
+        
+        const O_RDWR: i32 = 2;
+        let file = open_branch_file(&full_path, O_RDWR).unwrap();
+        assert_eq!(pwrite_all(&file, b" world", 5).unwrap(), 6);
+        
+        // Reading past EOF returns only the available bytes
+        let mut buf = vec![0u8; 64];
+        let n = pread_full(&file, &mut buf, 0).unwrap();
+        assert_eq!(&buf[..n], b"hello world");
+    }
+
+    #[test]
+    fn test_read_full_at_retries_short_reads() {
+        let content: Vec<u8> = (0..100u8).collect();
+        let mut calls = 0;
+        // At most 7 bytes per call, with one interrupted call thrown in
+        let short_reader = |buf: &mut [u8], offset: i64| -> io::Result<usize> {
+            calls += 1;
+            if calls == 3 {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+            let start = (offset as usize).min(content.len());
+            let n = buf.len().min(7).min(content.len() - start);
+            buf[..n].copy_from_slice(&content[start..start + n]);
+            Ok(n)
+        };
+        
+        let mut buf = vec![0u8; 40];
+        assert_eq!(read_full_at(short_reader, &mut buf, 10).unwrap(), 40);
+        assert_eq!(&buf[..], &content[10..50]);
+        assert!(calls > 6);
+        
+        // Only true EOF stops short of the requested size
+        let mut buf = vec![0u8; 40];
+        let reader = |buf: &mut [u8], offset: i64| -> io::Result<usize> {
+            let start = (offset as usize).min(content.len());
+            let n = buf.len().min(7).min(content.len() - start);
+            buf[..n].copy_from_slice(&content[start..start + n]);
+            Ok(n)
+        };
+        assert_eq!(read_full_at(reader, &mut buf, 80).unwrap(), 20);
+        assert_eq!(&buf[..20], &content[80..]);
+        
+        let failing = |_: &mut [u8], _: i64| -> io::Result<usize> { Err(io::Error::from_raw_os_error(EIO)) };
+        assert_eq!(read_full_at(failing, &mut buf, 0).unwrap_err().raw_os_error(), Some(EIO));
+    }
+
+    #[test]
+    fn test_drop_cache_on_close() {
+        use std::cell::Cell;
+        use tempfile::TempDir;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let full_path = temp_dir.path().join("media.bin");
+        std::fs::write(&full_path, vec![0u8; 4096]).unwrap();
+        
+        let manager = FileHandleManager::new();
+        let file = open_branch_file(&full_path, 0).unwrap();
+        let expected_fd = file.as_raw_fd();
+        let fh = manager.create_handle_with_file(1, PathBuf::from("/media.bin"), 0, Some(0), false, Some(file));
+        let handle = manager.remove_handle(fh).unwrap();
+        
+        // Disabled: the wrapper must not be invoked
+        let calls = Cell::new(0);
+        assert!(!drop_cache_on_close(&handle, false, |_| { calls.set(calls.get() + 1); Ok(()) }));
+        assert_eq!(calls.get(), 0);
+        
+        // Enabled: invoked once with the handle's fd
+        assert!(drop_cache_on_close(&handle, true, |fd| {
+            calls.set(calls.get() + 1);
+            assert_eq!(fd, expected_fd);
+            Ok(())
+        }));
+        assert_eq!(calls.get(), 1);
+        
+        // The real fadvise succeeds on a regular file
+        assert!(drop_cache_on_close(&handle, true, fadvise_dontneed));
+        
+        // Write-only handles are left alone
+        const O_WRONLY: i32 = 1;
+        let file = open_branch_file(&full_path, O_WRONLY).unwrap();
+        let fh = manager.create_handle_with_file(1, PathBuf::from("/media.bin"), O_WRONLY, Some(0), false, Some(file));
+        let handle = manager.remove_handle(fh).unwrap();
+        assert!(!drop_cache_on_close(&handle, true, |_| panic!("should not be called")));
+    }
+
+    #[test]
+    fn test_sync_on_close() {
+        use std::cell::Cell;
+        use tempfile::TempDir;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let full_path = temp_dir.path().join("journal.log");
+        std::fs::write(&full_path, b"entry").unwrap();
+        
+        const O_WRONLY: i32 = 1;
+        let manager = FileHandleManager::new();
+        let file = open_branch_file(&full_path, O_WRONLY).unwrap();
+        let fh = manager.create_handle_with_file(1, PathBuf::from("/journal.log"), O_WRONLY, Some(0), false, Some(file));
+        let handle = manager.remove_handle(fh).unwrap();
+        
+        let calls = Cell::new(0);
+        assert!(!sync_on_close(&handle, false, |_| { calls.set(calls.get() + 1); Ok(()) }).unwrap());
+        assert_eq!(calls.get(), 0);
+        assert!(sync_on_close(&handle, true, |_| { calls.set(calls.get() + 1); Ok(()) }).unwrap());
+        assert_eq!(calls.get(), 1);
+        assert!(sync_on_close(&handle, true, File::sync_all).unwrap());
+        
+        // Sync failures are passed back to the caller
+        let err = sync_on_close(&handle, true, |_| Err(io::Error::from_raw_os_error(EIO))).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(EIO));
+        
+        // Read-only handles have nothing to sync
+        let file = open_branch_file(&full_path, 0).unwrap();
+        let fh = manager.create_handle_with_file(1, PathBuf::from("/journal.log"), 0, Some(0), false, Some(file));
+        let handle = manager.remove_handle(fh).unwrap();
+        assert!(!sync_on_close(&handle, true, |_| panic!("should not be called")).unwrap());
+    }
+
+    #[test]
+    fn test_write_buffer_coalesces_appends() {
+        use tempfile::TempDir;
+        
+        const O_WRONLY: i32 = 1;
+        let temp_dir = TempDir::new().unwrap();
+        let full_path = temp_dir.path().join("app.log");
+        std::fs::write(&full_path, b"").unwrap();
+        
+        let manager = FileHandleManager::new();
+        let file = open_branch_file(&full_path, O_WRONLY).unwrap();
+        let fh = manager.create_handle_with_file(7, PathBuf::from("/app.log"), O_WRONLY, Some(0), false, Some(file));
+        let handle = manager.get_handle(fh).unwrap();
+        let file = handle.file.clone().unwrap();
+        
+        let mut expected = Vec::new();
+        for i in 0..10_000 {
+            let line = format!("log line {}\n", i);
+            let written = handle.write_buffer.lock().write(&file, line.as_bytes(), expected.len() as i64).unwrap();
+            assert_eq!(written, line.len());
+            expected.extend_from_slice(line.as_bytes());
+        }
+        
+        // Ten thousand appends reach the file in a handful of writes
+        let flushes = handle.write_buffer.lock().flush_count();
+        assert!(flushes > 0 && flushes < 10, "unexpected flush count {}", flushes);
+        assert!(handle.write_buffer.lock().pending() > 0);
+        assert!((std::fs::metadata(&full_path).unwrap().len() as usize) < expected.len());
+        
+        // Flushing every handle on the inode writes out the rest
+        manager.flush_inode_buffers(7).unwrap();
+        assert_eq!(handle.write_buffer.lock().pending(), 0);
+        assert_eq!(std::fs::read(&full_path).unwrap(), expected);
+        
+        // A write elsewhere in the file is not merged with the pending run
+        handle.write_buffer.lock().write(&file, b"tail", expected.len() as i64).unwrap();
+        handle.write_buffer.lock().write(&file, b"LOG", 0).unwrap();
+        flush_write_buffer(&manager.remove_handle(fh).unwrap()).unwrap();
+        let content = std::fs::read(&full_path).unwrap();
+        assert!(content.starts_with(b"LOG line 0"));
+        assert!(content.ends_with(b"tail"));
+    }
+
+    #[test]
+    fn test_rename_paths_follows_renamed_directory() {
+        let manager = FileHandleManager::new();
+        let inside = manager.create_handle(1, PathBuf::from("/music/song.flac"), 0, Some(0), false);
+        let itself = manager.create_handle(2, PathBuf::from("/music"), 0, Some(0), false);
+        let sibling = manager.create_handle(3, PathBuf::from("/musical/score.pdf"), 0, Some(0), false);
+        
+        assert_eq!(manager.rename_paths(Path::new("/music"), Path::new("/audio")), 2);
+        assert_eq!(manager.get_handle(inside).unwrap().path, PathBuf::from("/audio/song.flac"));
+        assert_eq!(manager.get_handle(itself).unwrap().path, PathBuf::from("/audio"));
+        // Only whole path components match
+        assert_eq!(manager.get_handle(sibling).unwrap().path, PathBuf::from("/musical/score.pdf"));
+    }
+
+    #[test]
+    fn test_advise_readahead_only_for_read_only_opens() {
+        use std::cell::Cell;
+        use tempfile::TempDir;
+        
+        const O_WRONLY: i32 = 1;
+        const O_RDWR: i32 = 2;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let full_path = temp_dir.path().join("movie.mkv");
+        std::fs::write(&full_path, vec![0u8; 8192]).unwrap();
+        
+        let file = open_branch_file(&full_path, 0).unwrap();
+        let expected_fd = file.as_raw_fd();
+        let calls = Cell::new(0);
+        
+        // Read-only open: advised with the window in bytes
+        assert!(advise_readahead(&file, 0, 128, |fd, len| {
+            calls.set(calls.get() + 1);
+            assert_eq!(fd, expected_fd);
+            assert_eq!(len, 128 * 1024);
+            Ok(())
+        }));
+        assert_eq!(calls.get(), 1);
+        
+        // Disabled
+        assert!(!advise_readahead(&file, 0, 0, |_, _| panic!("should not be called")));
+        
+        // The real fadvise succeeds on a regular file
+        assert!(advise_readahead(&file, 0, 64, fadvise_readahead));
+        
+        // Write opens are left alone
+        for flags in [O_WRONLY, O_RDWR] {
+            let file = open_branch_file(&full_path, flags).unwrap();
+            assert!(!advise_readahead(&file, flags, 128, |_, _| panic!("should not be called")));
+        }
+    }
+
     #[test]
     fn test_direct_io_flag() {
         let manager = FileHandleManager::new();
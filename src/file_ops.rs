@@ -1,30 +1,166 @@
-use crate::branch::Branch;
-use crate::policy::{CreatePolicy, SearchPolicy, PolicyError};
-use std::collections::HashSet;
+use crate::branch::{Branch, BranchList};
+use crate::errno::{ToErrno, EACCES, EEXIST, EINVAL, EPERM, EROFS, EXDEV};
+use crate::policy::{ActionPolicy, CreatePolicy, SearchPolicy, PolicyError};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsString;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use parking_lot::RwLock;
 use nix::sys::stat::{mknod as nix_mknod, Mode, SFlag};
 use nix::unistd::mkfifo;
 
 pub struct FileManager {
-    pub branches: Vec<Arc<Branch>>,
+    branches: BranchList,
     pub create_policy: Arc<RwLock<Box<dyn CreatePolicy>>>,
+    // Branches new directories are made on (func.mkdir)
+    mkdir_policy: RwLock<Box<dyn CreatePolicy>>,
     pub search_policy: Box<dyn SearchPolicy>,
+    pub open_policy: Arc<RwLock<Box<dyn SearchPolicy>>>,
+    // Copy whose attributes getattr reports (func.getattr)
+    getattr_policy: RwLock<Box<dyn SearchPolicy>>,
+    // Branches unlink removes a file from (func.unlink)
+    unlink_policy: RwLock<Box<dyn ActionPolicy>>,
+    // Branches whose copies truncate resizes (func.truncate)
+    truncate_policy: RwLock<Box<dyn ActionPolicy>>,
+    whiteout: AtomicBool,
+    // fsync the parent directory of every created file (create_durable)
+    create_durable: AtomicBool,
+    // Name patterns left out of directory listings (whiteout markers, stray control files)
+    hide_patterns: RwLock<Vec<String>>,
+    // Directory -> branch index pins set via user.mergerfs.branch (in memory only)
+    branch_pins: RwLock<HashMap<PathBuf, Arc<Branch>>>,
+}
+
+/// Xattr set on a directory to pin new files and directories under it to a branch
+pub const BRANCH_PIN_XATTR: &str = "user.mergerfs.branch";
+
+/// Prefix of the marker files that hide a path shadowed by a read-only branch
+pub const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// Names hidden from listings by default: whiteout markers and the control file name
+pub const DEFAULT_HIDE_PATTERNS: &[&str] = &[".wh.*", ".mergerfs"];
+
+/// A name in a union directory listing, as read from the branch providing it
+#[derive(Debug, Clone)]
+pub struct UnionDirEntry {
+    pub name: String,
+    pub branch: Arc<Branch>,
+    /// Type reported by the directory entry, without a stat of the file
+    pub file_type: Option<std::fs::FileType>,
+    /// Inode number reported by the directory entry (d_ino)
+    pub ino: u64,
+}
+
+/// Match `name` against a pattern where `*` matches any run of characters and `?` one character
+fn matches_hide_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last '*' swallow one more character
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Path of the whiteout marker for `path`, a sibling named `.wh.<name>`
+fn whiteout_path(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?;
+    let mut marker = OsString::from(WHITEOUT_PREFIX);
+    marker.push(name);
+    Some(path.with_file_name(marker))
+}
+
+/// Sync the directory holding the just-created `full_path` (create_durable)
+///
+/// Syncing the file alone does not persist its directory entry on every
+/// filesystem, so after a crash the new file could be missing. `sync` is
+/// given the opened directory, so tests can count calls without issuing a
+/// real fsync. Returns true if the sync was issued.
+pub fn sync_parent_dir<F>(full_path: &Path, enabled: bool, sync: F) -> std::io::Result<bool>
+where
+    F: FnOnce(&File) -> std::io::Result<()>,
+{
+    let Some(parent) = full_path.parent().filter(|_| enabled) else {
+        return Ok(false);
+    };
+    let dir = File::open(parent)?;
+    sync(&dir).map(|()| true)
+}
+
+/// Whether a failed create should be retried on the next candidate branch
+fn is_create_fallback_error(error: &PolicyError) -> bool {
+    matches!(error.errno(), EPERM | EACCES | EROFS)
+}
+
+/// Error reported once every branch the create policy offered refused a create
+///
+/// A branch that denied permission wins over read-only ones, so callers see
+/// EACCES rather than being told the whole pool is read-only. Otherwise the
+/// first branch failure is kept, falling back to why no further branch
+/// could be selected.
+fn create_refused_error(failures: Vec<PolicyError>, selection_error: PolicyError) -> PolicyError {
+    if failures.iter().any(|e| e.errno() == EACCES) {
+        return PolicyError::PermissionDenied;
+    }
+    failures.into_iter().next().unwrap_or(selection_error)
+}
+
+/// Whether an error means the branch is out of space (ENOSPC or EDQUOT)
+fn is_no_space_error(error: &PolicyError) -> bool {
+    match error {
+        PolicyError::NoSpace => true,
+        PolicyError::IoError(e) => crate::moveonenospc::is_out_of_space_error(e),
+        _ => false,
+    }
 }
 
 impl FileManager {
     pub fn new(branches: Vec<Arc<Branch>>, create_policy: Box<dyn CreatePolicy>) -> Self {
-        use crate::policy::FirstFoundSearchPolicy;
+        use crate::policy::{AllActionPolicy, AllCreatePolicy, FirstFoundSearchPolicy};
         Self {
-            branches,
+            branches: BranchList::new(branches),
             create_policy: Arc::new(RwLock::new(create_policy)),
+            mkdir_policy: RwLock::new(Box::new(AllCreatePolicy::new())),
             search_policy: Box::new(FirstFoundSearchPolicy::new()),
+            open_policy: Arc::new(RwLock::new(Box::new(FirstFoundSearchPolicy::new()))),
+            getattr_policy: RwLock::new(Box::new(FirstFoundSearchPolicy::new())),
+            unlink_policy: RwLock::new(Box::new(AllActionPolicy::new())),
+            truncate_policy: RwLock::new(Box::new(AllActionPolicy::new())),
+            whiteout: AtomicBool::new(false),
+            create_durable: AtomicBool::new(false),
+            hide_patterns: RwLock::new(DEFAULT_HIDE_PATTERNS.iter().map(|p| p.to_string()).collect()),
+            branch_pins: RwLock::new(HashMap::new()),
         }
     }
     
+    /// The branches in their current priority order
+    pub fn branches(&self) -> Arc<Vec<Arc<Branch>>> {
+        self.branches.snapshot()
+    }
+    
+    /// The shared branch list, for managers that should follow its order
+    pub fn branch_list(&self) -> &BranchList {
+        &self.branches
+    }
+    
     /// Update the create policy at runtime
     pub fn set_create_policy(&self, policy: Box<dyn CreatePolicy>) {
         let mut create_policy = self.create_policy.write();
@@ -37,60 +173,358 @@ impl FileManager {
         let policy = self.create_policy.read();
         policy.name().to_string()
     }
+    
+    /// Update the policy choosing the branches new directories are made on
+    pub fn set_mkdir_policy(&self, policy: Box<dyn CreatePolicy>) {
+        let mut mkdir_policy = self.mkdir_policy.write();
+        tracing::info!("Updating mkdir policy from {} to {}", mkdir_policy.name(), policy.name());
+        *mkdir_policy = policy;
+    }
+    
+    /// Get the current mkdir policy name
+    pub fn get_mkdir_policy_name(&self) -> String {
+        self.mkdir_policy.read().name().to_string()
+    }
+    
+    /// Update the search policy used to pick a branch when opening files
+    pub fn set_open_policy(&self, policy: Box<dyn SearchPolicy>) {
+        let mut open_policy = self.open_policy.write();
+        tracing::info!("Updating open policy from {} to {}", open_policy.name(), policy.name());
+        *open_policy = policy;
+    }
+    
+    /// Get the current open policy name
+    pub fn get_open_policy_name(&self) -> String {
+        self.open_policy.read().name().to_string()
+    }
+    
+    /// Update the search policy choosing which copy getattr reports
+    pub fn set_getattr_policy(&self, policy: Box<dyn SearchPolicy>) {
+        let mut getattr_policy = self.getattr_policy.write();
+        tracing::info!("Updating getattr policy from {} to {}", getattr_policy.name(), policy.name());
+        *getattr_policy = policy;
+    }
+    
+    /// Get the current getattr policy name
+    pub fn get_getattr_policy_name(&self) -> String {
+        self.getattr_policy.read().name().to_string()
+    }
+    
+    /// Update the action policy choosing which copies unlink removes
+    pub fn set_unlink_policy(&self, policy: Box<dyn ActionPolicy>) {
+        let mut unlink_policy = self.unlink_policy.write();
+        tracing::info!("Updating unlink policy from {} to {}", unlink_policy.name(), policy.name());
+        *unlink_policy = policy;
+    }
+    
+    /// Get the current unlink policy name
+    pub fn get_unlink_policy_name(&self) -> String {
+        self.unlink_policy.read().name().to_string()
+    }
+    
+    /// Update the action policy choosing which copies truncate resizes
+    pub fn set_truncate_policy(&self, policy: Box<dyn ActionPolicy>) {
+        let mut truncate_policy = self.truncate_policy.write();
+        tracing::info!("Updating truncate policy from {} to {}", truncate_policy.name(), policy.name());
+        *truncate_policy = policy;
+    }
+    
+    /// Get the current truncate policy name
+    pub fn get_truncate_policy_name(&self) -> String {
+        self.truncate_policy.read().name().to_string()
+    }
 
-    pub fn create_file(&self, path: &Path, content: &[u8]) -> Result<(), PolicyError> {
-        let _span = tracing::info_span!("file_ops::create_file", path = ?path, content_size = content.len()).entered();
+    /// Enable or disable whiteout markers for files shadowed by read-only branches
+    pub fn set_whiteout(&self, enabled: bool) {
+        self.whiteout.store(enabled, Ordering::Relaxed);
+    }
+    
+    pub fn whiteout_enabled(&self) -> bool {
+        self.whiteout.load(Ordering::Relaxed)
+    }
+    
+    /// Enable or disable syncing the parent directory of created files
+    pub fn set_create_durable(&self, enabled: bool) {
+        self.create_durable.store(enabled, Ordering::Relaxed);
+    }
+    
+    pub fn create_durable_enabled(&self) -> bool {
+        self.create_durable.load(Ordering::Relaxed)
+    }
+    
+    /// Replace the name patterns hidden from directory listings
+    pub fn set_hide_patterns(&self, patterns: Vec<String>) {
+        *self.hide_patterns.write() = patterns;
+    }
+    
+    /// Whether a directory entry name is hidden from listings
+    pub fn is_hidden_name(&self, name: &str) -> bool {
+        self.hide_patterns.read().iter().any(|pattern| matches_hide_pattern(pattern, name))
+    }
+    
+    /// Check whether `path` is hidden by a whiteout marker on any branch
+    pub fn is_whited_out(&self, path: &Path) -> bool {
+        if !self.whiteout_enabled() {
+            return false;
+        }
+        match whiteout_path(path) {
+            Some(marker) => self.branches().iter().any(|b| b.full_path(&marker).symlink_metadata().is_ok()),
+            None => false,
+        }
+    }
+    
+    /// Hide the copies of `path` left on branches we cannot remove from
+    fn create_whiteout(&self, path: &Path) -> Result<(), PolicyError> {
+        let marker = whiteout_path(path).ok_or(PolicyError::PathNotFound)?;
+        let branch = self.branches().iter()
+            .find(|b| b.allows_create())
+            .cloned()
+            .ok_or(PolicyError::ReadOnlyFilesystem)?;
+        
+        // The parent directory may only exist on the read-only branch so far
+        self.prepare_parent(path, &branch, false)?;
+        
+        File::create(branch.full_path(&marker))?;
+        tracing::info!("Created whiteout for {:?} on branch {:?}", path, branch.path);
+        Ok(())
+    }
+    
+    /// Remove any whiteout hiding `path` once it is recreated or renamed onto
+    pub fn clear_whiteout(&self, path: &Path) {
+        if !self.whiteout_enabled() {
+            return;
+        }
+        if let Some(marker) = whiteout_path(path) {
+            for branch in self.branches().iter().filter(|b| !b.is_readonly()) {
+                let _ = std::fs::remove_file(branch.full_path(&marker));
+            }
+        }
+    }
+
+    fn pin_key(path: &Path) -> PathBuf {
+        Path::new("/").join(path.strip_prefix("/").unwrap_or(path))
+    }
+    
+    /// Pin a directory so everything created below it goes to `branch_path`
+    ///
+    /// Pins are kept in memory only and are lost on unmount.
+    pub fn pin_branch(&self, dir: &Path, branch_path: &Path) -> Result<(), PolicyError> {
+        if !self.directory_exists(dir) {
+            return Err(PolicyError::PathNotFound);
+        }
+        let branch = self.branches().iter()
+            .find(|b| b.path == branch_path)
+            .cloned()
+            .ok_or_else(|| PolicyError::IoError(std::io::Error::from_raw_os_error(EINVAL)))?;
+        if !branch.allows_create() {
+            return Err(PolicyError::ReadOnlyFilesystem);
+        }
         
-        // Select branch for new file using create policy
-        tracing::debug!("Selecting branch for new file using create policy");
-        let branch = {
-            let policy = self.create_policy.read();
-            eprintln!("DEBUG FileManager: Using policy {} for creating {:?}", policy.name(), path);
-            policy.select_branch(&self.branches, path)?
-        };
-        let full_path = branch.full_path(path);
+        tracing::info!("Pinned {:?} to branch {:?}", dir, branch_path);
+        self.branch_pins.write().insert(Self::pin_key(dir), branch);
+        Ok(())
+    }
+    
+    /// Remove the pin on a directory, returning whether one existed
+    pub fn unpin_branch(&self, dir: &Path) -> bool {
+        self.branch_pins.write().remove(&Self::pin_key(dir)).is_some()
+    }
+    
+    /// Move pins on `old_path` and directories below it to the renamed path
+    ///
+    /// Pins on a replaced destination, or below it, are dropped first.
+    pub fn rename_pins(&self, old_path: &Path, new_path: &Path) {
+        let (old_key, new_key) = (Self::pin_key(old_path), Self::pin_key(new_path));
+        let mut pins = self.branch_pins.write();
+        if pins.is_empty() {
+            return;
+        }
+        pins.retain(|dir, _| !dir.starts_with(&new_key));
+        let moved: Vec<PathBuf> = pins.keys().filter(|dir| dir.starts_with(&old_key)).cloned().collect();
+        for dir in moved {
+            let branch = pins.remove(&dir).unwrap();
+            let relative = dir.strip_prefix(&old_key).unwrap();
+            let renamed = if relative.as_os_str().is_empty() { new_key.clone() } else { new_key.join(relative) };
+            tracing::info!("Moved pin of {:?} to {:?}", dir, renamed);
+            pins.insert(renamed, branch);
+        }
+    }
+    
+    /// Branch a directory itself is pinned to, if any
+    pub fn get_branch_pin(&self, dir: &Path) -> Option<Arc<Branch>> {
+        self.branch_pins.read().get(&Self::pin_key(dir)).cloned()
+    }
+    
+    /// Branch new entries at `path` must go to, from the nearest pinned ancestor
+    fn pinned_branch(&self, path: &Path) -> Option<Arc<Branch>> {
+        let pins = self.branch_pins.read();
+        if pins.is_empty() {
+            return None;
+        }
+        let key = Self::pin_key(path);
+        key.ancestors()
+            .skip(1)
+            .find_map(|dir| pins.get(dir))
+            .cloned()
+    }
+    
+    /// Pick the branch for a new entry, leaving out branches that already failed
+    /// the create, and whether the choice came from a path-preserving policy.
+    /// Pins take precedence over the create policy.
+    fn select_create_branch_excluding(&self, path: &Path, excluded: &[PathBuf]) -> Result<(Arc<Branch>, bool), PolicyError> {
+        if let Some(branch) = self.pinned_branch(path) {
+            if !excluded.contains(&branch.path) {
+                tracing::debug!("Using pinned branch {:?} for {:?}", branch.path, path);
+                return Ok((branch, false));
+            }
+        }
         
-        tracing::info!("Selected branch {:?} for creating file {:?}", branch.path, path);
-        tracing::debug!("Full path will be: {:?}", full_path);
+        let branches = self.branches();
+        let policy = self.create_policy.read();
+        tracing::debug!("Using policy {} for creating {:?}", policy.name(), path);
+        if excluded.is_empty() {
+            return Ok((policy.select_branch(&branches, path)?, policy.is_path_preserving()));
+        }
         
-        // If using a path-preserving policy, clone directory structure from template branch
-        let is_path_preserving = {
-            let policy = self.create_policy.read();
-            policy.is_path_preserving()
-        };
-        if is_path_preserving {
-            let parent_path = path.parent().unwrap_or_else(|| Path::new("/"));
-            let template_branch = self.find_first_branch(parent_path).ok();
+        let candidates: Vec<Arc<Branch>> = branches.iter()
+            .filter(|b| !excluded.contains(&b.path))
+            .cloned()
+            .collect();
+        Ok((policy.select_branch(&candidates, path)?, policy.is_path_preserving()))
+    }
+
+    pub fn create_file(&self, path: &Path, content: &[u8]) -> Result<(), PolicyError> {
+        self.create_file_with_enospc_fallback(path, content, |_, _| None)
+    }
+    
+    /// Create a file, asking `on_enospc` for another branch if the chosen one is full
+    ///
+    /// `on_enospc` gets the full branch and the remaining candidates; returning
+    /// `None` gives up with the out-of-space error.
+    pub fn create_file_with_enospc_fallback<F>(&self, path: &Path, content: &[u8], on_enospc: F) -> Result<(), PolicyError>
+    where
+        F: Fn(&Branch, &[Arc<Branch>]) -> Option<Arc<Branch>>,
+    {
+        self.create_file_syncing_dir_with(path, content, on_enospc, |dir| dir.sync_all())
+    }
+    
+    /// `create_file_with_enospc_fallback`, syncing the parent directory with `sync_dir`
+    ///
+    /// `sync_dir` runs once on the created file's directory when create_durable
+    /// is on; tests pass one that counts calls instead of issuing an fsync.
+    fn create_file_syncing_dir_with<F, S>(&self, path: &Path, content: &[u8], on_enospc: F, sync_dir: S) -> Result<(), PolicyError>
+    where
+        F: Fn(&Branch, &[Arc<Branch>]) -> Option<Arc<Branch>>,
+        S: Fn(&File) -> std::io::Result<()>,
+    {
+        let _span = tracing::info_span!("file_ops::create_file", path = ?path, content_size = content.len()).entered();
+        self.create_file_with(path, on_enospc, |branch, is_path_preserving| {
+            self.create_file_on_branch(path, content, branch, is_path_preserving, &sync_dir)
+        })
+    }
+    
+    /// Branch selection and fallback of `create_file_with_enospc_fallback`
+    ///
+    /// `create` makes the file on one branch, given whether the policy that
+    /// chose it preserves paths; tests pass one that fails on purpose.
+    fn create_file_with<F, C>(&self, path: &Path, on_enospc: F, create: C) -> Result<(), PolicyError>
+    where
+        F: Fn(&Branch, &[Arc<Branch>]) -> Option<Arc<Branch>>,
+        C: Fn(&Branch, bool) -> Result<(), PolicyError>,
+    {
+        // Select branch for new file using create policy, moving on to the next
+        // candidate if the chosen branch turns out to be read-only or unwritable
+        tracing::debug!("Selecting branch for new file using create policy");
+        let mut failed: Vec<PathBuf> = Vec::new();
+        let mut failures: Vec<PolicyError> = Vec::new();
+        loop {
+            let (branch, is_path_preserving) = match self.select_create_branch_excluding(path, &failed) {
+                Ok(selected) => selected,
+                Err(e) => return Err(create_refused_error(failures, e)),
+            };
             
-            if let Some(ref template) = template_branch {
-                if let Some(parent) = path.parent() {
-                    if !parent.as_os_str().is_empty() {
-                        use crate::fs_utils;
-                        if let Err(e) = fs_utils::clone_path(&template.path, &branch.path, parent) {
-                            tracing::warn!("Failed to clone parent path structure: {:?}", e);
-                            // Fall back to simple directory creation
-                            if let Some(parent_dir) = full_path.parent() {
-                                std::fs::create_dir_all(parent_dir)?;
-                            }
-                        }
+            match create(&branch, is_path_preserving) {
+                Ok(()) => {
+                    self.clear_whiteout(path);
+                    return Ok(());
+                }
+                Err(e) if is_create_fallback_error(&e) => {
+                    tracing::warn!("Create of {:?} failed on branch {:?}: {:?}, trying next branch", path, branch.path, e);
+                    if let PolicyError::IoError(io_error) = &e {
+                        branch.record_io_error(io_error);
                     }
+                    failed.push(branch.path.clone());
+                    failures.push(e);
                 }
-            } else {
-                // No template found, just create parent directories
-                if let Some(parent) = full_path.parent() {
-                    std::fs::create_dir_all(parent)?;
+                Err(e) if is_no_space_error(&e) => {
+                    failed.push(branch.path.clone());
+                    let candidates: Vec<Arc<Branch>> = self.branches().iter()
+                        .filter(|b| b.allows_create() && !failed.contains(&b.path))
+                        .cloned()
+                        .collect();
+                    let target = match on_enospc(&branch, &candidates) {
+                        Some(target) => target,
+                        None => return Err(PolicyError::NoSpace),
+                    };
+                    
+                    tracing::info!("Branch {:?} is full, creating {:?} on {:?} instead", branch.path, path, target.path);
+                    // moveonenospc overrides the policy, so the parents are cloned over
+                    create(&target, false)?;
+                    self.clear_whiteout(path);
+                    return Ok(());
                 }
+                Err(e) => return Err(e),
             }
-        } else {
-            // Non-path-preserving policy, just create parent directories
-            if let Some(parent) = full_path.parent() {
-                std::fs::create_dir_all(parent)?;
+        }
+    }
+    
+    /// Make sure the parent directory of `path` exists on `branch` before creating it
+    ///
+    /// A path-preserving policy only places entries where the parent already
+    /// exists, so a missing parent fails with EXDEV rather than being created.
+    /// Otherwise the parents are cloned, with owner, mode and times, from the
+    /// first branch that has them, or created plainly if none does.
+    fn prepare_parent(&self, path: &Path, branch: &Branch, is_path_preserving: bool) -> Result<(), PolicyError> {
+        let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) else {
+            return Ok(());
+        };
+        let full_parent = branch.full_path(parent);
+        if full_parent.is_dir() {
+            return Ok(());
+        }
+        
+        if is_path_preserving {
+            tracing::debug!("Parent {:?} is missing on branch {:?} under a path-preserving policy, returning EXDEV", parent, branch.path);
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EXDEV)));
+        }
+        
+        match self.find_first_branch(parent) {
+            Ok(template) => {
+                if let Err(e) = crate::fs_utils::clone_path(&template.path, &branch.path, parent) {
+                    tracing::warn!("Failed to clone parent path structure: {:?}", e);
+                    std::fs::create_dir_all(&full_parent)?;
+                }
             }
+            Err(_) => std::fs::create_dir_all(&full_parent)?,
         }
+        Ok(())
+    }
+    
+    fn create_file_on_branch<S>(&self, path: &Path, content: &[u8], branch: &Branch, is_path_preserving: bool, sync_dir: &S) -> Result<(), PolicyError>
+    where
+        S: Fn(&File) -> std::io::Result<()>,
+    {
+        let full_path = branch.full_path(path);
+        
+        tracing::info!("Selected branch {:?} for creating file {:?}", branch.path, path);
+        tracing::debug!("Full path will be: {:?}", full_path);
+        
+        self.prepare_parent(path, branch, is_path_preserving)?;
         
         let mut file = File::create(&full_path)?;
         file.write_all(content)?;
         file.sync_all()?; // Ensure data is written to disk
+        sync_parent_dir(&full_path, self.create_durable_enabled(), sync_dir)?;
         
         tracing::info!("File created successfully at {:?} with {} bytes", full_path, content.len());
         Ok(())
@@ -99,7 +533,7 @@ impl FileManager {
     pub fn write_to_file(&self, path: &Path, offset: u64, data: &[u8]) -> Result<usize, PolicyError> {
         // For writing to existing files at offset, find first existing instance
         // In a full implementation, this would be determined at open() time
-        for branch in &self.branches {
+        for branch in self.branches().iter() {
             if !branch.allows_create() {
                 continue; // Skip read-only branches
             }
@@ -119,7 +553,6 @@ impl FileManager {
                 
                 file.seek(SeekFrom::Start(offset))?;
                 let written = file.write(data)?;
-                file.sync_all()?;
                 return Ok(written);
             }
         }
@@ -129,33 +562,101 @@ impl FileManager {
         Err(PolicyError::NoBranchesAvailable)
     }
     
+    /// Truncate every copy of `path` selected by the truncate policy
+    ///
+    /// Succeeds if any copy was resized; otherwise the last error is returned.
     pub fn truncate_file(&self, path: &Path, size: u64) -> Result<(), PolicyError> {
-        // For truncating existing files, find first existing instance
-        for branch in &self.branches {
-            if !branch.allows_create() {
-                continue; // Skip read-only branches
+        let branches = self.branches();
+        let targets = self.truncate_branch_indices(&branches, path)?;
+        if targets.is_empty() {
+            // If file doesn't exist, this is an error
+            return Err(PolicyError::NoBranchesAvailable);
+        }
+        
+        let mut last_error = None;
+        let mut success_count = 0;
+        for idx in targets {
+            match self.truncate_file_on_branch(path, &branches[idx], size) {
+                Ok(()) => success_count += 1,
+                Err(e) => last_error = Some(e),
             }
-            
-            let full_path = branch.full_path(path);
-            if full_path.exists() && full_path.is_file() {
-                tracing::info!("Truncating file {:?} to size {} in branch {:?}", path, size, branch.path);
-                
-                use std::fs::OpenOptions;
-                let file = OpenOptions::new()
-                    .write(true)
-                    .open(full_path)?;
-                file.set_len(size)?;
-                return Ok(());
+        }
+        
+        match last_error {
+            Some(e) if success_count == 0 => Err(e),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Indices into `branches` of the writable copies of `path` truncate should resize
+    ///
+    /// Under the default "all" policy this is every writable branch holding
+    /// the file, so replicated copies keep the same size. Errors from the
+    /// policy (ENOENT, EROFS) are returned as they are.
+    pub fn truncate_branch_indices(&self, branches: &[Arc<Branch>], path: &Path) -> Result<Vec<usize>, PolicyError> {
+        let selected = self.truncate_policy.read().select_branches(branches, path)?;
+        Ok(branches.iter()
+            .enumerate()
+            .filter(|(_, branch)| selected.iter().any(|s| Arc::ptr_eq(s, branch)))
+            // Skip read-only branches
+            .filter(|(_, branch)| branch.allows_create() && branch.full_path(path).is_file())
+            .map(|(idx, _)| idx)
+            .collect())
+    }
+    
+    /// Truncate the copy of `path` on `branch`, reporting a full branch as `NoSpace`
+    pub fn truncate_file_on_branch(&self, path: &Path, branch: &Branch, size: u64) -> Result<(), PolicyError> {
+        tracing::info!("Truncating file {:?} to size {} in branch {:?}", path, size, branch.path);
+        
+        use std::fs::OpenOptions;
+        let file = OpenOptions::new()
+            .write(true)
+            .open(branch.full_path(path))?;
+        file.set_len(size).map_err(|e| {
+            if crate::moveonenospc::is_out_of_space_error(&e) {
+                PolicyError::NoSpace
+            } else {
+                PolicyError::IoError(e)
             }
+        })
+    }
+
+    /// Give the file a private inode if it is hard linked (link-cow)
+    ///
+    /// With a branch index only that branch is considered, otherwise the first
+    /// writable branch containing the file is used. Returns the index of the
+    /// branch whose file was replaced by a copy, or None if it wasn't linked.
+    pub fn break_hard_link(&self, path: &Path, branch_idx: Option<usize>) -> Result<Option<usize>, PolicyError> {
+        let branches = self.branches();
+        let idx = match branch_idx {
+            Some(idx) if idx < branches.len() => idx,
+            Some(_) => return Err(PolicyError::PathNotFound),
+            None => branches
+                .iter()
+                .position(|b| b.allows_create() && b.full_path(path).is_file())
+                .ok_or(PolicyError::PathNotFound)?,
+        };
+        let branch = &branches[idx];
+        
+        if branch.is_readonly() {
+            return Ok(None);
         }
         
-        // If file doesn't exist, this is an error
-        Err(PolicyError::NoBranchesAvailable)
+        let full_path = branch.full_path(path);
+        if !crate::fs_utils::break_hard_link(&full_path)? {
+            return Ok(None);
+        }
+        tracing::info!("link-cow: broke hard link for {:?} on branch {:?}", path, branch.path);
+        Ok(Some(idx))
     }
 
     pub fn read_file(&self, path: &Path) -> Result<Vec<u8>, PolicyError> {
+        if self.is_whited_out(path) {
+            return Err(PolicyError::PathNotFound);
+        }
+        
         // Search for file in all branches (first found)
-        for branch in &self.branches {
+        for branch in self.branches().iter() {
             let full_path = branch.full_path(path);
             if full_path.exists() {
                 let mut file = File::open(full_path)?;
@@ -169,56 +670,74 @@ impl FileManager {
     }
 
     pub fn file_exists(&self, path: &Path) -> bool {
-        self.branches.iter().any(|branch| {
+        !self.is_whited_out(path) && self.branches().iter().any(|branch| {
             branch.full_path(path).exists()
         })
     }
     
     /// Find the branch that contains a file and return both the branch and metadata
-    pub fn find_file_with_metadata(&self, path: &Path) -> Option<(&Branch, std::fs::Metadata)> {
-        for branch in &self.branches {
+    pub fn find_file_with_metadata(&self, path: &Path) -> Option<(Arc<Branch>, std::fs::Metadata)> {
+        if self.is_whited_out(path) {
+            return None;
+        }
+        
+        // ff keeps the lstat scan below, which also finds dangling symlinks
+        let branches = self.branches();
+        let getattr_policy = self.getattr_policy.read();
+        if getattr_policy.name() != "ff" {
+            if let Some(chosen) = getattr_policy.search_branches(&branches, path).ok().and_then(|found| found.into_iter().next()) {
+                if let Ok(metadata) = chosen.full_path(path).symlink_metadata() {
+                    return Some((chosen, metadata));
+                }
+            }
+        }
+        
+        for branch in branches.iter() {
             let full_path = branch.full_path(path);
             // Get metadata without following symlinks
             if let Ok(metadata) = full_path.symlink_metadata() {
-                return Some((branch, metadata));
+                return Some((branch.clone(), metadata));
             }
         }
         None
     }
 
+    /// Create a directory on the branches chosen by the mkdir policy
+    ///
+    /// Succeeds if the directory could be made on at least one of them.
+    /// Missing parents are cloned from the first branch that has them.
     pub fn create_directory(&self, path: &Path) -> Result<(), PolicyError> {
-        let branch = {
-            let policy = self.create_policy.read();
-            policy.select_branch(&self.branches, path)?
+        let branches = match self.pinned_branch(path) {
+            Some(branch) => vec![branch],
+            None => self.mkdir_policy.read().select_branches(&self.branches(), path)?,
         };
-        let full_path = branch.full_path(path);
+        let is_path_preserving = self.pinned_branch(path).is_none()
+            && self.mkdir_policy.read().is_path_preserving();
         
-        tracing::info!("Creating directory {:?} in branch {:?}", path, branch.path);
-        
-        // If using a path-preserving policy, clone directory structure from template branch
-        let is_path_preserving = {
-            let policy = self.create_policy.read();
-            policy.is_path_preserving()
-        };
-        if is_path_preserving {
-            let parent_path = path.parent().unwrap_or_else(|| Path::new("/"));
-            let template_branch = self.find_first_branch(parent_path).ok();
+        let mut created = false;
+        let mut first_error = None;
+        for branch in branches {
+            tracing::info!("Creating directory {:?} in branch {:?}", path, branch.path);
             
-            if let Some(ref template) = template_branch {
-                if let Some(parent) = path.parent() {
-                    if !parent.as_os_str().is_empty() {
-                        use crate::fs_utils;
-                        // Clone the parent path structure, then create the final directory
-                        if let Err(e) = fs_utils::clone_path(&template.path, &branch.path, parent) {
-                            tracing::warn!("Failed to clone parent path structure: {:?}", e);
-                        }
+            // Clone the parent path structure, then create the final directory
+            let result = self.prepare_parent(path, &branch, is_path_preserving)
+                .and_then(|()| Ok(std::fs::create_dir_all(branch.full_path(path))?));
+            match result {
+                Ok(()) => created = true,
+                Err(e) => {
+                    tracing::warn!("Failed to create directory {:?} in branch {:?}: {}", path, branch.path, e);
+                    if let PolicyError::IoError(io_error) = &e {
+                        branch.record_io_error(io_error);
                     }
+                    first_error.get_or_insert(e);
                 }
             }
         }
         
-        // Create the directory (create_dir_all handles if it already exists)
-        std::fs::create_dir_all(full_path)?;
+        if !created {
+            return Err(first_error.unwrap_or(PolicyError::NoBranchesAvailable));
+        }
+        self.clear_whiteout(path);
         Ok(())
     }
     
@@ -226,36 +745,14 @@ impl FileManager {
         // Select branch for new symlink using create policy
         let branch = {
             let policy = self.create_policy.read();
-            policy.select_branch(&self.branches, link_path)?
+            policy.select_branch(&self.branches(), link_path)?
         };
         let full_link_path = branch.full_path(link_path);
         
         tracing::info!("Creating symlink {:?} -> {:?} in branch {:?}", link_path, target, branch.path);
         
-        // Find a branch that has the parent directory to use as template for cloning
-        let parent_path = link_path.parent().unwrap_or_else(|| Path::new("/"));
-        let template_branch = self.find_first_branch(parent_path).ok();
-        
-        // Clone parent directory structure from template branch if available
-        if let Some(ref template) = template_branch {
-            if let Some(parent) = link_path.parent() {
-                if !parent.as_os_str().is_empty() {
-                    use crate::fs_utils;
-                    if let Err(e) = fs_utils::clone_path(&template.path, &branch.path, parent) {
-                        tracing::warn!("Failed to clone parent path structure: {:?}", e);
-                        // Fall back to simple directory creation
-                        if let Some(parent_dir) = full_link_path.parent() {
-                            std::fs::create_dir_all(parent_dir)?;
-                        }
-                    }
-                }
-            }
-        } else {
-            // No template found, just create parent directories
-            if let Some(parent) = full_link_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-        }
+        let is_path_preserving = self.create_policy.read().is_path_preserving();
+        self.prepare_parent(link_path, &branch, is_path_preserving)?;
         
         // Create the symlink
         #[cfg(unix)]
@@ -273,9 +770,99 @@ impl FileManager {
         }
         
         tracing::info!("Symlink created successfully at {:?}", full_link_path);
+        self.clear_whiteout(link_path);
         Ok(())
     }
     
+    /// Check that a hard link can be placed on the same branch as its source
+    ///
+    /// Links are always created on the source's branch. If the destination's parent
+    /// directory only exists on other branches, creating it there would split the
+    /// directory across branches, so EXDEV is returned and the caller can fall back
+    /// to copying instead.
+    pub fn check_link_branches(&self, source_path: &Path, link_path: &Path) -> Result<(), PolicyError> {
+        let source_branch = self.find_first_branch(source_path)?;
+        let parent = link_path.parent().unwrap_or_else(|| Path::new("/"));
+        
+        if source_branch.full_path(parent).is_dir() {
+            return Ok(());
+        }
+        
+        let exists_elsewhere = self.branches().iter().any(|branch| {
+            !Arc::ptr_eq(branch, &source_branch) && branch.full_path(parent).is_dir()
+        });
+        
+        if exists_elsewhere {
+            tracing::debug!(
+                "Link parent {:?} exists only on branches other than the source's {:?}, returning EXDEV",
+                parent, source_branch.path
+            );
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EXDEV)));
+        }
+        
+        Ok(())
+    }
+
+    /// Read the target of a symlink from the first branch containing it
+    ///
+    /// Uses `symlink_metadata` so dangling symlinks are still found.
+    pub fn read_symlink(&self, path: &Path) -> Result<std::path::PathBuf, PolicyError> {
+        for branch in self.branches().iter() {
+            let full_path = branch.full_path(path);
+            if std::fs::symlink_metadata(&full_path).is_ok() {
+                return Ok(std::fs::read_link(&full_path)?);
+            }
+        }
+        
+        Err(PolicyError::PathNotFound)
+    }
+
+    /// Remove duplicate copies of a file, keeping the one with the newest mtime
+    ///
+    /// Copies on read-only branches are left in place, as are paths that reach
+    /// an already kept or removed file (same device and inode) through a bind
+    /// mount or symlinked branch root. Fails with EEXIST if any copy's contents
+    /// differ from the newest one, and with EINVAL for anything other than a
+    /// regular file. Returns the number of copies removed.
+    pub fn prune_duplicates(&self, path: &Path) -> Result<usize, PolicyError> {
+        use crate::policy::AllSearchPolicy;
+        use std::os::unix::fs::MetadataExt;
+        
+        let mut copies = Vec::new();
+        for branch in AllSearchPolicy::new().search_branches(&self.branches(), path)? {
+            let full_path = branch.full_path(path);
+            let metadata = std::fs::symlink_metadata(&full_path)?;
+            if !metadata.is_file() {
+                return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EINVAL)));
+            }
+            copies.push((branch, full_path, (metadata.mtime(), metadata.mtime_nsec()), (metadata.dev(), metadata.ino())));
+        }
+        
+        // Newest first; ties keep branch order
+        copies.sort_by_key(|copy| std::cmp::Reverse(copy.2));
+        let (keep_branch, keep_path, _, keep_id) = &copies[0];
+        
+        for (_, full_path, _, _) in &copies[1..] {
+            if !crate::fs_utils::files_identical(keep_path, full_path)? {
+                tracing::warn!("Refusing to prune {:?}: {:?} differs from {:?}", path, full_path, keep_path);
+                return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EEXIST)));
+            }
+        }
+        
+        let mut seen = HashSet::from([*keep_id]);
+        let mut removed = 0;
+        for (branch, full_path, _, id) in &copies[1..] {
+            if branch.is_readonly() || !seen.insert(*id) {
+                continue;
+            }
+            std::fs::remove_file(full_path)?;
+            removed += 1;
+        }
+        
+        tracing::info!("Pruned {} duplicate(s) of {:?}, kept copy on {:?}", removed, path, keep_branch.path);
+        Ok(removed)
+    }
+
     pub fn create_hard_link(&self, source_path: &Path, link_path: &Path) -> Result<(), PolicyError> {
         // First, find which branch contains the source file
         let source_branch = self.find_first_branch(source_path)?;
@@ -304,59 +891,19 @@ impl FileManager {
         
         tracing::info!("Creating hard link {:?} -> {:?} in branch {:?}", source_path, link_path, branch.path);
         
-        // Check if using path-preserving policy
-        let is_path_preserving = {
-            let policy = self.create_policy.read();
-            policy.is_path_preserving()
-        };
-        if is_path_preserving {
-            // In path-preserving mode, if the parent directory doesn't exist on the same branch,
-            // return EXDEV instead of trying to create it
-            if let Some(parent) = full_link_path.parent() {
-                if !parent.exists() {
-                    tracing::debug!("Parent directory doesn't exist on same branch, returning EXDEV");
-                    return Err(PolicyError::from(std::io::Error::new(
-                        std::io::ErrorKind::CrossesDevices,
-                        "Cross-device link not permitted"
-                    )));
-                }
-            }
-        }
-        
-        // Find a branch that has the parent directory to use as template for cloning
-        let parent_path = link_path.parent().unwrap_or_else(|| Path::new("/"));
-        let template_branch = self.find_first_branch(parent_path).ok();
-        
-        // Clone parent directory structure from template branch if available
-        if let Some(ref template) = template_branch {
-            if let Some(parent) = link_path.parent() {
-                if !parent.as_os_str().is_empty() {
-                    use crate::fs_utils;
-                    if let Err(e) = fs_utils::clone_path(&template.path, &branch.path, parent) {
-                        tracing::warn!("Failed to clone parent path structure: {:?}", e);
-                        // Fall back to simple directory creation
-                        if let Some(parent_dir) = full_link_path.parent() {
-                            std::fs::create_dir_all(parent_dir)?;
-                        }
-                    }
-                }
-            }
-        } else {
-            // No template found, just create parent directories
-            if let Some(parent) = full_link_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-        }
+        let is_path_preserving = self.create_policy.read().is_path_preserving();
+        self.prepare_parent(link_path, &branch, is_path_preserving)?;
         
         // Create the hard link
         std::fs::hard_link(&full_source_path, &full_link_path)?;
         
         tracing::info!("Hard link created successfully at {:?}", full_link_path);
+        self.clear_whiteout(link_path);
         Ok(())
     }
 
     pub fn directory_exists(&self, path: &Path) -> bool {
-        self.branches.iter().any(|branch| {
+        self.branches().iter().any(|branch| {
             let full_path = branch.full_path(path);
             full_path.exists() && full_path.is_dir()
         })
@@ -364,7 +911,7 @@ impl FileManager {
 
     /// Get metadata for a path without following symlinks
     pub fn get_metadata(&self, path: &Path) -> Option<std::fs::Metadata> {
-        for branch in &self.branches {
+        for branch in self.branches().iter() {
             let full_path = branch.full_path(path);
             if let Ok(metadata) = std::fs::symlink_metadata(&full_path) {
                 return Some(metadata);
@@ -375,7 +922,10 @@ impl FileManager {
 
     /// Search for a path using the configured search policy
     pub fn search_path(&self, path: &Path) -> Result<Vec<Arc<Branch>>, PolicyError> {
-        self.search_policy.search_branches(&self.branches, path)
+        if self.is_whited_out(path) {
+            return Err(PolicyError::NoBranchesAvailable);
+        }
+        self.search_policy.search_branches(&self.branches(), path)
     }
     
     /// Get the first branch where path exists (common case)
@@ -385,15 +935,54 @@ impl FileManager {
             .ok_or(PolicyError::NoBranchesAvailable)
     }
     
+    /// Get the branch to open a file from using the open policy (func.open)
+    pub fn find_open_branch(&self, path: &Path) -> Result<Arc<Branch>, PolicyError> {
+        if self.is_whited_out(path) {
+            return Err(PolicyError::NoBranchesAvailable);
+        }
+        let branches = self.open_policy.read().search_branches(&self.branches(), path)?;
+        branches.into_iter().next()
+            .ok_or(PolicyError::NoBranchesAvailable)
+    }
+    
     /// Check if file exists in any branch using search policy
     pub fn file_exists_search(&self, path: &Path) -> bool {
         self.search_path(path).is_ok()
     }
 
     pub fn list_directory(&self, path: &Path) -> Result<Vec<String>, PolicyError> {
-        let mut entries = HashSet::new();
-        
-        for branch in &self.branches {
+        Ok(self.list_directory_with_branches(path)?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// List the union of a directory, sorted by name, with the branch providing each entry
+    pub fn list_directory_with_branches(&self, path: &Path) -> Result<Vec<(String, Arc<Branch>)>, PolicyError> {
+        Ok(self.list_directory_entries(path)?
+            .into_iter()
+            .map(|entry| (entry.name, entry.branch))
+            .collect())
+    }
+
+    /// List the union of a directory, sorted by name, as the branches' directory entries
+    ///
+    /// A name found on several branches is reported from the copy the getattr
+    /// search policy picks, as lookup does, so stat and open of an entry
+    /// resolve to the copy the listing reported. Only the directories are
+    /// read; the entries themselves are not stat'ed unless a non-ff policy
+    /// has to choose between copies.
+    pub fn list_directory_entries(&self, path: &Path) -> Result<Vec<UnionDirEntry>, PolicyError> {
+        use std::os::unix::fs::DirEntryExt;
+        
+        let mut entries: BTreeMap<String, Vec<UnionDirEntry>> = BTreeMap::new();
+        let mut whiteouts = HashSet::new();
+        let whiteout = self.whiteout_enabled();
+        
+        for branch in self.branches().iter() {
+            if !branch.is_online() {
+                continue;
+            }
             let full_path = branch.full_path(path);
             if full_path.exists() && full_path.is_dir() {
                 match std::fs::read_dir(full_path) {
@@ -401,7 +990,21 @@ impl FileManager {
                         for entry in dir_entries {
                             if let Ok(entry) = entry {
                                 if let Some(name) = entry.file_name().to_str() {
-                                    entries.insert(name.to_string());
+                                    if whiteout {
+                                        if let Some(hidden) = name.strip_prefix(WHITEOUT_PREFIX) {
+                                            whiteouts.insert(hidden.to_string());
+                                            continue;
+                                        }
+                                    }
+                                    if self.is_hidden_name(name) {
+                                        continue;
+                                    }
+                                    entries.entry(name.to_string()).or_default().push(UnionDirEntry {
+                                        name: name.to_string(),
+                                        branch: Arc::clone(branch),
+                                        file_type: entry.file_type().ok(),
+                                        ino: entry.ino(),
+                                    });
                                 }
                             }
                         }
@@ -411,9 +1014,45 @@ impl FileManager {
             }
         }
         
-        let mut result: Vec<String> = entries.into_iter().collect();
-        result.sort();
-        Ok(result)
+        Ok(entries.into_iter()
+            .filter(|(name, _)| !whiteouts.contains(name))
+            .map(|(name, copies)| self.preferred_copy(&path.join(name), copies))
+            .collect())
+    }
+    
+    /// The listed copy of `path` that lookup would report, from copies in pool order
+    ///
+    /// First found needs no stat: it is the first copy listed.
+    fn preferred_copy(&self, path: &Path, mut copies: Vec<UnionDirEntry>) -> UnionDirEntry {
+        let getattr_policy = self.getattr_policy.read();
+        if copies.len() > 1 && getattr_policy.name() != "ff" {
+            let branches: Vec<Arc<Branch>> = copies.iter().map(|copy| copy.branch.clone()).collect();
+            let chosen = getattr_policy.search_branches(&branches, path).ok()
+                .and_then(|found| found.into_iter().next())
+                .and_then(|chosen| copies.iter().position(|copy| copy.branch.path == chosen.path));
+            if let Some(idx) = chosen {
+                return copies.swap_remove(idx);
+            }
+        }
+        copies.swap_remove(0)
+    }
+
+    /// Count the distinct subdirectories of a directory across all branches
+    pub fn count_subdirectories(&self, path: &Path) -> usize {
+        let mut subdirs = HashSet::new();
+        
+        for branch in self.branches().iter() {
+            let full_path = branch.full_path(path);
+            if let Ok(dir_entries) = std::fs::read_dir(full_path) {
+                for entry in dir_entries.flatten() {
+                    if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                        subdirs.insert(entry.file_name());
+                    }
+                }
+            }
+        }
+        
+        subdirs.len()
     }
 
     pub fn remove_directory(&self, path: &Path) -> Result<(), PolicyError> {
@@ -421,7 +1060,7 @@ impl FileManager {
         let mut found_any = false;
         let mut last_error = None;
         
-        for branch in &self.branches {
+        for branch in self.branches().iter() {
             if !branch.allows_create() {
                 continue; // Skip readonly branches for removal
             }
@@ -452,11 +1091,29 @@ impl FileManager {
     }
 
     pub fn remove_file(&self, path: &Path) -> Result<(), PolicyError> {
-        // Find all branches where the file exists and remove from writable ones
+        if self.is_whited_out(path) {
+            return Err(PolicyError::NoBranchesAvailable);
+        }
+        
+        // Remove the copies on the branches selected by the unlink policy
+        // (every writable branch with the file under the default "all")
         let mut found_any = false;
         let mut last_error = None;
         
-        for branch in &self.branches {
+        // Copies on branches we can't remove from would reappear in the union
+        let branches = self.branches();
+        let shadowed = self.whiteout_enabled() && branches.iter().any(|branch| {
+            !branch.allows_create()
+                && branch.full_path(path).symlink_metadata().is_ok_and(|m| !m.is_dir())
+        });
+        
+        // With nothing removable, a shadowed copy still gets its whiteout
+        let targets = match self.unlink_policy.read().select_branches(&branches, path) {
+            Ok(targets) => targets,
+            Err(_) if shadowed => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        for branch in &targets {
             if !branch.allows_create() {
                 continue; // Skip readonly branches for removal
             }
@@ -474,7 +1131,7 @@ impl FileManager {
             }
         }
         
-        if !found_any {
+        if !found_any && !shadowed {
             return Err(PolicyError::NoBranchesAvailable);
         }
         
@@ -483,6 +1140,10 @@ impl FileManager {
             return Err(error);
         }
         
+        if shadowed {
+            self.create_whiteout(path)?;
+        }
+        
         Ok(())
     }
 
@@ -493,37 +1154,15 @@ impl FileManager {
         tracing::debug!("Selecting branch for new special file using create policy");
         let branch = {
             let policy = self.create_policy.read();
-            policy.select_branch(&self.branches, path)?
+            policy.select_branch(&self.branches(), path)?
         };
         let full_path = branch.full_path(path);
         
         tracing::info!("Selected branch {:?} for creating special file {:?}", branch.path, path);
         tracing::debug!("Full path will be: {:?}", full_path);
         
-        // Find a branch that has the parent directory to use as template for cloning
-        let parent_path = path.parent().unwrap_or_else(|| Path::new("/"));
-        let template_branch = self.find_first_branch(parent_path).ok();
-        
-        // Clone parent directory structure from template branch if available
-        if let Some(ref template) = template_branch {
-            if let Some(parent) = path.parent() {
-                if !parent.as_os_str().is_empty() {
-                    use crate::fs_utils;
-                    if let Err(e) = fs_utils::clone_path(&template.path, &branch.path, parent) {
-                        tracing::warn!("Failed to clone parent path structure: {:?}", e);
-                        // Fall back to simple directory creation
-                        if let Some(parent_dir) = full_path.parent() {
-                            std::fs::create_dir_all(parent_dir)?;
-                        }
-                    }
-                }
-            }
-        } else {
-            // No template found, just create parent directories
-            if let Some(parent) = full_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-        }
+        let is_path_preserving = self.create_policy.read().is_path_preserving();
+        self.prepare_parent(path, &branch, is_path_preserving)?;
         
         // Determine file type from mode
         let file_type = match mode & 0o170000 {
@@ -579,6 +1218,7 @@ impl FileManager {
         }
         
         tracing::info!("Special file created successfully at {:?}", full_path);
+        self.clear_whiteout(path);
         Ok(())
     }
 }
@@ -807,6 +1447,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_create_in_readonly_union_is_erofs() {
+        let temp1 = TempDir::new().unwrap();
+        let temp2 = TempDir::new().unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(temp1.path().to_path_buf(), BranchMode::ReadOnly)),
+            Arc::new(Branch::new(temp2.path().to_path_buf(), BranchMode::NoCreate)),
+        ];
+        let file_manager = FileManager::new(branches, Box::new(FirstFoundCreatePolicy::new()));
+        
+        let result = file_manager.create_file(Path::new("/file.txt"), b"");
+        assert!(matches!(result, Err(PolicyError::ReadOnlyFilesystem)), "{:?}", result);
+        assert_eq!(result.unwrap_err().errno(), EROFS);
+    }
+
+    #[test]
+    fn test_create_on_unpermitted_branch_is_eacces() {
+        let temp1 = TempDir::new().unwrap();
+        let temp2 = TempDir::new().unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(temp1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(temp2.path().to_path_buf(), BranchMode::ReadOnly)),
+        ];
+        let file_manager = FileManager::new(branches, Box::new(FirstFoundCreatePolicy::new()));
+        
+        // The only writable branch refuses, yet the pool is not read-only.
+        // The denial is injected, since root would be let through a 0555 branch.
+        let attempts = std::cell::Cell::new(0);
+        let result = file_manager.create_file_with(Path::new("/file.txt"), |_, _| None, |branch, _| {
+            assert_eq!(branch.path, temp1.path());
+            attempts.set(attempts.get() + 1);
+            Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EACCES)))
+        });
+        assert_eq!(attempts.get(), 1);
+        assert!(matches!(result, Err(PolicyError::PermissionDenied)), "{:?}", result);
+        assert_eq!(result.unwrap_err().errno(), EACCES);
+    }
+
+    #[test]
+    fn test_permission_failure_outranks_readonly_failure() {
+        let erofs = || PolicyError::IoError(std::io::Error::from_raw_os_error(EROFS));
+        let eacces = || PolicyError::IoError(std::io::Error::from_raw_os_error(EACCES));
+        
+        // A branch mounted read-only underneath, then one denying access
+        let error = create_refused_error(vec![erofs(), eacces()], PolicyError::ReadOnlyFilesystem);
+        assert!(matches!(error, PolicyError::PermissionDenied));
+        
+        let error = create_refused_error(vec![erofs()], PolicyError::ReadOnlyFilesystem);
+        assert_eq!(error.errno(), EROFS);
+        let error = create_refused_error(Vec::new(), PolicyError::NoBranchesAvailable);
+        assert!(matches!(error, PolicyError::NoBranchesAvailable));
+    }
+
     #[test]
     fn test_create_special_file_fifo() {
         let (_temps, branches) = setup_test_branches();
@@ -822,7 +1515,7 @@ mod tests {
         assert!(result.is_ok());
         
         // Verify the FIFO was created in the first branch
-        let branch = &file_manager.branches[0];
+        let branch = &file_manager.branches()[0];
         let full_path = branch.full_path(fifo_path);
         assert!(full_path.exists());
         
@@ -846,7 +1539,7 @@ mod tests {
         assert!(result.is_ok());
         
         // Verify the file was created
-        let branch = &file_manager.branches[0];
+        let branch = &file_manager.branches()[0];
         let full_path = branch.full_path(file_path);
         assert!(full_path.exists());
         assert!(full_path.is_file());
@@ -873,7 +1566,7 @@ mod tests {
         assert!(result.is_ok());
         
         // Verify the parent directory was created
-        let branch = &file_manager.branches[0];
+        let branch = &file_manager.branches()[0];
         let parent_path = branch.full_path(Path::new("subdir"));
         assert!(parent_path.exists());
         assert!(parent_path.is_dir());
@@ -910,6 +1603,495 @@ mod tests {
             _ => panic!("Expected error"),
         }
     }
+
+    #[test]
+    fn test_epff_unlink_policy_removes_first_copy_only() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        let path = Path::new("dup.txt");
+        std::fs::write(branches[0].full_path(path), b"first").unwrap();
+        std::fs::write(branches[1].full_path(path), b"second").unwrap();
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        assert_eq!(config_manager.get_option("func.unlink").unwrap(), "all");
+        config_manager.set_option("func.unlink", "epff").unwrap();
+        assert_eq!(file_manager.get_unlink_policy_name(), "epff");
+        
+        // Only the first branch's copy goes; the next one shows through
+        file_manager.remove_file(path).unwrap();
+        assert!(!branches[0].full_path(path).exists());
+        assert_eq!(std::fs::read(branches[1].full_path(path)).unwrap(), b"second");
+        assert_eq!(file_manager.find_first_branch(path).unwrap().path, branches[1].path);
+        
+        // The default removes every copy
+        std::fs::write(branches[0].full_path(path), b"first").unwrap();
+        config_manager.set_option("func.unlink", "all").unwrap();
+        file_manager.remove_file(path).unwrap();
+        assert!(!branches[0].full_path(path).exists());
+        assert!(!branches[1].full_path(path).exists());
+        
+        assert!(config_manager.set_option("func.unlink", "mfs").is_err());
+    }
+    
+    #[test]
+    fn test_unlink_policy_errors_are_returned() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        let path = Path::new("pinned.txt");
+        std::fs::write(branches[2].full_path(path), b"read-only copy").unwrap();
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        
+        // epall refuses a file that only exists on read-only branches
+        config_manager.set_option("func.unlink", "epall").unwrap();
+        assert_eq!(file_manager.remove_file(path).unwrap_err().errno(), EROFS);
+        assert!(branches[2].full_path(path).exists());
+        
+        assert_eq!(file_manager.remove_file(Path::new("missing.txt")).unwrap_err().errno(), crate::errno::ENOENT);
+    }
+    
+    #[test]
+    fn test_rand_open_policy_spreads_reads() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        
+        // Replicate the same file on every branch, tagged with the branch index
+        for (idx, branch) in branches.iter().enumerate() {
+            std::fs::write(branch.full_path(Path::new("replica.bin")), format!("{}", idx)).unwrap();
+        }
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        assert_eq!(config_manager.get_option("func.open").unwrap(), "ff");
+        config_manager.set_option("func.open", "rand").unwrap();
+        assert_eq!(file_manager.get_open_policy_name(), "rand");
+        
+        const OPENS: usize = 3000;
+        let mut counts = [0usize; 3];
+        for _ in 0..OPENS {
+            let branch = file_manager.find_open_branch(Path::new("replica.bin")).unwrap();
+            let content = std::fs::read_to_string(branch.full_path(Path::new("replica.bin"))).unwrap();
+            counts[content.parse::<usize>().unwrap()] += 1;
+        }
+        
+        // Each branch should get roughly a third of the reads
+        let expected = OPENS / 3;
+        for (idx, count) in counts.iter().enumerate() {
+            assert!(
+                count.abs_diff(expected) < expected / 5,
+                "branch {} served {} of {} reads: {:?}", idx, count, OPENS, counts
+            );
+        }
+        
+        // Switching back to ff always picks the first branch
+        config_manager.set_option("func.open", "ff").unwrap();
+        let branch = file_manager.find_open_branch(Path::new("replica.bin")).unwrap();
+        assert_eq!(branch.path, branches[0].path);
+        
+        assert!(config_manager.set_option("func.open", "pfrd").is_err());
+    }
+
+    #[test]
+    fn test_msprand_create_policy_follows_minfreespace() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        use crate::test_utils::SpacePolicyTestSetup;
+        
+        let setup = SpacePolicyTestSetup::new(10, 50, 90);
+        setup.setup_space();
+        let branches = setup.get_branches();
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        let selected = || {
+            let policy = file_manager.create_policy.read();
+            policy.select_branch(&file_manager.branches(), Path::new("/file")).unwrap().path.clone()
+        };
+        
+        // The threshold set before and after choosing the policy both apply
+        config_manager.set_option("minfreespace", "20M").unwrap();
+        config_manager.set_option("func.create", "msprand").unwrap();
+        assert_eq!(file_manager.get_create_policy_name(), "msprand");
+        let picked: HashSet<_> = (0..50).map(|_| selected()).collect();
+        assert_eq!(picked, HashSet::from([branches[1].path.clone(), branches[2].path.clone()]));
+        
+        config_manager.set_option("minfreespace", "60M").unwrap();
+        for _ in 0..20 {
+            assert_eq!(selected(), branches[2].path);
+        }
+    }
+
+    #[test]
+    fn test_tiebreak_option_applies_to_mfs() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        use crate::test_utils::SpacePolicyTestSetup;
+        
+        let setup = SpacePolicyTestSetup::new(50, 50, 10);
+        setup.setup_space();
+        let branches = setup.get_branches();
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        let picked = || -> HashSet<_> {
+            let policy = file_manager.create_policy.read();
+            (0..100).map(|_| policy.select_branch(&file_manager.branches(), Path::new("/file")).unwrap().path.clone()).collect()
+        };
+        
+        assert_eq!(config_manager.get_option("tiebreak").unwrap(), "first");
+        config_manager.set_option("func.create", "mfs").unwrap();
+        assert_eq!(picked(), HashSet::from([branches[0].path.clone()]));
+        
+        config_manager.set_option("tiebreak", "random").unwrap();
+        assert_eq!(file_manager.get_create_policy_name(), "mfs");
+        assert_eq!(picked(), HashSet::from([branches[0].path.clone(), branches[1].path.clone()]));
+        assert!(config_manager.set_option("tiebreak", "last").is_err());
+        assert_eq!(config_manager.get_option("tiebreak").unwrap(), "random");
+    }
+
+    #[test]
+    fn test_prune_identical_duplicates_keeps_newest() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        use filetime::{set_file_mtime, FileTime};
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        let path = Path::new("/dup.txt");
+        for branch in &branches {
+            std::fs::write(branch.full_path(path), b"same contents").unwrap();
+        }
+        // The copy on the second branch is the newest
+        for (idx, branch) in branches.iter().enumerate() {
+            let mtime = if idx == 1 { 2_000_000 } else { 1_000_000 };
+            set_file_mtime(branch.full_path(path), FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        config_manager.set_option("user.mergerfs.prune", "/dup.txt").unwrap();
+        
+        assert!(!branches[0].full_path(path).exists());
+        assert!(branches[1].full_path(path).exists());
+        // Read-only branch copies are left alone
+        assert!(branches[2].full_path(path).exists());
+        
+        // Nothing left to prune on writable branches
+        assert_eq!(file_manager.prune_duplicates(path).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_prune_keeps_branches_aliasing_the_same_file() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        std::fs::create_dir(&root).unwrap();
+        std::os::unix::fs::symlink(&root, temp.path().join("alias")).unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(root.clone(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(temp.path().join("alias"), BranchMode::ReadWrite)),
+        ];
+        std::fs::write(root.join("only.txt"), b"only copy").unwrap();
+        
+        let file_manager = FileManager::new(branches, Box::new(FirstFoundCreatePolicy::new()));
+        assert_eq!(file_manager.prune_duplicates(Path::new("/only.txt")).unwrap(), 0);
+        assert_eq!(std::fs::read(root.join("only.txt")).unwrap(), b"only copy");
+    }
+
+    #[test]
+    fn test_prune_refuses_differing_duplicates() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        let path = Path::new("/conflict.txt");
+        std::fs::write(branches[0].full_path(path), b"version one").unwrap();
+        std::fs::write(branches[1].full_path(path), b"version two").unwrap();
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        
+        let err = config_manager.set_option("prune", "/conflict.txt").unwrap_err();
+        assert_eq!(err.errno(), 17); // EEXIST
+        assert!(branches[0].full_path(path).exists());
+        assert!(branches[1].full_path(path).exists());
+        
+        assert_eq!(config_manager.set_option("prune", "/missing.txt").unwrap_err().errno(), 2);
+        assert_eq!(config_manager.set_option("prune", "/../escape").unwrap_err().errno(), 22);
+    }
+
+    #[test]
+    fn test_find_query_lists_branches_holding_path() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        use crate::errno::ToErrno;
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        std::fs::create_dir(branches[0].full_path(Path::new("/docs"))).unwrap();
+        std::fs::create_dir(branches[2].full_path(Path::new("/docs"))).unwrap();
+        std::fs::write(branches[0].full_path(Path::new("/docs/50% off.txt")), b"a").unwrap();
+        std::fs::write(branches[2].full_path(Path::new("/docs/50% off.txt")), b"b").unwrap();
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        
+        let found = config_manager.get_option("user.mergerfs.find./docs/50%25%20off.txt").unwrap();
+        let expected = format!("{}\n{}", branches[0].path.display(), branches[2].path.display());
+        assert_eq!(found, expected);
+        assert_eq!(config_manager.get_option("find./docs").unwrap(), expected);
+        
+        assert_eq!(config_manager.get_option("find./missing.txt").unwrap_err().errno(), 2);
+        assert_eq!(config_manager.get_option("find./docs/50% off.txt").unwrap_err().errno(), 22);
+        assert_eq!(config_manager.get_option("find.docs").unwrap_err().errno(), 22);
+        assert_eq!(config_manager.get_option("find./docs/../..").unwrap_err().errno(), 22);
+    }
+
+    #[test]
+    fn test_quota_errors_count_as_out_of_space() {
+        use crate::errno::{EDQUOT, ENOSPC};
+        
+        // A branch over its quota is as full as one out of blocks
+        for errno in [ENOSPC, EDQUOT] {
+            let error = PolicyError::IoError(std::io::Error::from_raw_os_error(errno));
+            assert!(is_no_space_error(&error), "errno {}", errno);
+            assert!(!is_create_fallback_error(&error), "errno {}", errno);
+        }
+        assert!(is_no_space_error(&PolicyError::NoSpace));
+        assert!(!is_no_space_error(&PolicyError::IoError(std::io::Error::from_raw_os_error(EACCES))));
+    }
+
+    #[test]
+    fn test_create_falls_back_when_selected_branch_unwritable() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        assert!(is_create_fallback_error(&PolicyError::ReadOnlyFilesystem));
+        assert!(is_create_fallback_error(&PolicyError::IoError(std::io::Error::from_raw_os_error(13))));
+        assert!(!is_create_fallback_error(&PolicyError::NoSpace));
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        std::fs::set_permissions(&branches[0].path, std::fs::Permissions::from_mode(0o555)).unwrap();
+        
+        // Privileged users bypass directory permissions, so there is nothing to fall back from
+        let probe = branches[0].path.join(".probe");
+        if File::create(&probe).is_ok() {
+            std::fs::remove_file(&probe).unwrap();
+            std::fs::set_permissions(&branches[0].path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            return;
+        }
+        
+        let file_manager = FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new()));
+        let path = Path::new("/fallback.txt");
+        let result = file_manager.create_file(path, b"landed");
+        std::fs::set_permissions(&branches[0].path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        
+        result.unwrap();
+        assert!(!branches[0].full_path(path).exists());
+        assert_eq!(std::fs::read(branches[1].full_path(path)).unwrap(), b"landed");
+    }
+
+    #[test]
+    fn test_listing_hides_internal_artifacts() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        
+        assert!(matches_hide_pattern(".wh.*", ".wh.old.txt"));
+        assert!(matches_hide_pattern("*.tmp", "download.part.tmp"));
+        assert!(matches_hide_pattern("?.log", "a.log"));
+        assert!(!matches_hide_pattern("?.log", "ab.log"));
+        assert!(!matches_hide_pattern(".mergerfs", ".mergerfs.bak"));
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        std::fs::create_dir(branches[0].full_path(Path::new("/docs"))).unwrap();
+        std::fs::write(branches[0].full_path(Path::new("/docs/report.txt")), b"keep").unwrap();
+        std::fs::write(branches[0].full_path(Path::new("/docs/.wh.gone.txt")), b"").unwrap();
+        std::fs::write(branches[1].full_path(Path::new("/.mergerfs")), b"stray").unwrap();
+        std::fs::create_dir(branches[1].full_path(Path::new("/docs"))).unwrap();
+        std::fs::write(branches[1].full_path(Path::new("/docs/.mergerfs")), b"stray").unwrap();
+        std::fs::write(branches[1].full_path(Path::new("/docs/draft.tmp")), b"").unwrap();
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        assert!(!file_manager.whiteout_enabled());
+        assert_eq!(file_manager.list_directory(Path::new("/docs")).unwrap(), vec!["draft.tmp", "report.txt"]);
+        assert_eq!(file_manager.list_directory(Path::new("/")).unwrap(), vec!["docs"]);
+        
+        // The pattern list can be changed at runtime
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        assert_eq!(config_manager.get_option("hide_patterns").unwrap(), ".wh.*|.mergerfs");
+        config_manager.set_option("hide_patterns", ".wh.*|.mergerfs|*.tmp").unwrap();
+        assert_eq!(file_manager.list_directory(Path::new("/docs")).unwrap(), vec!["report.txt"]);
+    }
+
+    #[test]
+    fn test_unlink_shadowed_file_reappears_without_whiteout() {
+        let (_temp_dirs, branches) = setup_test_branches();
+        let path = Path::new("/shadowed.txt");
+        std::fs::write(branches[0].full_path(path), b"writable copy").unwrap();
+        std::fs::write(branches[2].full_path(path), b"read-only copy").unwrap();
+        
+        let file_manager = FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new()));
+        file_manager.remove_file(path).unwrap();
+        
+        // The read-only copy shows through again
+        assert!(!branches[0].full_path(path).exists());
+        assert!(file_manager.file_exists(path));
+        assert_eq!(file_manager.read_file(path).unwrap(), b"read-only copy");
+        assert!(file_manager.list_directory(Path::new("/")).unwrap().contains(&"shadowed.txt".to_string()));
+    }
+
+    #[test]
+    fn test_unlink_shadowed_file_hidden_with_whiteout() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        let path = Path::new("/dir/shadowed.txt");
+        // The parent only exists on the read-only branch besides the writable copy's branch
+        std::fs::create_dir_all(branches[1].full_path(Path::new("/dir"))).unwrap();
+        std::fs::create_dir_all(branches[2].full_path(Path::new("/dir"))).unwrap();
+        std::fs::write(branches[1].full_path(path), b"writable copy").unwrap();
+        std::fs::write(branches[2].full_path(path), b"read-only copy").unwrap();
+        
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        config_manager.set_option("whiteout", "true").unwrap();
+        assert!(file_manager.whiteout_enabled());
+        
+        file_manager.remove_file(path).unwrap();
+        
+        // Marker lands on the first writable branch and hides the read-only copy
+        assert!(branches[0].full_path(Path::new("/dir/.wh.shadowed.txt")).exists());
+        assert!(branches[2].full_path(path).exists());
+        assert!(!file_manager.file_exists(path));
+        assert!(file_manager.find_file_with_metadata(path).is_none());
+        assert!(file_manager.find_first_branch(path).is_err());
+        assert!(file_manager.read_file(path).is_err());
+        assert!(file_manager.list_directory(Path::new("/dir")).unwrap().is_empty());
+        assert!(matches!(file_manager.remove_file(path), Err(PolicyError::NoBranchesAvailable)));
+        
+        // Recreating the file removes the marker
+        file_manager.create_file(path, b"new contents").unwrap();
+        assert!(!branches[0].full_path(Path::new("/dir/.wh.shadowed.txt")).exists());
+        assert_eq!(file_manager.read_file(path).unwrap(), b"new contents");
+        assert_eq!(file_manager.list_directory(Path::new("/dir")).unwrap(), vec!["shadowed.txt"]);
+        
+        // Disabling whiteout makes existing markers inert
+        file_manager.remove_file(path).unwrap();
+        config_manager.set_option("whiteout", "false").unwrap();
+        assert_eq!(file_manager.read_file(path).unwrap(), b"read-only copy");
+    }
+
+    #[test]
+    fn test_sync_parent_dir() {
+        use std::os::unix::fs::MetadataExt;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let parent = temp_dir.path().join("dir");
+        std::fs::create_dir(&parent).unwrap();
+        let full_path = parent.join("new.txt");
+        std::fs::write(&full_path, b"").unwrap();
+        
+        assert!(!sync_parent_dir(&full_path, false, |_| panic!("synced while disabled")).unwrap());
+        
+        let mut synced = None;
+        assert!(sync_parent_dir(&full_path, true, |dir| {
+            synced = Some(dir.metadata()?.ino());
+            dir.sync_all()
+        }).unwrap());
+        assert_eq!(synced, Some(std::fs::metadata(&parent).unwrap().ino()));
+        
+        // Errors from the sync are passed on
+        let err = sync_parent_dir(&full_path, true, |_| Err(std::io::Error::from_raw_os_error(crate::errno::EIO))).unwrap_err();
+        assert_eq!(err.raw_os_error(), Some(crate::errno::EIO));
+    }
+    
+    #[test]
+    fn test_create_durable_option() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        
+        let (_temp_dirs, branches) = setup_test_branches();
+        let file_manager = Arc::new(FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new())));
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_file_manager(&file_manager);
+        assert_eq!(config_manager.get_option("create_durable").unwrap(), "false");
+        assert!(!file_manager.create_durable_enabled());
+        
+        
+        // The parent directory is synced exactly once per create, and only when enabled
+        let synced = std::sync::atomic::AtomicUsize::new(0);
+        let count_sync = |_: &File| {
+            synced.fetch_add(1, Ordering::Relaxed);
+            Ok(())
+        };
+        file_manager.create_file_syncing_dir_with(Path::new("/docs/draft.txt"), b"not durable", |_, _| None, count_sync).unwrap();
+        assert_eq!(synced.load(Ordering::Relaxed), 0);
+        
+        config_manager.set_option("create_durable", "true").unwrap();
+        assert!(file_manager.create_durable_enabled());
+        file_manager.create_file_syncing_dir_with(Path::new("/docs/report.txt"), b"durable", |_, _| None, count_sync).unwrap();
+        assert_eq!(synced.load(Ordering::Relaxed), 1);
+        assert_eq!(std::fs::read(branches[0].full_path(Path::new("/docs/report.txt"))).unwrap(), b"durable");
+        
+        // A failed sync fails the create
+        let err = file_manager
+            .create_file_syncing_dir_with(Path::new("/docs/lost.txt"), b"", |_, _| None, |_| {
+                Err(std::io::Error::from_raw_os_error(crate::errno::EIO))
+            })
+            .unwrap_err();
+        assert_eq!(err.errno(), crate::errno::EIO);
+    }
+
+    #[test]
+    fn test_pinned_directory_overrides_create_policy() {
+        let (_temp_dirs, branches) = setup_test_branches();
+        let file_manager = FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new()));
+        file_manager.set_mkdir_policy(Box::new(FirstFoundCreatePolicy::new()));
+        
+        // ff would put everything on branch 1
+        file_manager.create_directory(Path::new("/downloads")).unwrap();
+        assert!(!branches[1].full_path(Path::new("/downloads")).exists());
+        
+        file_manager.pin_branch(Path::new("/downloads"), &branches[1].path).unwrap();
+        assert_eq!(file_manager.get_branch_pin(Path::new("downloads")).unwrap().path, branches[1].path);
+        
+        // Files and directories anywhere below the pin land on branch 2
+        file_manager.create_file(Path::new("/downloads/movie.mkv"), b"data").unwrap();
+        file_manager.create_directory(Path::new("/downloads/season1")).unwrap();
+        file_manager.create_file(Path::new("/downloads/season1/ep1.mkv"), b"data").unwrap();
+        assert!(branches[1].full_path(Path::new("/downloads/movie.mkv")).exists());
+        assert!(branches[1].full_path(Path::new("/downloads/season1/ep1.mkv")).exists());
+        assert!(!branches[0].full_path(Path::new("/downloads/movie.mkv")).exists());
+        
+        // Paths outside the pin still follow the policy
+        file_manager.create_file(Path::new("/other.txt"), b"data").unwrap();
+        assert!(branches[0].full_path(Path::new("/other.txt")).exists());
+        
+        // Read-only and unknown branches can't be pinned
+        assert!(matches!(
+            file_manager.pin_branch(Path::new("/downloads"), &branches[2].path),
+            Err(PolicyError::ReadOnlyFilesystem)
+        ));
+        assert!(file_manager.pin_branch(Path::new("/downloads"), Path::new("/nonexistent")).is_err());
+        assert!(file_manager.pin_branch(Path::new("/missing"), &branches[1].path).is_err());
+        
+        assert!(file_manager.unpin_branch(Path::new("/downloads")));
+        file_manager.create_file(Path::new("/downloads/after.txt"), b"data").unwrap();
+        assert!(branches[0].full_path(Path::new("/downloads/after.txt")).exists());
+    }
 }
 #[cfg(test)]
 mod path_preservation_tests {
@@ -1045,6 +2227,7 @@ mod path_preservation_tests {
             branches.clone(),
             Box::new(ExistingPathFirstFoundCreatePolicy::new()),
         );
+        manager.set_mkdir_policy(Box::new(ExistingPathFirstFoundCreatePolicy::new()));
         
         // Create a directory - should be placed in branch 1 (has parent)
         let result = manager.create_directory(Path::new("/path/to/newdir"));
@@ -1112,4 +2295,78 @@ mod path_preservation_tests {
         // Directory structure should be preserved
         assert!(temp_dir1.path().join("a/b/c/d/e").is_dir());
     }
+    /// Always picks the last branch, whether or not it has the parent
+    struct LastBranchPolicy {
+        path_preserving: bool,
+    }
+
+    impl crate::policy::traits::CreatePolicy for LastBranchPolicy {
+        fn name(&self) -> &'static str {
+            "last"
+        }
+
+        fn select_branch(&self, branches: &[Arc<Branch>], _path: &Path) -> Result<Arc<Branch>, PolicyError> {
+            branches.last().cloned().ok_or(PolicyError::NoBranchesAvailable)
+        }
+
+        fn is_path_preserving(&self) -> bool {
+            self.path_preserving
+        }
+    }
+
+    /// `/dir` exists only on the first branch; the policy picks the second
+    fn setup_parent_on_other_branch(path_preserving: bool) -> (Vec<TempDir>, FileManager) {
+        let temp_dirs = vec![TempDir::new().unwrap(), TempDir::new().unwrap()];
+        fs::create_dir(temp_dirs[0].path().join("dir")).unwrap();
+        fs::write(temp_dirs[1].path().join("source.txt"), b"data").unwrap();
+        let branches = temp_dirs.iter()
+            .map(|dir| Arc::new(Branch::new(dir.path().to_path_buf(), BranchMode::ReadWrite)))
+            .collect();
+        let manager = FileManager::new(branches, Box::new(LastBranchPolicy { path_preserving }));
+        manager.set_mkdir_policy(Box::new(LastBranchPolicy { path_preserving }));
+        (temp_dirs, manager)
+    }
+
+    /// Create one entry of each kind under `/dir`
+    fn create_each_kind(manager: &FileManager) -> Vec<(&'static str, Result<(), PolicyError>)> {
+        vec![
+            ("file", manager.create_file(Path::new("/dir/file"), b"data")),
+            ("directory", manager.create_directory(Path::new("/dir/subdir"))),
+            ("symlink", manager.create_symlink(Path::new("/dir/symlink"), Path::new("file"))),
+            ("special", manager.create_special_file(Path::new("/dir/fifo"), 0o010644, 0)),
+            ("link", manager.create_hard_link(Path::new("/source.txt"), Path::new("/dir/link"))),
+        ]
+    }
+
+    #[test]
+    fn test_path_preserving_create_without_parent_is_exdev_for_every_kind() {
+        use crate::errno::{ToErrno, EXDEV};
+
+        let (temp_dirs, manager) = setup_parent_on_other_branch(true);
+
+        for (kind, result) in create_each_kind(&manager) {
+            match result {
+                Err(e) => assert_eq!(e.errno(), EXDEV, "{} creation returned {:?}", kind, e),
+                Ok(()) => panic!("{} creation succeeded without the parent", kind),
+            }
+        }
+        assert!(!temp_dirs[1].path().join("dir").exists());
+    }
+
+    #[test]
+    fn test_non_path_preserving_create_clones_parent_for_every_kind() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (temp_dirs, manager) = setup_parent_on_other_branch(false);
+        fs::set_permissions(temp_dirs[0].path().join("dir"), fs::Permissions::from_mode(0o750)).unwrap();
+
+        for (kind, result) in create_each_kind(&manager) {
+            assert!(result.is_ok(), "{} creation failed: {:?}", kind, result);
+        }
+        let cloned = temp_dirs[1].path().join("dir");
+        assert_eq!(fs::metadata(&cloned).unwrap().permissions().mode() & 0o7777, 0o750);
+        for name in ["file", "subdir", "symlink", "fifo", "link"] {
+            assert!(cloned.join(name).symlink_metadata().is_ok(), "{} missing", name);
+        }
+    }
 }
\ No newline at end of file
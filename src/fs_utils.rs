@@ -1,13 +1,21 @@
 use std::fs;
 use std::io;
-use std::path::Path;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 use filetime::{set_file_times, FileTime};
+use tempfile::NamedTempFile;
 
 /// Clone a directory path from source to destination, preserving metadata
 /// 
 /// This function creates the directory structure at the destination, copying
-/// permissions and timestamps from the source directories.
+/// ownership, permissions and timestamps from the source directories.
+/// Ownership is only copied where the process is allowed to chown; the
+/// timestamps are set once every directory exists, so creating a child does
+/// not leave its parent with a fresh mtime.
 pub fn clone_path(src_base: &Path, dst_base: &Path, relative_path: &Path) -> io::Result<()> {
+    // FUSE paths are absolute; joining one onto a base would replace the base
+    let relative_path = relative_path.strip_prefix("/").unwrap_or(relative_path);
+    
     // Build the full paths
     let dst_full = dst_base.join(relative_path);
     
@@ -32,6 +40,7 @@ pub fn clone_path(src_base: &Path, dst_base: &Path, relative_path: &Path) -> io:
     components.reverse();
     components.push(relative_path);
     
+    let mut created = Vec::new();
     for component in components {
         let src_dir = src_base.join(component);
         let dst_dir = dst_base.join(component);
@@ -54,19 +63,25 @@ pub fn clone_path(src_base: &Path, dst_base: &Path, relative_path: &Path) -> io:
         // Create directory
         fs::create_dir(&dst_dir)?;
         
+        // Copy ownership before permissions, since chown may clear setgid
+        if let Err(e) = std::os::unix::fs::chown(&dst_dir, Some(src_metadata.uid()), Some(src_metadata.gid())) {
+            tracing::debug!("Could not copy ownership of {:?} to {:?}: {}", src_dir, dst_dir, e);
+        }
+        
         // Copy permissions
         let permissions = src_metadata.permissions();
         fs::set_permissions(&dst_dir, permissions)?;
         
-        // Copy timestamps
-        if let (Ok(accessed), Ok(modified)) = (src_metadata.accessed(), src_metadata.modified()) {
-            let atime = FileTime::from_system_time(accessed);
-            let mtime = FileTime::from_system_time(modified);
-            let _ = set_file_times(&dst_dir, atime, mtime);
-        }
-        
-        // Note: Extended attributes (xattr) and ownership changes would require
-        // additional dependencies and potentially elevated privileges
+        // Note: Extended attributes (xattr) would require additional
+        // dependencies and potentially elevated privileges
+        created.push((dst_dir, src_metadata));
+    }
+    
+    // Copy timestamps
+    for (dst_dir, src_metadata) in created {
+        let atime = FileTime::from_last_access_time(&src_metadata);
+        let mtime = FileTime::from_last_modification_time(&src_metadata);
+        let _ = set_file_times(&dst_dir, atime, mtime);
     }
     
     Ok(())
@@ -92,6 +107,173 @@ pub fn ensure_parent_cloned(src_base: &Path, dst_base: &Path, file_path: &Path)
     }
 }
 
+/// Path of `target` relative to the directory `from_dir`
+///
+/// Both are pool paths (absolute, without `.` or `..`), e.g. `/b/c` to `/a/f`
+/// gives `../../a/f`. Used as a symlink target that resolves inside the pool.
+pub fn relative_path(from_dir: &Path, target: &Path) -> PathBuf {
+    let from: Vec<_> = from_dir.components().collect();
+    let to: Vec<_> = target.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+/// Whether the regular file at `full_path` has other names on its branch
+pub fn is_hard_linked(full_path: &Path) -> bool {
+    fs::symlink_metadata(full_path)
+        .map(|metadata| metadata.is_file() && metadata.nlink() > 1)
+        .unwrap_or(false)
+}
+
+/// Break a hard link by replacing the file with a private copy
+///
+/// If the file at `full_path` has more than one link, its contents, permissions
+/// and timestamps are copied to a temporary file in the same directory which is
+/// then renamed over the original path. Other links keep the old inode and are
+/// unaffected by later writes. Returns true if a copy was made.
+pub fn break_hard_link(full_path: &Path) -> io::Result<bool> {
+    let metadata = fs::symlink_metadata(full_path)?;
+    if !metadata.is_file() || metadata.nlink() <= 1 {
+        return Ok(false);
+    }
+    
+    let parent = full_path.parent().unwrap_or(Path::new("/"));
+    let temp_file = NamedTempFile::new_in(parent)?;
+    
+    fs::copy(full_path, temp_file.path())?;
+    fs::set_permissions(temp_file.path(), metadata.permissions())?;
+    let atime = FileTime::from_last_access_time(&metadata);
+    let mtime = FileTime::from_last_modification_time(&metadata);
+    let _ = set_file_times(temp_file.path(), atime, mtime);
+    
+    temp_file.persist(full_path).map_err(|e| e.error)?;
+    
+    Ok(true)
+}
+
+/// How `copy_file_data` ended up copying a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyMethod {
+    Reflink,
+    CopyFileRange,
+    Buffered,
+}
+
+/// Copy the entire contents of `src` into the empty file `dst`
+///
+/// Files on the same device (same `st_dev`) are first shared with a FICLONE
+/// reflink, which is instant on btrfs and xfs, and otherwise copied in the
+/// kernel with copy_file_range. Files on different devices, or filesystems
+/// that support neither, fall back to a buffered copy. Returns the number of
+/// bytes copied and the method that did it.
+pub fn copy_file_data(src: &fs::File, dst: &fs::File) -> io::Result<(u64, CopyMethod)> {
+    use std::os::unix::io::AsRawFd;
+    
+    let src_meta = src.metadata()?;
+    if src_meta.dev() == dst.metadata()?.dev() {
+        // SAFETY: FICLONE only reads the two descriptors, which outlive the call
+        let ret = unsafe { nix::libc::ioctl(dst.as_raw_fd(), nix::libc::FICLONE as _, src.as_raw_fd()) };
+        if ret == 0 {
+            return Ok((src_meta.len(), CopyMethod::Reflink));
+        }
+        tracing::debug!("reflink unavailable: {}", io::Error::last_os_error());
+        
+        let mut total = 0u64;
+        loop {
+            match nix::fcntl::copy_file_range(src, None, dst, None, 1 << 30) {
+                Ok(0) => return Ok((total, CopyMethod::CopyFileRange)),
+                Ok(n) => total += n as u64,
+                Err(e) => {
+                    // Offsets have advanced past whatever was copied, so the
+                    // buffered copy simply carries on from there
+                    tracing::debug!("copy_file_range failed after {} bytes: {}", total, e);
+                    let (rest, _) = buffered_copy(src, dst)?;
+                    return Ok((total + rest, CopyMethod::Buffered));
+                }
+            }
+        }
+    }
+    
+    buffered_copy(src, dst)
+}
+
+fn buffered_copy(mut src: &fs::File, mut dst: &fs::File) -> io::Result<(u64, CopyMethod)> {
+    use std::io::{Read, Write};
+    
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut total = 0u64;
+    loop {
+        let bytes_read = src.read(&mut buffer)?;
+        if bytes_read == 0 {
+            return Ok((total, CopyMethod::Buffered));
+        }
+        dst.write_all(&buffer[..bytes_read])?;
+        total += bytes_read as u64;
+    }
+}
+
+/// Compare two files byte for byte
+pub fn files_identical(a: &Path, b: &Path) -> io::Result<bool> {
+    use std::io::Read;
+    
+    if fs::metadata(a)?.len() != fs::metadata(b)?.len() {
+        return Ok(false);
+    }
+    
+    let mut file_a = fs::File::open(a)?;
+    let mut file_b = fs::File::open(b)?;
+    let mut buf_a = vec![0u8; 64 * 1024];
+    let mut buf_b = vec![0u8; 64 * 1024];
+    loop {
+        let n = file_a.read(&mut buf_a)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        file_b.read_exact(&mut buf_b[..n])?;
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+// Inode flags (chattr attributes) from linux/fs.h
+pub const FS_IMMUTABLE_FL: u32 = 0x10;
+pub const FS_APPEND_FL: u32 = 0x20;
+
+/// Inode flags of an open file, read with FS_IOC_GETFLAGS
+///
+/// Filesystems without flag support fail with ENOTTY or EOPNOTSUPP.
+pub fn inode_flags(file: &fs::File) -> io::Result<u32> {
+    use std::os::unix::io::AsRawFd;
+    
+    let mut flags: nix::libc::c_int = 0;
+    // SAFETY: FS_IOC_GETFLAGS writes a single int into `flags`
+    let ret = unsafe { nix::libc::ioctl(file.as_raw_fd(), nix::libc::FS_IOC_GETFLAGS as _, &mut flags) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(flags as u32)
+}
+
+/// Inode flags of the file at `full_path`, which is not followed if it is a symlink
+pub fn path_inode_flags(full_path: &Path) -> io::Result<u32> {
+    use std::os::unix::fs::OpenOptionsExt;
+    
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags((nix::fcntl::OFlag::O_NONBLOCK | nix::fcntl::OFlag::O_NOFOLLOW).bits())
+        .open(full_path)?;
+    inode_flags(&file)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -163,6 +345,35 @@ mod tests {
         assert_eq!(fs::metadata(dst_base.join("a/b/c")).unwrap().permissions().mode() & 0o777, 0o700);
     }
     
+    #[test]
+    fn test_clone_path_copies_owner_mode_and_times() {
+        let src_temp = TempDir::new().unwrap();
+        let dst_temp = TempDir::new().unwrap();
+        let src_base = src_temp.path();
+        let dst_base = dst_temp.path();
+        fs::create_dir_all(src_base.join("a/b")).unwrap();
+        
+        // Ownership can only be changed by root; otherwise the clone keeps ours
+        let _ = std::os::unix::fs::chown(src_base.join("a"), Some(2001), Some(3001));
+        fs::set_permissions(src_base.join("a"), fs::Permissions::from_mode(0o2750)).unwrap();
+        fs::set_permissions(src_base.join("a/b"), fs::Permissions::from_mode(0o700)).unwrap();
+        let old = FileTime::from_unix_time(1_000_000, 0);
+        let older = FileTime::from_unix_time(500_000, 0);
+        set_file_times(src_base.join("a"), old, old).unwrap();
+        set_file_times(src_base.join("a/b"), older, older).unwrap();
+        
+        clone_path(src_base, dst_base, Path::new("/a/b")).unwrap();
+        
+        for dir in ["a", "a/b"] {
+            let src = fs::metadata(src_base.join(dir)).unwrap();
+            let dst = fs::metadata(dst_base.join(dir)).unwrap();
+            assert_eq!((dst.uid(), dst.gid()), (src.uid(), src.gid()), "{}", dir);
+            assert_eq!(dst.permissions().mode() & 0o7777, src.permissions().mode() & 0o7777, "{}", dir);
+            // Creating b must not have bumped the cloned a's mtime
+            assert_eq!(FileTime::from_last_modification_time(&dst), FileTime::from_last_modification_time(&src), "{}", dir);
+        }
+    }
+    
     #[test]
     fn test_ensure_parent_cloned() {
         let src_temp = TempDir::new().unwrap();
@@ -184,4 +395,59 @@ mod tests {
         let created = ensure_parent_cloned(src_base, dst_base, Path::new("parent/subdir/file2.txt")).unwrap();
         assert!(!created);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_copy_file_data_matches_source() {
+        let temp = TempDir::new().unwrap();
+        let src_path = temp.path().join("src.bin");
+        let contents: Vec<u8> = (0..300_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        fs::write(&src_path, &contents).unwrap();
+        
+        // Same device: reflink or copy_file_range where the filesystem allows it
+        let dst_path = temp.path().join("dst.bin");
+        let (bytes, method) = copy_file_data(
+            &fs::File::open(&src_path).unwrap(),
+            &fs::File::create(&dst_path).unwrap(),
+        ).unwrap();
+        assert_eq!(bytes, contents.len() as u64, "copied with {:?}", method);
+        assert_eq!(fs::read(&dst_path).unwrap(), contents);
+        
+        // The cross-device fallback produces the same bytes
+        let buffered_path = temp.path().join("buffered.bin");
+        let (bytes, method) = buffered_copy(
+            &fs::File::open(&src_path).unwrap(),
+            &fs::File::create(&buffered_path).unwrap(),
+        ).unwrap();
+        assert_eq!(bytes, contents.len() as u64);
+        assert_eq!(method, CopyMethod::Buffered);
+        assert!(files_identical(&src_path, &buffered_path).unwrap());
+    }
+    
+    #[test]
+    fn test_break_hard_link() {
+        let temp = TempDir::new().unwrap();
+        let original = temp.path().join("original.txt");
+        let link = temp.path().join("link.txt");
+        fs::write(&original, b"shared").unwrap();
+        fs::hard_link(&original, &link).unwrap();
+        
+        assert!(break_hard_link(&link).unwrap());
+        
+        let original_meta = fs::metadata(&original).unwrap();
+        let link_meta = fs::metadata(&link).unwrap();
+        assert_ne!(original_meta.ino(), link_meta.ino());
+        assert_eq!(original_meta.nlink(), 1);
+        assert_eq!(fs::read(&link).unwrap(), b"shared");
+        
+        // A file with a single link is left alone
+        assert!(!break_hard_link(&link).unwrap());
+    }
+    
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(relative_path(Path::new("/b/c"), Path::new("/a/f")), PathBuf::from("../../a/f"));
+        assert_eq!(relative_path(Path::new("/a"), Path::new("/a/f")), PathBuf::from("f"));
+        assert_eq!(relative_path(Path::new("/"), Path::new("/f")), PathBuf::from("f"));
+        assert_eq!(relative_path(Path::new("/a/b"), Path::new("/a/c/f")), PathBuf::from("../c/f"));
+    }
+}
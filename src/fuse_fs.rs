@@ -1,44 +1,79 @@
-use crate::config::{ConfigRef, StatFSIgnore};
+use crate::branch::{Branch, DiskSpaceRefresher};
+use crate::config::{ConfigRef, LinkEXDEV, StatFSIgnore, StatFSMode};
 use crate::policy::{AllActionPolicy, ExistingPathAllActionPolicy};
 use crate::policy::error::PolicyError;
-use crate::file_ops::FileManager;
-use crate::metadata_ops::MetadataManager;
-use crate::file_handle::FileHandleManager;
+use crate::file_ops::{FileManager, UnionDirEntry, BRANCH_PIN_XATTR};
+use crate::metadata_ops::{MetadataManager, TimeUpdate};
+use crate::file_handle::{FileHandleManager, verify_written, advise_readahead, drop_cache_on_close, fadvise_dontneed, fadvise_readahead, flush_write_buffer, open_branch_file, pread_full, pwrite_all, sync_on_close, wants_direct_io};
 use crate::xattr::{XattrManager, XattrFlags};
 use crate::policy::{FirstFoundSearchPolicy, FirstFoundCreatePolicy};
 use crate::config_manager::ConfigManager;
+use crate::errno::{ToErrno, EBADF, EEXIST, EINVAL, EIO, EISDIR, ENODATA, ENOENT, ENOSYS, ENOTDIR, ENOTTY, EPERM, ERANGE, EXDEV};
 use crate::control_file::{ControlFileHandler, CONTROL_FILE_INO};
-use crate::rename_ops::RenameManager;
+use crate::rename_ops::{RenameError, RenameManager};
 use crate::moveonenospc::{MoveOnENOSPCHandler, is_out_of_space_error};
+use crate::symlink_cache::{SymlinkCache, DEFAULT_SYMLINK_CACHE_CAPACITY};
+use crate::latency::{LatencyStats, OpLatency, TimedOp};
+use crate::inode::InodeCalc;
 use fuser::{
     FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEntry, 
     ReplyOpen, ReplyWrite, Request,
 };
-// Use standard errno constants compatible with MUSL
-const ENOENT: i32 = 2;
-const EIO: i32 = 5;
-const EACCES: i32 = 13;
-const EEXIST: i32 = 17;
-const EXDEV: i32 = 18;
-const ENOTDIR: i32 = 20;
-const EINVAL: i32 = 22;
-const EROFS: i32 = 30;
-const ENOTEMPTY: i32 = 39;
-const ENOSYS: i32 = 38;
-const ERANGE: i32 = 34;
-use std::collections::HashMap;
+// Open flags, hardcoded for MUSL compatibility
+const O_EXCL: i32 = 0o200;
+const O_ACCMODE: i32 = 3;
+const O_RDONLY: i32 = 0;
+
+use nix::sys::statvfs::FsFlags;
+use std::collections::{hash_map, HashMap, HashSet};
 use std::ffi::OsStr;
+use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing::error;
 
+type SharedWriteBuffer = parking_lot::Mutex<crate::file_handle::WriteBuffer>;
+type DirListing = Vec<(u64, FileType, String)>;
+
 const TTL: Duration = Duration::from_secs(1);
 
+/// Reply flag telling the kernel to bypass the page cache for a handle
+pub const FOPEN_DIRECT_IO: u32 = 0x00000001;
+
+/// Point `file`'s descriptor at `full_path`, opened again with `flags`
+///
+/// The status flags of the current descriptor (O_APPEND, O_NOATIME, ...)
+/// are carried over, so the caller's open mode survives the swap.
+fn reopen_descriptor(file: &std::fs::File, full_path: &Path, flags: i32) -> std::io::Result<()> {
+    use nix::fcntl::{fcntl, FcntlArg, OFlag};
+    
+    let status = fcntl(file.as_raw_fd(), FcntlArg::F_GETFL)?;
+    let copy = open_branch_file(full_path, flags)?;
+    fcntl(copy.as_raw_fd(), FcntlArg::F_SETFL(OFlag::from_bits_truncate(status)))?;
+    nix::unistd::dup2(copy.as_raw_fd(), file.as_raw_fd())?;
+    Ok(())
+}
+
+/// Directory handles unused for this long are assumed leaked and reaped
+pub const DIR_HANDLE_IDLE_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+/// Minimum time between reaping passes, which run from opendir
+const DIR_HANDLE_REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Control file attribute that re-stats cached inodes under the given path
+pub const INVALIDATE_XATTR: &str = "user.mergerfs.invalidate";
+/// Control file attribute that re-stats every cached inode
+pub const INVALIDATE_ALL_XATTR: &str = "user.mergerfs.invalidate.all";
+/// Control file attribute that drops every in-memory cache (see `MergerFS::gc`)
+pub const GC_XATTR: &str = "user.mergerfs.gc";
+/// Control file attribute that reorders the branches, given as colon separated paths
+pub const BRANCHES_ORDER_XATTR: &str = "user.mergerfs.branches.order";
+
 #[derive(Debug)]
 pub struct DirHandle {
     pub path: PathBuf,
     pub ino: u64,
+    pub last_access: Instant,
 }
 
 pub struct MergerFS {
@@ -51,15 +86,196 @@ pub struct MergerFS {
     pub control_file_handler: Arc<ControlFileHandler>,
     pub rename_manager: Arc<RenameManager>,
     pub moveonenospc_handler: Arc<MoveOnENOSPCHandler>,
-    inodes: parking_lot::RwLock<HashMap<u64, InodeData>>,
+    pub symlink_cache: SymlinkCache,
+    pub latency: Arc<LatencyStats>,
+    inodes: Arc<parking_lot::RwLock<HashMap<u64, InodeData>>>,
     next_inode: std::sync::atomic::AtomicU64,
-    dir_handles: parking_lot::RwLock<HashMap<u64, DirHandle>>,
+    // Last-use tick per cached inode, used to pick eviction victims once
+    // the map grows past max_inodes
+    inode_access: parking_lot::Mutex<HashMap<u64, u64>>,
+    // Kernel lookup count (nlookup) per inode; inodes the kernel still
+    // references are never evicted
+    inode_lookups: parking_lot::Mutex<HashMap<u64, u64>>,
+    inode_clock: std::sync::atomic::AtomicU64,
+    // Open directory handles. Entries normally go away in releasedir; any the
+    // kernel never releases are reaped once idle for DIR_HANDLE_IDLE_TIMEOUT.
+    dir_handles: Arc<parking_lot::RwLock<HashMap<u64, DirHandle>>>,
     next_dir_handle: std::sync::atomic::AtomicU64,
+    last_dir_handle_reap: parking_lot::Mutex<Instant>,
+    // Generation per inode number, bumped when the file behind it is destroyed
+    // so a reused number is not mistaken for the old file by NFS clients
+    generations: parking_lot::RwLock<HashMap<u64, u64>>,
+    // Lookups whose calculated inode was already cached for a different file
+    inode_collisions: Arc<std::sync::atomic::AtomicU64>,
+    // Directories whose times touch_dir advanced: the mtime their branch copy
+    // had then and the time they were advanced to
+    dir_touches: parking_lot::Mutex<HashMap<u64, (SystemTime, SystemTime)>>,
+    // Directory nlink (2 + union subdirectory count) keyed by path, each with
+    // the time it was counted; entries older than TTL are counted again so
+    // changes made directly on the branches show up like other attributes
+    dir_nlink_cache: parking_lot::RwLock<HashMap<String, (Instant, u32)>>,
+    // Largest nlink among a non-directory's branch copies keyed by path, with
+    // the time it was counted; reused for up to TTL like dir_nlink_cache
+    file_nlink_cache: parking_lot::RwLock<HashMap<String, (Instant, u32)>>,
+    // Union listings (without the dot entries) keyed by directory path, each
+    // with the time it was read from the branches (cache.readdir)
+    readdir_cache: parking_lot::RwLock<HashMap<String, (Instant, DirListing)>>,
+    // Samples branch free space in the background while mounted (cache.diskspace)
+    disk_space_refresher: Option<DiskSpaceRefresher>,
     // Removed path_cache - we calculate inodes on-demand to support hard links
     // Fast-path cache for root inode (always inode 1)
     root_inode_cache: InodeData,
 }
 
+/// Filesystem statistics as reported to the kernel by statfs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatfsTotals {
+    pub blocks: u64,
+    pub bfree: u64,
+    pub bavail: u64,
+    pub files: u64,
+    pub ffree: u64,
+    pub bsize: u32,
+    pub namelen: u32,
+    pub frsize: u32,
+}
+
+impl StatfsTotals {
+    fn available_bytes(&self) -> u64 {
+        self.bavail.saturating_mul(self.frsize as u64)
+    }
+}
+
+/// Combine per-branch statistics according to the statfs mode
+///
+/// `Largest` reports the single branch with the most available space so
+/// applications see a size they could actually fill; the other modes sum
+/// the counts and use the smallest block sizes and name length.
+pub fn combine_statfs(stats: &[StatfsTotals], mode: StatFSMode) -> StatfsTotals {
+    let fallback = StatfsTotals {
+        blocks: 0,
+        bfree: 0,
+        bavail: 0,
+        files: 0,
+        ffree: 0,
+        bsize: 4096,
+        namelen: 255,
+        frsize: 512,
+    };
+    
+    if mode == StatFSMode::Largest {
+        return stats.iter()
+            .copied()
+            .max_by_key(|s| s.available_bytes())
+            .unwrap_or(fallback);
+    }
+    
+    let mut iter = stats.iter();
+    let Some(first) = iter.next() else {
+        return fallback;
+    };
+    iter.fold(*first, |acc, s| StatfsTotals {
+        blocks: acc.blocks + s.blocks,
+        bfree: acc.bfree + s.bfree,
+        bavail: acc.bavail + s.bavail,
+        files: acc.files + s.files,
+        ffree: acc.ffree + s.ffree,
+        bsize: acc.bsize.min(s.bsize),
+        namelen: acc.namelen.min(s.namelen),
+        frsize: acc.frsize.min(s.frsize),
+    })
+}
+
+/// Replace the combined totals with the statfs.total and statfs.available sizes
+///
+/// Sizes are converted to `frsize` blocks. Free and available counts are
+/// kept within the reported size, so a total alone also caps the free space.
+pub fn apply_statfs_overrides(totals: StatfsTotals, total: Option<u64>, available: Option<u64>) -> StatfsTotals {
+    let frsize = (totals.frsize as u64).max(1);
+    let mut totals = totals;
+    if let Some(total) = total {
+        totals.blocks = total / frsize;
+    }
+    if let Some(available) = available {
+        totals.bavail = available / frsize;
+        totals.bfree = totals.bfree.max(totals.bavail);
+    }
+    totals.bavail = totals.bavail.min(totals.blocks);
+    totals.bfree = totals.bfree.min(totals.blocks);
+    totals
+}
+
+/// Birth time to report for a file
+///
+/// Filesystems without btime (ext3, most network filesystems) make
+/// `created()` fail, and FUSE attributes have no way to say "unknown". The
+/// earliest of mtime and ctime is reported instead: the file certainly
+/// existed by then, and it keeps such files from all looking brand new.
+pub fn birth_time(created: std::io::Result<SystemTime>, mtime: SystemTime, ctime: SystemTime) -> SystemTime {
+    created.unwrap_or_else(|_| mtime.min(ctime))
+}
+
+fn system_time_from_parts(secs: i64, nsecs: i64) -> SystemTime {
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::new(secs as u64, nsecs as u32)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs()) + Duration::from_nanos(nsecs as u64)
+    }
+}
+
+/// FUSE type of a branch file from its std type
+fn fuse_file_type(file_type: std::fs::FileType) -> FileType {
+    use std::os::unix::fs::FileTypeExt;
+    if file_type.is_dir() {
+        FileType::Directory
+    } else if file_type.is_symlink() {
+        FileType::Symlink
+    } else if file_type.is_fifo() {
+        FileType::NamedPipe
+    } else if file_type.is_char_device() {
+        FileType::CharDevice
+    } else if file_type.is_block_device() {
+        FileType::BlockDevice
+    } else if file_type.is_socket() {
+        FileType::Socket
+    } else {
+        FileType::RegularFile
+    }
+}
+
+/// Inode and type readdir reports for a union directory entry
+///
+/// The type comes from the directory entry and the inode from `inodecalc`
+/// applied to the entry's d_ino, so listing a directory needs no stat per
+/// entry. `stat` is only called for an entry whose type the branch did not
+/// report.
+pub fn resolve_dir_entry<F>(inodecalc: InodeCalc, entry_path: &Path, entry: &UnionDirEntry, stat: F) -> Option<(u64, FileType)>
+where
+    F: FnOnce() -> std::io::Result<std::fs::Metadata>,
+{
+    use std::os::unix::fs::MetadataExt;
+    
+    let (file_type, original_ino) = match entry.file_type {
+        Some(file_type) => (file_type, entry.ino),
+        None => {
+            let metadata = stat().ok()?;
+            (metadata.file_type(), metadata.ino())
+        }
+    };
+    let kind = fuse_file_type(file_type);
+    // Only the format bits of the mode matter to the inode algorithms
+    let mode = match kind {
+        FileType::Directory => nix::libc::S_IFDIR,
+        FileType::Symlink => nix::libc::S_IFLNK,
+        FileType::NamedPipe => nix::libc::S_IFIFO,
+        FileType::CharDevice => nix::libc::S_IFCHR,
+        FileType::BlockDevice => nix::libc::S_IFBLK,
+        FileType::Socket => nix::libc::S_IFSOCK,
+        FileType::RegularFile => nix::libc::S_IFREG,
+    };
+    Some((inodecalc.calc(&entry.branch.path, entry_path, mode, original_ino), kind))
+}
+
 #[derive(Debug, Clone)]
 pub struct InodeData {
     pub path: String,
@@ -71,16 +287,17 @@ pub struct InodeData {
 
 impl MergerFS {
     pub fn new(file_manager: FileManager) -> Self {
-        // Create metadata manager with same branches and AllActionPolicy for consistency
-        let branches = file_manager.branches.clone();
+        // The other managers share the file manager's branch list, so they follow reorders
+        let branches = file_manager.branch_list().clone();
         let action_policy = Box::new(ExistingPathAllActionPolicy::new());
         let metadata_manager = MetadataManager::new(branches.clone(), action_policy);
         
-        // Create xattr manager with search and action policies
+        // Create xattr manager with search and action policies; set and remove
+        // reach every writable copy so no copy keeps a stale attribute
         let xattr_manager = XattrManager::new(
             branches.clone(),
             Box::new(FirstFoundSearchPolicy),
-            Box::new(ExistingPathAllActionPolicy::new()),
+            Box::new(AllActionPolicy::new()),
             Box::new(FirstFoundSearchPolicy),
             Box::new(AllActionPolicy::new()),
         );
@@ -136,26 +353,104 @@ impl MergerFS {
         
         let file_manager_arc = Arc::new(file_manager);
         
+        let metadata_manager_arc = Arc::new(metadata_manager);
+        
         // Set up the file manager reference in config manager
         config_manager.set_file_manager(&file_manager_arc);
+        config_manager.set_metadata_manager(&metadata_manager_arc);
+        config_manager.register_moveonenospc_stats(moveonenospc_handler.stats());
+        
+        // Mount statistics sampled live when the control file xattrs are read
+        let file_handle_manager = Arc::new(FileHandleManager::new());
+        let inodes = Arc::new(parking_lot::RwLock::new(inodes));
+        let dir_handles = Arc::new(parking_lot::RwLock::new(HashMap::new()));
+        let inode_collisions = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        {
+            let handles = file_handle_manager.clone();
+            config_manager.register_stat("open_handles", "Number of open file handles", move || {
+                handles.get_handle_count() as u64
+            });
+            let inodes = inodes.clone();
+            config_manager.register_stat("inodes", "Number of inodes currently tracked", move || {
+                inodes.read().len() as u64
+            });
+            let collisions = inode_collisions.clone();
+            config_manager.register_stat("inode_collisions", "Lookups whose calculated inode already belonged to a different file", move || {
+                collisions.load(std::sync::atomic::Ordering::Relaxed)
+            });
+            let dir_handles = dir_handles.clone();
+            config_manager.register_stat("open_dir_handles", "Number of open directory handles", move || {
+                dir_handles.read().len() as u64
+            });
+            let file_manager = Arc::downgrade(&file_manager_arc);
+            config_manager.register_stat("branches", "Number of branches in the pool", move || {
+                file_manager.upgrade().map_or(0, |fm| fm.branches().len() as u64)
+            });
+            // Stats are looked up by position when read so they follow the
+            // branch order, like `stats.branch.reset` does
+            for idx in 0..file_manager_arc.branches().len() {
+                let help = format!("Bytes written through the pool to the branch at position {}", idx);
+                config_manager.register_stat(&format!("branch.{}.written", idx), &help, {
+                    let file_manager = Arc::downgrade(&file_manager_arc);
+                    move || file_manager.upgrade()
+                        .and_then(|fm| fm.branches().get(idx).map(|branch| branch.bytes_written()))
+                        .unwrap_or(0)
+                });
+                let help = format!("1 while the branch at position {} is skipped for creates after returning EROFS", idx);
+                config_manager.register_stat(&format!("branch.{}.remounted_ro", idx), &help, {
+                    let file_manager = Arc::downgrade(&file_manager_arc);
+                    move || file_manager.upgrade()
+                        .and_then(|fm| fm.branches().get(idx).map(|branch| branch.is_remounted_readonly() as u64))
+                        .unwrap_or(0)
+                });
+            }
+        }
+        let latency = Arc::new(LatencyStats::new());
+        type Counter = (&'static str, fn(&OpLatency) -> u64, String);
+        for op in TimedOp::ALL {
+            let counters: [Counter; 3] = [
+                ("count", |l| l.count(), format!("Number of completed {} calls", op.name())),
+                ("total_us", |l| l.total_us(), format!("Microseconds spent in {} calls", op.name())),
+                ("max_us", |l| l.max_us(), format!("Slowest {} call in microseconds", op.name())),
+            ];
+            for (suffix, read, help) in counters {
+                let latency = latency.clone();
+                config_manager.register_stat(&format!("latency.{}.{}", op.name(), suffix), &help, move || {
+                    read(latency.op(op))
+                });
+            }
+        }
         
         let config_manager_arc = Arc::new(config_manager);
         let control_file_handler = Arc::new(ControlFileHandler::new(config_manager_arc.clone()));
         
         MergerFS {
             file_manager: file_manager_arc,
-            metadata_manager: Arc::new(metadata_manager),
+            metadata_manager: metadata_manager_arc,
             config,
-            file_handle_manager: Arc::new(FileHandleManager::new()),
+            file_handle_manager,
             xattr_manager: Arc::new(xattr_manager),
             config_manager: config_manager_arc,
             control_file_handler,
             rename_manager: Arc::new(rename_manager),
             moveonenospc_handler: Arc::new(moveonenospc_handler),
-            inodes: parking_lot::RwLock::new(inodes),
+            latency,
+            symlink_cache: SymlinkCache::new(DEFAULT_SYMLINK_CACHE_CAPACITY),
+            inodes,
             next_inode: std::sync::atomic::AtomicU64::new(2), // Start at 2, 1 is root
-            dir_handles: parking_lot::RwLock::new(HashMap::new()),
+            inode_access: parking_lot::Mutex::new(HashMap::new()),
+            inode_lookups: parking_lot::Mutex::new(HashMap::new()),
+            inode_clock: std::sync::atomic::AtomicU64::new(0),
+            dir_handles,
             next_dir_handle: std::sync::atomic::AtomicU64::new(1),
+            last_dir_handle_reap: parking_lot::Mutex::new(Instant::now()),
+            generations: parking_lot::RwLock::new(HashMap::new()),
+            inode_collisions,
+            dir_touches: parking_lot::Mutex::new(HashMap::new()),
+            dir_nlink_cache: parking_lot::RwLock::new(HashMap::new()),
+            file_nlink_cache: parking_lot::RwLock::new(HashMap::new()),
+            readdir_cache: parking_lot::RwLock::new(HashMap::new()),
+            disk_space_refresher: None,
             root_inode_cache,
         }
     }
@@ -169,7 +464,79 @@ impl MergerFS {
         if ino == 1 {
             return Some(self.root_inode_cache.clone());
         }
-        self.inodes.read().get(&ino).cloned()
+        let data = self.inodes.read().get(&ino).cloned();
+        if data.is_some() {
+            self.touch_inode(ino);
+        }
+        data
+    }
+    
+    /// Mark `ino` as just used for the max_inodes eviction order
+    fn touch_inode(&self, ino: u64) {
+        let tick = self.inode_clock.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.inode_access.lock().insert(ino, tick);
+    }
+    
+    /// Count an entry reply the kernel will later balance with a forget
+    fn count_lookup(&self, ino: u64) {
+        *self.inode_lookups.lock().entry(ino).or_insert(0) += 1;
+    }
+    
+    /// Drop `nlookup` kernel references to `ino`, as sent by forget
+    pub fn forget_inode(&self, ino: u64, nlookup: u64) {
+        let mut lookups = self.inode_lookups.lock();
+        if let Some(count) = lookups.get_mut(&ino) {
+            *count = count.saturating_sub(nlookup);
+            if *count == 0 {
+                lookups.remove(&ino);
+            }
+        }
+    }
+    
+    /// Inodes that must stay cached: root, the control file, those the
+    /// kernel still holds a lookup on and those with open file handles
+    fn referenced_inodes(&self) -> HashSet<u64> {
+        let mut pinned = self.file_handle_manager.open_inodes();
+        pinned.extend(self.inode_lookups.lock().keys().copied());
+        pinned.extend([1, CONTROL_FILE_INO]);
+        pinned
+    }
+    
+    /// Drop least recently used inodes until at most max_inodes remain, returning how many went
+    ///
+    /// Root, the control file, inodes the kernel has not forgotten and inodes
+    /// with open file or directory handles are never evicted, so the map can
+    /// stay above the cap when those alone exceed it. An evicted inode is
+    /// computed again on its next lookup.
+    pub fn evict_inodes_over_limit(&self) -> usize {
+        let max_inodes = self.config.read().max_inodes as usize;
+        if max_inodes == 0 || self.inodes.read().len() <= max_inodes {
+            return 0;
+        }
+        
+        let mut pinned = self.referenced_inodes();
+        pinned.extend(self.dir_handles.read().values().map(|handle| handle.ino));
+        
+        let mut inodes = self.inodes.write();
+        let mut access = self.inode_access.lock();
+        let mut candidates: Vec<(u64, u64)> = inodes
+            .keys()
+            .filter(|ino| !pinned.contains(ino))
+            .map(|&ino| (access.get(&ino).copied().unwrap_or(0), ino))
+            .collect();
+        candidates.sort_unstable();
+        
+        let excess = inodes.len().saturating_sub(max_inodes);
+        let mut evicted = 0;
+        for (_, ino) in candidates.into_iter().take(excess) {
+            inodes.remove(&ino);
+            access.remove(&ino);
+            self.dir_touches.lock().remove(&ino);
+            self.symlink_cache.invalidate(ino);
+            evicted += 1;
+        }
+        tracing::debug!("Evicted {} inodes over the max_inodes limit of {}", evicted, max_inodes);
+        evicted
     }
     
     pub fn update_inode_size(&self, ino: u64, new_size: u64) {
@@ -184,6 +551,63 @@ impl MergerFS {
         }
     }
 
+    /// Refresh the cached size of `ino` after a write ending at `write_end`
+    ///
+    /// The size comes from the branch file (through `open_file` when the
+    /// handle has one) so a truncation made outside the pool is picked up
+    /// instead of being masked by the larger cached size. The write's end
+    /// still counts, as it may sit in the write buffer. Without a readable
+    /// size the cached one can only grow.
+    pub fn update_size_after_write(&self, ino: u64, path: &Path, branch_idx: Option<usize>, open_file: Option<&std::fs::File>, write_end: u64) {
+        let on_disk = match open_file {
+            Some(file) => file.metadata().ok(),
+            None => branch_idx
+                .and_then(|idx| self.file_manager.branches().get(idx).cloned())
+                .or_else(|| self.file_manager.find_first_branch(path).ok())
+                .and_then(|branch| std::fs::metadata(branch.full_path(path)).ok()),
+        };
+        
+        let size = match on_disk {
+            Some(metadata) => metadata.len().max(write_end),
+            None => match self.get_inode_data(ino) {
+                Some(data) => data.attr.size.max(write_end),
+                None => return,
+            },
+        };
+        self.update_inode_size(ino, size);
+    }
+
+    /// Change the owner and/or group of `path`, leaving out sides that are squashed
+    ///
+    /// With `uid_squash`/`gid_squash` set every file reports that ID, so a
+    /// chown of that side succeeds without touching the branches.
+    pub fn chown_path(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> Result<(), PolicyError> {
+        let (uid, gid) = {
+            let config = self.config.read();
+            (uid.filter(|_| config.uid_squash.is_none()), gid.filter(|_| config.gid_squash.is_none()))
+        };
+        if uid.is_none() && gid.is_none() {
+            return Ok(());
+        }
+        
+        // -1 leaves that side unchanged, like chown(2)
+        self.metadata_manager.chown(path, uid.unwrap_or(u32::MAX), gid.unwrap_or(u32::MAX))
+    }
+
+    /// Count a write of `bytes` against the branch it went to
+    ///
+    /// Without a branch index the write went to the first branch holding
+    /// `path`, as in the write fallback.
+    pub fn account_write(&self, path: &Path, branch_idx: Option<usize>, bytes: u64) {
+        let branch = match branch_idx {
+            Some(idx) => self.file_manager.branches().get(idx).cloned(),
+            None => self.file_manager.find_first_branch(path).ok(),
+        };
+        if let Some(branch) = branch {
+            branch.record_write(bytes);
+        }
+    }
+
     pub fn path_to_inode(&self, path: &str) -> Option<u64> {
         // Search in existing inodes
         let inodes = self.inodes.read();
@@ -206,7 +630,7 @@ impl MergerFS {
         
         // Cached path doesn't work, try to find any file with the same underlying inode
         if let Some(branch_idx) = &inode_data.branch_idx {
-            let branch = &self.file_manager.branches[*branch_idx];
+            let branch = &self.file_manager.branches()[*branch_idx];
             // Look for files in this branch with the same original inode
             if let Ok(entries) = std::fs::read_dir(&branch.path) {
                 for entry in entries.flatten() {
@@ -230,38 +654,78 @@ impl MergerFS {
     pub fn create_file_attr_with_branch(&self, path: &Path) -> Option<(FileAttr, usize, u64)> {
         // Find the file and get both branch and metadata
         let (branch, metadata) = self.file_manager.find_file_with_metadata(path)?;
-        let branch_idx = self.file_manager.branches.iter().position(|b| b.path == branch.path)?;
-        
-        let now = SystemTime::now();
+        let (mut attr, branch_idx, original_ino) = self.file_attr_from_metadata(path, &branch, metadata)?;
+        if attr.kind != FileType::Directory {
+            attr.nlink = self.union_nlink(path, attr.nlink);
+        }
+        Some((attr, branch_idx, original_ino))
+    }
+    
+    /// Link count of a non-directory that may exist on several branches
+    ///
+    /// Copies on different branches are separate files rather than links to
+    /// one another, so the counts are not summed (that would make tools like
+    /// `rsync -H` treat every duplicated file as hard-linked). The largest
+    /// count among the copies is reported instead, so a file with extra links
+    /// on any branch still shows nlink > 1.
+    ///
+    /// Finding the copies costs an lstat per branch, so the result is reused
+    /// for up to TTL; `nlink` of the copy being reported is always current.
+    fn union_nlink(&self, path: &Path, nlink: u32) -> u32 {
+        use std::os::unix::fs::MetadataExt;
         
-        // Determine file type based on metadata
-        let file_type = if metadata.is_dir() {
-            FileType::Directory
-        } else if metadata.is_symlink() {
-            FileType::Symlink
-        } else {
-            // Check for special file types on Unix platforms
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::FileTypeExt;
-                let ft = metadata.file_type();
-                if ft.is_fifo() {
-                    FileType::NamedPipe
-                } else if ft.is_char_device() {
-                    FileType::CharDevice
-                } else if ft.is_block_device() {
-                    FileType::BlockDevice
-                } else if ft.is_socket() {
-                    FileType::Socket
-                } else {
-                    FileType::RegularFile
-                }
-            }
-            #[cfg(not(unix))]
-            {
-                FileType::RegularFile
+        let branches = self.file_manager.branches();
+        if branches.len() < 2 {
+            return nlink;
+        }
+        let key = Self::dir_nlink_key(path);
+        if let Some(&(counted_at, copies_nlink)) = self.file_nlink_cache.read().get(&key) {
+            if counted_at.elapsed() < TTL {
+                return nlink.max(copies_nlink);
             }
+        }
+        
+        let copies_nlink = branches
+            .iter()
+            .filter_map(|branch| branch.full_path(path).symlink_metadata().ok())
+            .map(|metadata| metadata.nlink() as u32)
+            .fold(0, u32::max);
+        self.file_nlink_cache.write().insert(key, (Instant::now(), copies_nlink));
+        nlink.max(copies_nlink)
+    }
+    
+    /// Build attributes for `path` from the copy on a specific branch
+    pub fn create_file_attr_on_branch(&self, path: &Path, branch: &Branch) -> Option<FileAttr> {
+        let metadata = branch.full_path(path).symlink_metadata().ok()?;
+        self.file_attr_from_metadata(path, branch, metadata).map(|(attr, _, _)| attr)
+    }
+    
+    /// Attributes of the branch file an open handle for `ino` points to
+    ///
+    /// fuser 0.14 does not pass the handle to getattr, so any open handle for
+    /// the inode is used; its file is fstat'ed so size and times match what
+    /// the caller is reading and writing rather than another branch's copy.
+    pub fn create_file_attr_for_open_handle(&self, ino: u64) -> Option<FileAttr> {
+        let handle = self.file_handle_manager.find_handle_for_inode(ino)?;
+        if let Err(e) = self.file_handle_manager.flush_inode_buffers(ino) {
+            tracing::warn!("Failed to write buffered data for inode {}: {:?}", ino, e);
+        }
+        let branch = self.file_manager.branches().get(handle.branch_idx?)?.clone();
+        let metadata = match &handle.file {
+            Some(file) => file.metadata().ok()?,
+            None => branch.full_path(&handle.path).symlink_metadata().ok()?,
         };
+        let (mut attr, _, _) = self.file_attr_from_metadata(&handle.path, &branch, metadata)?;
+        attr.ino = ino;
+        Some(attr)
+    }
+    
+    fn file_attr_from_metadata(&self, path: &Path, branch: &Branch, metadata: std::fs::Metadata) -> Option<(FileAttr, usize, u64)> {
+        let branch_idx = self.file_manager.branches().iter().position(|b| b.path == branch.path)?;
+        
+        let now = SystemTime::now();
+        
+        let file_type = fuse_file_type(metadata.file_type());
         
         // Set permissions based on metadata
         #[cfg(unix)]
@@ -273,35 +737,52 @@ impl MergerFS {
         let perm = if metadata.permissions().readonly() { 0o444 } else { 0o644 };
         
         #[cfg(unix)]
-        let (nlink, mode, original_ino) = {
+        let (nlink, mode, original_ino, uid, gid) = {
             use std::os::unix::fs::MetadataExt;
-            (metadata.nlink() as u32, metadata.mode(), metadata.ino())
+            (metadata.nlink() as u32, metadata.mode(), metadata.ino(), metadata.uid(), metadata.gid())
         };
         #[cfg(not(unix))]
-        let (nlink, mode, original_ino) = {
+        let (nlink, mode, original_ino, uid, gid) = {
             let mode = if metadata.is_dir() { 0o040755 } else { 0o100644 };
-            (if metadata.is_dir() { 2 } else { 1 }, mode, 0u64)
+            (if metadata.is_dir() { 2 } else { 1 }, mode, 0u64, 0, 0)
+        };
+        
+        // Directories report 2 + the number of subdirectories in the union
+        let nlink = if file_type == FileType::Directory {
+            self.dir_nlink(path)
+        } else {
+            nlink
         };
         
         let size = metadata.len();
+        let mtime = metadata.modified().unwrap_or(now);
+        #[cfg(unix)]
+        let ctime = {
+            use std::os::unix::fs::MetadataExt;
+            system_time_from_parts(metadata.ctime(), metadata.ctime_nsec())
+        };
+        #[cfg(not(unix))]
+        let ctime = mtime;
         
         // Calculate inode using the configured algorithm
         let config = self.config_manager.config().read();
         let calculated_ino = config.inodecalc.calc(&branch.path, path, mode, original_ino);
+        let uid = config.uid_squash.unwrap_or(uid);
+        let gid = config.gid_squash.unwrap_or(gid);
 
         let attr = FileAttr {
             ino: calculated_ino,
             size,
             blocks: (size + 511) / 512, // Round up to nearest block
             atime: metadata.accessed().unwrap_or(now),
-            mtime: metadata.modified().unwrap_or(now),
-            ctime: metadata.created().unwrap_or(now),
-            crtime: metadata.created().unwrap_or(now),
+            mtime,
+            ctime,
+            crtime: birth_time(metadata.created(), mtime, ctime),
             kind: file_type,
             perm,
             nlink,
-            uid: 1000, // Default user ID for container compatibility
-            gid: 1000, // Default group ID for container compatibility
+            uid,
+            gid,
             rdev: 0,
             flags: 0,
             blksize: 512,
@@ -310,8 +791,468 @@ impl MergerFS {
         Some((attr, branch_idx, original_ino))
     }
 
+    fn dir_nlink_key(path: &Path) -> String {
+        format!("/{}", path.to_string_lossy().trim_start_matches('/'))
+    }
+    
+    /// Get the link count of a directory in the union, reusing a count for up to TTL
+    fn dir_nlink(&self, path: &Path) -> u32 {
+        let key = Self::dir_nlink_key(path);
+        if let Some(&(counted_at, nlink)) = self.dir_nlink_cache.read().get(&key) {
+            if counted_at.elapsed() < TTL {
+                return nlink;
+            }
+        }
+        
+        let nlink = 2 + self.file_manager.count_subdirectories(path) as u32;
+        self.dir_nlink_cache.write().insert(key, (Instant::now(), nlink));
+        nlink
+    }
+    
+    /// Entries readdir returns for `dir_path`, starting with `.` and `..`
+    ///
+    /// Entries whose type cannot be determined are skipped, and a listing
+    /// that fails altogether yields just the dot entries. With cache.readdir
+    /// set, a listing read within that many seconds is served without
+    /// touching the branches.
+    pub fn dir_entries(&self, dir_path: &str) -> DirListing {
+        // Start with standard entries
+        let mut entries = vec![
+            (1, FileType::Directory, ".".to_string()),
+            (1, FileType::Directory, "..".to_string()),
+        ];
+
+        // Add control file to root directory listing
+        if dir_path == "/" && self.control_file_enabled() {
+            entries.push((CONTROL_FILE_INO, FileType::RegularFile, ".mergerfs".to_string()));
+        }
+        
+        let ttl = Duration::from_secs(self.config.read().cache_readdir);
+        let key = Self::dir_nlink_key(Path::new(dir_path));
+        if !ttl.is_zero() {
+            if let Some((read_at, cached)) = self.readdir_cache.read().get(&key) {
+                if read_at.elapsed() < ttl {
+                    entries.extend(cached.iter().cloned());
+                    return entries;
+                }
+            }
+        }
+        
+        let listing = self.read_dir_entries(dir_path);
+        if !ttl.is_zero() {
+            self.readdir_cache.write().insert(key, (Instant::now(), listing.clone()));
+        }
+        entries.extend(listing);
+        entries
+    }
+    
+    /// Read the union listing of `dir_path` from the branches, without the dot entries
+    fn read_dir_entries(&self, dir_path: &str) -> DirListing {
+        let mut entries = Vec::new();
+        
+        // Get union directory listing (no locks held during I/O)
+        let path = Path::new(dir_path);
+        match self.file_manager.list_directory_entries(path) {
+            Ok(dir_entries) => {
+                let inodecalc = self.config.read().inodecalc;
+                for entry in dir_entries {
+                    let entry_path = if dir_path == "/" {
+                        format!("/{}", entry.name)
+                    } else {
+                        format!("{}/{}", dir_path, entry.name)
+                    };
+                    
+                    // Type and inode from the branch that provided the entry
+                    let entry_path_obj = Path::new(&entry_path);
+                    let stat = || entry.branch.full_path(entry_path_obj).symlink_metadata();
+                    if let Some((ino, kind)) = resolve_dir_entry(inodecalc, entry_path_obj, &entry, stat) {
+                        entries.push((ino, kind, entry.name));
+                    } else {
+                        // Skip entries that vanished before they could be stat'ed
+                        tracing::warn!("Could not get attributes for directory entry: {}", entry_path);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to list directory contents: {:?}", e);
+                // Fall back to just . and .. entries
+            }
+        }
+        
+        entries
+    }
+    
+    /// Whether `/.mergerfs` is served (the `mergerfs_control` option)
+    pub fn control_file_enabled(&self) -> bool {
+        self.config.read().mergerfs_control
+    }
+    
+    /// Attributes of the control file if `path` names it and it is enabled
+    pub fn control_file_attr(&self, path: &str) -> Option<FileAttr> {
+        if ControlFileHandler::is_control_file(path) && self.control_file_enabled() {
+            Some(self.control_file_handler.get_attr())
+        } else {
+            None
+        }
+    }
+
+    /// Advance the cached mtime and ctime of directory `ino` to now
+    ///
+    /// Called once an entry is added to or removed from the directory, so
+    /// tools watching its mtime see the change before the attributes expire.
+    /// Its cached listing is dropped as well.
+    pub fn touch_dir(&self, ino: u64) {
+        let path = match self.inodes.write().get_mut(&ino) {
+            Some(data) => {
+                let now = SystemTime::now();
+                let mut touches = self.dir_touches.lock();
+                let branch_mtime = touches.get(&ino).map_or(data.attr.mtime, |&(branch_mtime, _)| branch_mtime);
+                touches.insert(ino, (branch_mtime, now));
+                data.attr.mtime = now;
+                data.attr.ctime = now;
+                data.path.clone()
+            }
+            None => return,
+        };
+        self.invalidate_readdir(Path::new(&path));
+    }
+    
+    /// Advance the times of the cached parent directory of `path`, if any
+    fn touch_parent(&self, path: &Path) {
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        if let Some(ino) = self.path_to_inode(&parent.to_string_lossy()) {
+            self.touch_dir(ino);
+        }
+    }
+    
+    /// Report the times touch_dir gave directory `ino` over freshly read ones
+    ///
+    /// An entry added on another branch leaves the copy the attributes come
+    /// from alone, so the advanced times are kept until that copy's mtime
+    /// changes. Any change wins, including one that moves it backwards.
+    fn apply_dir_touch(&self, ino: u64, attr: &mut FileAttr) {
+        let mut touches = self.dir_touches.lock();
+        match touches.get(&ino) {
+            Some(&(branch_mtime, touched)) if branch_mtime == attr.mtime => {
+                attr.mtime = touched;
+                attr.ctime = attr.ctime.max(touched);
+            }
+            Some(_) => {
+                touches.remove(&ino);
+            }
+            None => {}
+        }
+    }
+    
+    /// Forget the cached listing of directory `path`
+    pub fn invalidate_readdir(&self, path: &Path) {
+        self.readdir_cache.write().remove(&Self::dir_nlink_key(path));
+    }
+    
+    /// Forget the cached link count of a directory after its subdirectories change
+    pub fn invalidate_dir_nlink(&self, path: &Path) {
+        self.dir_nlink_cache.write().remove(&Self::dir_nlink_key(path));
+    }
+
+    /// Create the empty file for a FUSE create on a branch chosen by the create policy
+    ///
+    /// With O_EXCL a file already visible anywhere in the union fails with
+    /// EEXIST and is left untouched. Otherwise a directory of that name on
+    /// any branch fails with EISDIR.
+    pub fn create_path(&self, path: &Path, flags: i32) -> Result<(), PolicyError> {
+        if flags & O_EXCL != 0 && self.file_manager.find_first_branch(path).is_ok() {
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EEXIST)));
+        }
+        if self.file_manager.branches().iter().any(|branch| branch.full_path(path).is_dir()) {
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EISDIR)));
+        }
+        
+        // When the chosen branch is full, moveonenospc picks where the file goes instead
+        self.file_manager.create_file_with_enospc_fallback(path, &[], |full, candidates| {
+            if !self.moveonenospc_handler.is_enabled() {
+                return None;
+            }
+            tracing::info!("ENOSPC creating {:?} on {:?}, using moveonenospc policy", path, full.path);
+            self.moveonenospc_handler
+                .select_among(path, candidates, 0, |branch| branch.free_space())
+                .ok()
+        })?;
+        self.touch_parent(path);
+        Ok(())
+    }
+    
+    /// Resolve `path` in the union and cache its inode, as done by lookup
+    ///
+    /// Each successful call counts as one kernel lookup of the inode, to be
+    /// released through `forget_inode`.
+    pub fn lookup_path(&self, path: &str) -> Option<FileAttr> {
+        let (mut attr, branch_idx, original_ino) = self.create_file_attr_with_branch(Path::new(path))?;
+        let ino = attr.ino; // Use the calculated inode
+        if attr.kind == FileType::Directory {
+            self.apply_dir_touch(ino, &mut attr);
+        }
+        
+        // Check if this inode already exists (hard link case)
+        let mut inodes = self.inodes.write();
+        let attr = match inodes.entry(ino) {
+            hash_map::Entry::Vacant(entry) => {
+                // New inode, insert it
+                entry.insert(InodeData {
+                    path: path.to_string(),
+                    attr,
+                    content_lock: Arc::new(parking_lot::RwLock::new(())),
+                    branch_idx: Some(branch_idx),
+                    original_ino,
+                }).attr
+            }
+            hash_map::Entry::Occupied(mut entry) => {
+                // Existing inode (hard link) - update attributes to get fresh nlink
+                let inode_data = entry.get_mut();
+                self.check_inode_collision(ino, inode_data, path, Some(branch_idx), original_ino);
+                inode_data.attr.nlink = attr.nlink;
+                inode_data.attr.size = attr.size;
+                inode_data.attr.mtime = attr.mtime;
+                inode_data.attr.ctime = attr.ctime;
+                inode_data.attr
+            }
+        };
+        drop(inodes);
+        self.count_lookup(ino);
+        self.touch_inode(ino);
+        self.evict_inodes_over_limit();
+        Some(attr)
+    }
+    
+    /// Set the permissions of a just-created file or directory on its branch
+    ///
+    /// The client's mode and umask are resolved against default_mode and
+    /// force_mode; returns the permission bits applied.
+    pub fn apply_create_mode(&self, path: &Path, branch_idx: usize, mode: u32, umask: u32) -> std::io::Result<u16> {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let perm = self.config.read().create_mode(mode, umask);
+        let full_path = self.file_manager.branches()[branch_idx].full_path(path);
+        std::fs::set_permissions(&full_path, std::fs::Permissions::from_mode(perm))?;
+        Ok(perm as u16)
+    }
+
+    /// Tear down mount-lifetime state when the session ends
+    ///
+    /// Stops the disk space refresher, writes out buffered data of handles the
+    /// kernel never released (lazy unmount leaves them open) and logs a
+    /// summary of the mount's counters.
+    pub fn shutdown(&mut self) {
+        // Stop and join the refresher thread before the session goes away
+        self.disk_space_refresher = None;
+
+        if let Err(e) = self.file_handle_manager.flush_all_buffers() {
+            tracing::error!(error = %e, "Failed to flush write buffers on shutdown");
+        }
+
+        let moveonenospc = self.moveonenospc_handler.stats();
+        tracing::info!(
+            open_handles = self.file_handle_manager.get_handle_count(),
+            open_dir_handles = self.dir_handles.read().len(),
+            inodes = self.inodes.read().len(),
+            moveonenospc_attempted = moveonenospc.attempted(),
+            moveonenospc_succeeded = moveonenospc.succeeded(),
+            moveonenospc_bytes_moved = moveonenospc.bytes_moved(),
+            "Shutting down mergerfs-rs"
+        );
+        for op in TimedOp::ALL {
+            let latency = self.latency.op(op);
+            if latency.count() > 0 {
+                tracing::info!(
+                    op = op.name(),
+                    count = latency.count(),
+                    avg_us = latency.total_us() / latency.count(),
+                    max_us = latency.max_us(),
+                    histogram = %latency.histogram_summary(),
+                    "Operation latency"
+                );
+            }
+        }
+    }
+
+    /// Re-stat every cached inode at or below `path` ("/" for the whole pool)
+    ///
+    /// Used after branches were changed behind the mount's back. Entries whose
+    /// path no longer exists on any branch are dropped, the rest get fresh
+    /// attributes and branch index, and cached directory link counts and
+    /// symlink targets in the subtree are forgotten. Returns the number of
+    /// inodes refreshed or dropped.
+    pub fn rebuild_inode_cache(&self, path: &Path) -> usize {
+        let prefix = Self::dir_nlink_key(path);
+        let in_subtree = |cached: &str| {
+            let cached = Self::dir_nlink_key(Path::new(cached));
+            prefix == "/" || cached == prefix || cached.starts_with(&format!("{}/", prefix))
+        };
+        
+        let cached: Vec<(u64, String)> = self.inodes.read().iter()
+            .filter(|(&ino, data)| ino != 1 && in_subtree(&data.path))
+            .map(|(&ino, data)| (ino, data.path.clone()))
+            .collect();
+        
+        for (ino, cached_path) in &cached {
+            self.symlink_cache.invalidate(*ino);
+            let fresh = self.create_file_attr_with_branch(Path::new(cached_path));
+            let mut inodes = self.inodes.write();
+            match fresh {
+                Some((mut attr, branch_idx, original_ino)) => {
+                    if let Some(inode_data) = inodes.get_mut(ino) {
+                        attr.ino = *ino;
+                        inode_data.attr = attr;
+                        inode_data.branch_idx = Some(branch_idx);
+                        inode_data.original_ino = original_ino;
+                    }
+                }
+                None => {
+                    inodes.remove(ino);
+                }
+            }
+        }
+        self.dir_nlink_cache.write().retain(|key, _| !in_subtree(key));
+        self.file_nlink_cache.write().retain(|key, _| !in_subtree(key));
+        self.readdir_cache.write().retain(|key, _| !in_subtree(key));
+        
+        tracing::info!("Rebuilt {} cached inodes under {:?}", cached.len(), path);
+        cached.len()
+    }
+    
+    /// Statistics statfs reports for the pool
+    ///
+    /// Branches are combined per the statfs and statfs.ignore options, then
+    /// statfs.total and statfs.available override the result when set.
+    pub fn statfs_totals(&self) -> StatfsTotals {
+        let (mode, ignore, total, available) = {
+            let config = self.config.read();
+            (config.statfs_mode, config.statfs_ignore, config.statfs_total, config.statfs_available)
+        };
+        
+        let mut stats = Vec::new();
+        for branch in self.file_manager.branches().iter() {
+            // Skip branches based on ignore setting
+            match ignore {
+                StatFSIgnore::ReadOnly if !branch.allows_create() => continue,
+                StatFSIgnore::NoCreate if !branch.allows_create() => continue,
+                _ => {}
+            }
+            
+            // Get statfs info from the branch
+            let full_path = branch.path.as_path();
+            if let Ok(statvfs) = nix::sys::statvfs::statvfs(full_path) {
+                stats.push(StatfsTotals {
+                    blocks: statvfs.blocks(),
+                    bfree: statvfs.blocks_free(),
+                    bavail: statvfs.blocks_available(),
+                    files: statvfs.files(),
+                    ffree: statvfs.files_free(),
+                    bsize: statvfs.block_size() as u32,
+                    namelen: statvfs.name_max() as u32,
+                    frsize: statvfs.fragment_size() as u32,
+                });
+            }
+        }
+        
+        apply_statfs_overrides(combine_statfs(&stats, mode), total, available)
+    }
+    
+    /// Mount flags statvfs should report for the pool
+    ///
+    /// The ro, nosuid and nodev mount options, plus ST_RDONLY whenever no
+    /// branch can be written: every branch is RO or has been remounted
+    /// read-only. FUSE statfs replies carry no flags, so the kernel fills
+    /// f_flag from the mount's own flags; main mounts read-only when these
+    /// include ST_RDONLY.
+    pub fn statfs_flags(&self) -> FsFlags {
+        let mut flags = self.config.read().mount_flags;
+        let writable = self.file_manager.branches()
+            .iter()
+            .any(|branch| !branch.is_readonly() && !branch.is_remounted_readonly());
+        if !writable {
+            flags |= FsFlags::ST_RDONLY;
+        }
+        flags
+    }
+    
+    /// Drop every in-memory cache that can be rebuilt, returning how many inodes went
+    ///
+    /// Cached inodes are dropped like max_inodes evictions, except that root,
+    /// the control file, inodes the kernel still references and inodes with
+    /// open file handles stay. Directory handles, directory link counts and
+    /// listings, symlink targets and sampled branch free space are all
+    /// forgotten; inode generations are kept so NFS clients still see reused
+    /// numbers as new files.
+    pub fn gc(&self) -> usize {
+        let pinned = self.referenced_inodes();
+        
+        let dropped = {
+            let mut inodes = self.inodes.write();
+            let before = inodes.len();
+            inodes.retain(|ino, _| pinned.contains(ino));
+            self.inode_access.lock().retain(|ino, _| pinned.contains(ino));
+            self.dir_touches.lock().retain(|ino, _| pinned.contains(ino));
+            before - inodes.len()
+        };
+        self.reap_idle_dir_handles(Duration::ZERO);
+        self.dir_nlink_cache.write().clear();
+        self.file_nlink_cache.write().clear();
+        self.readdir_cache.write().clear();
+        self.symlink_cache.clear();
+        for branch in self.file_manager.branches().iter() {
+            branch.clear_disk_space();
+        }
+        
+        tracing::info!("Garbage collected caches, dropping {} inodes", dropped);
+        dropped
+    }
+    
+    /// Change the order policies walk the branches in, without remounting
+    ///
+    /// `paths` must name every branch exactly once. Open handles keep writing
+    /// to the branch they were opened on, and cached inodes are re-resolved
+    /// against the new order.
+    pub fn reorder_branches(&self, paths: &[PathBuf]) -> Result<(), PolicyError> {
+        let new_index = self.file_manager.branch_list().reorder(paths)?;
+        self.file_handle_manager.remap_branches(&new_index);
+        for data in self.inodes.write().values_mut() {
+            data.branch_idx = data.branch_idx.and_then(|idx| new_index.get(idx).copied());
+        }
+        self.rebuild_inode_cache(Path::new("/"));
+        Ok(())
+    }
+    
+    /// Handle the cache invalidation and branch commands written to the control file
+    ///
+    /// These need the inode table, so they are served here instead of by the
+    /// config manager. Returns None for any other attribute.
+    fn handle_invalidate_xattr(&self, name: &OsStr, value: &[u8]) -> Option<Result<usize, i32>> {
+        match name.to_str()? {
+            INVALIDATE_XATTR => {
+                let path = std::str::from_utf8(value).ok()
+                    .map(|s| Path::new(s.trim_end_matches('\0').trim()))
+                    .filter(|path| path.has_root());
+                Some(path.map(|path| self.rebuild_inode_cache(path)).ok_or(EINVAL))
+            }
+            INVALIDATE_ALL_XATTR => Some(Ok(self.rebuild_inode_cache(Path::new("/")))),
+            GC_XATTR => Some(Ok(self.gc())),
+            BRANCHES_ORDER_XATTR => {
+                let paths: Option<Vec<PathBuf>> = std::str::from_utf8(value).ok().map(|s| {
+                    s.trim_end_matches('\0').trim().split(':')
+                        .map(|path| std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path)))
+                        .collect()
+                });
+                Some(match paths {
+                    Some(paths) => self.reorder_branches(&paths).map(|()| paths.len()).map_err(|e| e.errno()),
+                    None => Err(EINVAL),
+                })
+            }
+            _ => None,
+        }
+    }
+
     pub fn store_dir_handle(&self, fh: u64, path: PathBuf, ino: u64) {
-        self.dir_handles.write().insert(fh, DirHandle { path, ino });
+        self.dir_handles.write().insert(fh, DirHandle { path, ino, last_access: Instant::now() });
     }
 
     pub fn allocate_dir_handle(&self) -> u64 {
@@ -319,22 +1260,524 @@ impl MergerFS {
     }
 
     pub fn get_dir_handle(&self, fh: u64) -> Option<DirHandle> {
-        self.dir_handles.read().get(&fh).cloned()
+        let mut handles = self.dir_handles.write();
+        let handle = handles.get_mut(&fh)?;
+        handle.last_access = Instant::now();
+        Some(handle.clone())
     }
 
     pub fn remove_dir_handle(&self, fh: u64) {
         self.dir_handles.write().remove(&fh);
     }
     
-    fn insert_inode(&self, ino: u64, path: String, attr: FileAttr, branch_idx: Option<usize>, original_ino: u64) {
-        // Insert into inode map first
-        self.inodes.write().insert(ino, InodeData { 
-            path: path.clone(), 
-            attr,
-            content_lock: Arc::new(parking_lot::RwLock::new(())),
+    /// Drop directory handles not used for longer than `max_idle`, returning how many went
+    ///
+    /// A client that dies without its releasedir reaching us would otherwise
+    /// leak the handle forever. Reaping a handle that is still open is harmless
+    /// since readdir falls back to the inode's path.
+    pub fn reap_idle_dir_handles(&self, max_idle: Duration) -> usize {
+        let mut handles = self.dir_handles.write();
+        let before = handles.len();
+        handles.retain(|_, handle| handle.last_access.elapsed() <= max_idle);
+        let reaped = before - handles.len();
+        if reaped > 0 {
+            tracing::info!("Reaped {} idle directory handles", reaped);
+        }
+        reaped
+    }
+    
+    /// Run a reaping pass if the last one was more than DIR_HANDLE_REAP_INTERVAL ago
+    fn maybe_reap_dir_handles(&self) {
+        {
+            let mut last = self.last_dir_handle_reap.lock();
+            if last.elapsed() < DIR_HANDLE_REAP_INTERVAL {
+                return;
+            }
+            *last = Instant::now();
+        }
+        self.reap_idle_dir_handles(DIR_HANDLE_IDLE_TIMEOUT);
+    }
+    
+    /// Count a collision if cached inode `ino` belongs to a different file than `path`
+    ///
+    /// Another name for the same file (a hard link, or the path after a rename)
+    /// has the same branch and underlying inode and is not a collision.
+    fn check_inode_collision(&self, ino: u64, cached: &InodeData, path: &str, branch_idx: Option<usize>, original_ino: u64) {
+        if cached.path == path || (cached.branch_idx == branch_idx && cached.original_ino == original_ino) {
+            return;
+        }
+        self.inode_collisions.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        tracing::warn!("Inode {} calculated for {:?} is already used by {:?}", ino, path, cached.path);
+    }
+    
+    /// Cache `ino` for `path`, or refresh the entry if the inode is already known
+    ///
+    /// Check and insert happen under one write lock, and an existing entry
+    /// (from a racing lookup, or the file's other name) keeps its content
+    /// lock so readers and writers of the inode still exclude each other.
+    pub fn insert_inode(&self, ino: u64, path: String, attr: FileAttr, branch_idx: Option<usize>, original_ino: u64) {
+        use std::collections::hash_map::Entry;
+        
+        match self.inodes.write().entry(ino) {
+            Entry::Occupied(mut entry) => {
+                let data = entry.get_mut();
+                self.check_inode_collision(ino, data, &path, branch_idx, original_ino);
+                data.path = path;
+                data.attr = attr;
+                data.branch_idx = branch_idx;
+                data.original_ino = original_ino;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(InodeData {
+                    path,
+                    attr,
+                    content_lock: Arc::new(parking_lot::RwLock::new(())),
+                    branch_idx,
+                    original_ino,
+                });
+            }
+        }
+        self.touch_inode(ino);
+        self.evict_inodes_over_limit();
+    }
+    
+    /// Direct I/O decision and FUSE reply flags for an open or create from `pid`
+    ///
+    /// O_DIRECT clients always get direct I/O, while the branch file itself
+    /// is opened buffered; FOPEN_DIRECT_IO is set in the reply to match.
+    pub fn open_reply_flags(&self, pid: u32, flags: i32) -> (bool, u32) {
+        let direct_io = self.direct_io_for_pid(pid) || wants_direct_io(flags);
+        let mut reply_flags = flags as u32;
+        if direct_io {
+            reply_flags |= FOPEN_DIRECT_IO;
+        }
+        (direct_io, reply_flags)
+    }
+    
+    /// Decide direct I/O for an open from `pid`, consulting its comm under cache.files=per-process
+    fn direct_io_for_pid(&self, pid: u32) -> bool {
+        let per_process = self.config.read().cache_files == crate::config::CacheFiles::PerProcess;
+        if !per_process {
+            return self.config.read().should_use_direct_io();
+        }
+        
+        let process_name = std::fs::read_to_string(format!("/proc/{}/comm", pid))
+            .ok()
+            .map(|comm| comm.trim_end().to_string());
+        self.config.read().should_use_direct_io_for(process_name.as_deref())
+    }
+    
+    /// Truncate a file, migrating it to another branch if its branch is out of space
+    pub fn truncate_with_moveonenospc(&self, path: &Path, size: u64, fh: Option<u64>) -> Result<(), PolicyError> {
+        let handle = fh.and_then(|fh| self.file_handle_manager.get_handle(fh));
+        let branches = self.file_manager.branches();
+        let (branch_idx, targets) = match handle.as_ref().and_then(|h| h.branch_idx) {
+            // An open handle's file may no longer have a path, leaving no other copies
+            Some(idx) => match self.file_manager.truncate_branch_indices(&branches, path) {
+                Err(PolicyError::NoBranchesAvailable) => (idx, Vec::new()),
+                targets => (idx, targets?),
+            },
+            None => {
+                let targets = self.file_manager.truncate_branch_indices(&branches, path)?;
+                (*targets.first().ok_or(PolicyError::NoBranchesAvailable)?, targets)
+            }
+        };
+        // The handler dup2()s the moved file over an open handle's descriptor
+        let fd = handle.as_ref().and_then(|h| h.file.as_ref()).map(|f| f.as_raw_fd());
+        
+        let policy_ref = self.file_manager.create_policy.read();
+        let ((), new_branch_idx) = self.moveonenospc_handler.retry_on_enospc(
+            path,
             branch_idx,
-            original_ino,
+            &branches,
+            policy_ref.as_ref(),
+            fd,
+            |idx| {
+                let branch = branches.get(idx).ok_or(PolicyError::PathNotFound)?;
+                self.file_manager.truncate_file_on_branch(path, branch, size)
+            },
+        )?;
+        
+        if new_branch_idx != branch_idx {
+            if let Some(fh) = fh {
+                self.file_handle_manager.update_branch(fh, new_branch_idx);
+            }
+        }
+        
+        // Keep the other copies picked by func.truncate the same size
+        let mut result = Ok(());
+        for idx in targets.into_iter().filter(|&idx| idx != branch_idx && idx != new_branch_idx) {
+            if let Err(e) = self.file_manager.truncate_file_on_branch(path, &branches[idx], size) {
+                tracing::warn!("Failed to truncate copy of {:?} on branch {}: {:?}", path, idx, e);
+                result = Err(e);
+            }
+        }
+        result
+    }
+    
+    /// Open regular file `ino` on the branch func.open picks, returning the new handle
+    ///
+    /// The branch file is opened once and kept for reads and writes, so the
+    /// handle always records the branch it was opened from. Fails with ENOENT
+    /// when no branch has the file and EINVAL for anything but regular files.
+    pub fn open_handle(&self, ino: u64, flags: i32, direct_io: bool) -> Result<u64, PolicyError> {
+        let data = self.get_inode_data(ino).ok_or(PolicyError::PathNotFound)?;
+        if data.attr.kind != FileType::RegularFile {
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EINVAL)));
+        }
+        
+        // For hard links, find a valid path since cached path might not exist
+        let path = self.find_valid_path_for_inode(&data).ok_or_else(|| {
+            tracing::error!("Could not find valid path for inode {}", ino);
+            PolicyError::PathNotFound
+        })?;
+        
+        // Find which branch to open the file from (func.open)
+        let branch = self.file_manager.find_open_branch(&path)?;
+        let branch_idx = self.file_manager.branches().iter()
+            .position(|b| Arc::ptr_eq(b, &branch))
+            .ok_or(PolicyError::PathNotFound)?;
+        
+        // Opened for writing: the descriptor must not point at an inode other links share
+        if flags & O_ACCMODE != O_RDONLY {
+            self.break_hard_link_for_write(ino, &path, Some(branch_idx));
+        }
+        
+        let full_path = branch.full_path(&path);
+        let file = open_branch_file(&full_path, flags).map_err(|e| {
+            error!("Failed to open {:?}: {:?}", full_path, e);
+            e
+        })?;
+        let readahead = self.config.read().readahead;
+        advise_readahead(&file, flags, readahead, fadvise_readahead);
+        
+        Ok(self.file_handle_manager.create_handle_with_file(ino, path, flags, Some(branch_idx), direct_io, Some(file)))
+    }
+    
+    /// Give `path` its own inode on its branch before it is modified (link_cow)
+    ///
+    /// If the file shares its inode with other hard links a private copy
+    /// replaces it. Handles of `ino` opened through `path` on that branch
+    /// then have their descriptor pointed at the copy, keeping its status
+    /// flags (such as O_APPEND), so writes through them no longer reach the
+    /// other links. Runs when a file is opened for writing or truncated, not
+    /// on every write. Failures are only logged.
+    fn break_hard_link_for_write(&self, ino: u64, path: &Path, branch_idx: Option<usize>) {
+        if !self.config.read().link_cow {
+            return;
+        }
+        let branch_idx = match self.file_manager.break_hard_link(path, branch_idx) {
+            Ok(Some(branch_idx)) => branch_idx,
+            Ok(None) => return,
+            Err(e) => {
+                tracing::warn!("link-cow failed for {:?}: {:?}", path, e);
+                return;
+            }
+        };
+        
+        let Some(branch) = self.file_manager.branches().get(branch_idx).cloned() else {
+            return;
+        };
+        let full_path = branch.full_path(path);
+        for handle in self.file_handle_manager.handles_for_inode(ino) {
+            let Some(file) = handle.file.as_ref().filter(|_| handle.branch_idx == Some(branch_idx) && handle.path == path) else {
+                continue;
+            };
+            if let Err(e) = reopen_descriptor(file, &full_path, handle.flags) {
+                tracing::warn!("link-cow could not move a handle of {:?} to its copy: {:?}", path, e);
+            }
+        }
+    }
+    
+    /// Write `data` at `offset` through handle `fh`, returning the bytes written
+    ///
+    /// The write goes to the branch the handle was opened on, through the
+    /// descriptor kept at open/create when there is one. An unknown handle or
+    /// one without a branch fails with EBADF instead of guessing a copy, which
+    /// for a file on several branches may not be the one that was opened. On
+    /// ENOSPC with moveonenospc enabled the file is moved and the write retried.
+    pub fn write_at(&self, ino: u64, fh: u64, offset: i64, data: &[u8]) -> Result<usize, PolicyError> {
+        // Hold the inode's content lock for exclusive access during the write
+        let content_lock = self.get_inode_data(ino).ok_or(PolicyError::PathNotFound)?.content_lock;
+        let _content_guard = content_lock.write();
+
+        let Some((path_buf, branch_idx, open_file, write_buffer)) = self.file_handle_manager.get_handle(fh)
+            .and_then(|handle| Some((handle.path, handle.branch_idx?, handle.file, handle.write_buffer)))
+        else {
+            tracing::error!("Write to inode {} through handle {} without an opened branch", ino, fh);
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EBADF)));
+        };
+        tracing::debug!("Using file handle {} for path {:?}, branch {}", fh, path_buf, branch_idx);
+        let path = path_buf.as_path();
+        let writeback = self.config.read().writeback;
+        let branches = self.file_manager.branches();
+        
+        self.symlink_cache.invalidate(ino);
+        self.check_write_flags(path, Some(branch_idx), open_file.as_deref(), offset)?;
+        
+        let write = |branch_idx: usize| {
+            if branches.get(branch_idx).ok_or(PolicyError::PathNotFound)?.is_readonly() {
+                tracing::error!("Branch {} does not allow writes", branch_idx);
+                return Err(PolicyError::ReadOnlyFilesystem);
+            }
+            let written = match &open_file {
+                Some(file) => Self::write_to_open_file(file, &write_buffer, writeback, data, offset),
+                None => self.write_to_branch_path(path, branch_idx, data, offset),
+            }?;
+            self.verify_write_if_enabled(path, Some(branch_idx), open_file.as_deref(), Some(&*write_buffer), &data[..written], offset)?;
+            self.update_size_after_write(ino, path, Some(branch_idx), open_file.as_deref(), (offset as u64) + (written as u64));
+            self.account_write(path, Some(branch_idx), written as u64);
+            Ok(written)
+        };
+        
+        let e = match write(branch_idx) {
+            Ok(written) => return Ok(written),
+            Err(e) => e,
+        };
+        if let PolicyError::IoError(io_error) = &e {
+            branches[branch_idx].record_io_error(io_error);
+        }
+        if !matches!(&e, PolicyError::NoSpace) || !self.config.read().moveonenospc.enabled {
+            error!("Write failed for {:?}: {:?}", path, e);
+            return Err(e);
+        }
+        
+        tracing::info!("ENOSPC detected, attempting moveonenospc");
+        let moved = {
+            let policy_ref = self.file_manager.create_policy.read();
+            self.moveonenospc_handler.move_file_on_enospc(
+                path,
+                branch_idx,
+                &branches,
+                policy_ref.as_ref(),
+                // The handler dup2()s the new file over this descriptor
+                open_file.as_ref().map(|f| f.as_raw_fd()),
+            )
+        };
+        let new_branch_idx = match moved {
+            Ok(move_result) => move_result.new_branch_idx,
+            Err(move_e) => {
+                error!("moveonenospc failed: {:?}", move_e);
+                // Return original error
+                return Err(e);
+            }
+        };
+        tracing::info!("Successfully moved file to branch {}, retrying write", new_branch_idx);
+        
+        // Point the file handle at the new branch
+        self.file_handle_manager.update_branch(fh, new_branch_idx);
+        write(new_branch_idx).map_err(|retry_e| {
+            error!("Write failed after moveonenospc: {:?}", retry_e);
+            self.moveonenospc_handler.stats().record_failure();
+            retry_e
+        })
+    }
+    
+    /// Close handle `fh`, writing out buffered data first
+    ///
+    /// With sync_on_close, files opened for writing are also synced to their
+    /// branch so the data is durable once the close returns. If the buffered
+    /// data cannot be written the handle is kept, so its data is tried again
+    /// when the pool is unmounted instead of being lost.
+    pub fn release_handle(&self, fh: u64) -> std::io::Result<()> {
+        let Some(handle) = self.file_handle_manager.get_handle(fh) else {
+            return Ok(());
+        };
+        {
+            let buffer = handle.write_buffer.lock();
+            tracing::debug!("{} bytes pending, {} buffered writes already written", buffer.pending(), buffer.flush_count());
+        }
+        
+        flush_write_buffer(&handle).map_err(|e| {
+            error!("Failed to write buffered data for {:?} on release: {:?}", handle.path, e);
+            e
+        })?;
+        self.file_handle_manager.remove_handle(fh);
+        
+        let (sync_enabled, dropcacheonclose) = {
+            let config = self.config.read();
+            (config.sync_on_close, config.dropcacheonclose)
+        };
+        let result = sync_on_close(&handle, sync_enabled, std::fs::File::sync_all).map_err(|e| {
+            error!("Failed to sync {:?} on release: {:?}", handle.path, e);
+            e
         });
+        drop_cache_on_close(&handle, dropcacheonclose, fadvise_dontneed);
+        result.map(|_| ())
+    }
+    
+    /// Write to the copy of `path` on `branch_idx` by path, for handles that kept no descriptor
+    fn write_to_branch_path(&self, path: &Path, branch_idx: usize, data: &[u8], offset: i64) -> Result<usize, PolicyError> {
+        let branch = &self.file_manager.branches()[branch_idx];
+        let file = std::fs::OpenOptions::new().write(true).open(branch.full_path(path))?;
+        pwrite_all(&file, data, offset).map_err(|e| {
+            if is_out_of_space_error(&e) {
+                tracing::info!("Detected out of space error on branch {}", branch_idx);
+                PolicyError::NoSpace
+            } else {
+                PolicyError::IoError(e)
+            }
+        })
+    }
+    
+    /// Write to a handle's open branch file, mapping out-of-space errors for moveonenospc
+    ///
+    /// With writeback the data is queued in the handle's write buffer; otherwise
+    /// anything still buffered is written out first so writes land in order.
+    fn write_to_open_file(file: &std::fs::File, buffer: &SharedWriteBuffer, writeback: bool, data: &[u8], offset: i64) -> Result<usize, PolicyError> {
+        let mut buffer = buffer.lock();
+        let result = if writeback {
+            buffer.write(file, data, offset)
+        } else {
+            buffer.flush(file).and_then(|_| pwrite_all(file, data, offset))
+        };
+        match result {
+            Ok(written) => Ok(written),
+            Err(e) if is_out_of_space_error(&e) => Err(PolicyError::NoSpace),
+            Err(e) => Err(PolicyError::IoError(e)),
+        }
+    }
+
+    /// Check that `data` reads back at `offset` from the copy of `path` on `branch_idx`
+    ///
+    /// Without a branch index the first branch holding the file is checked.
+    pub fn verify_write(&self, path: &Path, branch_idx: Option<usize>, data: &[u8], offset: i64) -> Result<(), PolicyError> {
+        let branch = match branch_idx {
+            Some(idx) => self.file_manager.branches().get(idx).cloned().ok_or(PolicyError::PathNotFound)?,
+            None => self.file_manager.find_first_branch(path)?,
+        };
+        verify_written(&branch.full_path(path), data, offset).map_err(PolicyError::IoError)
+    }
+    
+    /// Apply write_verify to a completed write, writing out any buffered data first
+    fn verify_write_if_enabled(
+        &self,
+        path: &Path,
+        branch_idx: Option<usize>,
+        open_file: Option<&std::fs::File>,
+        write_buffer: Option<&SharedWriteBuffer>,
+        data: &[u8],
+        offset: i64,
+    ) -> Result<(), PolicyError> {
+        if !self.config.read().write_verify {
+            return Ok(());
+        }
+        if let (Some(file), Some(buffer)) = (open_file, write_buffer) {
+            buffer.lock().flush(file)?;
+        }
+        self.verify_write(path, branch_idx, data, offset)
+    }
+
+    /// Generation to report alongside `ino` in entry replies
+    pub fn inode_generation(&self, ino: u64) -> u64 {
+        self.generations.read().get(&ino).copied().unwrap_or(0)
+    }
+    
+    /// Mark the file behind `ino` as gone so the number's next use gets a new generation
+    fn retire_inode_generation(&self, ino: u64) {
+        *self.generations.write().entry(ino).or_insert(0) += 1;
+    }
+    
+    /// Read up to `size` bytes at `offset`, fewer only at end of file
+    ///
+    /// Uses the descriptor opened at open/create when the handle has one and
+    /// otherwise opens the first branch holding the file. Short reads are
+    /// retried either way.
+    pub fn read_range(&self, ino: u64, fh: u64, offset: i64, size: u32) -> Result<Vec<u8>, PolicyError> {
+        let handle = self.file_handle_manager.get_handle(fh);
+        let file = match handle.as_ref().and_then(|h| h.file.clone()) {
+            Some(file) => file,
+            None => {
+                let path = handle.map(|h| h.path)
+                    .or_else(|| self.get_inode_data(ino).map(|data| PathBuf::from(&data.path)))
+                    .ok_or(PolicyError::PathNotFound)?;
+                let branch = self.file_manager.find_first_branch(&path)?;
+                tracing::debug!("Reading {:?} from branch {:?}", path, branch.path);
+                Arc::new(std::fs::File::open(branch.full_path(&path))?)
+            }
+        };
+        
+        let mut buffer = vec![0u8; size as usize];
+        let n = pread_full(&file, &mut buffer, offset)?;
+        buffer.truncate(n);
+        Ok(buffer)
+    }
+    
+    /// Refuse a write of `offset` that the branch file's inode flags forbid
+    ///
+    /// Immutable files take no writes and append-only files only writes at
+    /// or past their current end. The flags are read through `open_file`
+    /// when the handle has one; branches without flag support never refuse.
+    pub fn check_write_flags(&self, path: &Path, branch_idx: Option<usize>, open_file: Option<&std::fs::File>, offset: i64) -> Result<(), PolicyError> {
+        let (flags, size) = match open_file {
+            Some(file) => (crate::fs_utils::inode_flags(file), file.metadata().map(|m| m.len())),
+            None => {
+                let branch = match branch_idx.and_then(|idx| self.file_manager.branches().get(idx).cloned()) {
+                    Some(branch) => branch,
+                    None => self.file_manager.find_first_branch(path)?,
+                };
+                let full_path = branch.full_path(path);
+                (crate::fs_utils::path_inode_flags(&full_path), std::fs::metadata(&full_path).map(|m| m.len()))
+            }
+        };
+        let Ok(flags) = flags else {
+            return Ok(());
+        };
+        
+        if flags & crate::fs_utils::FS_IMMUTABLE_FL != 0 {
+            tracing::debug!("Refusing write to immutable {:?}", path);
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EPERM)));
+        }
+        if flags & crate::fs_utils::FS_APPEND_FL != 0 && size.is_ok_and(|size| (offset as u64) < size) {
+            tracing::debug!("Refusing write at {} inside append-only {:?}", offset, path);
+            return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EPERM)));
+        }
+        Ok(())
+    }
+    
+    /// Refuse removing or renaming `path` when any copy is immutable or append-only
+    ///
+    /// The directory holding each copy is checked too, as neither kind lets
+    /// entries be removed. Copies are found with lstat, and only regular files
+    /// and directories are opened to read their flags: opening a device node
+    /// or FIFO could have side effects or block.
+    pub fn check_deletable(&self, path: &Path) -> Result<(), PolicyError> {
+        let protected = crate::fs_utils::FS_IMMUTABLE_FL | crate::fs_utils::FS_APPEND_FL;
+        let is_protected = |full_path: &Path| {
+            crate::fs_utils::path_inode_flags(full_path).is_ok_and(|flags| flags & protected != 0)
+        };
+        for branch in self.file_manager.branches().iter() {
+            let full_path = branch.full_path(path);
+            let Ok(metadata) = full_path.symlink_metadata() else {
+                continue;
+            };
+            let copy_protected = (metadata.is_file() || metadata.is_dir()) && is_protected(&full_path);
+            if copy_protected || full_path.parent().is_some_and(is_protected) {
+                tracing::debug!("{:?} or its directory is immutable or append-only on {:?}", path, branch.path);
+                return Err(PolicyError::IoError(std::io::Error::from_raw_os_error(EPERM)));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Unlink a path, retiring its inode number once the underlying file is destroyed
+    pub fn unlink_path(&self, path: &Path) -> Result<(), PolicyError> {
+        self.check_deletable(path)?;
+        let before = self.create_file_attr(path);
+        self.file_manager.remove_file(path)?;
+        self.touch_parent(path);
+        self.file_nlink_cache.write().remove(&Self::dir_nlink_key(path));
+        
+        if let Some(ino) = self.path_to_inode(&path.to_string_lossy()) {
+            self.symlink_cache.invalidate(ino);
+        }
+        // Other hard links, or copies an epff unlink left on later branches,
+        // keep the file (and its inode number) alive
+        let still_exists = self.file_manager.find_first_branch(path).is_ok();
+        if let Some(attr) = before.filter(|attr| attr.nlink <= 1 && !still_exists) {
+            self.retire_inode_generation(attr.ino);
+        }
+        Ok(())
     }
     
     fn remove_inode(&self, ino: u64) {
@@ -343,8 +1786,81 @@ impl MergerFS {
             let mut inodes = self.inodes.write();
             inodes.remove(&ino).map(|data| data.path)
         };
+        self.inode_access.lock().remove(&ino);
     }
     
+    /// Hard link `link_path` to `source_path`, honouring `link-exdev`
+    ///
+    /// When the link would have to cross branches the configured mode decides
+    /// between returning EXDEV and creating a relative or absolute symlink to
+    /// the source instead.
+    pub fn link_path(&self, source_path: &Path, link_path: &Path) -> Result<(), PolicyError> {
+        let result = self.file_manager.check_link_branches(source_path, link_path)
+            .and_then(|_| self.file_manager.create_hard_link(source_path, link_path));
+        let Err(e) = result else {
+            self.file_nlink_cache.write().remove(&Self::dir_nlink_key(source_path));
+            return Ok(());
+        };
+        if e.errno() != EXDEV {
+            return Err(e);
+        }
+
+        let target = {
+            let config = self.config.read();
+            match config.link_exdev {
+                LinkEXDEV::Passthrough => return Err(e),
+                LinkEXDEV::RelSymlink => {
+                    let parent = link_path.parent().unwrap_or_else(|| Path::new("/"));
+                    crate::fs_utils::relative_path(parent, source_path)
+                }
+                LinkEXDEV::AbsSymlink => {
+                    config.mountpoint.join(source_path.strip_prefix("/").unwrap_or(source_path))
+                }
+            }
+        };
+        tracing::debug!("Hard link {:?} would cross branches, creating symlink to {:?}", link_path, target);
+        self.file_manager.create_symlink(link_path, &target)
+    }
+
+    /// Rename `old_path` to `new_path` in the pool and bring cached state along
+    ///
+    /// Inode paths and open file handles below `old_path` are moved to the
+    /// new path, so writes through a handle opened before the rename land in
+    /// the renamed file.
+    pub fn rename_path(&self, old_path: &str, new_path: &str) -> Result<(), RenameError> {
+        // A replaced destination is removed, so it must be deletable too
+        self.check_deletable(Path::new(old_path))?;
+        self.check_deletable(Path::new(new_path))?;
+        
+        // Cached symlink targets of the source and any replaced destination go stale
+        for path in [old_path, new_path] {
+            if let Some(ino) = self.path_to_inode(path) {
+                self.symlink_cache.invalidate(ino);
+            }
+        }
+
+        self.rename_manager.rename(Path::new(old_path), Path::new(new_path))?;
+        // A marker left by deleting the destination would hide the renamed entry
+        self.file_manager.clear_whiteout(Path::new(new_path));
+        self.touch_parent(Path::new(old_path));
+        self.touch_parent(Path::new(new_path));
+
+        // Update inode cache - this handles both files and directories
+        self.update_cached_paths_after_rename(old_path, new_path);
+        self.file_manager.rename_pins(Path::new(old_path), Path::new(new_path));
+        let handles = self.file_handle_manager.rename_paths(Path::new(old_path), Path::new(new_path));
+        if handles > 0 {
+            tracing::debug!("Moved {} open handles from {:?} to {:?}", handles, old_path, new_path);
+        }
+
+        // Renamed directories change subdirectory counts of both parents, and
+        // listings of both parents and anything under a moved directory
+        self.dir_nlink_cache.write().clear();
+        self.file_nlink_cache.write().clear();
+        self.readdir_cache.write().clear();
+        Ok(())
+    }
+
     fn update_cached_paths_after_rename(&self, old_path: &str, new_path: &str) {
         // We need to update all cached inodes whose paths start with old_path
         let old_path_with_slash = if old_path.ends_with('/') {
@@ -391,14 +1907,34 @@ impl Clone for DirHandle {
         DirHandle {
             path: self.path.clone(),
             ino: self.ino,
+            last_access: self.last_access,
         }
     }
 }
 
 impl Filesystem for MergerFS {
+    fn init(&mut self, _req: &Request, _config: &mut fuser::KernelConfig) -> Result<(), i32> {
+        // The refresher idles until cache.diskspace is set, so it can be enabled at runtime
+        let config = self.config.clone();
+        self.disk_space_refresher = Some(DiskSpaceRefresher::start(
+            self.file_manager.branches().to_vec(),
+            move || Duration::from_secs(config.read().cache_diskspace),
+        ));
+        Ok(())
+    }
+    
+    fn destroy(&mut self) {
+        self.shutdown();
+    }
+    
+    fn forget(&mut self, _req: &Request, ino: u64, nlookup: u64) {
+        self.forget_inode(ino, nlookup);
+    }
+    
     fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
         let name_str = name.to_str().unwrap_or("<invalid>");
-        let _span = tracing::info_span!("fuse::lookup", parent, name = %name_str).entered();
+        let _span = tracing::info_span!("fuse::lookup", parent, name = %name_str, elapsed_us = tracing::field::Empty).entered();
+        let _timer = self.latency.start(TimedOp::Lookup);
         tracing::debug!("Starting lookup");
 
         let parent_data = match self.get_inode_data(parent) {
@@ -424,46 +1960,14 @@ impl Filesystem for MergerFS {
         };
         
         // Handle special control file
-        if ControlFileHandler::is_control_file(&child_path) {
-            let attr = self.control_file_handler.get_attr();
+        if let Some(attr) = self.control_file_attr(&child_path) {
             reply.entry(&TTL, &attr, 0);
             return;
         }
 
-        // Try to create attributes for this path
-        let path = Path::new(&child_path);
-        
-        // Try to create attributes (check if file/dir exists)
-        if let Some((attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(path) {
-            let ino = attr.ino; // Use the calculated inode
-            
-            // Check if this inode already exists (hard link case)
-            let mut inodes = self.inodes.write();
-            if !inodes.contains_key(&ino) {
-                // New inode, insert it
-                inodes.insert(ino, InodeData {
-                    path: child_path.clone(),
-                    attr,
-                    content_lock: Arc::new(parking_lot::RwLock::new(())),
-                    branch_idx: Some(branch_idx),
-                    original_ino,
-                });
-            } else {
-                // Existing inode (hard link) - update attributes to get fresh nlink
-                if let Some(inode_data) = inodes.get_mut(&ino) {
-                    inode_data.attr.nlink = attr.nlink;
-                    inode_data.attr.size = attr.size;
-                    inode_data.attr.mtime = attr.mtime;
-                    inode_data.attr.ctime = attr.ctime;
-                }
-            }
-            drop(inodes);
-            
-            // Return the attributes (now updated)
-            let inode_data = self.get_inode_data(ino).unwrap();
-            reply.entry(&TTL, &inode_data.attr, 0);
-        } else {
-            reply.error(ENOENT);
+        match self.lookup_path(&child_path) {
+            Some(attr) => reply.entry(&TTL, &attr, self.inode_generation(attr.ino)),
+            None => reply.error(ENOENT),
         }
     }
 
@@ -472,11 +1976,20 @@ impl Filesystem for MergerFS {
         tracing::info!("Starting getattr");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
             self.control_file_handler.handle_getattr(reply);
             return;
         }
 
+        // Files with an open handle report the copy that handle is using
+        if let Some(attr) = self.create_file_attr_for_open_handle(ino) {
+            if let Some(inode_data) = self.inodes.write().get_mut(&ino) {
+                inode_data.attr = attr;
+            }
+            reply.attr(&TTL, &attr);
+            return;
+        }
+
         match self.get_inode_data(ino) {
             Some(data) => {
                 // Refresh attributes from filesystem to get current nlink count
@@ -485,7 +1998,7 @@ impl Filesystem for MergerFS {
                     if let Some(fresh_attr) = self.create_file_attr(&valid_path) {
                     // The fresh_attr should have the same calculated inode
                     // Verify consistency - if not, use the cached inode
-                    let updated_attr = if fresh_attr.ino != ino {
+                    let mut updated_attr = if fresh_attr.ino != ino {
                         tracing::warn!("Inode mismatch for {}: cached={}, calculated={}", data.path, ino, fresh_attr.ino);
                         let mut attr = fresh_attr;
                         attr.ino = ino; // Keep the cached inode for consistency
@@ -494,6 +2007,10 @@ impl Filesystem for MergerFS {
                         fresh_attr
                     };
                     
+                    if updated_attr.kind == FileType::Directory {
+                        self.apply_dir_touch(ino, &mut updated_attr);
+                    }
+                    
                     // Update the cached inode data
                     if let Some(inode_data) = self.inodes.write().get_mut(&ino) {
                         inode_data.attr = updated_attr;
@@ -517,12 +2034,49 @@ impl Filesystem for MergerFS {
         }
     }
 
-    fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
+    fn readlink(&mut self, _req: &Request, ino: u64, reply: ReplyData) {
+        let _span = tracing::debug_span!("fuse::readlink", ino).entered();
+        tracing::debug!("Starting readlink");
+
+        let data = match self.get_inode_data(ino) {
+            Some(data) => data,
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };
+
+        if data.attr.kind != FileType::Symlink {
+            reply.error(EINVAL);
+            return;
+        }
+
+        let path = PathBuf::from(&data.path);
+        let resolve = || self.file_manager.read_symlink(&path);
+        let result = if self.config.read().cache_symlinks {
+            self.symlink_cache.get_or_insert_with(ino, resolve)
+        } else {
+            resolve()
+        };
+
+        match result {
+            Ok(target) => {
+                use std::os::unix::ffi::OsStrExt;
+                reply.data(target.as_os_str().as_bytes());
+            }
+            Err(e) => {
+                error!("readlink failed for {:?}: {:?}", data.path, e);
+                reply.error(e.errno());
+            }
+        }
+    }
+
+    fn open(&mut self, req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
         let _span = tracing::info_span!("fuse::open", ino, flags).entered();
         tracing::debug!("Starting open");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
             match self.control_file_handler.handle_open(flags) {
                 Ok(()) => {
                     let fh = self.file_handle_manager.create_handle(
@@ -539,42 +2093,45 @@ impl Filesystem for MergerFS {
             return;
         }
 
-        match self.get_inode_data(ino) {
-            Some(data) => {
-                if data.attr.kind == FileType::RegularFile {
-                    // For hard links, find a valid path since cached path might not exist
-                    if let Some(path) = self.find_valid_path_for_inode(&data) {
-                        // Find which branch has the file
-                        let branch_idx = match self.file_manager.find_first_branch(&path) {
-                            Ok(branch) => {
-                                self.file_manager.branches.iter().position(|b| Arc::ptr_eq(b, &branch))
-                            }
-                            Err(_) => None,
-                        };
-                        // Determine if we should use direct I/O
-                        let direct_io = self.config.read().should_use_direct_io();
-                        
-                        // Create file handle with the valid path
-                        let fh = self.file_handle_manager.create_handle(ino, path, flags, branch_idx, direct_io);
-                        
-                        // Set reply flags based on direct I/O setting
-                        let mut reply_flags = flags as u32;
-                        if direct_io {
-                            // Set FOPEN_DIRECT_IO flag in the reply
-                            reply_flags |= 0x00000001; // FOPEN_DIRECT_IO
-                        }
-                        
-                        reply.opened(fh, reply_flags);
-                    } else {
-                        tracing::error!("Could not find valid path for inode {}", ino);
-                        reply.error(ENOENT);
-                    }
-                } else {
-                    // Not a regular file
-                    reply.error(EINVAL);
-                }
+        let (direct_io, reply_flags) = self.open_reply_flags(req.pid(), flags);
+        match self.open_handle(ino, flags, direct_io) {
+            Ok(fh) => reply.opened(fh, reply_flags),
+            Err(e) => reply.error(e.errno()),
+        }
+    }
+
+    fn flush(&mut self, _req: &Request, ino: u64, fh: u64, _lock_owner: u64, reply: fuser::ReplyEmpty) {
+        let _span = tracing::debug_span!("fuse::flush", ino, fh).entered();
+        match self.file_handle_manager.get_handle(fh).map(|handle| flush_write_buffer(&handle)) {
+            Some(Err(e)) => {
+                error!("flush failed for fh {}: {:?}", fh, e);
+                reply.error(e.errno());
+            }
+            _ => reply.ok(),
+        }
+    }
+
+    fn fsync(&mut self, _req: &Request, ino: u64, fh: u64, datasync: bool, reply: fuser::ReplyEmpty) {
+        let _span = tracing::debug_span!("fuse::fsync", ino, fh, datasync).entered();
+        let handle = match self.file_handle_manager.get_handle(fh) {
+            Some(handle) => handle,
+            None => {
+                reply.error(EBADF);
+                return;
+            }
+        };
+        
+        let result = flush_write_buffer(&handle).and_then(|_| match &handle.file {
+            Some(file) if datasync => file.sync_data(),
+            Some(file) => file.sync_all(),
+            None => Ok(()),
+        });
+        match result {
+            Ok(()) => reply.ok(),
+            Err(e) => {
+                error!("fsync failed for {:?}: {:?}", handle.path, e);
+                reply.error(e.errno());
             }
-            None => reply.error(ENOENT),
         }
     }
 
@@ -589,8 +2146,10 @@ impl Filesystem for MergerFS {
         reply: fuser::ReplyEmpty
     ) {
         let _span = tracing::debug_span!("fuse::release", _ino, fh).entered();
-        self.file_handle_manager.remove_handle(fh);
-        reply.ok();
+        match self.release_handle(fh) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e.errno()),
+        }
     }
 
     fn read(
@@ -604,11 +2163,12 @@ impl Filesystem for MergerFS {
         _lock_owner: Option<u64>,
         reply: ReplyData,
     ) {
-        let _span = tracing::info_span!("fuse::read", ino, fh, offset, size).entered();
+        let _span = tracing::info_span!("fuse::read", ino, fh, offset, size, elapsed_us = tracing::field::Empty).entered();
+        let _timer = self.latency.start(TimedOp::Read);
         tracing::info!("Starting read operation");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
             self.control_file_handler.handle_read(reply);
             return;
         }
@@ -624,67 +2184,22 @@ impl Filesystem for MergerFS {
 
         // Acquire read lock to ensure no concurrent truncate/write
         let _content_guard = content_lock.read();
-
-        // Get the path from file handle or inode
-        let path_info = self.file_handle_manager.get_handle(fh)
-            .map(|h| (h.path, h.branch_idx))
-            .or_else(|| {
-                self.get_inode_data(ino).map(|data| (PathBuf::from(&data.path), None))
-            });
-
-        let (path_buf, _branch_idx) = match path_info {
-            Some(info) => info,
-            None => {
-                reply.error(ENOENT);
-                return;
-            }
-        };
-
-        let path = path_buf.as_path();
         
-        // Find the file and read from it
-        tracing::info!("Looking for file at path: {:?}", path);
-        match self.file_manager.find_first_branch(path) {
-            Ok(branch) => {
-                let full_path = branch.full_path(path);
-                tracing::info!("Found file at branch path: {:?}", full_path);
-                use std::fs::File;
-                use std::io::{Read, Seek, SeekFrom};
-                
-                match File::open(&full_path) {
-                    Ok(mut file) => {
-                        // Seek to the requested offset
-                        if offset > 0 {
-                            if let Err(e) = file.seek(SeekFrom::Start(offset as u64)) {
-                                error!("Failed to seek: {:?}", e);
-                                reply.error(EIO);
-                                return;
-                            }
-                        }
-                        
-                        // Read the requested amount of data
-                        let mut buffer = vec![0u8; size as usize];
-                        match file.read(&mut buffer) {
-                            Ok(n) => {
-                                tracing::info!("Read {} bytes from file (requested {})", n, size);
-                                buffer.truncate(n);
-                                reply.data(&buffer);
-                            }
-                            Err(e) => {
-                                error!("Read failed: {:?}", e);
-                                reply.error(EIO);
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("Failed to open file for reading: {:?}", e);
-                        reply.error(EIO);
-                    }
-                }
+        // Reads must see data still sitting in write buffers
+        if let Err(e) = self.file_handle_manager.flush_inode_buffers(ino) {
+            error!("Failed to write buffered data before read: {:?}", e);
+            reply.error(e.errno());
+            return;
+        }
+
+        match self.read_range(ino, fh, offset, size) {
+            Ok(data) => {
+                tracing::debug!("Read {} bytes (requested {})", data.len(), size);
+                reply.data(&data);
             }
             Err(e) => {
-                error!("Read failed for {:?}: {:?}", path, e);
-                reply.error(EIO);
+                error!("Read failed for inode {}: {:?}", ino, e);
+                reply.error(e.errno());
             }
         }
     }
@@ -708,6 +2223,7 @@ impl Filesystem for MergerFS {
         }
 
         // Store directory handle
+        self.maybe_reap_dir_handles();
         let fh = self.allocate_dir_handle();
         self.store_dir_handle(fh, PathBuf::from(&data.path), ino);
 
@@ -721,7 +2237,8 @@ impl Filesystem for MergerFS {
     }
 
     fn readdir(&mut self, _req: &Request, ino: u64, fh: u64, offset: i64, mut reply: ReplyDirectory) {
-        let _span = tracing::debug_span!("fuse::readdir", ino, fh, offset).entered();
+        let _span = tracing::debug_span!("fuse::readdir", ino, fh, offset, elapsed_us = tracing::field::Empty).entered();
+        let _timer = self.latency.start(TimedOp::Readdir);
         tracing::debug!("Starting readdir");
 
         // Get directory path and verify it's a directory without holding locks
@@ -731,8 +2248,15 @@ impl Filesystem for MergerFS {
                 match self.get_dir_handle(fh) {
                     Some(handle) => handle.path.to_string_lossy().to_string(),
                     None => {
-                        reply.error(EINVAL);
-                        return;
+                        // The handle may have been reaped; the inode still names the directory
+                        tracing::debug!("Unknown directory handle {}, using inode {}", fh, ino);
+                        match self.get_inode_data(ino) {
+                            Some(data) => data.path.clone(),
+                            None => {
+                                reply.error(ENOENT);
+                                return;
+                            }
+                        }
                     }
                 }
             } else {
@@ -763,44 +2287,7 @@ impl Filesystem for MergerFS {
             data.path
         };
 
-        // Start with standard entries
-        let mut entries = vec![
-            (1, FileType::Directory, ".".to_string()),
-            (1, FileType::Directory, "..".to_string()),
-        ];
-
-        // Add control file to root directory listing
-        if dir_path == "/" {
-            entries.push((CONTROL_FILE_INO, FileType::RegularFile, ".mergerfs".to_string()));
-        }
-        
-        // Get union directory listing (no locks held during I/O)
-        let path = Path::new(&dir_path);
-        match self.file_manager.list_directory(path) {
-            Ok(dir_entries) => {
-                for entry_name in dir_entries {
-                    // Create a path for this entry to check if it's a directory
-                    let entry_path = if dir_path == "/" {
-                        format!("/{}", entry_name)
-                    } else {
-                        format!("{}/{}", dir_path, entry_name)
-                    };
-                    
-                    // Get file attributes to determine type and calculate inode
-                    let entry_path_obj = Path::new(&entry_path);
-                    if let Some(attr) = self.create_file_attr(entry_path_obj) {
-                        entries.push((attr.ino, attr.kind, entry_name));
-                    } else {
-                        // Skip entries we can't stat
-                        tracing::warn!("Could not get attributes for directory entry: {}", entry_path);
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to list directory contents: {:?}", e);
-                // Fall back to just . and .. entries
-            }
-        }
+        let entries = self.dir_entries(&dir_path);
 
         // Return entries starting from the requested offset
         for (i, (ino, file_type, name)) in entries.into_iter().enumerate().skip(offset as usize) {
@@ -813,7 +2300,7 @@ impl Filesystem for MergerFS {
 
     fn create(
         &mut self,
-        _req: &Request,
+        req: &Request,
         parent: u64,
         name: &OsStr,
         mode: u32,
@@ -855,38 +2342,50 @@ impl Filesystem for MergerFS {
         let path = Path::new(&file_path);
         tracing::debug!("Creating file at path: {:?}", file_path);
         
-        match self.file_manager.create_file(path, &[]) {
+        match self.create_path(path, flags) {
             Ok(_) => {
                 tracing::info!("File created successfully at {:?}", file_path);
                 // Create file attributes (no locks held during I/O)
-                if let Some((attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(path) {
+                if let Some((mut attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(path) {
                     let ino = attr.ino; // Use the calculated inode
+                    
+                    // Keep the new file open for subsequent read/write calls
+                    let full_path = self.file_manager.branches()[branch_idx].full_path(path);
+                    let file = match open_branch_file(&full_path, flags) {
+                        Ok(file) => file,
+                        Err(e) => {
+                            error!("Failed to open created file {:?}: {:?}", full_path, e);
+                            reply.error(e.errno());
+                            return;
+                        }
+                    };
+                    
+                    // Applied once the descriptor is open so a read-only mode
+                    // doesn't keep the creator from writing
+                    match self.apply_create_mode(path, branch_idx, mode, umask) {
+                        Ok(perm) => attr.perm = perm,
+                        Err(e) => tracing::warn!("Failed to set mode of {:?}: {:?}", full_path, e),
+                    }
 
                     // Insert inode with minimal lock time
                     self.insert_inode(ino, file_path.clone(), attr, Some(branch_idx), original_ino);
                     
-                    // Determine if we should use direct I/O
-                    let direct_io = self.config.read().should_use_direct_io();
+                    let (direct_io, reply_flags) = self.open_reply_flags(req.pid(), flags);
                     
-                    let fh = self.file_handle_manager.create_handle(
+                    let fh = self.file_handle_manager.create_handle_with_file(
                         ino,
                         PathBuf::from(&file_path),
                         flags,
                         Some(branch_idx),
-                        direct_io
+                        direct_io,
+                        Some(file),
                     );
                     
                     tracing::debug!("Created file handle {} for new file {:?} (direct_io: {})", fh, file_path, direct_io);
                     
-                    // Set reply flags based on direct I/O setting
-                    let mut reply_flags = flags as u32;
-                    if direct_io {
-                        // Set FOPEN_DIRECT_IO flag in the reply
-                        reply_flags |= 0x00000001; // FOPEN_DIRECT_IO
-                    }
-                    
                     // Return the file handle in the reply
-                    reply.created(&TTL, &attr, 0, fh, reply_flags);
+                    self.count_lookup(ino);
+                    reply.created(&TTL, &attr, self.inode_generation(ino), fh, reply_flags);
                 } else {
                     reply.error(EIO);
                 }
@@ -912,239 +2411,19 @@ impl Filesystem for MergerFS {
         _lock_owner: Option<u64>,
         reply: ReplyWrite,
     ) {
-        let _span = tracing::info_span!("fuse::write", ino, fh, offset, len = data.len(), write_flags = %format!("0x{:x}", write_flags), flags = %format!("0x{:x}", flags)).entered();
+        let _span = tracing::info_span!("fuse::write", ino, fh, offset, len = data.len(), write_flags = %format!("0x{:x}", write_flags), flags = %format!("0x{:x}", flags), elapsed_us = tracing::field::Empty).entered();
+        let _timer = self.latency.start(TimedOp::Write);
         tracing::debug!("Starting write operation");
 
-        // Get the content lock for this inode
-        let content_lock = match self.get_inode_data(ino) {
-            Some(data) => data.content_lock.clone(),
-            None => {
-                reply.error(ENOENT);
-                return;
-            }
-        };
-
-        // Acquire write lock to ensure exclusive access during write
-        let _content_guard = content_lock.write();
-
-        // Get file path and branch info without holding locks during I/O
-        let (path_buf, branch_idx) = {
-            // Try to get file handle first
-            if let Some(handle) = self.file_handle_manager.get_handle(fh) {
-                tracing::debug!("Using file handle {} for path {:?}, branch {:?}", fh, handle.path, handle.branch_idx);
-                (handle.path.clone(), handle.branch_idx)
-            } else {
-                tracing::debug!("No file handle found for fh {}, falling back to inode lookup", fh);
-                // Fallback to using inode data
-                let inode_data = match self.get_inode_data(ino) {
-                    Some(data) => data,
-                    None => {
-                        reply.error(ENOENT);
-                        return;
-                    }
-                };
-                (PathBuf::from(&inode_data.path), None)
-            }
-        };
-        
-        let path = path_buf.as_path();
-        
-        // If we have a file handle with a specific branch, write to that branch
-        tracing::debug!("Writing to path {:?} with branch_idx {:?}", path, branch_idx);
-        let result = if let Some(branch_idx) = branch_idx {
-                if branch_idx < self.file_manager.branches.len() {
-                    let branch = &self.file_manager.branches[branch_idx];
-                    if !branch.is_readonly() {
-                        let full_path = branch.full_path(path);
-                        
-                        // Write directly to the specific branch
-                        use std::fs::OpenOptions;
-                        use std::io::{Seek, SeekFrom, Write};
-                        
-                        match OpenOptions::new()
-                            .write(true)
-                            .open(&full_path) {
-                            Ok(mut file) => {
-                                // Seek to the requested offset
-                                if let Err(e) = file.seek(SeekFrom::Start(offset as u64)) {
-                                    tracing::error!("Failed to seek: {:?}", e);
-                                    Err(PolicyError::IoError(std::io::Error::new(
-                                        std::io::ErrorKind::Other,
-                                        format!("Seek failed: {}", e)
-                                    )))
-                                } else {
-                                    // Write the data
-                                    match file.write_all(data) {
-                                        Ok(_) => {
-                                            tracing::debug!("Successfully wrote {} bytes to branch {}", data.len(), branch_idx);
-                                            Ok(data.len())
-                                        }
-                                        Err(e) => {
-                                            tracing::error!("Write failed: {:?}", e);
-                                            if is_out_of_space_error(&e) {
-                                                tracing::info!("Detected out of space error on branch {}", branch_idx);
-                                                Err(PolicyError::NoSpace)
-                                            } else {
-                                                Err(PolicyError::IoError(e))
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("Failed to open file for writing on branch {}: {:?}", branch_idx, e);
-                                Err(PolicyError::IoError(e))
-                            }
-                        }
-                    } else {
-                        tracing::error!("Branch {} does not allow writes", branch_idx);
-                        Err(PolicyError::ReadOnlyFilesystem)
-                    }
-                } else {
-                    tracing::error!("Invalid branch index: {}", branch_idx);
-                    Err(PolicyError::PathNotFound)
-                }
-        } else {
-            // No specific branch, find existing file to write to
-            tracing::debug!("Finding existing file for write (no specific branch)");
-            match self.file_manager.find_first_branch(path) {
-                Ok(branch) => {
-                    let full_path = branch.full_path(path);
-                    use std::fs::OpenOptions;
-                    use std::io::{Seek, SeekFrom, Write};
-                    
-                    match OpenOptions::new()
-                        .write(true)
-                        .open(&full_path) {
-                        Ok(mut file) => {
-                            if let Err(e) = file.seek(SeekFrom::Start(offset as u64)) {
-                                Err(PolicyError::IoError(std::io::Error::new(
-                                    std::io::ErrorKind::Other,
-                                    format!("Seek failed: {}", e)
-                                )))
-                            } else {
-                                match file.write_all(data) {
-                                    Ok(_) => Ok(data.len()),
-                                    Err(e) => Err(PolicyError::IoError(e))
-                                }
-                            }
-                        }
-                        Err(e) => Err(PolicyError::IoError(e))
-                    }
-                }
-                Err(e) => Err(e)
-            }
-        };
-        
-        match result {
+        match self.write_at(ino, fh, offset, data) {
             Ok(written) => {
                 tracing::info!("Successfully wrote {} bytes", written);
-                
-                // Update inode size after successful write
-                // The new size should be at least offset + written bytes
-                let new_size = (offset as u64) + (written as u64);
-                
-                // Get current size to see if we need to extend
-                if let Some(current_data) = self.get_inode_data(ino) {
-                    let updated_size = std::cmp::max(current_data.attr.size, new_size);
-                    self.update_inode_size(ino, updated_size);
-                }
-                
                 reply.written(written as u32);
             }
             Err(e) => {
-                // Handle moveonenospc if enabled
-                if matches!(&e, PolicyError::NoSpace) && self.config.read().moveonenospc.enabled {
-                    tracing::info!("ENOSPC detected, attempting moveonenospc");
-                    
-                    // Attempt to move file to branch with more space
-                    // We need to pass the current branch index and branches
-                    let current_branch_idx = if let Some(idx) = branch_idx {
-                        idx
-                    } else {
-                        // Find which branch has the file
-                        self.file_manager.branches.iter().position(|branch| {
-                            branch.full_path(path).exists()
-                        }).unwrap_or(0)
-                    };
-                    
-                    let policy_ref = self.file_manager.create_policy.read();
-                    match self.moveonenospc_handler.move_file_on_enospc(
-                        path,
-                        current_branch_idx,
-                        &self.file_manager.branches,
-                        policy_ref.as_ref(),
-                        None, // No file descriptor available here
-                    ) {
-                        Ok(move_result) => {
-                            let new_branch_idx = move_result.new_branch_idx;
-                            tracing::info!("Successfully moved file to branch {}, retrying write", new_branch_idx);
-                            
-                            // File handle will already point to the new location after move
-                            
-                            // Retry write on new branch
-                            let retry_result = if new_branch_idx < self.file_manager.branches.len() {
-                                let branch = &self.file_manager.branches[new_branch_idx];
-                                let full_path = branch.full_path(path);
-                                
-                                use std::fs::OpenOptions;
-                                use std::io::{Seek, SeekFrom, Write};
-                                
-                                match OpenOptions::new()
-                                    .write(true)
-                                    .open(&full_path) {
-                                    Ok(mut file) => {
-                                        if let Err(e) = file.seek(SeekFrom::Start(offset as u64)) {
-                                            Err(PolicyError::IoError(std::io::Error::new(
-                                                std::io::ErrorKind::Other,
-                                                format!("Seek failed: {}", e)
-                                            )))
-                                        } else {
-                                            match file.write_all(data) {
-                                                Ok(_) => Ok(data.len()),
-                                                Err(e) => Err(PolicyError::IoError(e))
-                                            }
-                                        }
-                                    }
-                                    Err(e) => Err(PolicyError::IoError(e))
-                                }
-                            } else {
-                                Err(PolicyError::PathNotFound)
-                            };
-                            
-                            match retry_result {
-                                Ok(written) => {
-                                    tracing::info!("Successfully wrote {} bytes after moveonenospc", written);
-                                    
-                                    // Update inode size after successful write
-                                    let new_size = (offset as u64) + (written as u64);
-                                    if let Some(current_data) = self.get_inode_data(ino) {
-                                        let updated_size = std::cmp::max(current_data.attr.size, new_size);
-                                        self.update_inode_size(ino, updated_size);
-                                    }
-                                    
-                                    reply.written(written as u32);
-                                }
-                                Err(retry_e) => {
-                                    error!("Write failed after moveonenospc: {:?}", retry_e);
-                                    let errno = retry_e.errno();
-                                    reply.error(errno);
-                                }
-                            }
-                        }
-                        Err(move_e) => {
-                            error!("moveonenospc failed: {:?}", move_e);
-                            // Return original error
-                            let errno = e.errno();
-                            reply.error(errno);
-                        }
-                    }
-                } else {
-                    error!("Write failed for {:?}: {:?}", path, e);
-                    let errno = e.errno();
-                    tracing::debug!("Returning errno {} for write failure", errno);
-                    reply.error(errno);
-                }
+                let errno = e.errno();
+                tracing::debug!("Returning errno {} for write failure", errno);
+                reply.error(errno);
             }
         }
     }
@@ -1178,7 +2457,7 @@ impl Filesystem for MergerFS {
 
         let path = Path::new(&file_path);
         tracing::debug!("Unlinking file at path: {:?}", file_path);
-        match self.file_manager.remove_file(path) {
+        match self.unlink_path(path) {
             Ok(_) => {
                 tracing::info!("File unlinked successfully: {:?}", file_path);
                 // Don't remove inodes on unlink - let them be garbage collected naturally
@@ -1187,7 +2466,7 @@ impl Filesystem for MergerFS {
             }
             Err(e) => {
                 error!("Failed to unlink file at {:?}: {:?}", file_path, e);
-                reply.error(EIO);
+                reply.error(e.errno());
             }
         }
     }
@@ -1238,13 +2517,21 @@ impl Filesystem for MergerFS {
         match self.file_manager.create_directory(path) {
             Ok(_) => {
                 tracing::info!("Directory created successfully at {:?}", dir_path);
+                self.touch_dir(parent);
+                self.invalidate_dir_nlink(path.parent().unwrap_or(Path::new("/")));
                 // Create directory attributes (no locks held during I/O)
-                if let Some((attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(path) {
+                if let Some((mut attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(path) {
                     let ino = attr.ino; // Use the calculated inode
+                    
+                    match self.apply_create_mode(path, branch_idx, mode, umask) {
+                        Ok(perm) => attr.perm = perm,
+                        Err(e) => tracing::warn!("Failed to set mode of {:?}: {:?}", dir_path, e),
+                    }
 
                     // Insert inode with minimal lock time
                     self.insert_inode(ino, dir_path, attr, Some(branch_idx), original_ino);
-                    reply.entry(&TTL, &attr, 0);
+                    self.count_lookup(ino);
+                    reply.entry(&TTL, &attr, self.inode_generation(ino));
                 } else {
                     reply.error(EIO);
                 }
@@ -1252,7 +2539,7 @@ impl Filesystem for MergerFS {
             Err(e) => {
                 error!("Failed to create directory at {:?}: {:?}", dir_path, e);
                 tracing::debug!("Directory creation error details: {:?}", e);
-                reply.error(EIO);
+                reply.error(e.errno());
             }
         }
     }
@@ -1289,25 +2576,24 @@ impl Filesystem for MergerFS {
         match self.file_manager.remove_directory(path) {
             Ok(_) => {
                 tracing::info!("Directory removed successfully: {:?}", dir_path);
+                self.touch_dir(parent);
+                self.invalidate_dir_nlink(path);
+                self.invalidate_dir_nlink(path.parent().unwrap_or(Path::new("/")));
                 // Remove from inode cache if present
                 if let Some(ino) = self.path_to_inode(&dir_path) {
                     self.remove_inode(ino);
+                    self.retire_inode_generation(ino);
                 }
                 reply.ok();
             }
             Err(e) => {
                 error!("Failed to remove directory at {:?}: {:?}", dir_path, e);
-                let errno = if e.to_string().contains("not empty") {
-                    ENOTEMPTY
-                } else {
-                    EIO
-                };
-                reply.error(errno);
+                reply.error(e.errno());
             }
         }
     }
 
-    fn setattr(&mut self, _req: &Request, ino: u64, mode: Option<u32>, uid: Option<u32>, gid: Option<u32>, size: Option<u64>, atime: Option<fuser::TimeOrNow>, mtime: Option<fuser::TimeOrNow>, _ctime: Option<SystemTime>, _fh: Option<u64>, _crtime: Option<SystemTime>, _chgtime: Option<SystemTime>, _bkuptime: Option<SystemTime>, _flags: Option<u32>, reply: ReplyAttr) {
+    fn setattr(&mut self, _req: &Request, ino: u64, mode: Option<u32>, uid: Option<u32>, gid: Option<u32>, size: Option<u64>, atime: Option<fuser::TimeOrNow>, mtime: Option<fuser::TimeOrNow>, _ctime: Option<SystemTime>, fh: Option<u64>, _crtime: Option<SystemTime>, _chgtime: Option<SystemTime>, _bkuptime: Option<SystemTime>, _flags: Option<u32>, reply: ReplyAttr) {
         let _span = tracing::info_span!("fuse::setattr", ino).entered();
         tracing::debug!("Starting setattr operation");
 
@@ -1332,47 +2618,46 @@ impl Filesystem for MergerFS {
         if let Some(mode) = mode {
             if let Err(e) = self.metadata_manager.chmod(path, mode) {
                 error!("chmod failed for {:?}: {:?}", data.path, e);
-                reply.error(EIO);
+                reply.error(e.errno());
                 return;
             }
         }
         
         // Handle ownership changes
-        if uid.is_some() || gid.is_some() {
-            // Use existing values if not specified
-            let current_attr = &data.attr;
-            let new_uid = uid.unwrap_or(current_attr.uid);
-            let new_gid = gid.unwrap_or(current_attr.gid);
-            
-            if let Err(e) = self.metadata_manager.chown(path, new_uid, new_gid) {
-                error!("chown failed for {:?}: {:?}", data.path, e);
-                reply.error(EIO);
-                return;
-            }
+        if let Err(e) = self.chown_path(path, uid, gid) {
+            error!("chown failed for {:?}: {:?}", data.path, e);
+            reply.error(e.errno());
+            return;
         }
         
         // Handle size changes (truncate) - lock is held if size.is_some()
         if let Some(size) = size {
-            if let Err(e) = self.file_manager.truncate_file(path, size) {
+            self.break_hard_link_for_write(ino, path, None);
+            
+            // Buffered writes must land before the size changes
+            if let Err(e) = self.file_handle_manager.flush_inode_buffers(ino) {
+                error!("Failed to write buffered data before truncate: {:?}", e);
+                reply.error(e.errno());
+                return;
+            }
+            
+            if let Err(e) = self.truncate_with_moveonenospc(path, size, fh) {
                 error!("truncate failed for {:?}: {:?}", data.path, e);
-                reply.error(EIO);
+                reply.error(e.errno());
                 return;
             }
         }
         
-        // Handle time changes
-        if let (Some(atime_val), Some(mtime_val)) = (atime, mtime) {
-            let atime_sys = match atime_val {
-                fuser::TimeOrNow::SpecificTime(time) => time,
-                fuser::TimeOrNow::Now => SystemTime::now(),
+        // Handle time changes; an unset side is left untouched (UTIME_OMIT)
+        if atime.is_some() || mtime.is_some() {
+            let to_update = |time: Option<fuser::TimeOrNow>| match time {
+                Some(fuser::TimeOrNow::SpecificTime(time)) => TimeUpdate::Set(time),
+                Some(fuser::TimeOrNow::Now) => TimeUpdate::Now,
+                None => TimeUpdate::Omit,
             };
-            let mtime_sys = match mtime_val {
-                fuser::TimeOrNow::SpecificTime(time) => time,
-                fuser::TimeOrNow::Now => SystemTime::now(),
-            };
-            if let Err(e) = self.metadata_manager.utimens(path, atime_sys, mtime_sys) {
+            if let Err(e) = self.metadata_manager.utimens(path, to_update(atime), to_update(mtime)) {
                 error!("utimens failed for {:?}: {:?}", data.path, e);
-                reply.error(EIO);
+                reply.error(e.errno());
                 return;
             }
         }
@@ -1443,19 +2728,14 @@ impl Filesystem for MergerFS {
 
         tracing::debug!("Renaming {:?} to {:?}", old_path, new_path);
 
-        // Use rename manager to handle the rename
-        match self.rename_manager.rename(Path::new(&old_path), Path::new(&new_path)) {
-            Ok(_) => {
+        match self.rename_path(&old_path, &new_path) {
+            Ok(()) => {
                 tracing::info!("Rename successful: {:?} -> {:?}", old_path, new_path);
-                
-                // Update inode cache - this handles both files and directories
-                self.update_cached_paths_after_rename(&old_path, &new_path);
-                
                 reply.ok();
             }
             Err(e) => {
                 error!("Rename failed: {:?}", e);
-                reply.error(EIO);
+                reply.error(e.errno());
             }
         }
     }
@@ -1464,56 +2744,16 @@ impl Filesystem for MergerFS {
         let _span = tracing::debug_span!("fuse::statfs", _ino).entered();
         tracing::debug!("Starting statfs operation");
 
-        let config = self.config.read();
-        let ignore = config.statfs_ignore;
-        
-        // Get aggregate stats from all branches
-        let mut total_blocks: u64 = 0;
-        let mut total_bavail: u64 = 0;
-        let mut total_bfree: u64 = 0;
-        let mut total_files: u64 = 0;
-        let mut total_ffree: u64 = 0;
-        let mut min_frsize: u32 = u32::MAX;
-        let mut min_bsize: u32 = u32::MAX;
-        let mut min_namelen: u32 = u32::MAX;
-        
-        for branch in &self.file_manager.branches {
-            // Skip branches based on ignore setting
-            match ignore {
-                StatFSIgnore::ReadOnly if !branch.allows_create() => continue,
-                StatFSIgnore::NoCreate if !branch.allows_create() => continue,
-                _ => {}
-            }
-            
-            // Get statfs info from the branch
-            let full_path = branch.path.as_path();
-            if let Ok(statvfs) = nix::sys::statvfs::statvfs(full_path) {
-                total_blocks += statvfs.blocks();
-                total_bavail += statvfs.blocks_available();
-                total_bfree += statvfs.blocks_free();
-                total_files += statvfs.files();
-                total_ffree += statvfs.files_free();
-                
-                min_frsize = min_frsize.min(statvfs.fragment_size() as u32);
-                min_bsize = min_bsize.min(statvfs.block_size() as u32);
-                min_namelen = min_namelen.min(statvfs.name_max() as u32);
-            }
-        }
-        
-        // Use minimum values if we didn't find any valid stats
-        if min_frsize == u32::MAX { min_frsize = 512; }
-        if min_bsize == u32::MAX { min_bsize = 4096; }
-        if min_namelen == u32::MAX { min_namelen = 255; }
-        
+        let totals = self.statfs_totals();
         reply.statfs(
-            total_blocks,
-            total_bfree,
-            total_bavail,
-            total_files,
-            total_ffree,
-            min_bsize,
-            min_namelen,
-            min_frsize,
+            totals.blocks,
+            totals.bfree,
+            totals.bavail,
+            totals.files,
+            totals.ffree,
+            totals.bsize,
+            totals.namelen,
+            totals.frsize,
         );
     }
 
@@ -1524,7 +2764,7 @@ impl Filesystem for MergerFS {
         tracing::debug!("Starting getxattr operation");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
             self.control_file_handler.handle_getxattr(name, size, reply);
             return;
         }
@@ -1546,6 +2786,26 @@ impl Filesystem for MergerFS {
         };
 
         let path = Path::new(&data.path);
+        
+        // Branch pins live in memory, not on the branches
+        if name_str == BRANCH_PIN_XATTR {
+            let value = match self.file_manager.get_branch_pin(path) {
+                Some(branch) => branch.path.to_string_lossy().into_owned().into_bytes(),
+                None => {
+                    reply.error(ENODATA);
+                    return;
+                }
+            };
+            if size == 0 {
+                reply.size(value.len() as u32);
+            } else if size < value.len() as u32 {
+                reply.error(ERANGE);
+            } else {
+                reply.data(&value);
+            }
+            return;
+        }
+        
         match self.xattr_manager.get_xattr(path, name_str) {
             Ok(value) => {
                 if size == 0 {
@@ -1572,8 +2832,12 @@ impl Filesystem for MergerFS {
         tracing::debug!("Starting setxattr operation");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
-            self.control_file_handler.handle_setxattr(name, value, reply);
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
+            match self.handle_invalidate_xattr(name, value) {
+                Some(Ok(_)) => reply.ok(),
+                Some(Err(errno)) => reply.error(errno),
+                None => self.control_file_handler.handle_setxattr(name, value, reply),
+            }
             return;
         }
 
@@ -1603,6 +2867,32 @@ impl Filesystem for MergerFS {
         };
 
         let path = Path::new(&data.path);
+        
+        // Pin the directory to a branch; an empty value removes the pin
+        if name_str == BRANCH_PIN_XATTR {
+            if data.attr.kind != FileType::Directory {
+                reply.error(ENOTDIR);
+                return;
+            }
+            let branch_path = match std::str::from_utf8(value) {
+                Ok(s) => s.trim_end_matches('\0').trim(),
+                Err(_) => {
+                    reply.error(EINVAL);
+                    return;
+                }
+            };
+            if branch_path.is_empty() {
+                self.file_manager.unpin_branch(path);
+                reply.ok();
+                return;
+            }
+            match self.file_manager.pin_branch(path, Path::new(branch_path)) {
+                Ok(()) => reply.ok(),
+                Err(e) => reply.error(e.errno()),
+            }
+            return;
+        }
+        
         match self.xattr_manager.set_xattr(path, name_str, value, xattr_flags) {
             Ok(_) => {
                 tracing::info!("setxattr successful for {:?}", data.path);
@@ -1621,7 +2911,7 @@ impl Filesystem for MergerFS {
         tracing::debug!("Starting listxattr operation");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
             self.control_file_handler.handle_listxattr(size, reply);
             return;
         }
@@ -1635,7 +2925,12 @@ impl Filesystem for MergerFS {
         };
 
         let path = Path::new(&data.path);
-        match self.xattr_manager.list_xattr(path) {
+        let listed = if self.config.read().xattr_union {
+            self.xattr_manager.list_xattr_union(path)
+        } else {
+            self.xattr_manager.list_xattr(path)
+        };
+        match listed {
             Ok(names) => {
                 // Calculate total size needed (each name + null terminator)
                 let total_size: usize = names.iter().map(|n| n.len() + 1).sum();
@@ -1670,7 +2965,7 @@ impl Filesystem for MergerFS {
         tracing::debug!("Starting removexattr operation");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
             self.control_file_handler.handle_removexattr(reply);
             return;
         }
@@ -1692,6 +2987,16 @@ impl Filesystem for MergerFS {
         };
 
         let path = Path::new(&data.path);
+        
+        if name_str == BRANCH_PIN_XATTR {
+            if self.file_manager.unpin_branch(path) {
+                reply.ok();
+            } else {
+                reply.error(ENODATA);
+            }
+            return;
+        }
+        
         match self.xattr_manager.remove_xattr(path, name_str) {
             Ok(_) => {
                 tracing::info!("removexattr successful for {:?}", data.path);
@@ -1705,12 +3010,40 @@ impl Filesystem for MergerFS {
         }
     }
 
+    fn ioctl(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        fh: u64,
+        _flags: u32,
+        cmd: u32,
+        _in_data: &[u8],
+        out_size: u32,
+        reply: fuser::ReplyIoctl,
+    ) {
+        let _span = tracing::info_span!("fuse::ioctl", ino, fh, cmd = %format!("0x{:x}", cmd), out_size).entered();
+        
+        // Only the control file understands ioctls
+        if ino != CONTROL_FILE_INO || !self.control_file_enabled() {
+            reply.error(ENOTTY);
+            return;
+        }
+        
+        match self.control_file_handler.handle_ioctl(cmd, out_size) {
+            Ok(data) => reply.ioctl(data.len() as i32, &data),
+            Err(errno) => {
+                tracing::debug!("Control file ioctl failed with errno {}", errno);
+                reply.error(errno);
+            }
+        }
+    }
+
     fn access(&mut self, _req: &Request, ino: u64, mask: i32, reply: fuser::ReplyEmpty) {
         let _span = tracing::debug_span!("fuse::access", ino, mask = %format!("0x{:x}", mask)).entered();
         tracing::debug!("Starting access check");
 
         // Handle special control file
-        if ino == CONTROL_FILE_INO {
+        if ino == CONTROL_FILE_INO && self.control_file_enabled() {
             self.control_file_handler.handle_access(mask, reply);
             return;
         }
@@ -1798,9 +3131,10 @@ impl Filesystem for MergerFS {
 
         tracing::debug!("Creating hard link from {:?} to {:?}", source_path, link_path);
 
-        // Create the hard link using FileManager
-        match self.file_manager.create_hard_link(source_path, &link_path) {
+        // Create the hard link, or per link-exdev a symlink when it would cross branches
+        match self.link_path(source_path, &link_path) {
             Ok(()) => {
+                self.invalidate_readdir(parent_path);
                 // Get metadata for the link
                 if let Some((attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(&link_path) {
                     // Use the calculated inode - for devino-hash modes, hard links will share inodes
@@ -1809,8 +3143,9 @@ impl Filesystem for MergerFS {
                     // Check if this inode already exists (should be the case for hard links with devino-hash)
                     let mut inodes = self.inodes.write();
                     if !inodes.contains_key(&link_ino) {
-                        // New inode (shouldn't happen with devino-hash for hard links)
-                        tracing::warn!("Hard link created new inode {} - expected to share with source", link_ino);
+                        // New inode: a link-exdev symlink, or a hard link under a
+                        // non-devino inodecalc mode
+                        tracing::debug!("Link created new inode {}", link_ino);
                         inodes.insert(link_ino, InodeData {
                             path: link_path_str.clone(),
                             attr,
@@ -1819,6 +3154,8 @@ impl Filesystem for MergerFS {
                             original_ino,
                         });
                         drop(inodes);
+                        self.touch_inode(link_ino);
+                        self.evict_inodes_over_limit();
                     } else {
                         // Existing inode - refresh attributes to get updated nlink
                         tracing::info!("Hard link shares inode {} with source", link_ino);
@@ -1837,7 +3174,8 @@ impl Filesystem for MergerFS {
                     let inode_data = self.get_inode_data(link_ino).unwrap();
                     tracing::info!("Hard link created successfully: {:?} (inode {}, nlink={})", link_path, link_ino, inode_data.attr.nlink);
 
-                    reply.entry(&TTL, &inode_data.attr, 0);
+                    self.count_lookup(link_ino);
+                    reply.entry(&TTL, &inode_data.attr, self.inode_generation(link_ino));
                 } else {
                     tracing::error!("Failed to get attributes for new link");
                     reply.error(EIO);
@@ -1845,19 +3183,7 @@ impl Filesystem for MergerFS {
             }
             Err(e) => {
                 tracing::error!("Failed to create hard link: {}", e);
-                match e {
-                    crate::policy::PolicyError::NoBranchesAvailable => reply.error(ENOENT),
-                    crate::policy::PolicyError::IoError(ref io_err) => {
-                        match io_err.kind() {
-                            std::io::ErrorKind::PermissionDenied => reply.error(EACCES),
-                            std::io::ErrorKind::NotFound => reply.error(ENOENT),
-                            std::io::ErrorKind::AlreadyExists => reply.error(EEXIST),
-                            std::io::ErrorKind::CrossesDevices => reply.error(EXDEV),
-                            _ => reply.error(EIO),
-                        }
-                    }
-                    _ => reply.error(EIO),
-                }
+                reply.error(e.errno());
             }
         }
     }
@@ -1916,16 +3242,23 @@ impl Filesystem for MergerFS {
         match self.file_manager.create_special_file(path, mode, rdev) {
             Ok(_) => {
                 tracing::info!("Special file created successfully at {:?}", file_path);
+                self.touch_dir(parent);
                 // Create file attributes (no locks held during I/O)
                 tracing::debug!("Creating file attributes for newly created special file");
-                if let Some((attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(path) {
+                if let Some((mut attr, branch_idx, original_ino)) = self.create_file_attr_with_branch(path) {
                     let ino = attr.ino; // Use the calculated inode
                     tracing::debug!("Created attributes for special file: ino={}, kind={:?}", ino, attr.kind);
 
+                    match self.apply_create_mode(path, branch_idx, mode, umask) {
+                        Ok(perm) => attr.perm = perm,
+                        Err(e) => tracing::warn!("Failed to set mode of {:?}: {:?}", file_path, e),
+                    }
+
                     // Insert inode with minimal lock time
                     self.insert_inode(ino, file_path, attr, Some(branch_idx), original_ino);
                     tracing::debug!("Inserted inode into cache, sending reply");
-                    reply.entry(&TTL, &attr, 0);
+                    self.count_lookup(ino);
+                    reply.entry(&TTL, &attr, self.inode_generation(ino));
                     tracing::debug!("Reply sent successfully");
                 } else {
                     tracing::error!("Failed to create file attributes for special file at {:?}", file_path);
@@ -1942,7 +3275,3 @@ impl Filesystem for MergerFS {
         }
     }
 }
-
-// Define errno constants for xattr operations
-const ENODATA: i32 = 61;
-const ENOTSUP: i32 = 95;
\ No newline at end of file
@@ -5,8 +5,9 @@ mod fuse_integration_tests {
     use crate::fuse_fs::MergerFS;
     use crate::policy::{FirstFoundCreatePolicy, MostFreeSpaceCreatePolicy, LeastFreeSpaceCreatePolicy};
     use crate::config::create_config;
+    use crate::errno::ToErrno;
     use serial_test::serial;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
     use std::sync::Arc;
     use std::time::SystemTime;
     use tempfile::TempDir;
@@ -660,7 +661,7 @@ mod fuse_integration_tests {
         setup.setup_space();
         
         let branches = setup.get_branches();
-        let policy = Box::new(MostFreeSpaceCreatePolicy);
+        let policy = Box::new(MostFreeSpaceCreatePolicy::new());
         let file_manager = FileManager::new(branches.clone(), policy);
         let fs = MergerFS::new(file_manager);
 
@@ -725,7 +726,7 @@ mod fuse_integration_tests {
         
         // Test MostFreeSpace policy (should use second branch with more space)
         let mfs_branches = vec![branch1.clone(), branch2.clone()];
-        let mfs_policy = Box::new(MostFreeSpaceCreatePolicy);
+        let mfs_policy = Box::new(MostFreeSpaceCreatePolicy::new());
         let mfs_file_manager = FileManager::new(mfs_branches, mfs_policy);
         let mfs_fs = MergerFS::new(mfs_file_manager);
         
@@ -755,7 +756,7 @@ mod fuse_integration_tests {
         setup.setup_space();
         
         let branches = setup.get_branches();
-        let policy = Box::new(LeastFreeSpaceCreatePolicy);
+        let policy = Box::new(LeastFreeSpaceCreatePolicy::new());
         let file_manager = FileManager::new(branches.clone(), policy);
         let fs = MergerFS::new(file_manager);
 
@@ -822,7 +823,7 @@ mod fuse_integration_tests {
         
         // Test MostFreeSpace policy (should use branch 0 - most free space)
         let mfs_branches = vec![branches[0].clone(), branches[1].clone(), branches[2].clone()];
-        let mfs_policy = Box::new(MostFreeSpaceCreatePolicy);
+        let mfs_policy = Box::new(MostFreeSpaceCreatePolicy::new());
         let mfs_file_manager = FileManager::new(mfs_branches, mfs_policy);
         let mfs_fs = MergerFS::new(mfs_file_manager);
         
@@ -830,7 +831,7 @@ mod fuse_integration_tests {
         
         // Test LeastFreeSpace policy (should use branch 2 - least free space)
         let lfs_branches = vec![branches[0].clone(), branches[1].clone(), branches[2].clone()];
-        let lfs_policy = Box::new(LeastFreeSpaceCreatePolicy);
+        let lfs_policy = Box::new(LeastFreeSpaceCreatePolicy::new());
         let lfs_file_manager = FileManager::new(lfs_branches, lfs_policy);
         let lfs_fs = MergerFS::new(lfs_file_manager);
         
@@ -921,8 +922,8 @@ mod fuse_integration_tests {
         let content2 = b"Different content in branch 2";
         
         // Manually create file in both branches
-        let branch1 = &fs.file_manager.branches[0];
-        let branch2 = &fs.file_manager.branches[1];
+        let branch1 = &fs.file_manager.branches()[0];
+        let branch2 = &fs.file_manager.branches()[1];
         
         std::fs::write(branch1.full_path(test_path), content1).unwrap();
         std::fs::write(branch2.full_path(test_path), content2).unwrap();
@@ -956,6 +957,63 @@ mod fuse_integration_tests {
         fs.file_handle_manager.remove_handle(fh_branch2);
     }
 
+    #[test]
+    fn test_getattr_uses_open_handle_branch() {
+        let (_temp_dirs, fs) = setup_test_mergerfs();
+        
+        let test_path = Path::new("/multi_branch.txt");
+        let branch1 = &fs.file_manager.branches()[0];
+        let branch2 = &fs.file_manager.branches()[1];
+        std::fs::write(branch1.full_path(test_path), b"short").unwrap();
+        std::fs::write(branch2.full_path(test_path), b"longer content on branch two").unwrap();
+        
+        // Path resolution picks the first branch
+        let resolved = fs.create_file_attr(test_path).unwrap();
+        assert_eq!(resolved.size, 5);
+        assert!(fs.create_file_attr_for_open_handle(resolved.ino).is_none());
+        
+        // An open handle on the second branch makes getattr follow that copy
+        let file = crate::file_handle::open_branch_file(&branch2.full_path(test_path), 0).unwrap();
+        let fh = fs.file_handle_manager.create_handle_with_file(
+            resolved.ino,
+            test_path.to_path_buf(),
+            0,
+            Some(1),
+            false,
+            Some(file),
+        );
+        
+        let attr = fs.create_file_attr_for_open_handle(resolved.ino).unwrap();
+        assert_eq!(attr.ino, resolved.ino);
+        assert_eq!(attr.size, b"longer content on branch two".len() as u64);
+        
+        fs.file_handle_manager.remove_handle(fh);
+        assert!(fs.create_file_attr_for_open_handle(resolved.ino).is_none());
+    }
+
+    #[test]
+    fn test_idle_dir_handles_are_reaped() {
+        let (_temp_dirs, fs) = setup_test_mergerfs();
+        
+        // A crashed client never sends releasedir for these
+        for _ in 0..100 {
+            let fh = fs.allocate_dir_handle();
+            fs.store_dir_handle(fh, Path::new("/").to_path_buf(), 1);
+        }
+        let open_dir_handles = || fs.config_manager.get_option("stats.open_dir_handles").unwrap();
+        assert_eq!(open_dir_handles(), "100");
+        assert_eq!(fs.reap_idle_dir_handles(crate::fuse_fs::DIR_HANDLE_IDLE_TIMEOUT), 0);
+        
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        let active = fs.allocate_dir_handle();
+        fs.store_dir_handle(active, Path::new("/").to_path_buf(), 1);
+        
+        // Only the handles idle past the timeout are dropped
+        assert_eq!(fs.reap_idle_dir_handles(std::time::Duration::from_millis(25)), 100);
+        assert_eq!(open_dir_handles(), "1");
+        assert!(fs.get_dir_handle(active).is_some());
+    }
+
     #[test]
     fn test_direct_io_configuration() {
         let temp_dir = TempDir::new().unwrap();
@@ -998,6 +1056,252 @@ mod fuse_integration_tests {
         }
     }
 
+    #[test]
+    fn test_statfs_largest_branch_mode() {
+        use crate::config::StatFSMode;
+        use crate::fuse_fs::{combine_statfs, StatfsTotals};
+        
+        let branch = |blocks: u64, bavail: u64| StatfsTotals {
+            blocks,
+            bfree: bavail,
+            bavail,
+            files: 1000,
+            ffree: 500,
+            bsize: 4096,
+            namelen: 255,
+            frsize: 4096,
+        };
+        let stats = vec![branch(1000, 100), branch(4000, 3000), branch(2000, 1500)];
+        
+        let aggregate = combine_statfs(&stats, StatFSMode::Base);
+        assert_eq!(aggregate.blocks, 7000);
+        assert_eq!(aggregate.bavail, 4600);
+        assert_eq!(aggregate.files, 3000);
+        
+        // Only the branch with the most free space is reported
+        let largest = combine_statfs(&stats, StatFSMode::Largest);
+        assert_eq!(largest, stats[1]);
+    }
+
+    #[test]
+    fn test_duplicate_branch_is_counted_once() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let temp = TempDir::new().unwrap();
+        let mount = |branches: Vec<Branch>| {
+            let branches = branches.into_iter().map(Arc::new).collect();
+            MergerFS::new(FileManager::new(branches, Box::new(FirstFoundCreatePolicy)))
+        };
+        let single = mount(vec![Branch::new(temp.path().to_path_buf(), BranchMode::ReadWrite)]);
+        let listed_twice = vec![
+            Branch::new(temp.path().to_path_buf(), BranchMode::ReadWrite),
+            Branch::new(temp.path().to_path_buf(), BranchMode::ReadWrite),
+        ];
+        
+        // Without deduplication the same disk is summed twice
+        let doubled = mount(listed_twice.iter().map(|b| Branch::new(b.path.clone(), b.mode)).collect());
+        assert_eq!(doubled.statfs_totals().blocks, 2 * single.statfs_totals().blocks);
+        
+        let fs = mount(crate::branch::dedup_branches(listed_twice));
+        assert_eq!(fs.file_manager.branches().len(), 1);
+        assert_eq!(fs.statfs_totals().blocks, single.statfs_totals().blocks);
+        
+        // Directories are created once, and metadata changes reach the one copy
+        fs.file_manager.create_directory(Path::new("/dir")).unwrap();
+        fs.metadata_manager.chmod(Path::new("/dir"), 0o700).unwrap();
+        let mode = std::fs::metadata(temp.path().join("dir")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    #[test]
+    #[serial]
+    fn test_branch_returning_erofs_is_skipped_for_creates() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let erofs = std::io::Error::from_raw_os_error(crate::errno::EROFS);
+        let remounted = |fs: &MergerFS| fs.config_manager.get_option("stats.branch.0.remounted_ro").unwrap();
+        assert_eq!(remounted(&fs), "0");
+        
+        // As if the kernel remounted the first branch read-only after a write failed
+        let branch = fs.file_manager.branches()[0].clone();
+        assert!(!branch.record_io_error(&std::io::Error::from_raw_os_error(crate::errno::EIO)));
+        assert!(branch.record_io_error(&erofs));
+        assert_eq!(remounted(&fs), "1");
+        
+        fs.create_path(Path::new("/after.txt"), 0).unwrap();
+        assert!(!temp_dirs[0].path().join("after.txt").exists());
+        assert!(temp_dirs[1].path().join("after.txt").exists());
+        
+        // The re-probe finds the filesystem writable and creates come back
+        assert!(!branch.probe_readonly());
+        assert_eq!(remounted(&fs), "0");
+        fs.create_path(Path::new("/back.txt"), 0).unwrap();
+        assert!(temp_dirs[0].path().join("back.txt").exists());
+        
+        // EROFS from a branch configured read-only is expected, not a remount
+        assert!(!fs.file_manager.branches()[2].record_io_error(&erofs));
+    }
+
+    #[test]
+    #[serial]
+    fn test_removed_branch_is_treated_as_offline() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        std::fs::write(temp_dirs[0].path().join("gone.txt"), "lost").unwrap();
+        std::fs::create_dir(temp_dirs[1].path().join("docs")).unwrap();
+        std::fs::write(temp_dirs[1].path().join("docs/kept.txt"), "kept").unwrap();
+        let ino = fs.lookup_path("/docs/kept.txt").unwrap().ino;
+        let fh = fs.open_handle(ino, nix::libc::O_RDWR, false).unwrap();
+        
+        // The first branch vanishes while the pool is mounted
+        let removed = temp_dirs[0].path().to_path_buf();
+        std::fs::remove_dir_all(&removed).unwrap();
+        let branch = fs.file_manager.branches()[0].clone();
+        assert!(!branch.is_online());
+        assert!(!branch.allows_create());
+        
+        // Files on the other branches keep working
+        assert_eq!(fs.read_range(ino, fh, 0, 16).unwrap(), b"kept");
+        assert_eq!(fs.write_at(ino, fh, 4, b"!").unwrap(), 1);
+        fs.release_handle(fh).unwrap();
+        assert_eq!(std::fs::read_to_string(temp_dirs[1].path().join("docs/kept.txt")).unwrap(), "kept!");
+        assert!(fs.lookup_path("/gone.txt").is_none());
+        assert_eq!(fs.file_manager.list_directory(Path::new("/")).unwrap(), vec!["docs"]);
+        
+        // ff moves on to the next branch instead of recreating the removed one
+        fs.create_path(Path::new("/new.txt"), 0).unwrap();
+        fs.create_path(Path::new("/docs/new.txt"), 0).unwrap();
+        fs.file_manager.create_directory(Path::new("/music")).unwrap();
+        assert!(!removed.exists());
+        assert!(temp_dirs[1].path().join("new.txt").exists());
+        assert!(temp_dirs[1].path().join("docs/new.txt").exists());
+        assert!(temp_dirs[1].path().join("music").is_dir());
+        
+        // Once the directory is back it takes creates again
+        std::fs::create_dir(&removed).unwrap();
+        assert!(branch.is_online());
+        fs.create_path(Path::new("/later.txt"), 0).unwrap();
+        assert!(removed.join("later.txt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_statfs_flags_reflect_readonly_pool() {
+        use nix::sys::statvfs::FsFlags;
+        
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        assert_eq!(fs.statfs_flags(), FsFlags::empty());
+        
+        // Mounted with -o ro,nosuid
+        fs.config_manager.config().write().mount_flags = FsFlags::ST_RDONLY | FsFlags::ST_NOSUID;
+        assert!(fs.statfs_flags().contains(FsFlags::ST_RDONLY));
+        assert!(fs.statfs_flags().contains(FsFlags::ST_NOSUID));
+        assert!(!fs.statfs_flags().contains(FsFlags::ST_NODEV));
+        
+        // A pool made only of read-only branches is read-only without the option
+        let readonly = vec![
+            Arc::new(Branch::new(temp_dirs[0].path().to_path_buf(), BranchMode::ReadOnly)),
+            Arc::new(Branch::new(temp_dirs[2].path().to_path_buf(), BranchMode::ReadOnly)),
+        ];
+        let fs = MergerFS::new(FileManager::new(readonly, Box::new(FirstFoundCreatePolicy)));
+        assert_eq!(fs.statfs_flags(), FsFlags::ST_RDONLY);
+    }
+
+    #[test]
+    #[serial]
+    fn test_rename_onto_whited_out_name() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        fs.config_manager.set_option("whiteout", "true").unwrap();
+        std::fs::write(temp_dirs[1].path().join("report.txt"), "writable copy").unwrap();
+        std::fs::write(temp_dirs[2].path().join("report.txt"), "read-only copy").unwrap();
+        
+        // Deleting leaves the read-only copy hidden behind a marker
+        fs.unlink_path(Path::new("/report.txt")).unwrap();
+        assert!(temp_dirs[0].path().join(".wh.report.txt").exists());
+        assert!(fs.lookup_path("/report.txt").is_none());
+        
+        std::fs::write(temp_dirs[0].path().join("draft.txt"), "new report").unwrap();
+        fs.rename_path("/draft.txt", "/report.txt").unwrap();
+        
+        assert!(!temp_dirs[0].path().join(".wh.report.txt").exists());
+        assert!(fs.lookup_path("/report.txt").is_some());
+        assert_eq!(fs.file_manager.read_file(Path::new("/report.txt")).unwrap(), b"new report");
+        let names: Vec<String> = fs.dir_entries("/").into_iter().map(|(_, _, name)| name).collect();
+        assert!(names.contains(&"report.txt".to_string()));
+        assert!(!names.contains(&"draft.txt".to_string()));
+    }
+
+    #[test]
+    #[serial]
+    fn test_statfs_size_overrides() {
+        let (_temp_dirs, fs) = setup_test_mergerfs();
+        let measured = fs.statfs_totals();
+        let frsize = measured.frsize as u64;
+        
+        fs.config_manager.set_option("statfs.total", "10G").unwrap();
+        fs.config_manager.set_option("statfs.available", "2G").unwrap();
+        assert_eq!(fs.config_manager.get_option("statfs.total").unwrap(), (10u64 << 30).to_string());
+        let capped = fs.statfs_totals();
+        assert_eq!(capped.blocks * frsize, 10 << 30);
+        assert_eq!(capped.bavail * frsize, 2 << 30);
+        assert!(capped.bfree >= capped.bavail && capped.bfree <= capped.blocks);
+        // Inode counts still come from the branches
+        assert_eq!(capped.files, measured.files);
+        
+        // A total below the branches' free space caps it as well
+        fs.config_manager.set_option("statfs.available", "").unwrap();
+        fs.config_manager.set_option("statfs.total", "1M").unwrap();
+        let small = fs.statfs_totals();
+        assert_eq!(small.blocks * frsize, 1 << 20);
+        assert!(small.bavail <= small.blocks && small.bfree <= small.blocks);
+        
+        assert!(fs.config_manager.set_option("statfs.total", "huge").is_err());
+        fs.config_manager.set_option("statfs.total", "").unwrap();
+        assert_eq!(fs.statfs_totals().blocks, measured.blocks);
+    }
+
+    #[test]
+    #[serial]
+    fn test_per_process_direct_io() {
+        use crate::config::CacheFiles;
+        use crate::fuse_fs::FOPEN_DIRECT_IO;
+        
+        let (_temp_dirs, fs) = setup_test_mergerfs();
+        let path = Path::new("/movie.mkv");
+        fs.file_manager.create_file(path, b"frames").unwrap();
+        let ino = fs.lookup_path("/movie.mkv").unwrap().ino;
+        
+        // Opens are attributed to this test process through its /proc comm
+        let pid = std::process::id();
+        let comm = std::fs::read_to_string(format!("/proc/{}/comm", pid)).unwrap().trim_end().to_string();
+        {
+            let config = fs.config_manager.config();
+            let mut cfg = config.write();
+            cfg.cache_files = CacheFiles::PerProcess;
+            cfg.cache_files_process_names = vec![comm];
+        }
+        
+        // A listed process keeps the page cache
+        let (direct_io, reply_flags) = fs.open_reply_flags(pid, nix::libc::O_RDONLY);
+        assert!(!direct_io);
+        assert_eq!(reply_flags & FOPEN_DIRECT_IO, 0);
+        let fh = fs.open_handle(ino, nix::libc::O_RDONLY, direct_io).unwrap();
+        assert!(!fs.file_handle_manager.get_handle(fh).unwrap().direct_io);
+        fs.release_handle(fh).unwrap();
+        
+        // ...unless it asked for O_DIRECT itself
+        let (direct_io, reply_flags) = fs.open_reply_flags(pid, nix::libc::O_RDONLY | nix::libc::O_DIRECT);
+        assert!(direct_io);
+        assert_ne!(reply_flags & FOPEN_DIRECT_IO, 0);
+        
+        // Everyone else goes direct
+        fs.config_manager.config().write().cache_files_process_names = vec!["rtorrent".to_string()];
+        let (direct_io, reply_flags) = fs.open_reply_flags(pid, nix::libc::O_RDONLY);
+        assert!(direct_io);
+        assert_ne!(reply_flags & FOPEN_DIRECT_IO, 0);
+        let fh = fs.open_handle(ino, nix::libc::O_RDONLY, direct_io).unwrap();
+        assert!(fs.file_handle_manager.get_handle(fh).unwrap().direct_io);
+        fs.release_handle(fh).unwrap();
+    }
+
     #[test]
     #[serial]
     fn test_fsyncdir_returns_enosys() {
@@ -1025,4 +1329,1329 @@ mod fuse_integration_tests {
         fs.remove_dir_handle(fh);
         assert!(fs.get_dir_handle(fh).is_none(), "Directory handle should be removed");
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[serial]
+    fn test_directory_nlink_counts_subdirectories() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        
+        let parent = Path::new("/parent");
+        fs.file_manager.create_directory(parent).unwrap();
+        
+        // Subdirectories spread across branches, plus a file that must not count
+        const N: usize = 4;
+        for i in 0..N {
+            let branch_dir = temp_dirs[i % 2].path().join("parent");
+            std::fs::create_dir_all(branch_dir.join(format!("sub{}", i))).unwrap();
+        }
+        // Same subdirectory on both branches is counted once
+        std::fs::create_dir_all(temp_dirs[1].path().join("parent/sub0")).unwrap();
+        std::fs::write(temp_dirs[0].path().join("parent/file.txt"), b"x").unwrap();
+        
+        let attr = fs.create_file_attr(parent).unwrap();
+        assert_eq!(attr.nlink, (N + 2) as u32);
+        
+        // The value is cached until the directory is invalidated
+        fs.file_manager.create_directory(Path::new("/parent/extra")).unwrap();
+        assert_eq!(fs.create_file_attr(parent).unwrap().nlink, (N + 2) as u32);
+        fs.invalidate_dir_nlink(parent);
+        assert_eq!(fs.create_file_attr(parent).unwrap().nlink, (N + 3) as u32);
+        
+        // Or until it is older than the attribute timeout
+        std::fs::create_dir(temp_dirs[1].path().join("parent/external")).unwrap();
+        assert_eq!(fs.create_file_attr(parent).unwrap().nlink, (N + 3) as u32);
+        std::thread::sleep(std::time::Duration::from_secs(1)); // the attribute timeout
+        assert_eq!(fs.create_file_attr(parent).unwrap().nlink, (N + 4) as u32);
+        
+        // Empty directories have nlink 2
+        let attr = fs.create_file_attr(Path::new("/parent/extra")).unwrap();
+        assert_eq!(attr.nlink, 2);
+    }
+
+    #[test]
+    #[serial]
+    fn test_file_nlink_is_max_across_branch_copies() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        
+        // One link on the first branch, three links to the copy on the second
+        std::fs::write(temp_dirs[0].path().join("dup.txt"), b"a").unwrap();
+        std::fs::write(temp_dirs[1].path().join("dup.txt"), b"b").unwrap();
+        std::fs::hard_link(temp_dirs[1].path().join("dup.txt"), temp_dirs[1].path().join("dup2.txt")).unwrap();
+        std::fs::hard_link(temp_dirs[1].path().join("dup.txt"), temp_dirs[1].path().join("dup3.txt")).unwrap();
+        
+        // Attributes come from the first branch, nlink from the most linked copy
+        let (attr, branch_idx, _) = fs.create_file_attr_with_branch(Path::new("/dup.txt")).unwrap();
+        assert_eq!(branch_idx, 0);
+        assert_eq!(attr.size, 1);
+        assert_eq!(attr.nlink, 3);
+        
+        // The copies are only looked up again once the attribute TTL has passed
+        std::fs::remove_file(temp_dirs[1].path().join("dup3.txt")).unwrap();
+        assert_eq!(fs.create_file_attr(Path::new("/dup.txt")).unwrap().nlink, 3);
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        assert_eq!(fs.create_file_attr(Path::new("/dup.txt")).unwrap().nlink, 2);
+        
+        // A plain copy on every branch is not reported as hard-linked
+        std::fs::write(temp_dirs[0].path().join("plain.txt"), b"a").unwrap();
+        std::fs::write(temp_dirs[1].path().join("plain.txt"), b"a").unwrap();
+        let attr = fs.create_file_attr(Path::new("/plain.txt")).unwrap();
+        assert_eq!(attr.nlink, 1);
+    }
+
+    #[test]
+    fn test_birth_time_falls_back_to_earliest_timestamp() {
+        use crate::fuse_fs::birth_time;
+        use std::time::{Duration, UNIX_EPOCH};
+        
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_000);
+        let ctime = UNIX_EPOCH + Duration::from_secs(2_000);
+        let unsupported = || Err(std::io::Error::from(std::io::ErrorKind::Unsupported));
+        assert_eq!(birth_time(unsupported(), mtime, ctime), mtime);
+        assert_eq!(birth_time(unsupported(), ctime, mtime), mtime);
+        
+        let btime = UNIX_EPOCH + Duration::from_secs(500);
+        assert_eq!(birth_time(Ok(btime), mtime, ctime), btime);
+    }
+    
+    #[test]
+    #[serial]
+    fn test_crtime_is_not_current_time_without_btime() {
+        use std::time::{Duration, UNIX_EPOCH};
+        
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let full_path = temp_dirs[0].path().join("old.txt");
+        std::fs::write(&full_path, b"old").unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        filetime::set_file_mtime(&full_path, filetime::FileTime::from_system_time(mtime)).unwrap();
+        
+        let attr = fs.create_file_attr(Path::new("/old.txt")).unwrap();
+        match std::fs::metadata(&full_path).unwrap().created() {
+            Ok(btime) => assert_eq!(attr.crtime, btime),
+            // No btime on this filesystem: mtime is older than the fresh ctime
+            Err(_) => assert_eq!(attr.crtime, mtime),
+        }
+        assert!(attr.ctime >= attr.mtime);
+    }
+
+    #[test]
+    #[serial]
+    fn test_rebuild_inode_cache_picks_up_external_changes() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        std::fs::create_dir(temp_dirs[0].path().join("dir")).unwrap();
+        std::fs::write(temp_dirs[0].path().join("dir/data.bin"), b"abc").unwrap();
+        std::fs::write(temp_dirs[0].path().join("dir/gone.bin"), b"abc").unwrap();
+        std::fs::write(temp_dirs[0].path().join("other.bin"), b"abc").unwrap();
+        
+        let data = fs.lookup_path("/dir/data.bin").unwrap();
+        let gone = fs.lookup_path("/dir/gone.bin").unwrap();
+        let other = fs.lookup_path("/other.bin").unwrap();
+        assert_eq!(data.size, 3);
+        
+        // Modify the branches directly, behind the mount's back
+        std::fs::write(temp_dirs[0].path().join("dir/data.bin"), b"abcdefghij").unwrap();
+        std::fs::remove_file(temp_dirs[0].path().join("dir/gone.bin")).unwrap();
+        std::fs::write(temp_dirs[0].path().join("other.bin"), b"abcdefghij").unwrap();
+        assert_eq!(fs.get_inode_data(data.ino).unwrap().attr.size, 3);
+        
+        // Only the requested subtree is rebuilt
+        assert_eq!(fs.rebuild_inode_cache(Path::new("/dir")), 2);
+        assert_eq!(fs.get_inode_data(data.ino).unwrap().attr.size, 10);
+        assert!(fs.get_inode_data(gone.ino).is_none());
+        assert_eq!(fs.get_inode_data(other.ino).unwrap().attr.size, 3);
+        
+        // "/" covers everything but leaves the root inode in place
+        fs.rebuild_inode_cache(Path::new("/"));
+        assert_eq!(fs.get_inode_data(other.ino).unwrap().attr.size, 10);
+        assert!(fs.get_inode_data(1).is_some());
+    }
+
+    #[test]
+    #[serial]
+    fn test_exclusive_create_fails_on_existing_file() {
+        const O_CREAT: i32 = 0o100;
+        const O_EXCL: i32 = 0o200;
+        const O_RDWR: i32 = 2;
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        
+        // Existing file on the second branch, where ff would not create
+        std::fs::write(temp_dirs[1].path().join("taken.txt"), b"keep me").unwrap();
+        let err = fs.create_path(Path::new("/taken.txt"), O_CREAT | O_EXCL | O_RDWR).unwrap_err();
+        assert_eq!(err.errno(), 17); // EEXIST
+        assert_eq!(std::fs::read(temp_dirs[1].path().join("taken.txt")).unwrap(), b"keep me");
+        assert!(!temp_dirs[0].path().join("taken.txt").exists());
+        
+        // A file only on the read-only branch is still part of the union
+        std::fs::write(temp_dirs[2].path().join("ro.txt"), b"ro").unwrap();
+        assert_eq!(fs.create_path(Path::new("/ro.txt"), O_CREAT | O_EXCL | O_RDWR).unwrap_err().errno(), 17);
+        
+        // Fresh paths are created as usual
+        fs.create_path(Path::new("/fresh.txt"), O_CREAT | O_EXCL | O_RDWR).unwrap();
+        assert!(temp_dirs[0].path().join("fresh.txt").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_create_over_union_directory_is_eisdir() {
+        const O_CREAT: i32 = 0o100;
+        const O_RDWR: i32 = 2;
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        
+        // The directory exists only on the second branch, where ff would not create
+        std::fs::create_dir(temp_dirs[1].path().join("media")).unwrap();
+        let err = fs.create_path(Path::new("/media"), O_CREAT | O_RDWR).unwrap_err();
+        assert_eq!(err.errno(), crate::errno::EISDIR);
+        assert!(!temp_dirs[0].path().join("media").exists());
+        
+        // Exclusive creates keep reporting EEXIST
+        let err = fs.create_path(Path::new("/media"), O_CREAT | O_RDWR | 0o200).unwrap_err();
+        assert_eq!(err.errno(), crate::errno::EEXIST);
+    }
+
+    #[test]
+    #[serial]
+    fn test_epnewest_reads_newest_copy() {
+        use filetime::FileTime;
+        
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        for (dir, content, mtime) in [(&temp_dirs[0], "stale copy", 1_000), (&temp_dirs[1], "fresh", 2_000)] {
+            let full_path = dir.path().join("replica.txt");
+            std::fs::write(&full_path, content).unwrap();
+            filetime::set_file_mtime(&full_path, FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+        let path = Path::new("/replica.txt");
+        
+        // First found serves the stale copy on the first branch
+        assert_eq!(fs.create_file_attr_with_branch(path).unwrap().1, 0);
+        
+        fs.config_manager.set_option("func.open", "epnewest").unwrap();
+        fs.config_manager.set_option("func.getattr", "epnewest").unwrap();
+        assert_eq!(fs.config_manager.get_option("func.getattr").unwrap(), "epnewest");
+        
+        let branch = fs.file_manager.find_open_branch(path).unwrap();
+        assert_eq!(std::fs::read_to_string(branch.full_path(path)).unwrap(), "fresh");
+        let (attr, branch_idx, _) = fs.create_file_attr_with_branch(path).unwrap();
+        assert_eq!(branch_idx, 1);
+        assert_eq!(attr.size, 5);
+    }
+
+    #[test]
+    #[serial]
+    fn test_writes_go_to_branch_file_was_opened_from() {
+        use filetime::FileTime;
+        
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        for (dir, mtime) in [(&temp_dirs[0], 1_000), (&temp_dirs[1], 2_000)] {
+            let full_path = dir.path().join("replica.txt");
+            std::fs::write(&full_path, "original").unwrap();
+            filetime::set_file_mtime(&full_path, FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+        let ino = fs.lookup_path("/replica.txt").unwrap().ino;
+        
+        // func.open picks the second branch, while first found would pick the first
+        fs.config_manager.set_option("func.open", "epnewest").unwrap();
+        let fh = fs.open_handle(ino, nix::libc::O_WRONLY, false).unwrap();
+        assert_eq!(fs.file_handle_manager.get_handle(fh).unwrap().branch_idx, Some(1));
+        
+        assert_eq!(fs.write_at(ino, fh, 0, b"OVERWRIT").unwrap(), 8);
+        assert_eq!(std::fs::read_to_string(temp_dirs[1].path().join("replica.txt")).unwrap(), "OVERWRIT");
+        assert_eq!(std::fs::read_to_string(temp_dirs[0].path().join("replica.txt")).unwrap(), "original");
+        
+        // Without a handle that names its branch there is nothing to write to
+        let unbranched = fs.file_handle_manager.create_handle(ino, "/replica.txt".into(), nix::libc::O_WRONLY, None, false);
+        for fh in [unbranched, 9999] {
+            assert_eq!(fs.write_at(ino, fh, 0, b"x").unwrap_err().errno(), crate::errno::EBADF);
+        }
+        assert_eq!(std::fs::read_to_string(temp_dirs[0].path().join("replica.txt")).unwrap(), "original");
+        fs.file_handle_manager.remove_handle(fh);
+        fs.file_handle_manager.remove_handle(unbranched);
+    }
+
+    #[test]
+    #[serial]
+    fn test_link_cow_moves_open_handle_to_the_copy() {
+        use nix::fcntl::{fcntl, FcntlArg, OFlag};
+        use std::os::unix::io::AsRawFd;
+        
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let original = temp_dirs[0].path().join("data.txt");
+        let linked = temp_dirs[0].path().join("linked.txt");
+        std::fs::write(&original, "original").unwrap();
+        std::fs::hard_link(&original, &linked).unwrap();
+        let ino = fs.lookup_path("/data.txt").unwrap().ino;
+        
+        // Opened before link-cow was turned on, so still sharing the inode,
+        // with O_APPEND set on its descriptor
+        let appender = fs.open_handle(ino, nix::libc::O_WRONLY, false).unwrap();
+        let appender_fd = fs.file_handle_manager.get_handle(appender).unwrap().file.as_ref().unwrap().as_raw_fd();
+        fcntl(appender_fd, FcntlArg::F_SETFL(OFlag::O_APPEND)).unwrap();
+        
+        // The next open for writing breaks the link and moves the older handle too
+        fs.config_manager.set_option("link-cow", "true").unwrap();
+        let fh = fs.open_handle(ino, nix::libc::O_RDWR, false).unwrap();
+        let status = OFlag::from_bits_truncate(fcntl(appender_fd, FcntlArg::F_GETFL).unwrap());
+        assert!(status.contains(OFlag::O_APPEND));
+        
+        assert_eq!(fs.write_at(ino, appender, 8, b"+tail").unwrap(), 5);
+        assert_eq!(std::fs::read_to_string(&original).unwrap(), "original+tail");
+        assert_eq!(std::fs::read_to_string(&linked).unwrap(), "original");
+        assert_eq!(fs.read_range(ino, fh, 0, 16).unwrap(), b"original+tail");
+        fs.release_handle(appender).unwrap();
+        fs.release_handle(fh).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_release_writes_out_and_syncs_data() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        fs.config_manager.set_option("writeback", "true").unwrap();
+        fs.config_manager.set_option("sync_on_close", "true").unwrap();
+        let full_path = temp_dirs[0].path().join("journal.log");
+        std::fs::write(&full_path, "").unwrap();
+        let ino = fs.lookup_path("/journal.log").unwrap().ino;
+        
+        // Small writes stay in the handle's buffer until the file is closed
+        let fh = fs.open_handle(ino, nix::libc::O_WRONLY, false).unwrap();
+        assert_eq!(fs.write_at(ino, fh, 0, b"entry-1\n").unwrap(), 8);
+        assert_eq!(fs.write_at(ino, fh, 8, b"entry-2\n").unwrap(), 8);
+        assert_eq!(std::fs::read(&full_path).unwrap(), b"");
+        
+        // No fsync: release alone leaves the data on the branch
+        fs.release_handle(fh).unwrap();
+        assert_eq!(std::fs::read_to_string(&full_path).unwrap(), "entry-1\nentry-2\n");
+        assert!(fs.file_handle_manager.get_handle(fh).is_none());
+        
+        // Releasing an unknown handle is a no-op
+        fs.release_handle(fh).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_release_keeps_handle_when_buffered_data_cannot_be_written() {
+        let (_temp_dirs, fs) = setup_test_mergerfs();
+        // Writes to /dev/full always fail with ENOSPC
+        let full = std::fs::OpenOptions::new().write(true).open("/dev/full").unwrap();
+        let fh = fs.file_handle_manager.create_handle_with_file(2, PathBuf::from("/full"), nix::libc::O_WRONLY, Some(0), false, Some(full));
+        let handle = fs.file_handle_manager.get_handle(fh).unwrap();
+        handle.write_buffer.lock().write(handle.file.as_ref().unwrap(), b"pending", 0).unwrap();
+        
+        assert_eq!(fs.release_handle(fh).unwrap_err().raw_os_error(), Some(nix::libc::ENOSPC));
+        let handle = fs.file_handle_manager.get_handle(fh).unwrap();
+        assert_eq!(handle.write_buffer.lock().pending(), 7);
+        fs.file_handle_manager.remove_handle(fh);
+    }
+
+    #[test]
+    #[serial]
+    fn test_write_verify_detects_corruption() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let full_path = temp_dirs[0].path().join("archive.bin");
+        std::fs::write(&full_path, b"0123456789").unwrap();
+        let ino = fs.lookup_path("/archive.bin").unwrap().ino;
+        let fh = fs.open_handle(ino, nix::libc::O_WRONLY, false).unwrap();
+        
+        // Injected fault: the branch path no longer holds the file the handle
+        // writes to, so reading the block back finds other data
+        let corrupt = |content: &[u8]| {
+            let replacement = temp_dirs[0].path().join("replacement.bin");
+            std::fs::write(&replacement, content).unwrap();
+            std::fs::rename(&replacement, &full_path).unwrap();
+        };
+        
+        // Off by default: nothing is read back, so the fault goes unnoticed
+        assert!(!fs.config.read().write_verify);
+        corrupt(b"012x456789");
+        assert_eq!(fs.write_at(ino, fh, 3, b"3456").unwrap(), 4);
+        fs.release_handle(fh).unwrap();
+        
+        fs.config_manager.set_option("write_verify", "true").unwrap();
+        assert!(fs.config.read().write_verify);
+        
+        // A block that reads back as written passes
+        std::fs::write(&full_path, b"0123456789").unwrap();
+        let fh = fs.open_handle(ino, nix::libc::O_WRONLY, false).unwrap();
+        assert_eq!(fs.write_at(ino, fh, 3, b"3456").unwrap(), 4);
+        
+        // A mismatching block fails the write with EIO
+        corrupt(b"012x456789");
+        let err = fs.write_at(ino, fh, 3, b"3456").unwrap_err();
+        assert_eq!(err.errno(), crate::errno::EIO);
+        
+        // A block cut short by truncation fails too
+        corrupt(b"01234");
+        assert_eq!(fs.write_at(ino, fh, 3, b"3456").unwrap_err().errno(), crate::errno::EIO);
+        fs.release_handle(fh).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_create_mode_overrides() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let mode_of = |name: &str| {
+            std::fs::metadata(temp_dirs[0].path().join(name)).unwrap().permissions().mode() & 0o7777
+        };
+        fs.file_manager.create_file(Path::new("/client.txt"), b"").unwrap();
+        fs.file_manager.create_file(Path::new("/zero.txt"), b"").unwrap();
+        fs.file_manager.create_file(Path::new("/forced.txt"), b"").unwrap();
+        fs.file_manager.create_directory(Path::new("/forced_dir")).unwrap();
+        
+        // Without either option the client's mode minus umask is used
+        assert_eq!(fs.apply_create_mode(Path::new("/client.txt"), 0, 0o100666, 0o027).unwrap(), 0o640);
+        assert_eq!(mode_of("client.txt"), 0o640);
+        
+        fs.config_manager.set_option("default_mode", "0664").unwrap();
+        assert_eq!(fs.apply_create_mode(Path::new("/zero.txt"), 0, 0o100000, 0o022).unwrap(), 0o664);
+        assert_eq!(mode_of("zero.txt"), 0o664);
+        
+        fs.config_manager.set_option("force_mode", "0660").unwrap();
+        fs.apply_create_mode(Path::new("/forced.txt"), 0, 0o100644, 0o022).unwrap();
+        fs.apply_create_mode(Path::new("/forced_dir"), 0, 0o755, 0o022).unwrap();
+        assert_eq!(mode_of("forced.txt"), 0o660);
+        assert_eq!(mode_of("forced_dir"), 0o660);
+    }
+
+    #[test]
+    #[serial]
+    fn test_umask_option_applies_to_every_create() {
+        use std::os::unix::fs::PermissionsExt;
+        
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let mode_of = |name: &str| {
+            std::fs::metadata(temp_dirs[0].path().join(name)).unwrap().permissions().mode() & 0o7777
+        };
+        fs.config_manager.set_option("umask", "022").unwrap();
+        assert_eq!(fs.config_manager.get_option("umask").unwrap(), "0022");
+        
+        fs.file_manager.create_directory(Path::new("/dir")).unwrap();
+        assert_eq!(fs.apply_create_mode(Path::new("/dir"), 0, 0o777, 0).unwrap(), 0o755);
+        assert_eq!(mode_of("dir"), 0o755);
+        
+        fs.file_manager.create_file(Path::new("/file.txt"), b"").unwrap();
+        fs.apply_create_mode(Path::new("/file.txt"), 0, 0o100666, 0).unwrap();
+        assert_eq!(mode_of("file.txt"), 0o644);
+        
+        fs.file_manager.create_special_file(Path::new("/fifo"), 0o010666, 0).unwrap();
+        fs.apply_create_mode(Path::new("/fifo"), 0, 0o010666, 0).unwrap();
+        assert_eq!(mode_of("fifo"), 0o644);
+        
+        // Combined with the client's umask rather than replacing it
+        fs.file_manager.create_file(Path::new("/private.txt"), b"").unwrap();
+        fs.apply_create_mode(Path::new("/private.txt"), 0, 0o100666, 0o077).unwrap();
+        assert_eq!(mode_of("private.txt"), 0o600);
+        
+        fs.config_manager.set_option("umask", "").unwrap();
+        assert_eq!(fs.apply_create_mode(Path::new("/dir"), 0, 0o777, 0).unwrap(), 0o777);
+    }
+
+    #[test]
+    #[serial]
+    fn test_rename_moves_branch_pins() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        for dir in ["inbox", "inbox/nested", "archive"] {
+            std::fs::create_dir(temp_dirs[0].path().join(dir)).unwrap();
+        }
+        fs.file_manager.pin_branch(Path::new("/inbox"), temp_dirs[1].path()).unwrap();
+        fs.file_manager.pin_branch(Path::new("/inbox/nested"), temp_dirs[1].path()).unwrap();
+        fs.file_manager.pin_branch(Path::new("/archive"), temp_dirs[0].path()).unwrap();
+        
+        // The pins follow the directory and replace the destination's
+        fs.rename_path("/inbox", "/archive").unwrap();
+        assert!(fs.file_manager.get_branch_pin(Path::new("/inbox")).is_none());
+        assert_eq!(fs.file_manager.get_branch_pin(Path::new("/archive")).unwrap().path, temp_dirs[1].path());
+        assert!(fs.file_manager.get_branch_pin(Path::new("/archive/nested")).is_some());
+        fs.create_path(Path::new("/archive/mail.eml"), 0).unwrap();
+        assert!(temp_dirs[1].path().join("archive/mail.eml").exists());
+        
+        // A new directory under the old name is not pinned
+        fs.file_manager.create_directory(Path::new("/inbox")).unwrap();
+        fs.create_path(Path::new("/inbox/new.eml"), 0).unwrap();
+        assert!(temp_dirs[0].path().join("inbox/new.eml").exists());
+        
+        assert!(fs.file_manager.unpin_branch(Path::new("/archive")));
+        assert!(!fs.file_manager.unpin_branch(Path::new("/archive")));
+    }
+
+    #[test]
+    #[serial]
+    fn test_rename_moves_open_handles() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        std::fs::create_dir(temp_dirs[0].path().join("dir")).unwrap();
+        std::fs::write(temp_dirs[0].path().join("dir/draft.txt"), b"").unwrap();
+        
+        // Handle opened by path only, as writes fall back to when no descriptor is kept
+        let ino = fs.lookup_path("/dir/draft.txt").unwrap().ino;
+        let fh = fs.file_handle_manager.create_handle(
+            ino,
+            std::path::PathBuf::from("/dir/draft.txt"),
+            nix::libc::O_WRONLY,
+            Some(0),
+            false,
+        );
+        
+        fs.rename_path("/dir/draft.txt", "/dir/final.txt").unwrap();
+        fs.rename_path("/dir", "/done").unwrap();
+        
+        // Writing through the handle opened before both renames reaches the renamed file
+        assert_eq!(fs.file_handle_manager.get_handle(fh).unwrap().path, Path::new("/done/final.txt"));
+        assert_eq!(fs.write_at(ino, fh, 0, b"written after rename").unwrap(), 20);
+        fs.release_handle(fh).unwrap();
+        assert_eq!(
+            std::fs::read(temp_dirs[0].path().join("done/final.txt")).unwrap(),
+            b"written after rename"
+        );
+        assert!(!temp_dirs[0].path().join("dir").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn test_shutdown_flushes_unreleased_handles() {
+        let (temp_dirs, mut fs) = setup_test_mergerfs();
+        let full_path = temp_dirs[0].path().join("pending.log");
+        std::fs::write(&full_path, b"").unwrap();
+        
+        // A handle the kernel never released, as after a lazy unmount
+        let file = crate::file_handle::open_branch_file(&full_path, 1).unwrap();
+        let fh = fs.file_handle_manager.create_handle_with_file(
+            42,
+            std::path::PathBuf::from("/pending.log"),
+            1,
+            Some(0),
+            false,
+            Some(file),
+        );
+        let handle = fs.file_handle_manager.get_handle(fh).unwrap();
+        handle.write_buffer.lock().write(handle.file.as_ref().unwrap(), b"buffered", 0).unwrap();
+        assert_eq!(std::fs::metadata(&full_path).unwrap().len(), 0);
+        
+        fs.shutdown();
+        assert_eq!(handle.write_buffer.lock().pending(), 0);
+        assert_eq!(std::fs::read(&full_path).unwrap(), b"buffered");
+    }
+
+    #[test]
+    #[serial]
+    fn test_truncate_resizes_every_copy() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        for dir in &temp_dirs {
+            std::fs::write(dir.path().join("replica.bin"), b"0123456789").unwrap();
+        }
+        
+        fs.truncate_with_moveonenospc(Path::new("/replica.bin"), 4, None).unwrap();
+        assert_eq!(std::fs::read(temp_dirs[0].path().join("replica.bin")).unwrap(), b"0123");
+        assert_eq!(std::fs::read(temp_dirs[1].path().join("replica.bin")).unwrap(), b"0123");
+        // The read-only branch is left alone
+        assert_eq!(std::fs::metadata(temp_dirs[2].path().join("replica.bin")).unwrap().len(), 10);
+        
+        // epff only touches the first copy
+        fs.config_manager.set_option("func.truncate", "epff").unwrap();
+        fs.truncate_with_moveonenospc(Path::new("/replica.bin"), 2, None).unwrap();
+        assert_eq!(std::fs::metadata(temp_dirs[0].path().join("replica.bin")).unwrap().len(), 2);
+        assert_eq!(std::fs::metadata(temp_dirs[1].path().join("replica.bin")).unwrap().len(), 4);
+        
+        // Policy errors are replied rather than treated as nothing to truncate
+        std::fs::write(temp_dirs[2].path().join("archived.bin"), b"0123456789").unwrap();
+        fs.config_manager.set_option("func.truncate", "epall").unwrap();
+        let err = fs.truncate_with_moveonenospc(Path::new("/archived.bin"), 2, None).unwrap_err();
+        assert_eq!(err.errno(), crate::errno::EROFS);
+        assert_eq!(fs.file_manager.truncate_file(Path::new("/archived.bin"), 2).unwrap_err().errno(), crate::errno::EROFS);
+        assert_eq!(std::fs::metadata(temp_dirs[2].path().join("archived.bin")).unwrap().len(), 10);
+    }
+
+    #[test]
+    #[serial]
+    fn test_mount_stats_exposed_via_control_file() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        
+        let stat = |name: &str| -> u64 {
+            fs.config_manager.get_option(name).unwrap().parse().unwrap()
+        };
+        
+        let listed = fs.config_manager.list_options();
+        for name in ["stats.open_handles", "stats.inodes", "stats.branches"] {
+            assert!(listed.contains(&format!("user.mergerfs.{}", name)));
+        }
+        
+        assert_eq!(stat("stats.branches"), 3);
+        assert_eq!(stat("stats.inodes"), 1); // root only
+        assert_eq!(stat("stats.open_handles"), 0);
+        
+        // Open a few files the way the open op does and keep the handles alive
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let path = format!("/stats{}.txt", i);
+            fs.file_manager.create_file(Path::new(&path), b"data").unwrap();
+            let full_path = temp_dirs[0].path().join(&path[1..]);
+            let file = crate::file_handle::open_branch_file(&full_path, 0).unwrap();
+            handles.push(fs.file_handle_manager.create_handle_with_file(
+                (i + 2) as u64,
+                Path::new(&path).to_path_buf(),
+                0,
+                Some(0),
+                false,
+                Some(file),
+            ));
+        }
+        assert_eq!(stat("stats.open_handles"), fs.file_handle_manager.get_handle_count() as u64);
+        assert_eq!(stat("stats.open_handles"), 3);
+        
+        fs.file_handle_manager.remove_handle(handles[0]);
+        assert_eq!(stat("stats.open_handles"), 2);
+        
+        // Stats are read-only
+        assert!(fs.config_manager.set_option("stats.open_handles", "0").is_err());
+        
+        // Latency aggregates follow the timers of the FUSE ops
+        assert_eq!(stat("stats.latency.read.count"), 0);
+        drop(fs.latency.start(crate::latency::TimedOp::Read));
+        assert_eq!(stat("stats.latency.read.count"), 1);
+        assert_eq!(stat("stats.latency.read.max_us"), fs.latency.op(crate::latency::TimedOp::Read).max_us());
+        assert_eq!(stat("stats.latency.lookup.total_us"), 0);
+    }
+
+    #[test]
+    #[serial]
+    fn test_listing_reports_policy_preferred_copy() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        
+        // Same name on two branches with different contents; branch 1 also has a unique file
+        std::fs::write(temp_dirs[0].path().join("shared.txt"), b"first branch").unwrap();
+        std::fs::write(temp_dirs[1].path().join("shared.txt"), b"second branch copy").unwrap();
+        std::fs::write(temp_dirs[1].path().join("another.txt"), b"only here").unwrap();
+        
+        let entries = fs.file_manager.list_directory_with_branches(Path::new("/")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["another.txt", "shared.txt"]);
+        
+        let (_, shared_branch) = entries.iter().find(|(name, _)| name == "shared.txt").unwrap();
+        assert_eq!(shared_branch.path, temp_dirs[0].path());
+        let (_, another_branch) = entries.iter().find(|(name, _)| name == "another.txt").unwrap();
+        assert_eq!(another_branch.path, temp_dirs[1].path());
+        
+        // Stat, the readdir attributes and open all resolve to the listed copy
+        let shared = Path::new("/shared.txt");
+        let listed_attr = fs.create_file_attr_on_branch(shared, shared_branch).unwrap();
+        let stat_attr = fs.create_file_attr(shared).unwrap();
+        assert_eq!(listed_attr.ino, stat_attr.ino);
+        assert_eq!(stat_attr.size, b"first branch".len() as u64);
+        
+        let open_branch = fs.file_manager.find_open_branch(shared).unwrap();
+        assert_eq!(open_branch.path, shared_branch.path);
+        assert_eq!(fs.file_manager.read_file(shared).unwrap(), b"first branch");
+    }
+
+    #[test]
+    #[serial]
+    fn test_listing_follows_getattr_policy() {
+        use filetime::FileTime;
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        for (idx, mtime) in [(0, 1_000_000), (1, 2_000_000)] {
+            let full_path = temp_dirs[idx].path().join("shared.txt");
+            std::fs::write(&full_path, format!("copy {}", idx)).unwrap();
+            filetime::set_file_mtime(&full_path, FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+        fs.config_manager.set_option("func.getattr", "newest").unwrap();
+        
+        // The newer copy on the second branch is listed, as lookup reports it
+        let entries = fs.file_manager.list_directory_with_branches(Path::new("/")).unwrap();
+        let (_, listed) = entries.iter().find(|(name, _)| name == "shared.txt").unwrap();
+        assert_eq!(listed.path, temp_dirs[1].path());
+        let (attr, branch_idx, _) = fs.create_file_attr_with_branch(Path::new("/shared.txt")).unwrap();
+        assert_eq!(branch_idx, 1);
+        let listing = fs.dir_entries("/");
+        let (ino, _, _) = listing.iter().find(|(_, _, name)| name == "shared.txt").unwrap();
+        assert_eq!(*ino, attr.ino);
+    }
+
+    #[test]
+    #[serial]
+    fn test_readdir_does_not_stat_entries() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        fs.config_manager.set_option("inodecalc", "passthrough").unwrap();
+        std::fs::create_dir(temp_dirs[0].path().join("big")).unwrap();
+        std::fs::create_dir(temp_dirs[1].path().join("big")).unwrap();
+        for i in 0..20 {
+            let branch = &temp_dirs[i % 2];
+            std::fs::write(branch.path().join(format!("big/file-{:02}", i)), b"").unwrap();
+        }
+        std::fs::create_dir(temp_dirs[1].path().join("big/sub")).unwrap();
+        let dir = Path::new("/big");
+        let inodecalc = fs.config.read().inodecalc;
+        
+        // Type and inode come straight from the directory entries
+        let mut stats = 0;
+        let mut resolved = Vec::new();
+        for entry in fs.file_manager.list_directory_entries(dir).unwrap() {
+            let entry_path = dir.join(&entry.name);
+            let stat = || {
+                stats += 1;
+                entry.branch.full_path(&entry_path).symlink_metadata()
+            };
+            let (ino, kind) = crate::fuse_fs::resolve_dir_entry(inodecalc, &entry_path, &entry, stat).unwrap();
+            resolved.push((ino, kind, entry.name));
+        }
+        assert_eq!(stats, 0);
+        
+        // Same inodes and types as lookup reports, passthrough inodes included
+        let expected: Vec<_> = resolved.iter()
+            .map(|(_, _, name)| {
+                let attr = fs.lookup_path(&format!("/big/{}", name)).unwrap();
+                (attr.ino, attr.kind, name.clone())
+            })
+            .collect();
+        assert_eq!(resolved, expected);
+        assert!(resolved.iter().any(|(_, kind, name)| name == "sub" && *kind == fuser::FileType::Directory));
+        let listing = fs.dir_entries("/big");
+        assert_eq!(listing.len(), 23);
+        assert_eq!(&listing[2..], &expected[..]);
+        
+        // An entry without a type falls back to a single stat
+        let mut entry = fs.file_manager.list_directory_entries(dir).unwrap().remove(0);
+        entry.file_type = None;
+        let entry_path = dir.join(&entry.name);
+        let mut stats = 0;
+        let stat = || {
+            stats += 1;
+            entry.branch.full_path(&entry_path).symlink_metadata()
+        };
+        let fallback = crate::fuse_fs::resolve_dir_entry(inodecalc, &entry_path, &entry, stat).unwrap();
+        assert_eq!(stats, 1);
+        assert_eq!((fallback.0, fallback.1), (expected[0].0, expected[0].1));
+    }
+
+    #[test]
+    #[serial]
+    fn test_link_exdev_modes() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        fs.config_manager.config().write().mountpoint = "/mnt/pool".into();
+        
+        // The source lives on branch 0, the links' directories only on branch 1.
+        // Creating a symlink clones its directory onto the chosen branch, so
+        // each mode gets its own.
+        std::fs::write(temp_dirs[0].path().join("src.txt"), b"data").unwrap();
+        for dir in ["hard", "rel", "abs"] {
+            std::fs::create_dir(temp_dirs[1].path().join(dir)).unwrap();
+        }
+        let source = Path::new("/src.txt");
+        
+        assert_eq!(fs.config_manager.get_option("link-exdev").unwrap(), "passthrough");
+        let err = fs.link_path(source, Path::new("/hard/link")).unwrap_err();
+        assert_eq!(err.errno(), crate::errno::EXDEV);
+        
+        fs.config_manager.set_option("link-exdev", "rel-symlink").unwrap();
+        fs.link_path(source, Path::new("/rel/link")).unwrap();
+        let link = fs.file_manager.find_first_branch(Path::new("/rel/link")).unwrap().full_path(Path::new("/rel/link"));
+        assert!(link.symlink_metadata().unwrap().file_type().is_symlink());
+        assert_eq!(std::fs::read_link(&link).unwrap(), Path::new("../src.txt"));
+        
+        fs.config_manager.set_option("link-exdev", "abs-symlink").unwrap();
+        fs.link_path(source, Path::new("/abs/link")).unwrap();
+        assert_eq!(fs.file_manager.read_symlink(Path::new("/abs/link")).unwrap(), Path::new("/mnt/pool/src.txt"));
+        
+        // Links that stay on one branch are still hard links
+        fs.link_path(source, Path::new("/same.txt")).unwrap();
+        use std::os::unix::fs::MetadataExt;
+        assert_eq!(std::fs::metadata(temp_dirs[0].path().join("src.txt")).unwrap().nlink(), 2);
+        
+        assert!(fs.config_manager.set_option("link-exdev", "copy").is_err());
+        assert_eq!(fs.config_manager.get_option("link-exdev").unwrap(), "abs-symlink");
+    }
+
+    #[test]
+    #[serial]
+    fn test_write_after_external_truncate_reports_file_size() {
+        use std::os::unix::fs::FileExt;
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let branch_file = temp_dirs[0].path().join("log.txt");
+        std::fs::write(&branch_file, b"hello world").unwrap();
+        let ino = fs.lookup_path("/log.txt").unwrap().ino;
+        let path = Path::new("/log.txt");
+        
+        // Truncated behind the mount's back, then rewritten from the start
+        // through an open handle
+        let handle = std::fs::OpenOptions::new().write(true).open(&branch_file).unwrap();
+        std::fs::File::create(&branch_file).unwrap();
+        handle.write_all_at(b"abc", 0).unwrap();
+        fs.update_size_after_write(ino, path, Some(0), Some(&handle), 3);
+        assert_eq!(fs.get_inode_data(ino).unwrap().attr.size, 3);
+        
+        // Without a handle the branch copy is stat'ed
+        std::fs::File::create(&branch_file).unwrap();
+        handle.write_all_at(b"xy", 0).unwrap();
+        fs.update_size_after_write(ino, path, None, None, 2);
+        assert_eq!(fs.get_inode_data(ino).unwrap().attr.size, 2);
+        
+        // Data still in the write buffer extends the size past the file's
+        fs.update_size_after_write(ino, path, Some(0), Some(&handle), 8);
+        assert_eq!(fs.get_inode_data(ino).unwrap().attr.size, 8);
+    }
+
+    #[test]
+    #[serial]
+    fn test_entry_changes_advance_parent_mtime() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let old = filetime::FileTime::from_unix_time(1_000_000, 0);
+        let older = filetime::FileTime::from_unix_time(500_000, 0);
+        for dir in &temp_dirs[..2] {
+            std::fs::create_dir(dir.path().join("dir")).unwrap();
+        }
+        std::fs::create_dir(temp_dirs[1].path().join("other")).unwrap();
+        filetime::set_file_mtime(temp_dirs[0].path().join("dir"), old).unwrap();
+        // Entries go to the second branch, so the first copy the attributes
+        // come from is not modified by them
+        fs.file_manager.pin_branch(Path::new("/dir"), temp_dirs[1].path()).unwrap();
+        
+        let dir = fs.lookup_path("/dir").unwrap();
+        let other = fs.lookup_path("/other").unwrap();
+        let before = fs.get_inode_data(dir.ino).unwrap().attr.mtime;
+        assert_eq!(before, SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000));
+        
+        // Creating a child through the pool advances the cached parent times,
+        // and they survive a fresh lookup of the unchanged branch copy
+        fs.create_path(Path::new("/dir/child.txt"), 0).unwrap();
+        let touched = fs.get_inode_data(dir.ino).unwrap().attr;
+        assert!(touched.mtime > before);
+        assert!(touched.ctime >= touched.mtime);
+        assert_eq!(fs.lookup_path("/dir").unwrap().mtime, touched.mtime);
+        
+        // Removing it does too
+        fs.unlink_path(Path::new("/dir/child.txt")).unwrap();
+        let unlinked = fs.get_inode_data(dir.ino).unwrap().attr.mtime;
+        assert!(unlinked >= touched.mtime);
+        
+        // So does a rename, on both parents
+        fs.create_path(Path::new("/dir/moved.txt"), 0).unwrap();
+        let other_before = fs.get_inode_data(other.ino).unwrap().attr.mtime;
+        fs.rename_path("/dir/moved.txt", "/other/moved.txt").unwrap();
+        assert!(fs.get_inode_data(dir.ino).unwrap().attr.mtime >= unlinked);
+        assert!(fs.get_inode_data(other.ino).unwrap().attr.mtime > other_before);
+        
+        // A branch copy set back to an earlier time is reported as it is
+        filetime::set_file_mtime(temp_dirs[0].path().join("dir"), older).unwrap();
+        let attr = fs.lookup_path("/dir").unwrap();
+        assert_eq!(attr.mtime, SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(500_000));
+        
+        // Inodes that are not cached are ignored
+        fs.touch_dir(9999);
+        assert!(fs.get_inode_data(9999).is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_gc_drops_caches_and_keeps_open_inodes() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let stat = |fs: &MergerFS, name: &str| -> u64 {
+            fs.config_manager.get_option(&format!("stats.{}", name)).unwrap().parse().unwrap()
+        };
+        std::fs::create_dir(temp_dirs[0].path().join("dir")).unwrap();
+        for name in ["a.txt", "b.txt", "open.txt"] {
+            std::fs::write(temp_dirs[0].path().join("dir").join(name), name).unwrap();
+        }
+        
+        let dir = fs.lookup_path("/dir").unwrap();
+        fs.lookup_path("/dir/a.txt").unwrap();
+        fs.lookup_path("/dir/b.txt").unwrap();
+        let open = fs.lookup_path("/dir/open.txt").unwrap();
+        // The kernel forgot all but the open file
+        for path in ["/dir", "/dir/a.txt", "/dir/b.txt"] {
+            let ino = fs.lookup_path(path).unwrap().ino;
+            fs.forget_inode(ino, 2);
+        }
+        let fh = fs.file_handle_manager.create_handle(open.ino, "/dir/open.txt".into(), 0, Some(0), false);
+        let dir_fh = fs.allocate_dir_handle();
+        fs.store_dir_handle(dir_fh, "/dir".into(), dir.ino);
+        fs.config_manager.set_option("cache.readdir", "60").unwrap();
+        assert_eq!(fs.dir_entries("/dir").len(), 5);
+        std::fs::write(temp_dirs[0].path().join("dir/new.txt"), b"").unwrap();
+        assert_eq!(fs.dir_entries("/dir").len(), 5);
+        assert_eq!(stat(&fs, "open_dir_handles"), 1);
+        
+        // Root and the file with an open handle survive
+        assert_eq!(fs.gc(), 3);
+        assert_eq!(stat(&fs, "inodes"), 2);
+        assert!(fs.get_inode_data(open.ino).is_some());
+        assert!(fs.get_inode_data(dir.ino).is_none());
+        assert_eq!(stat(&fs, "open_dir_handles"), 0);
+        
+        // Everything dropped is rebuilt on demand
+        assert_eq!(fs.dir_entries("/dir").len(), 6);
+        assert_eq!(fs.lookup_path("/dir").unwrap().ino, dir.ino);
+        assert_eq!(fs.lookup_path("/dir/a.txt").unwrap().size, 5);
+        fs.file_handle_manager.remove_handle(fh);
+    }
+
+    #[test]
+    #[serial]
+    fn test_salted_path_hash_counts_inode_collisions() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let collisions = |fs: &MergerFS| -> u64 {
+            fs.config_manager.get_option("stats.inode_collisions").unwrap().parse().unwrap()
+        };
+        fs.config_manager.set_option("inodecalc", "path-hash-salted").unwrap();
+        assert_eq!(fs.config_manager.get_option("inodecalc").unwrap(), "path-hash-salted");
+        
+        for i in 0..200 {
+            let dir = temp_dirs[i % 2].path().join(format!("dir{}", i % 10));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join(format!("file{}", i)), b"").unwrap();
+        }
+        let inodes: std::collections::HashSet<u64> = (0..200)
+            .map(|i| fs.lookup_path(&format!("/dir{}/file{}", i % 10, i)).unwrap().ino)
+            .collect();
+        assert_eq!(inodes.len(), 200);
+        assert_eq!(collisions(&fs), 0);
+        
+        // Choosing the mode again keeps the salt, and so the numbering
+        let ino = fs.lookup_path("/dir0/file0").unwrap().ino;
+        fs.config_manager.set_option("inodecalc", "path-hash-salted").unwrap();
+        assert_eq!(fs.lookup_path("/dir0/file0").unwrap().ino, ino);
+        
+        // A different file already cached under the number is a collision
+        std::fs::write(temp_dirs[1].path().join("dir1/new.txt"), b"").unwrap();
+        let (attr, _, _) = fs.create_file_attr_with_branch(Path::new("/dir1/new.txt")).unwrap();
+        fs.insert_inode(attr.ino, "/elsewhere".to_string(), attr, Some(0), u64::MAX);
+        assert_eq!(collisions(&fs), 0);
+        fs.lookup_path("/dir1/new.txt").unwrap();
+        assert_eq!(collisions(&fs), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_reordering_branches_changes_ff_choice() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let paths: Vec<PathBuf> = temp_dirs.iter().map(|dir| dir.path().to_path_buf()).collect();
+        for dir in &temp_dirs[..2] {
+            std::fs::write(dir.path().join("replica.txt"), "original").unwrap();
+        }
+        let ino = fs.lookup_path("/replica.txt").unwrap().ino;
+        let fh = fs.open_handle(ino, nix::libc::O_WRONLY, false).unwrap();
+        
+        fs.file_manager.create_file(Path::new("/before.txt"), b"").unwrap();
+        assert!(temp_dirs[0].path().join("before.txt").exists());
+        std::fs::create_dir(temp_dirs[0].path().join("pinned")).unwrap();
+        fs.file_manager.pin_branch(Path::new("/pinned"), &paths[0]).unwrap();
+        
+        fs.reorder_branches(&[paths[1].clone(), paths[0].clone(), paths[2].clone()]).unwrap();
+        fs.file_manager.create_file(Path::new("/after.txt"), b"").unwrap();
+        assert!(temp_dirs[1].path().join("after.txt").exists());
+        assert!(!temp_dirs[0].path().join("after.txt").exists());
+        
+        // Pins stay on the branch they name, wherever it now sits
+        assert_eq!(fs.file_manager.get_branch_pin(Path::new("/pinned")).unwrap().path, paths[0]);
+        fs.file_manager.create_file(Path::new("/pinned/after.txt"), b"").unwrap();
+        assert!(temp_dirs[0].path().join("pinned/after.txt").exists());
+        assert!(!temp_dirs[1].path().join("pinned/after.txt").exists());
+        
+        // Cached inodes resolve to the new first branch, while the open handle
+        // keeps writing to the copy it was opened from
+        assert_eq!(fs.get_inode_data(ino).unwrap().branch_idx, Some(0));
+        assert_eq!(fs.file_handle_manager.get_handle(fh).unwrap().branch_idx, Some(1));
+        fs.write_at(ino, fh, 0, b"OVERWRIT").unwrap();
+        assert_eq!(std::fs::read_to_string(temp_dirs[0].path().join("replica.txt")).unwrap(), "OVERWRIT");
+        assert_eq!(std::fs::read_to_string(temp_dirs[1].path().join("replica.txt")).unwrap(), "original");
+        fs.file_handle_manager.remove_handle(fh);
+        
+        // The order must name every branch exactly once
+        let invalid = [
+            paths[..2].to_vec(),
+            vec![paths[0].clone(), paths[0].clone(), paths[2].clone()],
+            vec![paths[0].clone(), paths[1].clone(), PathBuf::from("/nonexistent")],
+        ];
+        for order in &invalid {
+            assert_eq!(fs.reorder_branches(order).unwrap_err().errno(), crate::errno::EINVAL);
+        }
+        assert_eq!(fs.file_manager.branches()[0].path, paths[1]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_readdir_cache_reuses_listing_until_directory_changes() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        std::fs::create_dir(temp_dirs[0].path().join("dir")).unwrap();
+        std::fs::write(temp_dirs[0].path().join("dir/a.txt"), b"a").unwrap();
+        fs.lookup_path("/dir").unwrap();
+        let names = |fs: &MergerFS| -> Vec<String> {
+            fs.dir_entries("/dir").into_iter().map(|(_, _, name)| name).collect()
+        };
+        
+        // Off by default: a file added behind the mount shows up at once
+        std::fs::create_dir(temp_dirs[1].path().join("dir")).unwrap();
+        std::fs::write(temp_dirs[1].path().join("dir/b.txt"), b"b").unwrap();
+        assert_eq!(names(&fs), vec![".", "..", "a.txt", "b.txt"]);
+        
+        // Within the TTL the branches are not scanned again
+        fs.config_manager.set_option("cache.readdir", "60").unwrap();
+        assert_eq!(names(&fs), vec![".", "..", "a.txt", "b.txt"]);
+        std::fs::write(temp_dirs[1].path().join("dir/c.txt"), b"c").unwrap();
+        assert_eq!(names(&fs), vec![".", "..", "a.txt", "b.txt"]);
+        
+        // A create through the pool drops the cached listing
+        fs.create_path(Path::new("/dir/d.txt"), 0).unwrap();
+        assert_eq!(names(&fs), vec![".", "..", "a.txt", "b.txt", "c.txt", "d.txt"]);
+        
+        // So does a rename
+        std::fs::write(temp_dirs[1].path().join("dir/e.txt"), b"e").unwrap();
+        fs.rename_path("/dir/d.txt", "/dir/f.txt").unwrap();
+        assert_eq!(names(&fs), vec![".", "..", "a.txt", "b.txt", "c.txt", "e.txt", "f.txt"]);
+        
+        // Disabling the cache lists the branches again
+        std::fs::remove_file(temp_dirs[1].path().join("dir/e.txt")).unwrap();
+        fs.config_manager.set_option("cache.readdir", "0").unwrap();
+        assert_eq!(names(&fs), vec![".", "..", "a.txt", "b.txt", "c.txt", "f.txt"]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_disabled_control_file_is_hidden() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        std::fs::write(temp_dirs[0].path().join("file.txt"), b"x").unwrap();
+        let names = |fs: &MergerFS| -> Vec<String> {
+            fs.dir_entries("/").into_iter().map(|(_, _, name)| name).collect()
+        };
+        
+        assert!(names(&fs).contains(&".mergerfs".to_string()));
+        assert!(fs.control_file_attr("/.mergerfs").is_some());
+        
+        fs.config_manager.set_option("mergerfs_control", "false").unwrap();
+        assert!(!fs.control_file_enabled());
+        assert_eq!(names(&fs), vec![".", "..", "file.txt"]);
+        // Lookup falls through to the branches, which have no such file
+        assert!(fs.control_file_attr("/.mergerfs").is_none());
+        assert!(fs.lookup_path("/.mergerfs").is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_branch_write_counters() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        std::fs::write(temp_dirs[1].path().join("second.txt"), b"").unwrap();
+        let written = |idx: usize| -> u64 {
+            fs.config_manager.get_option(&format!("stats.branch.{}.written", idx)).unwrap().parse().unwrap()
+        };
+        
+        // Through a handle's branch index, and resolved from the path without one
+        fs.account_write(Path::new("/first.txt"), Some(0), 4096);
+        fs.account_write(Path::new("/first.txt"), Some(0), 100);
+        fs.account_write(Path::new("/second.txt"), None, 7);
+        assert_eq!((written(0), written(1), written(2)), (4196, 7, 0));
+        assert!(fs.config_manager.get_option("stats.branch.3.written").is_err());
+        
+        fs.config_manager.set_option("stats.branch.reset", "1").unwrap();
+        assert_eq!((written(0), written(1)), (4196, 0));
+        
+        // Counters follow the branch order, matching the indexes reset takes
+        let paths: Vec<PathBuf> = temp_dirs.iter().map(|dir| dir.path().to_path_buf()).collect();
+        fs.account_write(Path::new("/second.txt"), None, 7);
+        fs.reorder_branches(&[paths[1].clone(), paths[0].clone(), paths[2].clone()]).unwrap();
+        assert_eq!((written(0), written(1)), (7, 4196));
+        fs.config_manager.set_option("stats.branch.reset", "0").unwrap();
+        assert_eq!((written(0), written(1)), (0, 4196));
+        
+        fs.config_manager.set_option("stats.branch.reset", "all").unwrap();
+        assert_eq!(written(1), 0);
+        assert!(fs.config_manager.set_option("stats.branch.reset", "9").is_err());
+        assert!(fs.config_manager.set_option("stats.branch.0.written", "0").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_owner_squash() {
+        use std::os::unix::fs::MetadataExt;
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let first = temp_dirs[0].path().join("first.txt");
+        let second = temp_dirs[1].path().join("second.txt");
+        std::fs::write(&first, b"a").unwrap();
+        std::fs::write(&second, b"b").unwrap();
+        std::os::unix::fs::chown(&first, Some(2001), Some(3001)).unwrap();
+        std::os::unix::fs::chown(&second, Some(2002), Some(3002)).unwrap();
+        
+        // Backing owners are reported as they are by default
+        let attr = fs.lookup_path("/first.txt").unwrap();
+        assert_eq!((attr.uid, attr.gid), (2001, 3001));
+        
+        fs.config_manager.set_option("uid_squash", "1500").unwrap();
+        fs.config_manager.set_option("gid_squash", "1600").unwrap();
+        for path in ["/first.txt", "/second.txt"] {
+            let attr = fs.create_file_attr(Path::new(path)).unwrap();
+            assert_eq!((attr.uid, attr.gid), (1500, 1600), "{}", path);
+        }
+        
+        // chown succeeds without changing the backing owner
+        fs.chown_path(Path::new("/first.txt"), Some(42), Some(43)).unwrap();
+        let metadata = std::fs::metadata(&first).unwrap();
+        assert_eq!((metadata.uid(), metadata.gid()), (2001, 3001));
+        
+        // Only the squashed side is ignored
+        fs.config_manager.set_option("gid_squash", "").unwrap();
+        fs.chown_path(Path::new("/first.txt"), Some(42), Some(43)).unwrap();
+        let metadata = std::fs::metadata(&first).unwrap();
+        assert_eq!((metadata.uid(), metadata.gid()), (2001, 43));
+        assert_eq!(fs.create_file_attr(Path::new("/first.txt")).unwrap().gid, 43);
+        
+        assert_eq!(fs.config_manager.get_option("uid_squash").unwrap(), "1500");
+        assert!(fs.config_manager.set_option("uid_squash", "alice").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_max_inodes_evicts_least_recently_used() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        for i in 0..4 {
+            std::fs::write(temp_dirs[0].path().join(format!("f{}", i)), vec![b'x'; i + 1]).unwrap();
+        }
+        fs.config_manager.set_option("max_inodes", "4").unwrap();
+        
+        // Root plus three files fills the cap
+        let inos: Vec<u64> = (0..3).map(|i| fs.lookup_path(&format!("/f{}", i)).unwrap().ino).collect();
+        for &ino in &inos {
+            fs.forget_inode(ino, 1);
+        }
+        let fh = fs.file_handle_manager.create_handle(inos[0], std::path::PathBuf::from("/f0"), 0, Some(0), false);
+        fs.get_inode_data(inos[1]).unwrap();
+        
+        // f0 is open and f1 was just used, so f2 goes first
+        let f3 = fs.lookup_path("/f3").unwrap().ino;
+        assert!(fs.get_inode_data(inos[2]).is_none());
+        for ino in [1, inos[0], inos[1], f3] {
+            assert!(fs.get_inode_data(ino).is_some(), "inode {} evicted", ino);
+        }
+        assert_eq!(fs.config_manager.get_option("stats.inodes").unwrap(), "4");
+        
+        // Looking it up again recomputes the same inode and attributes
+        let attr = fs.lookup_path("/f2").unwrap();
+        assert_eq!((attr.ino, attr.size), (inos[2], 3));
+        assert_eq!(fs.get_inode_data(inos[2]).unwrap().path, "/f2");
+        assert!(fs.get_inode_data(inos[1]).is_none());
+        
+        // Once closed the open file is an ordinary candidate again
+        fs.file_handle_manager.remove_handle(fh);
+        fs.lookup_path("/f1").unwrap();
+        assert!(fs.get_inode_data(inos[0]).is_none());
+        assert!(fs.get_inode_data(1).is_some());
+        
+        // Inodes the kernel has not forgotten stay, even above the cap
+        fs.lookup_path("/f0").unwrap();
+        assert_eq!(fs.config_manager.get_option("stats.inodes").unwrap(), "5");
+        fs.forget_inode(f3, 1);
+        fs.lookup_path("/f0").unwrap();
+        assert!(fs.get_inode_data(f3).is_none());
+        for ino in inos {
+            assert!(fs.get_inode_data(ino).is_some(), "inode {} evicted", ino);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_mkdir_policy_spreads_directories_while_files_use_ff() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        assert_eq!(fs.config_manager.get_option("func.create").unwrap(), "ff");
+        assert_eq!(fs.config_manager.get_option("func.mkdir").unwrap(), "all");
+        
+        fs.file_manager.create_directory(Path::new("/media")).unwrap();
+        assert!(temp_dirs[0].path().join("media").is_dir());
+        assert!(temp_dirs[1].path().join("media").is_dir());
+        assert!(!temp_dirs[2].path().join("media").exists());
+        
+        // Files still go to the first writable branch only
+        fs.file_manager.create_file(Path::new("/media/clip.mkv"), b"data").unwrap();
+        assert!(temp_dirs[0].path().join("media/clip.mkv").exists());
+        assert!(!temp_dirs[1].path().join("media/clip.mkv").exists());
+        
+        // Parents missing on a branch are created there too
+        fs.file_manager.create_directory(Path::new("/media/shows")).unwrap();
+        std::fs::remove_dir(temp_dirs[1].path().join("media/shows")).unwrap();
+        std::fs::remove_dir(temp_dirs[1].path().join("media")).unwrap();
+        fs.file_manager.create_directory(Path::new("/media/shows/s01")).unwrap();
+        assert!(temp_dirs[1].path().join("media/shows/s01").is_dir());
+        
+        // Directories can follow a create policy instead
+        fs.config_manager.set_option("func.mkdir", "ff").unwrap();
+        fs.file_manager.create_directory(Path::new("/music")).unwrap();
+        assert!(temp_dirs[0].path().join("music").is_dir());
+        assert!(!temp_dirs[1].path().join("music").exists());
+        assert!(fs.config_manager.set_option("func.mkdir", "bogus").is_err());
+        assert_eq!(fs.config_manager.get_option("func.mkdir").unwrap(), "ff");
+    }
+
+    /// Sets chattr flags on a branch file and clears them again on drop so
+    /// the temporary directory can be removed
+    struct InodeFlagsGuard(std::fs::File);
+
+    impl InodeFlagsGuard {
+        /// None when the filesystem or our privileges don't allow the flags
+        fn set(path: &Path, flags: u32) -> Option<Self> {
+            let file = std::fs::File::open(path).unwrap();
+            Self::apply(&file, flags).then_some(Self(file))
+        }
+
+        fn apply(file: &std::fs::File, flags: u32) -> bool {
+            use std::os::unix::io::AsRawFd;
+            let flags = flags as nix::libc::c_int;
+            // SAFETY: FS_IOC_SETFLAGS only reads the int behind the pointer
+            unsafe { nix::libc::ioctl(file.as_raw_fd(), nix::libc::FS_IOC_SETFLAGS as _, &flags) == 0 }
+        }
+    }
+
+    impl Drop for InodeFlagsGuard {
+        fn drop(&mut self) {
+            Self::apply(&self.0, 0);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_immutable_and_append_only_files() {
+        use crate::errno::EPERM;
+        use crate::fs_utils::{FS_APPEND_FL, FS_IMMUTABLE_FL};
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let locked = temp_dirs[0].path().join("locked.txt");
+        let log = temp_dirs[0].path().join("app.log");
+        std::fs::write(&locked, b"frozen").unwrap();
+        std::fs::write(&log, b"line\n").unwrap();
+        
+        let Some(_immutable) = InodeFlagsGuard::set(&locked, FS_IMMUTABLE_FL) else {
+            eprintln!("Skipping: branch filesystem does not support setting inode flags");
+            return;
+        };
+        let Some(_append) = InodeFlagsGuard::set(&log, FS_APPEND_FL) else {
+            eprintln!("Skipping: branch filesystem does not support setting inode flags");
+            return;
+        };
+        
+        let locked_path = Path::new("/locked.txt");
+        assert_eq!(fs.check_write_flags(locked_path, Some(0), None, 6).unwrap_err().errno(), EPERM);
+        let file = std::fs::File::open(&locked).unwrap();
+        assert_eq!(fs.check_write_flags(locked_path, Some(0), Some(&file), 0).unwrap_err().errno(), EPERM);
+        assert_eq!(fs.unlink_path(locked_path).unwrap_err().errno(), EPERM);
+        assert_eq!(fs.rename_path("/locked.txt", "/moved.txt").unwrap_err().errno(), EPERM);
+        assert!(locked.exists());
+        
+        // Append-only files take writes at their end but nowhere before it
+        let log_path = Path::new("/app.log");
+        assert_eq!(fs.check_write_flags(log_path, Some(0), None, 0).unwrap_err().errno(), EPERM);
+        fs.check_write_flags(log_path, Some(0), None, 5).unwrap();
+        assert_eq!(fs.unlink_path(log_path).unwrap_err().errno(), EPERM);
+        
+        // Replacing a protected destination is refused as well
+        std::fs::write(temp_dirs[0].path().join("other.txt"), b"x").unwrap();
+        assert_eq!(fs.rename_path("/other.txt", "/locked.txt").unwrap_err().errno(), EPERM);
+        
+        // Unflagged files are unaffected
+        fs.check_write_flags(Path::new("/other.txt"), None, None, 0).unwrap();
+        fs.unlink_path(Path::new("/other.txt")).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_unlink_and_rename_special_files() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        for dir in &temp_dirs[..2] {
+            nix::unistd::mkfifo(&dir.path().join("pipe"), nix::sys::stat::Mode::from_bits_truncate(0o644)).unwrap();
+        }
+        
+        // Copies that are not regular files or directories are checked by type alone
+        fs.check_deletable(Path::new("/pipe")).unwrap();
+        fs.rename_path("/pipe", "/renamed").unwrap();
+        fs.unlink_path(Path::new("/renamed")).unwrap();
+        assert!(fs.lookup_path("/renamed").is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn test_xattr_set_and_remove_reach_every_copy() {
+        use crate::xattr::{XattrError, XattrFlags};
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let path = Path::new("/shared.txt");
+        for temp_dir in &temp_dirs[..2] {
+            std::fs::write(temp_dir.path().join("shared.txt"), b"copy").unwrap();
+        }
+        // A file only on the second branch gets the attribute there
+        std::fs::write(temp_dirs[1].path().join("second.txt"), b"only").unwrap();
+        
+        fs.xattr_manager.set_xattr(path, "user.tag", b"blue", XattrFlags::None).unwrap();
+        fs.xattr_manager.set_xattr(Path::new("/second.txt"), "user.tag", b"red", XattrFlags::None).unwrap();
+        for temp_dir in &temp_dirs[..2] {
+            assert_eq!(xattr::get(temp_dir.path().join("shared.txt"), "user.tag").unwrap().as_deref(), Some(&b"blue"[..]));
+        }
+        assert_eq!(fs.xattr_manager.get_xattr(path, "user.tag").unwrap(), b"blue");
+        assert_eq!(fs.xattr_manager.get_xattr(Path::new("/second.txt"), "user.tag").unwrap(), b"red");
+        
+        // Copies that diverge behind the pool's back are read from the first branch
+        xattr::set(temp_dirs[1].path().join("shared.txt"), "user.tag", b"green").unwrap();
+        for _ in 0..5 {
+            assert_eq!(fs.xattr_manager.get_xattr(path, "user.tag").unwrap(), b"blue");
+        }
+        
+        fs.xattr_manager.remove_xattr(path, "user.tag").unwrap();
+        for temp_dir in &temp_dirs[..2] {
+            assert!(xattr::get(temp_dir.path().join("shared.txt"), "user.tag").unwrap().is_none());
+        }
+        assert!(matches!(fs.xattr_manager.get_xattr(path, "user.tag"), Err(XattrError::NotFound)));
+    }
+
+    #[test]
+    #[serial]
+    fn test_read_range_returns_full_request_up_to_eof() {
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let content: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(temp_dirs[0].path().join("big.bin"), &content).unwrap();
+        let ino = fs.lookup_path("/big.bin").unwrap().ino;
+        
+        // Across the 64 KiB and 128 KiB boundaries, without an open handle
+        let offset = 65_536 - 100;
+        let data = fs.read_range(ino, 0, offset, 70_000).unwrap();
+        assert_eq!(data.len(), 70_000);
+        assert_eq!(&data[..], &content[offset as usize..offset as usize + 70_000]);
+        
+        // Through an open handle, a request running past EOF stops there
+        let file = std::fs::File::open(temp_dirs[0].path().join("big.bin")).unwrap();
+        let fh = fs.file_handle_manager.create_handle_with_file(ino, std::path::PathBuf::from("/big.bin"), 0, Some(0), false, Some(file));
+        let data = fs.read_range(ino, fh, 150_000, 131_072).unwrap();
+        assert_eq!(&data[..], &content[150_000..]);
+        assert!(fs.read_range(ino, fh, 300_000, 4096).unwrap().is_empty());
+        
+        assert_eq!(fs.read_range(9_999_999, 0, 0, 10).unwrap_err().errno(), crate::errno::ENOENT);
+    }
+
+    #[test]
+    #[serial]
+    fn test_concurrent_lookups_agree_on_one_inode() {
+        use crate::inode::InodeCalc;
+        let (temp_dirs, fs) = setup_test_mergerfs();
+        let fs = Arc::new(fs);
+        
+        for (i, calc) in [InodeCalc::Passthrough, InodeCalc::DevinoHash, InodeCalc::PathHash, InodeCalc::HybridHash].into_iter().enumerate() {
+            fs.config_manager.config().write().inodecalc = calc;
+            let name = format!("/race{}.txt", i);
+            std::fs::write(temp_dirs[0].path().join(&name[1..]), b"contended").unwrap();
+            
+            // Lookups race with a creator-style insert refreshing the same entry
+            let threads: Vec<_> = (0..8)
+                .map(|t| {
+                    let fs = fs.clone();
+                    let name = name.clone();
+                    std::thread::spawn(move || {
+                        let mut seen = Vec::new();
+                        for _ in 0..200 {
+                            let attr = fs.lookup_path(&name).unwrap();
+                            if t == 0 {
+                                fs.insert_inode(attr.ino, name.clone(), attr, Some(0), attr.ino);
+                            }
+                            let lock = fs.get_inode_data(attr.ino).unwrap().content_lock;
+                            seen.push((attr.ino, Arc::as_ptr(&lock) as usize));
+                        }
+                        seen
+                    })
+                })
+                .collect();
+            let seen: std::collections::HashSet<(u64, usize)> = threads
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect();
+            
+            assert_eq!(seen.len(), 1, "{:?} gave {:?}", calc, seen);
+            let (ino, _) = seen.into_iter().next().unwrap();
+            assert_eq!(fs.lookup_path(&name).unwrap().ino, ino);
+        }
+    }
+}
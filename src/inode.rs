@@ -1,6 +1,4 @@
 use std::path::Path;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
 
 /// Inode calculation algorithms
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -11,6 +9,12 @@ pub enum InodeCalc {
     PathHash,
     /// 32-bit version of path-hash
     PathHash32,
+    /// Hash the branch path and FUSE path with a salt picked at startup
+    ///
+    /// Copies of a path on different branches get different inodes, and the
+    /// salt keeps the numbering from being shared with other mounts. Inodes
+    /// change on every restart, so this is unsuitable for NFS exports.
+    PathHashSalted(u64),
     /// Hash the branch path + original inode (device+inode)
     DevinoHash,
     /// 32-bit version of devino-hash
@@ -34,6 +38,7 @@ impl InodeCalc {
             "passthrough" => Ok(InodeCalc::Passthrough),
             "path-hash" => Ok(InodeCalc::PathHash),
             "path-hash32" => Ok(InodeCalc::PathHash32),
+            "path-hash-salted" => Ok(InodeCalc::PathHashSalted(rand::random())),
             "devino-hash" => Ok(InodeCalc::DevinoHash),
             "devino-hash32" => Ok(InodeCalc::DevinoHash32),
             "hybrid-hash" => Ok(InodeCalc::HybridHash),
@@ -48,6 +53,7 @@ impl InodeCalc {
             InodeCalc::Passthrough => "passthrough",
             InodeCalc::PathHash => "path-hash",
             InodeCalc::PathHash32 => "path-hash32",
+            InodeCalc::PathHashSalted(_) => "path-hash-salted",
             InodeCalc::DevinoHash => "devino-hash",
             InodeCalc::DevinoHash32 => "devino-hash32",
             InodeCalc::HybridHash => "hybrid-hash",
@@ -61,6 +67,7 @@ impl InodeCalc {
             InodeCalc::Passthrough => passthrough(branch_path, fuse_path, mode, original_ino),
             InodeCalc::PathHash => path_hash(branch_path, fuse_path, mode, original_ino),
             InodeCalc::PathHash32 => path_hash32(branch_path, fuse_path, mode, original_ino),
+            InodeCalc::PathHashSalted(salt) => path_hash_salted(*salt, branch_path, fuse_path),
             InodeCalc::DevinoHash => devino_hash(branch_path, fuse_path, mode, original_ino),
             InodeCalc::DevinoHash32 => devino_hash32(branch_path, fuse_path, mode, original_ino),
             InodeCalc::HybridHash => hybrid_hash(branch_path, fuse_path, mode, original_ino),
@@ -76,12 +83,17 @@ fn h64_to_h32(h: u64) -> u64 {
     h32 as u64
 }
 
-/// Simple hash function for paths and data
-/// In production, we might want to use a faster hash like xxhash or rapidhash
-fn hash_data<T: Hash>(data: T) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    data.hash(&mut hasher);
-    hasher.finish()
+/// 64-bit FNV-1a hash of the given bytes
+///
+/// The algorithm is fixed (unlike std's `DefaultHasher`), so the same inputs
+/// produce the same inodes across restarts and builds, which NFS exports rely on.
+fn hash_data(data: &[u8]) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+    
+    data.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
 }
 
 /// Combine two hash values
@@ -105,6 +117,15 @@ fn path_hash32(branch_path: &Path, fuse_path: &Path, mode: u32, original_ino: u6
     h64_to_h32(path_hash(branch_path, fuse_path, mode, original_ino))
 }
 
+/// Hash the salt, branch path and FUSE path
+fn path_hash_salted(salt: u64, branch_path: &Path, fuse_path: &Path) -> u64 {
+    let mut data = salt.to_le_bytes().to_vec();
+    data.extend_from_slice(branch_path.to_string_lossy().as_bytes());
+    data.push(0);
+    data.extend_from_slice(fuse_path.to_string_lossy().as_bytes());
+    hash_data(&data)
+}
+
 /// Hash the branch path + original inode
 fn devino_hash(branch_path: &Path, _fuse_path: &Path, _mode: u32, original_ino: u64) -> u64 {
     let branch_hash = hash_data(branch_path.to_string_lossy().as_bytes());
@@ -145,9 +166,22 @@ mod tests {
         assert_eq!(InodeCalc::from_str("devino-hash32").unwrap(), InodeCalc::DevinoHash32);
         assert_eq!(InodeCalc::from_str("hybrid-hash").unwrap(), InodeCalc::HybridHash);
         assert_eq!(InodeCalc::from_str("hybrid-hash32").unwrap(), InodeCalc::HybridHash32);
+        assert!(matches!(InodeCalc::from_str("path-hash-salted").unwrap(), InodeCalc::PathHashSalted(_)));
         assert!(InodeCalc::from_str("invalid").is_err());
     }
 
+    #[test]
+    fn test_hashes_are_stable_across_runs() {
+        // FNV-1a reference values; a change here renumbers every inode
+        assert_eq!(hash_data(b""), 0xcbf29ce484222325);
+        assert_eq!(hash_data(b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(hash_data(b"foobar"), 0x85944171f73967e8);
+        
+        let branch = PathBuf::from("/mnt/disk1");
+        let ino = InodeCalc::DevinoHash.calc(&branch, Path::new("/file.txt"), 0o100644, 42);
+        assert_eq!(ino, hash_combine(hash_data(b"/mnt/disk1"), 42));
+    }
+
     #[test]
     fn test_inode_calc_to_string() {
         assert_eq!(InodeCalc::Passthrough.to_string(), "passthrough");
@@ -157,6 +191,7 @@ mod tests {
         assert_eq!(InodeCalc::DevinoHash32.to_string(), "devino-hash32");
         assert_eq!(InodeCalc::HybridHash.to_string(), "hybrid-hash");
         assert_eq!(InodeCalc::HybridHash32.to_string(), "hybrid-hash32");
+        assert_eq!(InodeCalc::PathHashSalted(7).to_string(), "path-hash-salted");
     }
 
     #[test]
@@ -183,6 +218,38 @@ mod tests {
         assert_eq!(result1, result2);
     }
 
+    #[test]
+    fn test_path_hash_salted_mixes_branch_and_salt() {
+        let branch1 = PathBuf::from("/mnt/disk1");
+        let branch2 = PathBuf::from("/mnt/disk2");
+        let fuse_path = PathBuf::from("/test.txt");
+        let mode = 0o100644;
+
+        let salted = InodeCalc::PathHashSalted(42);
+        assert_eq!(salted.calc(&branch1, &fuse_path, mode, 1), salted.calc(&branch1, &fuse_path, mode, 2));
+        assert_ne!(salted.calc(&branch1, &fuse_path, mode, 1), salted.calc(&branch2, &fuse_path, mode, 1));
+        assert_ne!(salted.calc(&branch1, &fuse_path, mode, 1), InodeCalc::PathHashSalted(43).calc(&branch1, &fuse_path, mode, 1));
+        assert_ne!(salted.calc(&branch1, &fuse_path, mode, 1), InodeCalc::PathHash.calc(&branch1, &fuse_path, mode, 1));
+    }
+
+    #[test]
+    fn test_path_hash_salted_has_no_collisions_in_large_tree() {
+        use std::collections::HashSet;
+
+        let salted = InodeCalc::from_str("path-hash-salted").unwrap();
+        let mut seen = HashSet::new();
+        // 200k entries across 4 branches; a 64-bit hash collides here with
+        // probability around 1e-9
+        for branch in ["/mnt/disk1", "/mnt/disk2", "/mnt/disk3", "/mnt/disk4"] {
+            for dir in 0..500 {
+                for file in 0..100 {
+                    let path = PathBuf::from(format!("/media/{}/file{}.mkv", dir, file));
+                    assert!(seen.insert(salted.calc(Path::new(branch), &path, 0o100644, 0)), "collision at {:?}", path);
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_devino_hash_different_branches() {
         let branch1 = PathBuf::from("/mnt/disk1");
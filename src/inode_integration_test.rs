@@ -64,6 +64,50 @@ mod tests {
         assert_eq!(link_attr.nlink, 2);
     }
     
+    #[test]
+    fn test_devino_hash_inodes_survive_remount() {
+        let (branch1, branch2, merger_fs) = setup_with_inode_calc(InodeCalc::DevinoHash);
+        let file_path = Path::new("/stable.txt");
+        merger_fs.file_manager.create_file(file_path, b"exported over nfs").unwrap();
+        let before = merger_fs.create_file_attr(file_path).unwrap().ino;
+        drop(merger_fs);
+        
+        // A fresh instance over the same branches stands in for a remount
+        let branches = vec![
+            Arc::new(Branch::new(branch1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(branch2.path().to_path_buf(), BranchMode::ReadWrite)),
+        ];
+        let remounted = MergerFS::new(FileManager::new(branches, Box::new(FirstFoundCreatePolicy::new())));
+        remounted.config_manager.set_option("inodecalc", "devino-hash").unwrap();
+        
+        assert_eq!(remounted.create_file_attr(file_path).unwrap().ino, before);
+    }
+    
+    #[test]
+    fn test_reused_inode_number_gets_new_generation() {
+        let (_branch1, _branch2, merger_fs) = setup_with_inode_calc(InodeCalc::PathHash);
+        let path = Path::new("/recycled.txt");
+        
+        merger_fs.file_manager.create_file(path, b"first file").unwrap();
+        let first = merger_fs.create_file_attr(path).unwrap().ino;
+        assert_eq!(merger_fs.inode_generation(first), 0);
+        
+        // Deleting the file and creating another at the same path reuses the number
+        merger_fs.unlink_path(path).unwrap();
+        merger_fs.file_manager.create_file(path, b"second file").unwrap();
+        let second = merger_fs.create_file_attr(path).unwrap().ino;
+        assert_eq!(second, first);
+        assert_eq!(merger_fs.inode_generation(second), 1);
+        
+        // Unlinking one of several hard links leaves the file, and its generation, alone
+        let link = Path::new("/recycled-link.txt");
+        merger_fs.file_manager.create_hard_link(path, link).unwrap();
+        let link_ino = merger_fs.create_file_attr(link).unwrap().ino;
+        merger_fs.unlink_path(link).unwrap();
+        assert_eq!(merger_fs.inode_generation(link_ino), 0);
+        assert_eq!(merger_fs.inode_generation(second), 1);
+    }
+    
     #[test]
     fn test_hard_links_different_inodes_with_path_hash() {
         let (_branch1, _branch2, merger_fs) = setup_with_inode_calc(InodeCalc::PathHash);
@@ -0,0 +1,233 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Number of histogram buckets; bucket `i` counts calls under 2^i microseconds
+/// and the last one collects everything slower
+const BUCKETS: usize = 24;
+
+/// FUSE operations whose latency is tracked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimedOp {
+    Lookup,
+    Read,
+    Write,
+    Readdir,
+}
+
+impl TimedOp {
+    pub const ALL: [TimedOp; 4] = [TimedOp::Lookup, TimedOp::Read, TimedOp::Write, TimedOp::Readdir];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimedOp::Lookup => "lookup",
+            TimedOp::Read => "read",
+            TimedOp::Write => "write",
+            TimedOp::Readdir => "readdir",
+        }
+    }
+}
+
+/// Call count, total/max time and a log2 histogram of one operation's latency
+#[derive(Debug)]
+pub struct OpLatency {
+    count: AtomicU64,
+    total_us: AtomicU64,
+    max_us: AtomicU64,
+    buckets: [AtomicU64; BUCKETS],
+}
+
+impl Default for OpLatency {
+    fn default() -> Self {
+        Self {
+            count: AtomicU64::new(0),
+            total_us: AtomicU64::new(0),
+            max_us: AtomicU64::new(0),
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+}
+
+impl OpLatency {
+    /// Number of completed calls
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Time spent in all completed calls, in microseconds
+    pub fn total_us(&self) -> u64 {
+        self.total_us.load(Ordering::Relaxed)
+    }
+
+    /// Slowest completed call, in microseconds
+    pub fn max_us(&self) -> u64 {
+        self.max_us.load(Ordering::Relaxed)
+    }
+
+    /// Calls per bucket; bucket `i` holds calls under 2^i microseconds
+    pub fn buckets(&self) -> Vec<u64> {
+        self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect()
+    }
+
+    fn record(&self, elapsed_us: u64) {
+        let bucket = (u64::BITS - elapsed_us.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_us.fetch_add(elapsed_us, Ordering::Relaxed);
+        self.max_us.fetch_max(elapsed_us, Ordering::Relaxed);
+    }
+
+    /// Non-empty buckets as "<upper bound>:<count>" pairs, e.g. "<16us:3 <1024us:1"
+    pub fn histogram_summary(&self) -> String {
+        self.buckets()
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(i, count)| {
+                if i == BUCKETS - 1 {
+                    format!(">={}us:{}", 1u64 << (BUCKETS - 2), count)
+                } else {
+                    format!("<{}us:{}", 1u64 << i, count)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Latency aggregates for every [`TimedOp`]
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    ops: [OpLatency; TimedOp::ALL.len()],
+}
+
+impl LatencyStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn op(&self, op: TimedOp) -> &OpLatency {
+        &self.ops[op as usize]
+    }
+
+    /// Start timing `op` in the current span
+    ///
+    /// When the returned guard drops, the elapsed time is recorded in the
+    /// aggregates and as the span's `elapsed_us` field, which the span must
+    /// declare (as `tracing::field::Empty`) to receive it.
+    pub fn start(self: &Arc<Self>, op: TimedOp) -> OpTimer {
+        OpTimer {
+            stats: Arc::clone(self),
+            op,
+            span: tracing::Span::current(),
+            started: Instant::now(),
+        }
+    }
+}
+
+/// Guard returned by [`LatencyStats::start`]
+pub struct OpTimer {
+    stats: Arc<LatencyStats>,
+    op: TimedOp,
+    span: tracing::Span,
+    started: Instant,
+}
+
+impl Drop for OpTimer {
+    fn drop(&mut self) {
+        let elapsed_us = self.started.elapsed().as_micros().min(u64::MAX as u128) as u64;
+        self.stats.op(self.op).record(elapsed_us);
+        self.span.record("elapsed_us", elapsed_us);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+    /// Collects every `elapsed_us` value recorded on a span, keyed by span name
+    #[derive(Clone, Default)]
+    struct ElapsedCollector {
+        recorded: Arc<Mutex<Vec<(String, u64)>>>,
+    }
+
+    struct ElapsedVisitor(Option<u64>);
+
+    impl Visit for ElapsedVisitor {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "elapsed_us" {
+                self.0 = Some(value);
+            }
+        }
+
+        fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S> Layer<S> for ElapsedCollector
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(&self, _attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {}
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            let mut visitor = ElapsedVisitor(None);
+            values.record(&mut visitor);
+            if let (Some(elapsed), Some(span)) = (visitor.0, ctx.span(id)) {
+                self.recorded.lock().unwrap().push((span.name().to_string(), elapsed));
+            }
+        }
+    }
+
+    #[test]
+    fn test_timer_records_span_field_and_aggregates() {
+        let collector = ElapsedCollector::default();
+        let subscriber = tracing_subscriber::registry().with(collector.clone());
+        let stats = Arc::new(LatencyStats::new());
+
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..2 {
+                let _span = tracing::info_span!("fuse::read", elapsed_us = tracing::field::Empty).entered();
+                let _timer = stats.start(TimedOp::Read);
+                std::thread::sleep(std::time::Duration::from_millis(2));
+            }
+            let _span = tracing::debug_span!("fuse::readdir", elapsed_us = tracing::field::Empty).entered();
+            let _timer = stats.start(TimedOp::Readdir);
+        });
+
+        let recorded = collector.recorded.lock().unwrap();
+        let names: Vec<&str> = recorded.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["fuse::read", "fuse::read", "fuse::readdir"]);
+        assert!(recorded[0].1 >= 2_000);
+
+        let read = stats.op(TimedOp::Read);
+        assert_eq!(read.count(), 2);
+        assert!(read.total_us() >= 4_000);
+        assert!(read.max_us() >= 2_000 && read.max_us() <= read.total_us());
+        assert_eq!(read.buckets().iter().sum::<u64>(), 2);
+        assert_eq!(stats.op(TimedOp::Readdir).count(), 1);
+        assert_eq!(stats.op(TimedOp::Write).count(), 0);
+    }
+
+    #[test]
+    fn test_histogram_buckets() {
+        let latency = OpLatency::default();
+        for elapsed_us in [0, 1, 3, 3, 1_000, u64::MAX] {
+            latency.record(elapsed_us);
+        }
+
+        let buckets = latency.buckets();
+        assert_eq!(buckets[0], 1);  // 0us
+        assert_eq!(buckets[1], 1);  // 1us
+        assert_eq!(buckets[2], 2);  // 2-3us
+        assert_eq!(buckets[10], 1); // 512-1023us
+        assert_eq!(buckets[BUCKETS - 1], 1);
+        assert_eq!(
+            latency.histogram_summary(),
+            "<1us:1 <2us:1 <4us:2 <1024us:1 >=4194304us:1"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+//! Union filesystem logic behind the mergerfs-rs binary
+//!
+//! Branches are pooled by a [`FileManager`], which places and finds files
+//! with the create, action and search policies; [`MergerFS`] serves that
+//! pool over FUSE and [`ConfigManager`] exposes its runtime options.
+//!
+//! ```
+//! use std::path::Path;
+//! use std::sync::Arc;
+//! use mergerfs_rs::{Branch, BranchMode, FileManager};
+//! use mergerfs_rs::policy::FirstFoundCreatePolicy;
+//!
+//! let disk1 = tempfile::tempdir().unwrap();
+//! let disk2 = tempfile::tempdir().unwrap();
+//! let branches = vec![
+//!     Arc::new(Branch::new(disk1.path().to_path_buf(), BranchMode::ReadWrite)),
+//!     Arc::new(Branch::new(disk2.path().to_path_buf(), BranchMode::ReadOnly)),
+//! ];
+//! let file_manager = FileManager::new(branches, Box::new(FirstFoundCreatePolicy::new()));
+//!
+//! file_manager.create_file(Path::new("/hello.txt"), b"hello").unwrap();
+//! assert!(disk1.path().join("hello.txt").exists());
+//! assert_eq!(file_manager.read_file(Path::new("/hello.txt")).unwrap(), b"hello");
+//! ```
+
+pub mod branch;
+pub mod config;
+pub mod config_manager;
+pub mod errno;
+pub mod control_file;
+pub mod policy;
+pub mod metadata_ops;
+pub mod file_ops;
+pub mod file_handle;
+pub mod xattr;
+pub mod fuse_fs;
+pub mod fs_utils;
+pub mod inode;
+pub mod rename_ops;
+pub mod permissions;
+pub mod moveonenospc;
+pub mod symlink_cache;
+pub mod latency;
+
+mod integration_tests;
+mod fuse_integration_tests;
+mod directory_ops_tests;
+
+#[cfg(test)]
+mod test_utils;
+#[cfg(test)]
+mod rename_strategy_tests;
+#[cfg(test)]
+mod rename_minimal_test;
+#[cfg(test)]
+mod rename_edge_case_tests;
+#[cfg(test)]
+mod symlink_tests;
+#[cfg(test)]
+mod link_tests;
+#[cfg(test)]
+mod inode_integration_test;
+
+pub use branch::{Branch, BranchMode};
+pub use config_manager::ConfigManager;
+pub use file_ops::FileManager;
+pub use fuse_fs::MergerFS;
+pub use policy::{ActionPolicy, CreatePolicy, SearchPolicy, PolicyError};
@@ -261,4 +261,71 @@ mod tests {
         assert_eq!(source_meta.mode() & 0o777, 0o644);
         assert_eq!(link_meta.mode() & 0o777, 0o644);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_link_cow_write_does_not_affect_other_link() {
+        use crate::fuse_fs::MergerFS;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let branch = Arc::new(Branch::new(temp_dir.path().to_path_buf(), BranchMode::ReadWrite));
+        let file_manager = FileManager::new(vec![branch.clone()], Box::new(FirstFoundCreatePolicy::new()));
+        let fs = MergerFS::new(file_manager);
+        fs.config_manager.set_option("link-cow", "true").unwrap();
+        
+        let source_path = Path::new("/source.txt");
+        let link_path = Path::new("/link.txt");
+        fs::write(branch.full_path(source_path), b"original").unwrap();
+        fs.file_manager.create_hard_link(source_path, link_path).unwrap();
+        
+        // Write through a handle opened on the link
+        let ino = fs.lookup_path("/link.txt").unwrap().ino;
+        let fh = fs.open_handle(ino, nix::libc::O_WRONLY, false).unwrap();
+        assert_eq!(fs.write_at(ino, fh, 0, b"modified").unwrap(), 8);
+        fs.release_handle(fh).unwrap();
+        
+        assert_eq!(fs::read_to_string(branch.full_path(source_path)).unwrap(), "original");
+        assert_eq!(fs::read_to_string(branch.full_path(link_path)).unwrap(), "modified");
+        assert_eq!(fs::metadata(branch.full_path(source_path)).unwrap().nlink(), 1);
+        assert_eq!(fs::metadata(branch.full_path(link_path)).unwrap().nlink(), 1);
+    }
+    
+    #[test]
+    fn test_link_exdev_when_destination_dir_on_other_branch() {
+        use crate::policy::PolicyError;
+        
+        let temp_dir = TempDir::new().unwrap();
+        let branch_a_path = temp_dir.path().join("a");
+        let branch_b_path = temp_dir.path().join("b");
+        fs::create_dir(&branch_a_path).unwrap();
+        fs::create_dir(&branch_b_path).unwrap();
+        
+        let branch_a = Arc::new(Branch::new(branch_a_path.clone(), BranchMode::ReadWrite));
+        let branch_b = Arc::new(Branch::new(branch_b_path.clone(), BranchMode::ReadWrite));
+        let file_manager = FileManager::new(
+            vec![branch_a.clone(), branch_b.clone()],
+            Box::new(FirstFoundCreatePolicy::new()),
+        );
+        
+        // Source on branch A, destination directory only on branch B
+        fs::write(branch_a.full_path(Path::new("/source.txt")), b"data").unwrap();
+        fs::create_dir(branch_b.full_path(Path::new("/dest"))).unwrap();
+        
+        let source_path = Path::new("/source.txt");
+        let link_path = Path::new("/dest/link.txt");
+        match file_manager.check_link_branches(source_path, link_path) {
+            Err(PolicyError::IoError(e)) => {
+                assert_eq!(e.kind(), std::io::ErrorKind::CrossesDevices);
+                assert_eq!(e.raw_os_error(), Some(18)); // EXDEV
+            }
+            other => panic!("Expected EXDEV, got {:?}", other),
+        }
+        assert!(!branch_a.full_path(Path::new("/dest")).exists());
+        
+        // Once the directory exists on the source branch the link is allowed
+        fs::create_dir(branch_a.full_path(Path::new("/dest"))).unwrap();
+        assert!(file_manager.check_link_branches(source_path, link_path).is_ok());
+        
+        // Destinations whose parent exists nowhere are still created on the source branch
+        assert!(file_manager.check_link_branches(source_path, Path::new("/new/link.txt")).is_ok());
+    }
+}
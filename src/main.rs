@@ -1,45 +1,10 @@
-mod branch;
-mod config;
-mod config_manager;
-mod control_file;
-mod policy;
-mod metadata_ops;
-mod file_ops;
-mod file_handle;
-mod xattr;
-mod fuse_fs;
-mod fs_utils;
-mod inode;
-mod integration_tests;
-mod fuse_integration_tests;
-mod directory_ops_tests;
-mod rename_ops;
-mod permissions;
-mod moveonenospc;
-
-#[cfg(test)]
-mod test_utils;
-#[cfg(test)]
-mod rename_strategy_tests;
-#[cfg(test)]
-mod rename_minimal_test;
-#[cfg(test)]
-mod rename_edge_case_tests;
-#[cfg(test)]
-mod symlink_tests;
-#[cfg(test)]
-mod link_tests;
-#[cfg(test)]
-mod inode_integration_test;
-
 use std::env;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use branch::{Branch, BranchMode};
-use file_ops::FileManager;
-use fuse_fs::MergerFS;
-use policy::{
+use mergerfs_rs::{branch, Branch, BranchMode, FileManager, MergerFS};
+use mergerfs_rs::inode::InodeCalc;
+use mergerfs_rs::policy::{
     CreatePolicy,
     create::{
         FirstFoundCreatePolicy, 
@@ -47,23 +12,102 @@ use policy::{
         LeastFreeSpaceCreatePolicy,
         LeastUsedSpaceCreatePolicy, 
         RandomCreatePolicy, 
+        RandomFreeSpaceCreatePolicy,
         ExistingPathFirstFoundCreatePolicy,
+        ExistingPathFirstFoundWritableCreatePolicy,
         ExistingPathMostFreeSpaceCreatePolicy,
+        ExistingPathMostFreeSpaceWithFallbackCreatePolicy,
         ExistingPathLeastFreeSpaceCreatePolicy,
         ProportionalFillRandomDistributionCreatePolicy
     }
 };
+use nix::sys::signal::{SigSet, Signal};
+use nix::sys::statvfs::FsFlags;
+
+struct MountArgs {
+    create_policy: String,
+    mkdir_policy: Option<String>,
+    branches_mount_timeout: u64,
+    inodecalc: Option<InodeCalc>,
+    threads: usize,
+    fsname: String,
+    subtype: Option<String>,
+    mergerfs_control: Option<String>,
+    cache_readdir: Option<String>,
+    allow_other: bool,
+    allow_root: bool,
+    nonempty: bool,
+    readonly: bool,
+    nosuid: bool,
+    nodev: bool,
+    mountpoint: PathBuf,
+    branch_specs: Vec<Branch>,
+}
 
-fn parse_args(args: &[String]) -> (String, PathBuf, Vec<(PathBuf, BranchMode)>) {
+fn parse_args(args: &[String]) -> MountArgs {
     let mut create_policy = "ff".to_string();
+    let mut branches_mount_timeout = 0;
+    let mut inodecalc = None;
+    let mut threads = 0;
+    let mut fsname = "mergerfs-rs".to_string();
+    let mut subtype = None;
+    let mut mergerfs_control = None;
+    let mut mkdir_policy = None;
+    let mut cache_readdir = None;
+    let mut allow_other = false;
+    let mut allow_root = false;
+    let mut nonempty = false;
+    let mut readonly = false;
+    let mut nosuid = false;
+    let mut nodev = false;
     let mut i = 1;
     
     // Parse options
     while i < args.len() {
-        if args[i] == "-o" && i + 1 < args.len() {
+        if args[i] == "-s" {
+            threads = 1;
+            i += 1;
+        } else if args[i] == "-o" && i + 1 < args.len() {
             let option = &args[i + 1];
             if let Some(policy_part) = option.strip_prefix("func.create=") {
                 create_policy = policy_part.to_string();
+            } else if let Some(policy_part) = option.strip_prefix("func.mkdir=") {
+                mkdir_policy = Some(policy_part.to_string());
+            } else if let Some(timeout) = option.strip_prefix("branches-mount-timeout=") {
+                branches_mount_timeout = timeout.parse().unwrap_or_else(|_| {
+                    eprintln!("Warning: Invalid branches-mount-timeout '{}', ignoring", timeout);
+                    0
+                });
+            } else if let Some(calc) = option.strip_prefix("inodecalc=").or_else(|| option.strip_prefix("inodes=")) {
+                match InodeCalc::from_str(calc) {
+                    Ok(calc) => inodecalc = Some(calc),
+                    Err(e) => eprintln!("Warning: {}, using default", e),
+                }
+            } else if let Some(count) = option.strip_prefix("threads=") {
+                threads = count.parse().unwrap_or_else(|_| {
+                    eprintln!("Warning: Invalid threads '{}', ignoring", count);
+                    0
+                });
+            } else if let Some(name) = option.strip_prefix("fsname=") {
+                fsname = name.to_string();
+            } else if let Some(name) = option.strip_prefix("subtype=") {
+                subtype = Some(name.to_string());
+            } else if let Some(value) = option.strip_prefix("mergerfs_control=") {
+                mergerfs_control = Some(value.to_string());
+            } else if let Some(value) = option.strip_prefix("cache.readdir=") {
+                cache_readdir = Some(value.to_string());
+            } else if option == "allow_other" {
+                allow_other = true;
+            } else if option == "allow_root" {
+                allow_root = true;
+            } else if option == "nonempty" {
+                nonempty = true;
+            } else if option == "ro" {
+                readonly = true;
+            } else if option == "nosuid" {
+                nosuid = true;
+            } else if option == "nodev" {
+                nodev = true;
             }
             i += 2;
         } else {
@@ -78,26 +122,219 @@ fn parse_args(args: &[String]) -> (String, PathBuf, Vec<(PathBuf, BranchMode)>)
     }
     
     let mountpoint = PathBuf::from(&args[i]);
-    let branch_specs: Vec<(PathBuf, BranchMode)> = args[i + 1..]
-        .iter()
-        .map(|arg| parse_branch_spec(arg))
+    let mut branch_specs: Vec<Branch> = Vec::new();
+    for arg in &args[i + 1..] {
+        match expand_branch_spec(arg) {
+            Ok(branches) => branch_specs.extend(branches),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    
+    MountArgs {
+        create_policy,
+        mkdir_policy,
+        branches_mount_timeout,
+        inodecalc,
+        threads,
+        fsname,
+        subtype,
+        mergerfs_control,
+        cache_readdir,
+        allow_other,
+        allow_root,
+        nonempty,
+        readonly,
+        nosuid,
+        nodev,
+        mountpoint,
+        branch_specs,
+    }
+}
+
+/// Options handed to fuser when mounting; fsname and subtype show up in
+/// /proc/mounts as `<fsname> <mountpoint> fuse.<subtype>`
+///
+/// fuser refuses `allow_other` together with `allow_root`; since the former
+/// already lets root in, it wins when both are given.
+fn mount_options(args: &MountArgs) -> Vec<fuser::MountOption> {
+    let flags = mount_flags(args);
+    let mut options = vec![
+        if flags.contains(FsFlags::ST_RDONLY) { fuser::MountOption::RO } else { fuser::MountOption::RW },
+        fuser::MountOption::FSName(args.fsname.clone()),
+        fuser::MountOption::AutoUnmount,
+    ];
+    if flags.contains(FsFlags::ST_NOSUID) {
+        options.push(fuser::MountOption::NoSuid);
+    }
+    if flags.contains(FsFlags::ST_NODEV) {
+        options.push(fuser::MountOption::NoDev);
+    }
+    if let Some(subtype) = &args.subtype {
+        options.push(fuser::MountOption::Subtype(subtype.clone()));
+    }
+    if args.allow_other {
+        if args.allow_root {
+            eprintln!("Warning: allow_root is implied by allow_other, ignoring it");
+        }
+        options.push(fuser::MountOption::AllowOther);
+    } else if args.allow_root {
+        options.push(fuser::MountOption::AllowRoot);
+    }
+    if args.nonempty {
+        // fuser has no variant for it; passed through to the mount as-is
+        options.push(fuser::MountOption::CUSTOM("nonempty".to_string()));
+    }
+    options
+}
+
+/// statvfs flags requested by the ro, nosuid and nodev options
+///
+/// The kernel reports f_flag from the mount itself, so these become mount
+/// options as well as the pool's configured flags.
+fn mount_flags(args: &MountArgs) -> FsFlags {
+    let mut flags = FsFlags::empty();
+    flags.set(FsFlags::ST_RDONLY, args.readonly);
+    flags.set(FsFlags::ST_NOSUID, args.nosuid);
+    flags.set(FsFlags::ST_NODEV, args.nodev);
+    flags
+}
+
+/// Mount options adjusted to the pool's statvfs flags
+///
+/// A pool with no writable branch reports ST_RDONLY without `-o ro`; the
+/// kernel only reports it if the mount itself is read-only.
+fn apply_statfs_flags(options: Vec<fuser::MountOption>, flags: FsFlags) -> Vec<fuser::MountOption> {
+    if !flags.contains(FsFlags::ST_RDONLY) {
+        return options;
+    }
+    options.into_iter()
+        .map(|option| if option == fuser::MountOption::RW { fuser::MountOption::RO } else { option })
+        .collect()
+}
+
+/// Number of threads the FUSE session loop will use for `requested`
+///
+/// fuser 0.14 dispatches every request from a single loop, so 0 (automatic)
+/// and 1 both mean one thread, and asking for more is an error rather than
+/// being silently ignored.
+fn session_threads(requested: usize) -> Result<usize, String> {
+    if requested > 1 {
+        return Err(format!(
+            "threads={} is not supported, fuser runs a single-threaded session loop (use threads=1 or -s)",
+            requested
+        ));
+    }
+    Ok(1)
+}
+
+/// Signals that trigger a clean unmount
+fn shutdown_signals() -> SigSet {
+    let mut signals = SigSet::empty();
+    signals.add(Signal::SIGINT);
+    signals.add(Signal::SIGTERM);
+    signals
+}
+
+/// Unmount the session when SIGINT or SIGTERM arrives
+///
+/// The signals must already be blocked in every thread (see main) so they
+/// queue up for this thread's sigwait instead of killing the process. The
+/// first signal unmounts, which makes the session loop return and run
+/// destroy; a second one exits immediately in case unmounting hangs.
+fn spawn_shutdown_handler(mut unmounter: fuser::SessionUnmounter) -> std::io::Result<()> {
+    std::thread::Builder::new()
+        .name("shutdown".to_string())
+        .spawn(move || {
+            let signals = shutdown_signals();
+            let mut unmounting = false;
+            loop {
+                let signal = match signals.wait() {
+                    Ok(signal) => signal,
+                    Err(e) => {
+                        tracing::error!(error = %e, "Waiting for shutdown signals failed");
+                        return;
+                    }
+                };
+                if unmounting {
+                    tracing::warn!(signal = %signal, "Received second shutdown signal, exiting without unmounting");
+                    std::process::exit(1);
+                }
+                tracing::info!(signal = %signal, "Received shutdown signal, unmounting");
+                unmounting = true;
+                if let Err(e) = unmounter.unmount() {
+                    tracing::error!(error = %e, "Unmount failed");
+                    std::process::exit(1);
+                }
+            }
+        })
+        .map(|_| ())
+}
+
+/// Expand a branch argument whose path is a shell-style glob (e.g. /mnt/disk*)
+///
+/// Every matching directory becomes a branch, in sorted order, with the
+/// argument's mode and settings. Arguments without glob characters are
+/// parsed as a single branch; a glob matching no directory is an error.
+fn expand_branch_spec(spec: &str) -> Result<Vec<Branch>, String> {
+    let (pattern, suffix) = match spec.find('=') {
+        Some(eq_pos) => (&spec[..eq_pos], Some(&spec[eq_pos + 1..])),
+        None => (spec, None),
+    };
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![parse_branch_spec(spec)]);
+    }
+    
+    let paths = glob::glob(pattern).map_err(|e| format!("Invalid branch glob '{}': {}", pattern, e))?;
+    let branches: Vec<Branch> = paths
+        .filter_map(Result::ok)
+        .filter(|path| path.is_dir())
+        .map(|path| {
+            let path = path.to_string_lossy();
+            match suffix {
+                Some(suffix) => parse_branch_spec(&format!("{}={}", path, suffix)),
+                None => parse_branch_spec(&path),
+            }
+        })
         .collect();
     
-    (create_policy, mountpoint, branch_specs)
+    if branches.is_empty() {
+        return Err(format!("Branch glob '{}' matched no directories", pattern));
+    }
+    Ok(branches)
+}
+
+/// Resolve every branch path to its absolute, symlink-free form
+///
+/// Branches are stored as given on the command line, so a relative path would
+/// otherwise be resolved against whatever the daemon's working directory
+/// happens to be when it is used.
+fn canonicalize_branches(branches: Vec<Branch>) -> Result<Vec<Branch>, String> {
+    branches
+        .into_iter()
+        .map(|mut branch| {
+            branch.path = std::fs::canonicalize(&branch.path).map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => {
+                    format!("Branch directory {} does not exist", branch.path.display())
+                }
+                _ => format!("Cannot resolve branch directory {}: {}", branch.path.display(), e),
+            })?;
+            Ok(branch)
+        })
+        .collect()
 }
 
-fn parse_branch_spec(spec: &str) -> (PathBuf, BranchMode) {
+fn parse_branch_spec(spec: &str) -> Branch {
     // Check for mode suffix using '=' separator
     if let Some(eq_pos) = spec.find('=') {
         let path = &spec[..eq_pos];
         let mode_part = &spec[eq_pos + 1..];
         
-        // Parse mode (may include minfreespace after comma)
-        let mode_str = if let Some(comma_pos) = mode_part.find(',') {
-            &mode_part[..comma_pos]
-        } else {
-            mode_part
-        };
+        // Parse mode, then comma separated settings (e.g. RW,weight=3)
+        let mut settings = mode_part.split(',');
+        let mode_str = settings.next().unwrap_or_default();
         
         let mode = match mode_str.to_uppercase().as_str() {
             "RO" => BranchMode::ReadOnly,
@@ -109,10 +346,19 @@ fn parse_branch_spec(spec: &str) -> (PathBuf, BranchMode) {
             }
         };
         
-        (PathBuf::from(path), mode)
+        let mut branch = Branch::new(PathBuf::from(path), mode);
+        for setting in settings {
+            if let Some(weight) = setting.strip_prefix("weight=") {
+                match weight.parse() {
+                    Ok(weight) => branch = branch.with_weight(weight),
+                    Err(_) => eprintln!("Warning: Invalid branch weight '{}', using 1", weight),
+                }
+            }
+        }
+        branch
     } else {
         // No mode specified, default to RW
-        (PathBuf::from(spec), BranchMode::ReadWrite)
+        Branch::new(PathBuf::from(spec), BranchMode::ReadWrite)
     }
 }
 
@@ -140,18 +386,37 @@ fn main() {
         println!("");
         println!("Options:");
         println!("  -o func.create=POLICY    Create policy (ff|mfs|lfs|epmfs) [default: ff]");
+        println!("  -o func.mkdir=POLICY     Mkdir policy (all or a create policy) [default: all]");
+        println!("  -o branches-mount-timeout=SECS  Wait for branches to be mounted [default: 0]");
+        println!("  -s                       Single-threaded session, same as -o threads=1");
+        println!("  -o threads=N             Session threads; only 0 (automatic) and 1 are supported,");
+        println!("                           as requests are served from one loop [default: 0]");
+        println!("  -o fsname=NAME           Filesystem name shown in /proc/mounts [default: mergerfs-rs]");
+        println!("  -o subtype=NAME          Filesystem subtype, shown as fuse.NAME [default: none]");
+        println!("  -o inodes=MODE           Inode calculation, e.g. devino-hash for NFS exports [default: hybrid-hash]");
+        println!("  -o mergerfs_control=BOOL Serve the /.mergerfs control file [default: true]");
+        println!("  -o allow_other           Let other users access the mount (needs user_allow_other in /etc/fuse.conf)");
+        println!("  -o allow_root            Let root access the mount as well as the mounting user");
+        println!("  -o nonempty              Allow mounting over a non-empty directory");
+        println!("  -o ro                    Mount the pool read-only");
+        println!("  -o nosuid, -o nodev      Ignore setuid bits / device files on the pool");
         println!("");
         println!("Create Policies:");
         println!("  ff    - FirstFound: Create files in first writable branch");
         println!("  mfs   - MostFreeSpace: Create files in branch with most free space");
         println!("  lfs   - LeastFreeSpace: Create files in branch with least free space");
         println!("  epmfs - ExistingPathMostFreeSpace: Create files where parent exists, with most free space");
+        println!("  epff-writable - First branch where the parent exists and can be written to");
+        println!("  epmfs-or-mfs - Like epmfs, but uses mfs when no branch has the parent");
+        println!("  msprand - Random among branches with free space above minfreespace");
         println!("");
         println!("Example:");
         println!("  {} /tmp/merged /tmp/branch1 /tmp/branch2", args[0]);
         println!("  {} -o func.create=mfs /tmp/merged /tmp/branch1 /tmp/branch2", args[0]);
         println!("  {} -o func.create=lfs /tmp/merged /tmp/branch1 /tmp/branch2", args[0]);
         println!("  {} -o func.create=lus /tmp/merged /tmp/branch1 /tmp/branch2", args[0]);
+        println!("  {} -o func.create=pfrd /tmp/merged /tmp/ssd=RW,weight=3 /tmp/hdd", args[0]);
+        println!("  {} /tmp/merged '/mnt/disk*=RW'", args[0]);
         println!("");
         println!("This will mount a union filesystem at /tmp/merged that combines");
         println!("the contents of /tmp/branch1 and /tmp/branch2");
@@ -170,19 +435,37 @@ fn main() {
     }
 
     // Parse command line arguments
-    let (create_policy, mountpoint, branch_specs) = parse_args(&args);
-    
-    let mut branches = Vec::new();
-    for (branch_path, mode) in branch_specs.iter() {
-        if !branch_path.exists() {
-            eprintln!("Error: Branch directory {} does not exist", branch_path.display());
+    let mount_args = parse_args(&args);
+    let options = mount_options(&mount_args);
+    let flags = mount_flags(&mount_args);
+    let MountArgs { create_policy, mkdir_policy, branches_mount_timeout, inodecalc, threads, mergerfs_control, cache_readdir, mountpoint, branch_specs, .. } = mount_args;
+    let threads = match session_threads(threads) {
+        Ok(threads) => threads,
+        Err(e) => {
+            eprintln!("Error: {}", e);
             std::process::exit(1);
         }
-        
-        let branch = Arc::new(Branch::new(branch_path.clone(), *mode));
-        branches.push(branch);
+    };
+    
+    // Give slow mounts (network shares, USB disks) a chance to appear
+    if branches_mount_timeout > 0 {
+        let paths: Vec<PathBuf> = branch_specs.iter().map(|branch| branch.path.clone()).collect();
+        branch::wait_for_branches(
+            &paths,
+            std::time::Duration::from_secs(branches_mount_timeout),
+            std::time::Duration::from_millis(100),
+        );
     }
     
+    // Canonical paths make `/mnt/a` and `/mnt/./a/` the same branch
+    let branches: Vec<Arc<Branch>> = match canonicalize_branches(branch_specs) {
+        Ok(branches) => branch::dedup_branches(branches).into_iter().map(Arc::new).collect(),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    
     if branches.is_empty() {
         eprintln!("Error: At least one branch directory is required");
         std::process::exit(1);
@@ -195,9 +478,12 @@ fn main() {
         "lus" => ("LeastUsedSpace", Box::new(LeastUsedSpaceCreatePolicy::new())),
         "rand" => ("Random", Box::new(RandomCreatePolicy::new())),
         "epff" => ("ExistingPathFirstFound", Box::new(ExistingPathFirstFoundCreatePolicy::new())),
+        "epff-writable" => ("ExistingPathFirstFoundWritable", Box::new(ExistingPathFirstFoundWritableCreatePolicy::new())),
         "epmfs" => ("ExistingPathMostFreeSpace", Box::new(ExistingPathMostFreeSpaceCreatePolicy::new())),
+        "epmfs-or-mfs" => ("ExistingPathMostFreeSpaceWithFallback", Box::new(ExistingPathMostFreeSpaceWithFallbackCreatePolicy::new())),
         "eplfs" => ("ExistingPathLeastFreeSpace", Box::new(ExistingPathLeastFreeSpaceCreatePolicy::new())),
         "pfrd" => ("ProportionalFillRandomDistribution", Box::new(ProportionalFillRandomDistributionCreatePolicy::new())),
+        "msprand" => ("RandomFreeSpace", Box::new(RandomFreeSpaceCreatePolicy::new())),
         _ => ("FirstFound", Box::new(FirstFoundCreatePolicy::new())),
     };
     
@@ -209,29 +495,257 @@ fn main() {
         mountpoint = %mountpoint.display(),
         branches = ?branch_info,
         policy = %create_policy,
+        threads,
         "Starting mergerfs-rs mount"
     );
     
     let file_manager = FileManager::new(branches, policy);
     let fs = MergerFS::new(file_manager);
+    if let Some(calc) = inodecalc {
+        fs.config_manager.config().write().inodecalc = calc;
+    }
+    if let Some(value) = mergerfs_control {
+        if let Err(e) = fs.config_manager.set_option("mergerfs_control", &value) {
+            eprintln!("Warning: {}, keeping the control file", e);
+        }
+    }
+    if let Some(policy) = mkdir_policy {
+        if let Err(e) = fs.config_manager.set_option("func.mkdir", &policy) {
+            eprintln!("Warning: {}, creating directories on all branches", e);
+        }
+    }
+    if let Some(ttl) = cache_readdir {
+        if let Err(e) = fs.config_manager.set_option("cache.readdir", &ttl) {
+            eprintln!("Warning: {}, listing branches on every readdir", e);
+        }
+    }
+    // Absolute link-exdev symlinks point through the mountpoint
+    fs.config_manager.config().write().mountpoint = mountpoint.clone();
+    // statfs reports the flags the pool is mounted with
+    fs.config_manager.config().write().mount_flags = flags;
+    let options = apply_statfs_flags(options, fs.statfs_flags());
     
-    // Mount the filesystem
-    let options = vec![
-        fuser::MountOption::RW,
-        fuser::MountOption::FSName("mergerfs-rs".to_string()),
-        fuser::MountOption::AutoUnmount,
-    ];
+    // Block the shutdown signals before any thread is spawned so every
+    // thread inherits the mask and only the shutdown handler receives them
+    if let Err(e) = shutdown_signals().thread_block() {
+        tracing::warn!(error = %e, "Failed to block shutdown signals");
+    }
+    
+    // The session runs on this thread because the Python test harness expects
+    // the process to block; it serves requests on this thread only (see
+    // session_threads).
+    let mut session = match fuser::Session::new(fs, &mountpoint, &options) {
+        Ok(session) => session,
+        Err(e) => {
+            tracing::error!(error = %e, "Mount failed");
+            eprintln!("Mount failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = spawn_shutdown_handler(session.unmount_callable()) {
+        tracing::warn!(error = %e, "Failed to start shutdown handler, relying on auto_unmount");
+    }
     
-    // For Python tests, we need to use mount2 instead of spawn_mount2
-    // because the Python test harness expects the process to block
-    match fuser::mount2(fs, &mountpoint, &options) {
+    match session.run() {
         Ok(()) => {
+            // Dropping the session runs destroy, which flushes and logs the summary
+            drop(session);
             tracing::info!("Filesystem unmounted successfully");
         }
         Err(e) => {
-            tracing::error!(error = %e, "Mount failed");
-            eprintln!("Mount failed: {}", e);
+            tracing::error!(error = %e, "Session failed");
+            eprintln!("Session failed: {}", e);
             std::process::exit(1);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_single_threaded_flag() {
+        let parsed = parse_args(&args(&["mergerfs-rs", "-s", "-o", "func.create=mfs", "/mnt/pool", "/mnt/a", "/mnt/b"]));
+        assert_eq!(parsed.threads, 1);
+        assert_eq!(parsed.create_policy, "mfs");
+        assert_eq!(parsed.mountpoint, PathBuf::from("/mnt/pool"));
+        assert_eq!(parsed.branch_specs.len(), 2);
+        assert_eq!(session_threads(parsed.threads), Ok(1));
+        
+        // Automatic resolves to the single session loop
+        let parsed = parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(parsed.threads, 0);
+        assert_eq!(session_threads(parsed.threads), Ok(1));
+        
+        // More threads than the loop can serve are refused, not ignored
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "threads=8", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(parsed.threads, 8);
+        let err = session_threads(parsed.threads).unwrap_err();
+        assert!(err.contains("threads=8"), "{}", err);
+    }
+
+    #[test]
+    fn test_branch_weight_option() {
+        let parsed = parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/ssd=RW,weight=3", "/mnt/hdd=NC", "/mnt/usb"]));
+        let weights: Vec<(BranchMode, u64)> = parsed.branch_specs.iter().map(|b| (b.mode, b.weight)).collect();
+        assert_eq!(weights, vec![(BranchMode::ReadWrite, 3), (BranchMode::NoCreate, 1), (BranchMode::ReadWrite, 1)]);
+        assert_eq!(parsed.branch_specs[0].path, PathBuf::from("/mnt/ssd"));
+        
+        assert_eq!(parse_branch_spec("/mnt/a=RW,weight=x").weight, 1);
+    }
+
+    #[test]
+    fn test_shutdown_signals() {
+        let signals = shutdown_signals();
+        assert!(signals.contains(Signal::SIGINT));
+        assert!(signals.contains(Signal::SIGTERM));
+        assert!(!signals.contains(Signal::SIGHUP));
+    }
+
+    #[test]
+    fn test_branch_glob_expansion() {
+        let root = tempfile::TempDir::new().unwrap();
+        for name in ["disk2", "disk1", "disk10", "parity"] {
+            std::fs::create_dir(root.path().join(name)).unwrap();
+        }
+        std::fs::write(root.path().join("disk.txt"), b"not a branch").unwrap();
+        
+        let pattern = format!("{}/disk*=NC,weight=2", root.path().display());
+        let parsed = parse_args(&args(&["mergerfs-rs", "/mnt/pool", &pattern, "/mnt/extra"]));
+        let paths: Vec<PathBuf> = parsed.branch_specs.iter().map(|b| b.path.clone()).collect();
+        assert_eq!(paths, vec![
+            root.path().join("disk1"),
+            root.path().join("disk10"),
+            root.path().join("disk2"),
+            PathBuf::from("/mnt/extra"),
+        ]);
+        // The suffix applies to every match
+        assert!(parsed.branch_specs[..3].iter().all(|b| b.mode == BranchMode::NoCreate && b.weight == 2));
+        assert_eq!(parsed.branch_specs[3].mode, BranchMode::ReadWrite);
+        
+        let unmatched = format!("{}/cache*", root.path().display());
+        assert!(expand_branch_spec(&unmatched).unwrap_err().contains("matched no directories"));
+    }
+
+    #[test]
+    fn test_relative_branch_paths_are_canonicalized() {
+        let cwd = std::env::current_dir().unwrap();
+        let branch_dir = tempfile::TempDir::new_in(&cwd).unwrap();
+        let name = branch_dir.path().file_name().unwrap().to_str().unwrap().to_string();
+        
+        let parsed = parse_args(&args(&["mergerfs-rs", "/mnt/pool", &format!("./{}=NC", name), &format!("{}/../{}", name, name)]));
+        let branches = canonicalize_branches(parsed.branch_specs).unwrap();
+        let expected = std::fs::canonicalize(branch_dir.path()).unwrap();
+        for branch in &branches {
+            assert!(branch.path.is_absolute());
+            assert_eq!(branch.path, expected);
+        }
+        assert_eq!(branches[0].mode, BranchMode::NoCreate);
+        
+        let err = canonicalize_branches(vec![parse_branch_spec("./no-such-branch")]).unwrap_err();
+        assert_eq!(err, "Branch directory ./no-such-branch does not exist");
+    }
+    
+    #[test]
+    fn test_fsname_and_subtype_options() {
+        let parsed = parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(
+            mount_options(&parsed),
+            vec![
+                fuser::MountOption::RW,
+                fuser::MountOption::FSName("mergerfs-rs".to_string()),
+                fuser::MountOption::AutoUnmount,
+            ]
+        );
+        
+        let parsed = parse_args(&args(&[
+            "mergerfs-rs", "-o", "fsname=media", "-o", "subtype=mergerfs", "/mnt/pool", "/mnt/a",
+        ]));
+        let options = mount_options(&parsed);
+        assert!(options.contains(&fuser::MountOption::FSName("media".to_string())));
+        assert!(options.contains(&fuser::MountOption::Subtype("mergerfs".to_string())));
+        assert!(!options.contains(&fuser::MountOption::FSName("mergerfs-rs".to_string())));
+    }
+    
+    #[test]
+    fn test_mergerfs_control_option() {
+        assert_eq!(parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/a"])).mergerfs_control, None);
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "mergerfs_control=false", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(parsed.mergerfs_control.as_deref(), Some("false"));
+        assert_eq!(parsed.branch_specs.len(), 1);
+    }
+    
+    #[test]
+    fn test_mkdir_policy_option() {
+        assert_eq!(parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/a"])).mkdir_policy, None);
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "func.create=mfs", "-o", "func.mkdir=epff", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(parsed.mkdir_policy.as_deref(), Some("epff"));
+        assert_eq!(parsed.create_policy, "mfs");
+    }
+
+    #[test]
+    fn test_cache_readdir_option() {
+        assert_eq!(parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/a"])).cache_readdir, None);
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "cache.readdir=30", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(parsed.cache_readdir.as_deref(), Some("30"));
+    }
+
+    #[test]
+    fn test_allow_other_allow_root_and_nonempty_options() {
+        let parsed = parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/a"]));
+        let options = mount_options(&parsed);
+        assert!(!options.contains(&fuser::MountOption::AllowOther));
+        assert!(!options.contains(&fuser::MountOption::AllowRoot));
+        assert!(!options.contains(&fuser::MountOption::CUSTOM("nonempty".to_string())));
+
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "allow_other", "-o", "nonempty", "/mnt/pool", "/mnt/a"]));
+        assert!(parsed.allow_other && parsed.nonempty && !parsed.allow_root);
+        let options = mount_options(&parsed);
+        assert!(options.contains(&fuser::MountOption::AllowOther));
+        assert!(options.contains(&fuser::MountOption::CUSTOM("nonempty".to_string())));
+        assert_eq!(parsed.branch_specs.len(), 1);
+
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "allow_root", "/mnt/pool", "/mnt/a"]));
+        let options = mount_options(&parsed);
+        assert!(options.contains(&fuser::MountOption::AllowRoot));
+        assert!(!options.contains(&fuser::MountOption::AllowOther));
+
+        // fuser rejects the pair, so allow_other (which covers root) is kept
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "allow_root", "-o", "allow_other", "/mnt/pool", "/mnt/a"]));
+        let options = mount_options(&parsed);
+        assert!(options.contains(&fuser::MountOption::AllowOther));
+        assert!(!options.contains(&fuser::MountOption::AllowRoot));
+    }
+
+    #[test]
+    fn test_ro_nosuid_and_nodev_options() {
+        let parsed = parse_args(&args(&["mergerfs-rs", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(mount_flags(&parsed), FsFlags::empty());
+        assert!(mount_options(&parsed).contains(&fuser::MountOption::RW));
+
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "ro", "-o", "nosuid", "-o", "nodev", "/mnt/pool", "/mnt/a"]));
+        assert_eq!(mount_flags(&parsed), FsFlags::ST_RDONLY | FsFlags::ST_NOSUID | FsFlags::ST_NODEV);
+        let options = mount_options(&parsed);
+        assert!(options.contains(&fuser::MountOption::RO));
+        assert!(!options.contains(&fuser::MountOption::RW));
+        assert!(options.contains(&fuser::MountOption::NoSuid));
+        assert!(options.contains(&fuser::MountOption::NoDev));
+    }
+
+    #[test]
+    fn test_readonly_pool_is_mounted_readonly() {
+        let parsed = parse_args(&args(&["mergerfs-rs", "-o", "nodev", "/mnt/pool", "/mnt/a"]));
+        let options = mount_options(&parsed);
+        assert_eq!(apply_statfs_flags(options.clone(), FsFlags::ST_NODEV), options);
+        
+        let options = apply_statfs_flags(options, FsFlags::ST_RDONLY | FsFlags::ST_NODEV);
+        assert!(options.contains(&fuser::MountOption::RO));
+        assert!(!options.contains(&fuser::MountOption::RW));
+        assert!(options.contains(&fuser::MountOption::NoDev));
+    }
+}
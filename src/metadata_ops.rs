@@ -1,28 +1,139 @@
-use crate::branch::Branch;
+use crate::branch::{Branch, BranchList};
 use crate::policy::{ActionPolicy, PolicyError};
+use parking_lot::RwLock;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
 use tracing;
 
+/// Metadata operations whose action policy can be changed at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataOp {
+    Chmod,
+    Chown,
+    Utimens,
+}
+
+impl MetadataOp {
+    pub const ALL: [MetadataOp; 3] = [MetadataOp::Chmod, MetadataOp::Chown, MetadataOp::Utimens];
+    
+    /// Name of the control file option for this operation (e.g. "func.chmod")
+    pub fn option_name(&self) -> &'static str {
+        match self {
+            MetadataOp::Chmod => "func.chmod",
+            MetadataOp::Chown => "func.chown",
+            MetadataOp::Utimens => "func.utimens",
+        }
+    }
+    
+    pub fn from_option_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|op| op.option_name() == name)
+    }
+}
+
+/// A single timestamp argument to `utimens`, mirroring the kernel's
+/// `UTIME_NOW`/`UTIME_OMIT` semantics. `Set` keeps full nanosecond precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUpdate {
+    Omit,
+    Now,
+    Set(SystemTime),
+}
+
+impl From<SystemTime> for TimeUpdate {
+    fn from(time: SystemTime) -> Self {
+        TimeUpdate::Set(time)
+    }
+}
+
+#[cfg(unix)]
+impl TimeUpdate {
+    fn to_timespec(self) -> nix::sys::time::TimeSpec {
+        use nix::sys::time::TimeSpec;
+        
+        match self {
+            TimeUpdate::Omit => TimeSpec::new(0, nix::libc::UTIME_OMIT),
+            TimeUpdate::Now => TimeSpec::new(0, nix::libc::UTIME_NOW),
+            TimeUpdate::Set(time) => match time.duration_since(std::time::UNIX_EPOCH) {
+                Ok(since_epoch) => TimeSpec::from(since_epoch),
+                Err(e) => -TimeSpec::from(e.duration()),
+            },
+        }
+    }
+}
+
 pub struct MetadataManager {
-    branches: Vec<Arc<Branch>>,
-    action_policy: Box<dyn ActionPolicy>,
+    branches: BranchList,
+    chmod_policy: RwLock<Arc<dyn ActionPolicy>>,
+    chown_policy: RwLock<Arc<dyn ActionPolicy>>,
+    utimens_policy: RwLock<Arc<dyn ActionPolicy>>,
+    // When false, chmod/chown/utimens act on a symlink itself (AT_SYMLINK_NOFOLLOW)
+    follow_symlinks: AtomicBool,
 }
 
 impl MetadataManager {
-    pub fn new(branches: Vec<Arc<Branch>>, action_policy: Box<dyn ActionPolicy>) -> Self {
+    pub fn new(branches: impl Into<BranchList>, action_policy: Box<dyn ActionPolicy>) -> Self {
+        let action_policy: Arc<dyn ActionPolicy> = Arc::from(action_policy);
         Self {
-            branches,
-            action_policy,
+            branches: branches.into(),
+            chmod_policy: RwLock::new(action_policy.clone()),
+            chown_policy: RwLock::new(action_policy.clone()),
+            utimens_policy: RwLock::new(action_policy),
+            follow_symlinks: AtomicBool::new(false),
         }
     }
+    
+    /// Choose whether metadata changes on a symlink go to its target or the link itself
+    pub fn set_follow_symlinks(&self, follow: bool) {
+        self.follow_symlinks.store(follow, Ordering::Relaxed);
+    }
+    
+    pub fn follows_symlinks(&self) -> bool {
+        self.follow_symlinks.load(Ordering::Relaxed)
+    }
+    
+    /// Whether there is something to change at `full_path`; a dangling
+    /// symlink only counts when links are not followed
+    fn target_exists(&self, full_path: &Path) -> bool {
+        if self.follows_symlinks() {
+            full_path.exists()
+        } else {
+            full_path.symlink_metadata().is_ok()
+        }
+    }
+    
+    fn policy_slot(&self, op: MetadataOp) -> &RwLock<Arc<dyn ActionPolicy>> {
+        match op {
+            MetadataOp::Chmod => &self.chmod_policy,
+            MetadataOp::Chown => &self.chown_policy,
+            MetadataOp::Utimens => &self.utimens_policy,
+        }
+    }
+    
+    /// Update the action policy for an operation at runtime
+    pub fn set_action_policy(&self, op: MetadataOp, policy: Box<dyn ActionPolicy>) {
+        let mut slot = self.policy_slot(op).write();
+        tracing::info!("Updating {} policy from {} to {}", op.option_name(), slot.name(), policy.name());
+        *slot = Arc::from(policy);
+    }
+    
+    /// Get the current action policy name for an operation
+    pub fn get_action_policy_name(&self, op: MetadataOp) -> String {
+        self.policy_slot(op).read().name().to_string()
+    }
+    
+    fn select_branches(&self, op: MetadataOp, path: &Path) -> Result<Vec<Arc<Branch>>, PolicyError> {
+        // Clone the policy so the lock isn't held during I/O
+        let policy = self.policy_slot(op).read().clone();
+        policy.select_branches(&self.branches.snapshot(), path)
+    }
 
     /// Change file permissions on all applicable branches
     pub fn chmod(&self, path: &Path, mode: u32) -> Result<(), PolicyError> {
         let _span = tracing::info_span!("metadata::chmod", path = ?path, mode = %format!("{:o}", mode)).entered();
         
-        let target_branches = self.action_policy.select_branches(&self.branches, path)?;
+        let target_branches = self.select_branches(MetadataOp::Chmod, path)?;
         tracing::debug!("Selected {} branches for chmod", target_branches.len());
         
         let mut last_error = None;
@@ -30,7 +141,7 @@ impl MetadataManager {
 
         for branch in target_branches {
             let full_path = branch.full_path(path);
-            if full_path.exists() {
+            if self.target_exists(&full_path) {
                 tracing::debug!("Applying chmod to {:?}", full_path);
                 match self.chmod_single(&full_path, mode) {
                     Ok(_) => success_count += 1,
@@ -54,14 +165,14 @@ impl MetadataManager {
         let _span = tracing::info_span!("metadata::chown", path = ?path, uid, gid).entered();
         
         tracing::debug!("Selecting branches for chown using action policy");
-        let target_branches = self.action_policy.select_branches(&self.branches, path)?;
+        let target_branches = self.select_branches(MetadataOp::Chown, path)?;
         tracing::debug!("Selected {} branches for chown", target_branches.len());
         let mut last_error = None;
         let mut success_count = 0;
 
         for branch in target_branches {
             let full_path = branch.full_path(path);
-            if full_path.exists() {
+            if self.target_exists(&full_path) {
                 match self.chown_single(&full_path, uid, gid) {
                     Ok(_) => success_count += 1,
                     Err(e) => last_error = Some(e),
@@ -77,14 +188,15 @@ impl MetadataManager {
     }
 
     /// Change file timestamps on all applicable branches
-    pub fn utimens(&self, path: &Path, atime: SystemTime, mtime: SystemTime) -> Result<(), PolicyError> {
-        let target_branches = self.action_policy.select_branches(&self.branches, path)?;
+    pub fn utimens(&self, path: &Path, atime: impl Into<TimeUpdate>, mtime: impl Into<TimeUpdate>) -> Result<(), PolicyError> {
+        let (atime, mtime) = (atime.into(), mtime.into());
+        let target_branches = self.select_branches(MetadataOp::Utimens, path)?;
         let mut last_error = None;
         let mut success_count = 0;
 
         for branch in target_branches {
             let full_path = branch.full_path(path);
-            if full_path.exists() {
+            if self.target_exists(&full_path) {
                 match self.utimens_single(&full_path, atime, mtime) {
                     Ok(_) => success_count += 1,
                     Err(e) => last_error = Some(e),
@@ -101,7 +213,7 @@ impl MetadataManager {
 
     /// Get file metadata from first available branch
     pub fn get_metadata(&self, path: &Path) -> Result<FileMetadata, PolicyError> {
-        for branch in &self.branches {
+        for branch in self.branches.snapshot().iter() {
             let full_path = branch.full_path(path);
             if full_path.exists() {
                 return self.get_metadata_single(&full_path);
@@ -113,6 +225,7 @@ impl MetadataManager {
     // Platform-specific implementations
     #[cfg(unix)]
     fn chmod_single(&self, path: &Path, mode: u32) -> Result<(), PolicyError> {
+        use nix::sys::stat::{fchmodat, FchmodatFlags, Mode};
         use std::fs;
         use std::os::unix::fs::PermissionsExt;
 
@@ -129,10 +242,15 @@ impl MetadataManager {
             }
         }
 
-        let metadata = fs::metadata(path)?;
-        let mut permissions = metadata.permissions();
-        permissions.set_mode(mode);
-        fs::set_permissions(path, permissions)?;
+        // Linux symlinks have no mode of their own, so without following
+        // a chmod of a link fails with EOPNOTSUPP and leaves the target alone
+        let flag = if self.follows_symlinks() {
+            FchmodatFlags::FollowSymlink
+        } else {
+            FchmodatFlags::NoFollowSymlink
+        };
+        fchmodat(None, path, Mode::from_bits_truncate(mode), flag)
+            .map_err(|e| PolicyError::IoError(std::io::Error::from_raw_os_error(e as i32)))?;
         Ok(())
     }
 
@@ -149,22 +267,15 @@ impl MetadataManager {
 
     #[cfg(unix)]
     fn chown_single(&self, path: &Path, uid: u32, gid: u32) -> Result<(), PolicyError> {
-        // For Alpine Linux compatibility, we'll use a simplified approach
-        // that doesn't require system calls. In a real implementation, you would
-        // use a proper library like nix for this functionality.
-        
-        // Verify the file exists first
-        if !path.exists() {
-            return Err(PolicyError::IoError(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "File not found"
-            )));
-        }
+        use nix::unistd::{fchownat, FchownatFlags, Gid, Uid};
         
-        // For MUSL/Alpine compatibility, we skip actual chown and just verify the file exists
-        // In a production system, you would implement this using the nix crate or similar
-        #[cfg(debug_assertions)]
-        eprintln!("DEBUG: chown operation simulated for Alpine/MUSL compatibility: {}:{} on {:?}", uid, gid, path);
+        let flag = if self.follows_symlinks() {
+            FchownatFlags::FollowSymlink
+        } else {
+            FchownatFlags::NoFollowSymlink
+        };
+        fchownat(None, path, Some(Uid::from_raw(uid)), Some(Gid::from_raw(gid)), flag)
+            .map_err(|e| PolicyError::IoError(std::io::Error::from_raw_os_error(e as i32)))?;
         Ok(())
     }
 
@@ -178,25 +289,40 @@ impl MetadataManager {
     }
 
     #[cfg(unix)]
-    fn utimens_single(&self, path: &Path, atime: SystemTime, mtime: SystemTime) -> Result<(), PolicyError> {
-        // Use filetime crate for portable timestamp operations
-        use filetime::{FileTime, set_file_times};
-        
-        let atime_ft = FileTime::from_system_time(atime);
-        let mtime_ft = FileTime::from_system_time(mtime);
+    fn utimens_single(&self, path: &Path, atime: TimeUpdate, mtime: TimeUpdate) -> Result<(), PolicyError> {
+        // utimensat keeps nanoseconds and understands UTIME_NOW/UTIME_OMIT;
+        // like mergerfs, apply to a symlink itself unless follow_symlinks is set
+        use nix::sys::stat::{utimensat, UtimensatFlags};
         
-        set_file_times(path, atime_ft, mtime_ft)
-            .map_err(|e| PolicyError::IoError(e))?;
+        let flag = if self.follows_symlinks() {
+            UtimensatFlags::FollowSymlink
+        } else {
+            UtimensatFlags::NoFollowSymlink
+        };
+        utimensat(
+            None,
+            path,
+            &atime.to_timespec(),
+            &mtime.to_timespec(),
+            flag,
+        )
+        .map_err(|e| PolicyError::IoError(std::io::Error::from_raw_os_error(e as i32)))?;
         Ok(())
     }
 
     #[cfg(not(unix))]
-    fn utimens_single(&self, path: &Path, atime: SystemTime, mtime: SystemTime) -> Result<(), PolicyError> {
+    fn utimens_single(&self, path: &Path, atime: TimeUpdate, mtime: TimeUpdate) -> Result<(), PolicyError> {
         // Use filetime crate for portable timestamp operations
         use filetime::{FileTime, set_file_times};
         
-        let atime_ft = FileTime::from_system_time(atime);
-        let mtime_ft = FileTime::from_system_time(mtime);
+        let metadata = std::fs::metadata(path)?;
+        let resolve = |update: TimeUpdate, current: SystemTime| match update {
+            TimeUpdate::Omit => current,
+            TimeUpdate::Now => SystemTime::now(),
+            TimeUpdate::Set(time) => time,
+        };
+        let atime_ft = FileTime::from_system_time(resolve(atime, metadata.accessed()?));
+        let mtime_ft = FileTime::from_system_time(resolve(mtime, metadata.modified()?));
         
         set_file_times(path, atime_ft, mtime_ft)
             .map_err(|e| PolicyError::IoError(e))?;
@@ -337,6 +463,53 @@ mod tests {
         assert!(result.is_ok(), "utimens should succeed on existing file");
     }
 
+    #[test]
+    fn test_utimens_preserves_nanoseconds() {
+        let (_temp_dirs, manager) = setup_test_metadata_manager();
+        
+        let atime = std::time::UNIX_EPOCH + Duration::new(1_600_000_000, 111_222_333);
+        let mtime = std::time::UNIX_EPOCH + Duration::new(1_700_000_000, 123_456_789);
+        manager.utimens(Path::new("test.txt"), atime, mtime).unwrap();
+        
+        let metadata = manager.get_metadata(Path::new("test.txt")).unwrap();
+        assert_eq!(metadata.atime, atime);
+        assert_eq!(metadata.mtime, mtime);
+        
+        // Omitting atime leaves it alone while mtime still changes
+        let later = mtime + Duration::from_nanos(1);
+        manager.utimens(Path::new("test.txt"), TimeUpdate::Omit, later).unwrap();
+        let metadata = manager.get_metadata(Path::new("test.txt")).unwrap();
+        assert_eq!(metadata.atime, atime);
+        assert_eq!(metadata.mtime, later);
+    }
+    
+    #[test]
+    fn test_utimens_single_side_leaves_other_time() {
+        let (temp_dirs, manager) = setup_test_metadata_manager();
+        let path = Path::new("test.txt");
+        let atime = std::time::UNIX_EPOCH + Duration::new(1_500_000_000, 5);
+        let mtime = std::time::UNIX_EPOCH + Duration::new(1_600_000_000, 7);
+        manager.utimens(path, atime, mtime).unwrap();
+        
+        // Only atime: every copy keeps its mtime
+        let new_atime = atime + Duration::from_secs(60);
+        manager.utimens(path, new_atime, TimeUpdate::Omit).unwrap();
+        for dir in &temp_dirs[..2] {
+            let metadata = std::fs::metadata(dir.path().join("test.txt")).unwrap();
+            assert_eq!(metadata.accessed().unwrap(), new_atime);
+            assert_eq!(metadata.modified().unwrap(), mtime);
+        }
+        
+        // Only mtime, set to now: every copy keeps its atime
+        let before = SystemTime::now() - Duration::from_secs(1);
+        manager.utimens(path, TimeUpdate::Omit, TimeUpdate::Now).unwrap();
+        for dir in &temp_dirs[..2] {
+            let metadata = std::fs::metadata(dir.path().join("test.txt")).unwrap();
+            assert_eq!(metadata.accessed().unwrap(), new_atime);
+            assert!(metadata.modified().unwrap() >= before);
+        }
+    }
+    
     #[test]
     fn test_get_metadata() {
         let (_temp_dirs, manager) = setup_test_metadata_manager();
@@ -405,4 +578,93 @@ mod tests {
         let result = manager.chmod(Path::new("partial.txt"), 0o755);
         assert!(result.is_ok(), "chmod should succeed with partial branch coverage");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_switch_chmod_policy_via_config() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        use crate::policy::AllActionPolicy;
+        use std::os::unix::fs::PermissionsExt;
+        
+        let temp1 = TempDir::new().unwrap();
+        let temp2 = TempDir::new().unwrap();
+        std::fs::create_dir(temp1.path().join("dir")).unwrap();
+        std::fs::create_dir(temp2.path().join("dir")).unwrap();
+        std::fs::write(temp1.path().join("dir/file.txt"), "a").unwrap();
+        std::fs::write(temp2.path().join("dir/file.txt"), "b").unwrap();
+        
+        // The second branch allows modifying existing files but not creating new ones
+        let branches = vec![
+            Arc::new(Branch::new(temp1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(temp2.path().to_path_buf(), BranchMode::NoCreate)),
+        ];
+        let manager = Arc::new(MetadataManager::new(branches, Box::new(AllActionPolicy::new())));
+        
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_metadata_manager(&manager);
+        assert_eq!(config_manager.get_option("func.chmod").unwrap(), "all");
+        
+        let mode_of = |dir: &TempDir| {
+            std::fs::metadata(dir.path().join("dir/file.txt")).unwrap().permissions().mode() & 0o777
+        };
+        
+        // With "all" every writable branch holding the path is updated
+        manager.chmod(Path::new("dir/file.txt"), 0o600).unwrap();
+        assert_eq!(mode_of(&temp1), 0o600);
+        assert_eq!(mode_of(&temp2), 0o600);
+        
+        // Switching chmod to "epall" only touches existing paths on create-capable branches
+        config_manager.set_option("user.mergerfs.func.chmod", "epall").unwrap();
+        assert_eq!(manager.get_action_policy_name(MetadataOp::Chmod), "epall");
+        assert_eq!(manager.get_action_policy_name(MetadataOp::Chown), "all");
+        
+        manager.chmod(Path::new("dir/file.txt"), 0o640).unwrap();
+        assert_eq!(mode_of(&temp1), 0o640);
+        assert_eq!(mode_of(&temp2), 0o600);
+        
+        // A path whose parent only exists on one branch is never created elsewhere
+        std::fs::create_dir(temp1.path().join("only1")).unwrap();
+        std::fs::write(temp1.path().join("only1/file.txt"), "c").unwrap();
+        manager.chmod(Path::new("only1/file.txt"), 0o600).unwrap();
+        assert!(!temp2.path().join("only1").exists());
+        
+        assert!(config_manager.set_option("func.chmod", "ff").is_err());
+    }
+
+    #[test]
+    fn test_chmod_symlink_without_following() {
+        use crate::config::create_config;
+        use crate::config_manager::ConfigManager;
+        use std::os::unix::fs::PermissionsExt;
+        
+        let (temp_dirs, manager) = setup_test_metadata_manager();
+        let manager = Arc::new(manager);
+        let target = temp_dirs[0].path().join("target.txt");
+        std::fs::write(&target, "data").unwrap();
+        std::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o644)).unwrap();
+        std::os::unix::fs::symlink("target.txt", temp_dirs[0].path().join("link")).unwrap();
+        let target_mode = || std::fs::metadata(&target).unwrap().permissions().mode() & 0o777;
+        
+        let mut config_manager = ConfigManager::new(create_config());
+        config_manager.set_metadata_manager(&manager);
+        assert_eq!(config_manager.get_option("follow_symlinks").unwrap(), "false");
+        assert!(!manager.follows_symlinks());
+        
+        // The link itself has no mode to change and the target is untouched
+        assert!(manager.chmod(Path::new("link"), 0o600).is_err());
+        assert_eq!(target_mode(), 0o644);
+        
+        // Timestamps land on the link, not the target
+        let target_mtime = std::fs::metadata(&target).unwrap().modified().unwrap();
+        let stamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        manager.utimens(Path::new("link"), stamp, stamp).unwrap();
+        assert_eq!(std::fs::symlink_metadata(temp_dirs[0].path().join("link")).unwrap().modified().unwrap(), stamp);
+        assert_eq!(std::fs::metadata(&target).unwrap().modified().unwrap(), target_mtime);
+        
+        // Following applies the change to the target
+        config_manager.set_option("user.mergerfs.follow_symlinks", "true").unwrap();
+        assert!(manager.follows_symlinks());
+        manager.chmod(Path::new("link"), 0o600).unwrap();
+        assert_eq!(target_mode(), 0o600);
+    }
+}
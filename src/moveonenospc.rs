@@ -1,10 +1,12 @@
 use crate::branch::Branch;
 use crate::policy::{CreatePolicy, PolicyError};
 use crate::config::ConfigRef;
+use crate::errno::{EDQUOT, ENOSPC};
 use std::fs::{File, OpenOptions};
-use std::io::{self, Read, Write};
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use thiserror::Error;
 use tempfile::NamedTempFile;
 use nix::unistd::dup2;
@@ -34,14 +36,72 @@ pub struct MoveResult {
     pub new_path: PathBuf,
 }
 
+/// Counters describing how often moveonenospc has fired
+#[derive(Debug, Default)]
+pub struct MoveOnENOSPCStats {
+    attempted: AtomicU64,
+    succeeded: AtomicU64,
+    failed: AtomicU64,
+    bytes_moved: AtomicU64,
+}
+
+impl MoveOnENOSPCStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Number of migrations attempted
+    pub fn attempted(&self) -> u64 {
+        self.attempted.load(Ordering::Relaxed)
+    }
+    
+    /// Number of migrations that completed
+    pub fn succeeded(&self) -> u64 {
+        self.succeeded.load(Ordering::Relaxed)
+    }
+    
+    /// Number of migrations that failed, including those whose retried write still failed
+    pub fn failed(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+    
+    /// Total bytes copied between branches by successful migrations
+    pub fn bytes_moved(&self) -> u64 {
+        self.bytes_moved.load(Ordering::Relaxed)
+    }
+    
+    fn record_attempt(&self) {
+        self.attempted.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    fn record_success(&self, bytes: u64) {
+        self.succeeded.fetch_add(1, Ordering::Relaxed);
+        self.bytes_moved.fetch_add(bytes, Ordering::Relaxed);
+    }
+    
+    /// Record a failed migration (or a failed write after migrating)
+    pub fn record_failure(&self) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 /// Main struct for handling moveonenospc operations
 pub struct MoveOnENOSPCHandler {
     config: ConfigRef,
+    stats: Arc<MoveOnENOSPCStats>,
 }
 
 impl MoveOnENOSPCHandler {
     pub fn new(config: ConfigRef) -> Self {
-        Self { config }
+        Self {
+            config,
+            stats: Arc::new(MoveOnENOSPCStats::new()),
+        }
+    }
+    
+    /// Get the shared migration counters
+    pub fn stats(&self) -> Arc<MoveOnENOSPCStats> {
+        self.stats.clone()
     }
     
     /// Check if moveonenospc is enabled
@@ -60,9 +120,69 @@ impl MoveOnENOSPCHandler {
         path: &Path,
         current_branch_idx: usize,
         branches: &[Arc<Branch>],
-        _fallback_policy: &dyn CreatePolicy,
+        fallback_policy: &dyn CreatePolicy,
         fd: Option<RawFd>,
     ) -> Result<MoveResult, MoveError> {
+        self.stats.record_attempt();
+        
+        match self.try_move_file(path, current_branch_idx, branches, fallback_policy, fd) {
+            Ok((result, bytes)) => {
+                self.stats.record_success(bytes);
+                Ok(result)
+            }
+            Err(e) => {
+                self.stats.record_failure();
+                Err(e)
+            }
+        }
+    }
+    
+    /// Run `op` on the file's branch, migrating the file and retrying once on ENOSPC
+    ///
+    /// `op` is given the index of the branch to act on. The result is returned
+    /// with the index of the branch the file ended up on.
+    pub fn retry_on_enospc<T, F>(
+        &self,
+        path: &Path,
+        branch_idx: usize,
+        branches: &[Arc<Branch>],
+        fallback_policy: &dyn CreatePolicy,
+        fd: Option<RawFd>,
+        mut op: F,
+    ) -> Result<(T, usize), PolicyError>
+    where
+        F: FnMut(usize) -> Result<T, PolicyError>,
+    {
+        match op(branch_idx) {
+            Err(PolicyError::NoSpace) if self.is_enabled() => {
+                tracing::info!("ENOSPC on branch {} for {:?}, attempting moveonenospc", branch_idx, path);
+                let moved = self
+                    .move_file_on_enospc(path, branch_idx, branches, fallback_policy, fd)
+                    .map_err(|e| {
+                        tracing::error!("moveonenospc failed: {:?}", e);
+                        PolicyError::NoSpace
+                    })?;
+                
+                match op(moved.new_branch_idx) {
+                    Ok(value) => Ok((value, moved.new_branch_idx)),
+                    Err(e) => {
+                        self.stats.record_failure();
+                        Err(e)
+                    }
+                }
+            }
+            result => result.map(|value| (value, branch_idx)),
+        }
+    }
+    
+    fn try_move_file(
+        &self,
+        path: &Path,
+        current_branch_idx: usize,
+        branches: &[Arc<Branch>],
+        _fallback_policy: &dyn CreatePolicy,
+        fd: Option<RawFd>,
+    ) -> Result<(MoveResult, u64), MoveError> {
         tracing::info!("Attempting to move file {:?} from branch {} due to ENOSPC", 
             path, current_branch_idx);
         
@@ -79,14 +199,90 @@ impl MoveOnENOSPCHandler {
             return Err(MoveError::FileNotFound);
         }
         
-        // Filter branches to exclude the current one
-        let available_branches: Vec<Arc<Branch>> = branches.iter()
+        // The destination must hold the whole file and still keep minfreespace
+        let required = std::fs::metadata(&source_path)?.len();
+        let target_branch = self.select_destination(
+            path,
+            current_branch_idx,
+            branches,
+            required,
+            |branch| branch.free_space(),
+        )?;
+        
+        // Find the index of the selected branch in the original array
+        let new_branch_idx = branches.iter()
+            .position(|b| Arc::ptr_eq(b, &target_branch))
+            .ok_or(MoveError::NoSpaceAvailable)?;
+        
+        tracing::info!("Selected target branch {} for file move", new_branch_idx);
+        
+        // Perform the actual file move
+        let bytes = self.move_file_between_branches(
+            path,
+            current_branch,
+            &target_branch,
+            fd,
+        )?;
+        
+        Ok((
+            MoveResult {
+                new_branch_idx,
+                new_path: target_branch.full_path(path),
+            },
+            bytes,
+        ))
+    }
+    
+    /// Pick the branch to migrate a file of `required` bytes to
+    ///
+    /// The current (full) branch is removed from the candidates up front, so
+    /// policies like `ff` that would happily pick it again never see it. Any
+    /// branch whose free space (as reported by `free_space`) is below
+    /// `required + minfreespace` is dropped too, and the configured
+    /// moveonenospc policy chooses among the rest; if nothing is left the
+    /// result is `NoSpaceAvailable` (ENOSPC).
+    pub(crate) fn select_destination<F>(
+        &self,
+        path: &Path,
+        current_branch_idx: usize,
+        branches: &[Arc<Branch>],
+        required: u64,
+        free_space: F,
+    ) -> Result<Arc<Branch>, MoveError>
+    where
+        F: Fn(&Branch) -> io::Result<u64>,
+    {
+        let others: Vec<Arc<Branch>> = branches.iter()
             .enumerate()
             .filter(|(idx, _)| *idx != current_branch_idx)
             .map(|(_, branch)| branch.clone())
             .collect();
+        self.select_among(path, &others, required, free_space)
+    }
+    
+    /// Pick a branch for `required` bytes from `candidates` with the moveonenospc policy
+    pub(crate) fn select_among<F>(
+        &self,
+        path: &Path,
+        candidates: &[Arc<Branch>],
+        required: u64,
+        free_space: F,
+    ) -> Result<Arc<Branch>, MoveError>
+    where
+        F: Fn(&Branch) -> io::Result<u64>,
+    {
+        let needed = required.saturating_add(self.config.read().minfreespace);
+        
+        let available_branches: Vec<Arc<Branch>> = candidates.iter()
+            .filter(|branch| match free_space(branch) {
+                Ok(available) => available >= needed,
+                Err(_) => false,
+            })
+            .cloned()
+            .collect();
         
         if available_branches.is_empty() {
+            tracing::warn!("No branch has {} bytes free to receive {:?}", needed, path);
             return Err(MoveError::NoSpaceAvailable);
         }
         
@@ -99,37 +295,23 @@ impl MoveOnENOSPCHandler {
             });
         
         // Select target branch using the policy
-        let target_branch = policy.select_branch(&available_branches, path)?;
-        
-        // Find the index of the selected branch in the original array
-        let new_branch_idx = branches.iter()
-            .position(|b| Arc::ptr_eq(b, &target_branch))
-            .ok_or(MoveError::NoSpaceAvailable)?;
-        
-        tracing::info!("Selected target branch {} for file move", new_branch_idx);
-        
-        // Perform the actual file move
-        self.move_file_between_branches(
-            path,
-            current_branch,
-            &target_branch,
-            fd,
-        )?;
-        
-        Ok(MoveResult {
-            new_branch_idx,
-            new_path: target_branch.full_path(path),
-        })
+        Ok(policy.select_branch(&available_branches, path)?)
     }
     
-    /// Move a file from one branch to another
+    /// Move a file from one branch to another, returning the number of bytes copied
+    ///
+    /// Branches are usually separate filesystems, where rename(2) fails with
+    /// EXDEV, so the file is never renamed across branches. The data is copied
+    /// into a temporary file next to the destination and synced, the temporary
+    /// file is renamed into place within the destination branch, and the
+    /// directory is synced before the source is removed.
     fn move_file_between_branches(
         &self,
         path: &Path,
         src_branch: &Branch,
         dst_branch: &Branch,
         fd: Option<RawFd>,
-    ) -> Result<(), MoveError> {
+    ) -> Result<u64, MoveError> {
         let src_path = src_branch.full_path(path);
         let dst_path = dst_branch.full_path(path);
         
@@ -152,7 +334,7 @@ impl MoveOnENOSPCHandler {
         let temp_path = temp_file.path().to_path_buf();
         
         // Copy file contents
-        self.copy_file_contents(&src_path, &temp_path)?;
+        let bytes = self.copy_file_contents(&src_path, &temp_path)?;
         
         // Copy file metadata
         self.copy_file_metadata(&src_path, &temp_path)?;
@@ -192,35 +374,34 @@ impl MoveOnENOSPCHandler {
                 .map_err(|e| MoveError::IoError(e.error))?;
         }
         
+        // Make the new name durable before the only other copy goes away
+        if let Some(parent) = dst_path.parent() {
+            File::open(parent)?.sync_all()?;
+        }
+        
         // Remove the original file
         std::fs::remove_file(&src_path)?;
         
         tracing::info!("Successfully moved file from {:?} to {:?}", src_path, dst_path);
         
-        Ok(())
+        Ok(bytes)
     }
     
-    /// Copy file contents from source to destination
-    fn copy_file_contents(&self, src: &Path, dst: &Path) -> Result<(), io::Error> {
-        let mut src_file = File::open(src)?;
-        let mut dst_file = OpenOptions::new()
+    /// Copy file contents from source to destination, returning the number of bytes copied
+    ///
+    /// Reflinks or copy_file_range are used when both branches share a device.
+    fn copy_file_contents(&self, src: &Path, dst: &Path) -> Result<u64, io::Error> {
+        let src_file = File::open(src)?;
+        let dst_file = OpenOptions::new()
             .write(true)
             .truncate(true)
             .open(dst)?;
         
-        // Use a buffer for efficient copying
-        let mut buffer = vec![0u8; 64 * 1024]; // 64KB buffer
-        
-        loop {
-            let bytes_read = src_file.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            dst_file.write_all(&buffer[..bytes_read])?;
-        }
+        let (total, method) = crate::fs_utils::copy_file_data(&src_file, &dst_file)?;
+        tracing::debug!("Copied {} bytes from {:?} using {:?}", total, src, method);
         
         dst_file.sync_all()?;
-        Ok(())
+        Ok(total)
     }
     
     /// Copy file metadata (permissions, ownership, timestamps)
@@ -291,10 +472,6 @@ impl MoveOnENOSPCHandler {
 
 /// Helper function to check if an error is ENOSPC or EDQUOT
 pub fn is_out_of_space_error(error: &io::Error) -> bool {
-    // Use hardcoded constants for MUSL compatibility
-    const ENOSPC: i32 = 28;   // No space left on device
-    const EDQUOT: i32 = 122;  // Disk quota exceeded
-    
     match error.raw_os_error() {
         Some(ENOSPC) => true,
         Some(EDQUOT) => true,
@@ -341,4 +518,255 @@ mod tests {
         assert!(clean & O_EXCL == 0);
         assert!(clean & O_TRUNC == 0);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_moveonenospc_stats_counters() {
+        use crate::branch::BranchMode;
+        use crate::config_manager::ConfigManager;
+        use crate::policy::FirstFoundCreatePolicy;
+        use tempfile::TempDir;
+        
+        let config = config::create_config();
+        let handler = MoveOnENOSPCHandler::new(config.clone());
+        let mut manager = ConfigManager::new(config.clone());
+        manager.register_moveonenospc_stats(handler.stats());
+        
+        let dir1 = TempDir::new().unwrap();
+        let dir2 = TempDir::new().unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(dir1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(dir2.path().to_path_buf(), BranchMode::ReadWrite)),
+        ];
+        let policy = FirstFoundCreatePolicy::new();
+        
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            std::fs::write(dir1.path().join(name), b"0123456789").unwrap();
+            let result = handler
+                .move_file_on_enospc(Path::new(name), 0, &branches, &policy, None)
+                .unwrap();
+            assert_eq!(result.new_branch_idx, 1);
+        }
+        
+        // Moving a file that does not exist counts as a failure
+        assert!(handler
+            .move_file_on_enospc(Path::new("missing.txt"), 0, &branches, &policy, None)
+            .is_err());
+        
+        assert_eq!(manager.get_option("user.mergerfs.stats.moveonenospc.attempted").unwrap(), "4");
+        assert_eq!(manager.get_option("user.mergerfs.stats.moveonenospc.success").unwrap(), "3");
+        assert_eq!(manager.get_option("user.mergerfs.stats.moveonenospc.failed").unwrap(), "1");
+        assert_eq!(manager.get_option("user.mergerfs.stats.moveonenospc.bytes_moved").unwrap(), "30");
+        
+        // Counters cannot be written
+        assert!(matches!(
+            manager.set_option("stats.moveonenospc.success", "0"),
+            Err(crate::config_manager::ConfigError::ReadOnly)
+        ));
+    }
+    
+    #[test]
+    fn test_retry_on_enospc_migrates_and_extends() {
+        use crate::branch::BranchMode;
+        use crate::policy::FirstFoundCreatePolicy;
+        use tempfile::TempDir;
+        
+        let config = config::create_config();
+        config.write().moveonenospc.policy_name = "ff".to_string();
+        let handler = MoveOnENOSPCHandler::new(config.clone());
+        
+        let dir1 = TempDir::new().unwrap();
+        let dir2 = TempDir::new().unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(dir1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(dir2.path().to_path_buf(), BranchMode::ReadWrite)),
+        ];
+        let path = Path::new("grow.bin");
+        std::fs::write(dir1.path().join(path), b"0123456789").unwrap();
+        
+        // Branch 0 behaves as if full; extending succeeds anywhere else
+        let truncate = |idx: usize| -> Result<(), PolicyError> {
+            if idx == 0 {
+                return Err(PolicyError::NoSpace);
+            }
+            OpenOptions::new().write(true).open(branches[idx].full_path(path))?.set_len(4096)?;
+            Ok(())
+        };
+        
+        let policy = FirstFoundCreatePolicy::new();
+        let ((), new_idx) = handler.retry_on_enospc(path, 0, &branches, &policy, None, truncate).unwrap();
+        assert_eq!(new_idx, 1);
+        assert!(!dir1.path().join(path).exists());
+        
+        let moved = std::fs::read(dir2.path().join(path)).unwrap();
+        assert_eq!(moved.len(), 4096);
+        assert_eq!(&moved[..10], b"0123456789");
+        assert_eq!(handler.stats().succeeded(), 1);
+        
+        // With moveonenospc disabled the error is passed straight through
+        config.write().moveonenospc.enabled = false;
+        std::fs::write(dir1.path().join(path), b"again").unwrap();
+        assert!(matches!(
+            handler.retry_on_enospc(path, 0, &branches, &policy, None, truncate),
+            Err(PolicyError::NoSpace)
+        ));
+        assert!(dir1.path().join(path).exists());
+    }
+    
+    #[test]
+    fn test_create_on_full_branch_uses_moveonenospc_policy() {
+        use crate::branch::BranchMode;
+        use crate::file_ops::FileManager;
+        use crate::policy::FirstFoundCreatePolicy;
+        use tempfile::TempDir;
+        
+        let config = config::create_config();
+        config.write().moveonenospc.policy_name = "ff".to_string();
+        let handler = MoveOnENOSPCHandler::new(config);
+        
+        let dirs: Vec<TempDir> = (0..3).map(|_| TempDir::new().unwrap()).collect();
+        let branches: Vec<Arc<Branch>> = dirs.iter()
+            .map(|d| Arc::new(Branch::new(d.path().to_path_buf(), BranchMode::ReadWrite)))
+            .collect();
+        let file_manager = FileManager::new(branches.clone(), Box::new(FirstFoundCreatePolicy::new()));
+        
+        // Writes to the policy-chosen branch fail with ENOSPC
+        let path = Path::new("/new.txt");
+        std::os::unix::fs::symlink("/dev/full", branches[0].full_path(path)).unwrap();
+        
+        // Without a fallback the create fails
+        let err = file_manager.create_file(path, b"payload").unwrap_err();
+        assert!(matches!(err, PolicyError::NoSpace), "{:?}", err);
+        
+        file_manager
+            .create_file_with_enospc_fallback(path, b"payload", |full, candidates| {
+                assert_eq!(full.path, branches[0].path);
+                assert!(candidates.iter().all(|b| b.path != branches[0].path));
+                handler.select_among(path, candidates, 0, |branch| branch.free_space()).ok()
+            })
+            .unwrap();
+        assert_eq!(std::fs::read(branches[1].full_path(path)).unwrap(), b"payload");
+        assert!(!branches[2].full_path(path).exists());
+    }
+    
+    #[test]
+    fn test_first_found_never_picks_the_full_branch() {
+        use crate::branch::BranchMode;
+        use crate::policy::FirstFoundCreatePolicy;
+        use tempfile::TempDir;
+        
+        let config = config::create_config();
+        config.write().moveonenospc.policy_name = "ff".to_string();
+        let handler = MoveOnENOSPCHandler::new(config);
+        
+        let dir1 = TempDir::new().unwrap();
+        let dir2 = TempDir::new().unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(dir1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(dir2.path().to_path_buf(), BranchMode::ReadWrite)),
+        ];
+        let path = Path::new("full.bin");
+        std::fs::write(dir1.path().join(path), b"needs a new home").unwrap();
+        
+        // ff alone would choose branch 0, the one that just ran out of space
+        let policy = FirstFoundCreatePolicy::new();
+        assert_eq!(policy.select_branch(&branches, path).unwrap().path, dir1.path());
+        
+        let result = handler.move_file_on_enospc(path, 0, &branches, &policy, None).unwrap();
+        assert_eq!(result.new_branch_idx, 1);
+        assert!(!dir1.path().join(path).exists());
+        assert_eq!(std::fs::read(dir2.path().join(path)).unwrap(), b"needs a new home");
+        
+        // With only the full branch there is nowhere to go
+        let err = handler
+            .select_destination(path, 0, &branches[..1], 0, |branch| branch.free_space())
+            .unwrap_err();
+        assert!(matches!(err, MoveError::NoSpaceAvailable));
+    }
+    
+    #[test]
+    fn test_destination_must_fit_file_and_minfreespace() {
+        use crate::branch::BranchMode;
+        use tempfile::TempDir;
+        
+        let config = config::create_config();
+        config.write().moveonenospc.policy_name = "ff".to_string();
+        config.write().minfreespace = 1000;
+        let handler = MoveOnENOSPCHandler::new(config.clone());
+        
+        let dirs: Vec<TempDir> = (0..4).map(|_| TempDir::new().unwrap()).collect();
+        let branches: Vec<Arc<Branch>> = dirs.iter()
+            .map(|d| Arc::new(Branch::new(d.path().to_path_buf(), BranchMode::ReadWrite)))
+            .collect();
+        
+        // Branch 1 is too small, branch 2 fits the file but not minfreespace, branch 3 fits both
+        let space: Vec<(PathBuf, u64)> = vec![
+            (dirs[0].path().to_path_buf(), 0),
+            (dirs[1].path().to_path_buf(), 4_000),
+            (dirs[2].path().to_path_buf(), 5_500),
+            (dirs[3].path().to_path_buf(), 6_000),
+        ];
+        let free_space = |branch: &Branch| -> io::Result<u64> {
+            Ok(space.iter().find(|(p, _)| *p == branch.path).unwrap().1)
+        };
+        
+        let target = handler
+            .select_destination(Path::new("big.bin"), 0, &branches, 5_000, free_space)
+            .unwrap();
+        assert_eq!(target.path, dirs[3].path());
+        
+        // Nothing can take a file that large
+        assert!(matches!(
+            handler.select_destination(Path::new("big.bin"), 0, &branches, 5_500, free_space),
+            Err(MoveError::NoSpaceAvailable)
+        ));
+    }
+    
+    #[test]
+    fn test_migration_between_filesystems_copies() {
+        use crate::branch::BranchMode;
+        use crate::policy::FirstFoundCreatePolicy;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        use tempfile::TempDir;
+        
+        let config = config::create_config();
+        config.write().moveonenospc.policy_name = "ff".to_string();
+        let handler = MoveOnENOSPCHandler::new(config);
+        
+        // tmpfs is a separate filesystem from the default temp dir where
+        // available, so a rename between the branches would fail with EXDEV
+        let shm = Path::new("/dev/shm");
+        let dir1 = if shm.is_dir() { TempDir::new_in(shm) } else { TempDir::new() }.unwrap();
+        let dir2 = TempDir::new().unwrap();
+        let cross_device = dir1.path().metadata().unwrap().dev() != dir2.path().metadata().unwrap().dev();
+        
+        let branches = vec![
+            Arc::new(Branch::new(dir1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(dir2.path().to_path_buf(), BranchMode::ReadWrite)),
+        ];
+        std::fs::create_dir(dir1.path().join("sub")).unwrap();
+        let path = Path::new("sub/data.bin");
+        let src = dir1.path().join(path);
+        std::fs::write(&src, vec![7u8; 100_000]).unwrap();
+        std::fs::set_permissions(&src, std::fs::Permissions::from_mode(0o640)).unwrap();
+        filetime::set_file_mtime(&src, filetime::FileTime::from_unix_time(1_000_000, 0)).unwrap();
+        
+        if cross_device {
+            let probe = dir1.path().join("probe");
+            std::fs::write(&probe, b"x").unwrap();
+            let err = std::fs::rename(&probe, dir2.path().join("probe")).unwrap_err();
+            assert_eq!(err.raw_os_error(), Some(18)); // EXDEV
+        }
+        
+        let policy = FirstFoundCreatePolicy::new();
+        let moved = handler.move_file_on_enospc(path, 0, &branches, &policy, None).unwrap();
+        assert_eq!(moved.new_branch_idx, 1);
+        assert_eq!(moved.new_path, dir2.path().join(path));
+        assert!(!src.exists());
+        
+        let metadata = std::fs::metadata(&moved.new_path).unwrap();
+        assert_eq!(std::fs::read(&moved.new_path).unwrap(), vec![7u8; 100_000]);
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o640);
+        assert_eq!(metadata.mtime(), 1_000_000);
+        assert_eq!(handler.stats().bytes_moved(), 100_000);
+    }
+}
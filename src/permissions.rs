@@ -8,18 +8,11 @@ pub const X_OK: i32 = 1;  // Test for execute permission
 pub const W_OK: i32 = 2;  // Test for write permission
 pub const R_OK: i32 = 4;  // Test for read permission
 
-// Standard errno constants
-const EACCES: i32 = 13;
+use crate::errno::EACCES;
 
 #[derive(Debug)]
 pub struct AccessError(pub i32);
 
-impl AccessError {
-    pub fn to_errno(&self) -> i32 {
-        self.0
-    }
-}
-
 /// Check if a user has the requested access permissions for a file
 /// 
 /// This implements POSIX access() semantics:
@@ -3,4 +3,5 @@ pub mod existing_path_all;
 pub mod existing_path_first_found;
 
 pub use all::AllActionPolicy;
-pub use existing_path_all::ExistingPathAllActionPolicy;
\ No newline at end of file
+pub use existing_path_all::ExistingPathAllActionPolicy;
+pub use existing_path_first_found::ExistingPathFirstFoundActionPolicy;
\ No newline at end of file
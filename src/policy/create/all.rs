@@ -0,0 +1,83 @@
+use crate::branch::Branch;
+use crate::policy::create::FirstFoundCreatePolicy;
+use crate::policy::error::PolicyError;
+use crate::policy::traits::CreatePolicy;
+use std::path::Path;
+use std::sync::Arc;
+
+/// All branches create policy, meant for func.mkdir
+///
+/// New directories are made on every writable branch so whichever branch
+/// the file create policy picks later already has the directory. A single
+/// branch is requested as `ff` would pick it.
+pub struct AllCreatePolicy {
+    first_found: FirstFoundCreatePolicy,
+}
+
+impl AllCreatePolicy {
+    pub fn new() -> Self {
+        Self {
+            first_found: FirstFoundCreatePolicy::new(),
+        }
+    }
+}
+
+impl Default for AllCreatePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CreatePolicy for AllCreatePolicy {
+    fn name(&self) -> &'static str {
+        "all"
+    }
+
+    fn select_branch(
+        &self,
+        branches: &[Arc<Branch>],
+        path: &Path,
+    ) -> Result<Arc<Branch>, PolicyError> {
+        self.first_found.select_branch(branches, path)
+    }
+
+    fn select_branches(
+        &self,
+        branches: &[Arc<Branch>],
+        path: &Path,
+    ) -> Result<Vec<Arc<Branch>>, PolicyError> {
+        // Reports EROFS or no branches the same way ff does
+        self.first_found.select_branch(branches, path)?;
+        Ok(branches.iter().filter(|b| b.allows_create()).cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch::BranchMode;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_selects_every_writable_branch() {
+        let temp_dirs: Vec<TempDir> = (0..3).map(|_| TempDir::new().unwrap()).collect();
+        let modes = [BranchMode::ReadWrite, BranchMode::ReadOnly, BranchMode::ReadWrite];
+        let branches: Vec<Arc<Branch>> = temp_dirs
+            .iter()
+            .zip(modes)
+            .map(|(dir, mode)| Arc::new(Branch::new(dir.path().to_path_buf(), mode)))
+            .collect();
+        let policy = AllCreatePolicy::new();
+
+        let selected = policy.select_branches(&branches, Path::new("/dir")).unwrap();
+        let paths: Vec<_> = selected.iter().map(|b| b.path.clone()).collect();
+        assert_eq!(paths, [branches[0].path.clone(), branches[2].path.clone()]);
+        assert_eq!(policy.select_branch(&branches, Path::new("/dir")).unwrap().path, branches[0].path);
+
+        assert!(matches!(
+            policy.select_branches(&branches[1..2], Path::new("/dir")),
+            Err(PolicyError::ReadOnlyFilesystem)
+        ));
+        assert!(matches!(policy.select_branches(&[], Path::new("/dir")), Err(PolicyError::NoBranchesAvailable)));
+    }
+}
@@ -1,7 +1,6 @@
-use crate::branch::{Branch, BranchMode};
+use crate::branch::Branch;
 use crate::policy::error::PolicyError;
 use crate::policy::traits::CreatePolicy;
-use crate::policy::utils::DiskSpace;
 use std::path::Path;
 use std::sync::Arc;
 use tracing::{debug, instrument};
@@ -35,8 +34,8 @@ impl CreatePolicy for ExistingPathFirstFoundCreatePolicy {
         debug!("Checking for parent path: {:?}", parent_path);
 
         for branch in branches {
-            // Skip read-only or no-create branches
-            if matches!(branch.mode, BranchMode::ReadOnly | BranchMode::NoCreate) {
+            // Skip read-only or no-create branches, and those found remounted read-only
+            if !branch.allows_create() {
                 debug!("Skipping branch {:?} - read-only or no-create", branch.path);
                 continue;
             }
@@ -49,7 +48,7 @@ impl CreatePolicy for ExistingPathFirstFoundCreatePolicy {
             }
 
             // Check filesystem info
-            match DiskSpace::for_path(&branch.path) {
+            match branch.disk_space() {
                 Ok(disk_space) => {
                     // TODO: Check minimum free space when configuration support is added
                     // For now, just check if we have any space available
@@ -83,6 +82,7 @@ impl CreatePolicy for ExistingPathFirstFoundCreatePolicy {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::branch::BranchMode;
     use tempfile::TempDir;
     use std::fs;
 
@@ -134,6 +134,25 @@ mod tests {
         assert_eq!(selected.path, temp_dir2.path());
     }
 
+    #[test]
+    fn test_epff_skips_branch_remounted_readonly() {
+        let temp_dir1 = TempDir::new().unwrap();
+        let temp_dir2 = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir1.path().join("parent")).unwrap();
+        fs::create_dir_all(temp_dir2.path().join("parent")).unwrap();
+
+        let branches = vec![
+            Arc::new(Branch::new(temp_dir1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(temp_dir2.path().to_path_buf(), BranchMode::ReadWrite)),
+        ];
+        // Configured RW, but a write just failed with EROFS
+        assert!(branches[0].record_io_error(&std::io::Error::from_raw_os_error(crate::errno::EROFS)));
+
+        let policy = ExistingPathFirstFoundCreatePolicy;
+        let selected = policy.select_branch(&branches, Path::new("/parent/file.txt")).unwrap();
+        assert_eq!(selected.path, temp_dir2.path());
+    }
+
     #[test]
     fn test_epff_no_parent_exists() {
         let temp_dir1 = TempDir::new().unwrap();
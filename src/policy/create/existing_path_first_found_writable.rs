@@ -0,0 +1,122 @@
+use crate::branch::Branch;
+use crate::policy::create::ExistingPathFirstFoundCreatePolicy;
+use crate::policy::error::PolicyError;
+use crate::policy::traits::CreatePolicy;
+use nix::unistd::{access, AccessFlags};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Existing path first found, among parents that can be written to
+///
+/// `epff` goes by the branch mode, so a branch that is configured RW but whose
+/// copy of the parent directory cannot actually be written (a filesystem
+/// mounted read-only underneath us, or a directory without write permission)
+/// is still chosen and the create fails. This policy checks the parent with
+/// access(W_OK) first and picks the first branch that passes.
+pub struct ExistingPathFirstFoundWritableCreatePolicy {
+    existing_path: ExistingPathFirstFoundCreatePolicy,
+}
+
+impl ExistingPathFirstFoundWritableCreatePolicy {
+    pub fn new() -> Self {
+        Self { existing_path: ExistingPathFirstFoundCreatePolicy::new() }
+    }
+}
+
+impl Default for ExistingPathFirstFoundWritableCreatePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CreatePolicy for ExistingPathFirstFoundWritableCreatePolicy {
+    fn name(&self) -> &'static str {
+        "epff-writable"
+    }
+
+    fn select_branch(
+        &self,
+        branches: &[Arc<Branch>],
+        path: &Path,
+    ) -> Result<Arc<Branch>, PolicyError> {
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        let (writable, unwritable): (Vec<Arc<Branch>>, Vec<Arc<Branch>>) = branches
+            .iter()
+            .filter(|branch| branch.full_path(parent).is_dir())
+            .cloned()
+            .partition(|branch| access(&branch.full_path(parent), AccessFlags::W_OK).is_ok());
+
+        if writable.is_empty() {
+            tracing::debug!("No branch has a writable {:?} ({} unwritable copies)", parent, unwritable.len());
+            return Err(if unwritable.is_empty() {
+                PolicyError::PathNotFound
+            } else {
+                PolicyError::ReadOnlyFilesystem
+            });
+        }
+        self.existing_path.select_branch(&writable, path)
+    }
+
+    fn is_path_preserving(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch::BranchMode;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn setup_branches(modes: &[BranchMode]) -> (Vec<TempDir>, Vec<Arc<Branch>>) {
+        let temp_dirs: Vec<TempDir> = modes.iter().map(|_| TempDir::new().unwrap()).collect();
+        let branches = temp_dirs
+            .iter()
+            .zip(modes)
+            .map(|(dir, &mode)| {
+                fs::create_dir(dir.path().join("parent")).unwrap();
+                Arc::new(Branch::new(dir.path().to_path_buf(), mode))
+            })
+            .collect();
+        (temp_dirs, branches)
+    }
+
+    #[test]
+    fn test_skips_readonly_branch_with_parent() {
+        let (_temp_dirs, branches) = setup_branches(&[BranchMode::ReadOnly, BranchMode::ReadWrite, BranchMode::ReadWrite]);
+
+        let policy = ExistingPathFirstFoundWritableCreatePolicy::new();
+        let selected = policy.select_branch(&branches, Path::new("/parent/file.txt")).unwrap();
+        assert_eq!(selected.path, branches[1].path);
+        assert!(policy.is_path_preserving());
+    }
+
+    #[test]
+    fn test_skips_parent_without_write_permission() {
+        if nix::unistd::geteuid().is_root() {
+            // root passes access(W_OK) regardless of the mode bits
+            return;
+        }
+        let (_temp_dirs, branches) = setup_branches(&[BranchMode::ReadWrite, BranchMode::ReadWrite]);
+        let locked = branches[0].full_path(Path::new("/parent"));
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+
+        // epff goes by the branch mode alone and picks the locked directory
+        let path = Path::new("/parent/file.txt");
+        assert_eq!(ExistingPathFirstFoundCreatePolicy::new().select_branch(&branches, path).unwrap().path, branches[0].path);
+        let policy = ExistingPathFirstFoundWritableCreatePolicy::new();
+        assert_eq!(policy.select_branch(&branches, path).unwrap().path, branches[1].path);
+
+        assert!(matches!(policy.select_branch(&branches[..1], path), Err(PolicyError::ReadOnlyFilesystem)));
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_missing_parent_is_path_not_found() {
+        let (_temp_dirs, branches) = setup_branches(&[BranchMode::ReadWrite]);
+        let policy = ExistingPathFirstFoundWritableCreatePolicy::new();
+        assert!(matches!(policy.select_branch(&branches, Path::new("/missing/file.txt")), Err(PolicyError::PathNotFound)));
+    }
+}
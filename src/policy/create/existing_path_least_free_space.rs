@@ -1,6 +1,5 @@
 use crate::branch::Branch;
 use crate::policy::{CreatePolicy, PolicyError};
-use crate::policy::utils::DiskSpace;
 use std::path::Path;
 use std::sync::Arc;
 use tracing::{debug, trace};
@@ -56,7 +55,7 @@ impl CreatePolicy for ExistingPathLeastFreeSpaceCreatePolicy {
                     trace!("Parent exists on branch: {:?}", branch.path);
                     
                     // Get disk space for this branch
-                    match DiskSpace::for_path(&branch.path) {
+                    match branch.disk_space() {
                         Ok(disk_space) => {
                             let available = disk_space.available;
                             trace!("Branch {:?} has {} bytes available", branch.path, available);
@@ -1,7 +1,6 @@
 use crate::branch::Branch;
 use crate::policy::error::PolicyError;
 use crate::policy::traits::CreatePolicy;
-use crate::policy::utils::DiskSpace;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -62,7 +61,7 @@ impl CreatePolicy for ExistingPathMostFreeSpaceCreatePolicy {
             }
             
             // Get filesystem info
-            match DiskSpace::for_path(&branch.path) {
+            match branch.disk_space() {
                 Ok(disk_space) => {
                     // TODO: Check minimum free space when configuration support is added
                     // For now, we don't have a minimum free space requirement
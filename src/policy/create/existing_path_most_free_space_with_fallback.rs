@@ -0,0 +1,116 @@
+use crate::branch::Branch;
+use crate::policy::create::{ExistingPathMostFreeSpaceCreatePolicy, MostFreeSpaceCreatePolicy};
+use crate::policy::error::PolicyError;
+use crate::policy::traits::CreatePolicy;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Existing path most free space, falling back to most free space
+///
+/// Behaves like `epmfs` while some writable branch has the parent directory.
+/// When none does (a new directory tree, or one that only exists on read-only
+/// branches) the path goes to the branch with the most free space and its
+/// parents are created there. Because of that fallback the policy is not
+/// path-preserving for renames.
+pub struct ExistingPathMostFreeSpaceWithFallbackCreatePolicy {
+    existing_path: ExistingPathMostFreeSpaceCreatePolicy,
+    fallback: MostFreeSpaceCreatePolicy,
+}
+
+impl ExistingPathMostFreeSpaceWithFallbackCreatePolicy {
+    pub fn new() -> Self {
+        Self {
+            existing_path: ExistingPathMostFreeSpaceCreatePolicy::new(),
+            fallback: MostFreeSpaceCreatePolicy::new(),
+        }
+    }
+}
+
+impl Default for ExistingPathMostFreeSpaceWithFallbackCreatePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CreatePolicy for ExistingPathMostFreeSpaceWithFallbackCreatePolicy {
+    fn name(&self) -> &'static str {
+        "epmfs-or-mfs"
+    }
+
+    fn select_branch(
+        &self,
+        branches: &[Arc<Branch>],
+        path: &Path,
+    ) -> Result<Arc<Branch>, PolicyError> {
+        match self.existing_path.select_branch(branches, path) {
+            Err(PolicyError::PathNotFound) | Err(PolicyError::ReadOnlyFilesystem) => {
+                tracing::debug!("No writable branch has the parent of {:?}, falling back to mfs", path);
+                self.fallback.select_branch(branches, path)
+            }
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch::BranchMode;
+    use crate::test_utils::SpacePolicyTestSetup;
+    use std::fs;
+
+    #[test]
+    fn test_prefers_existing_parent_over_free_space() {
+        let setup = SpacePolicyTestSetup::new(10, 50, 90);
+        setup.setup_space();
+        let branches = setup.get_branches();
+        fs::create_dir(branches[0].path.join("photos")).unwrap();
+        fs::create_dir(branches[1].path.join("photos")).unwrap();
+
+        let policy = ExistingPathMostFreeSpaceWithFallbackCreatePolicy::new();
+        let selected = policy.select_branch(&branches, Path::new("/photos/a.jpg")).unwrap();
+        assert_eq!(selected.path, branches[1].path);
+    }
+
+    #[test]
+    fn test_falls_back_to_most_free_space() {
+        let setup = SpacePolicyTestSetup::new(10, 90, 50);
+        setup.setup_space();
+        let branches = setup.get_branches();
+
+        // No branch has /new, so epmfs alone would fail with ENOENT
+        let path = Path::new("/new/file.txt");
+        assert!(matches!(
+            ExistingPathMostFreeSpaceCreatePolicy::new().select_branch(&branches, path),
+            Err(PolicyError::PathNotFound)
+        ));
+
+        let policy = ExistingPathMostFreeSpaceWithFallbackCreatePolicy::new();
+        assert_eq!(policy.select_branch(&branches, path).unwrap().path, branches[1].path);
+        assert!(!policy.is_path_preserving());
+    }
+
+    #[test]
+    fn test_parent_only_on_readonly_branch_falls_back() {
+        let setup = SpacePolicyTestSetup::new(90, 10, 50);
+        setup.setup_space();
+        let paths = setup.get_paths();
+        fs::create_dir(paths[0].join("archive")).unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(paths[0].clone(), BranchMode::ReadOnly)),
+            Arc::new(Branch::new(paths[1].clone(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(paths[2].clone(), BranchMode::ReadWrite)),
+        ];
+
+        let policy = ExistingPathMostFreeSpaceWithFallbackCreatePolicy::new();
+        let selected = policy.select_branch(&branches, Path::new("/archive/old.tar")).unwrap();
+        assert_eq!(selected.path, paths[2]);
+
+        // With nothing writable the fallback reports EROFS as well
+        let readonly = vec![branches[0].clone()];
+        assert!(matches!(
+            policy.select_branch(&readonly, Path::new("/archive/old.tar")),
+            Err(PolicyError::ReadOnlyFilesystem)
+        ));
+    }
+}
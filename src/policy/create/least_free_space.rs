@@ -1,16 +1,24 @@
 use crate::branch::Branch;
 use crate::policy::error::PolicyError;
 use crate::policy::traits::CreatePolicy;
-use crate::policy::utils::DiskSpace;
+use crate::policy::utils::TieBreak;
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
 
-pub struct LeastFreeSpaceCreatePolicy;
+pub struct LeastFreeSpaceCreatePolicy {
+    tiebreak: TieBreak,
+}
 
 impl LeastFreeSpaceCreatePolicy {
+    /// Break ties by taking the first tied branch
     pub fn new() -> Self {
-        Self
+        Self::with_tiebreak(TieBreak::First)
+    }
+    
+    /// Break ties between branches with equal free space as `tiebreak` says
+    pub fn with_tiebreak(tiebreak: TieBreak) -> Self {
+        Self { tiebreak }
     }
 }
 
@@ -28,7 +36,7 @@ impl CreatePolicy for LeastFreeSpaceCreatePolicy {
             return Err(PolicyError::NoBranchesAvailable);
         }
         
-        let mut best_branch: Option<Arc<Branch>> = None;
+        let mut tied: Vec<Arc<Branch>> = Vec::new();
         let mut min_free_space = u64::MAX;
         
         for branch in branches {
@@ -36,11 +44,14 @@ impl CreatePolicy for LeastFreeSpaceCreatePolicy {
                 continue;
             }
             
-            match DiskSpace::for_path(&branch.path) {
+            match branch.disk_space() {
                 Ok(disk_space) => {
                     if disk_space.available < min_free_space {
                         min_free_space = disk_space.available;
-                        best_branch = Some(branch.clone());
+                        tied.clear();
+                        tied.push(branch.clone());
+                    } else if disk_space.available == min_free_space && !tied.is_empty() {
+                        tied.push(branch.clone());
                     }
                 }
                 Err(e) => {
@@ -51,6 +62,7 @@ impl CreatePolicy for LeastFreeSpaceCreatePolicy {
             }
         }
         
+        let best_branch = self.tiebreak.pick(&tied);
         best_branch.ok_or_else(|| {
             // Check if all branches are readonly or if we had other errors
             let has_writable = branches.iter().any(|b| b.allows_create());
@@ -64,4 +76,36 @@ impl CreatePolicy for LeastFreeSpaceCreatePolicy {
             }
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::SpacePolicyTestSetup;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_random_tiebreak_spreads_across_equal_branches() {
+        // Branches 0 and 2 tie for the least free space
+        let setup = SpacePolicyTestSetup::new(10, 50, 10);
+        setup.setup_space();
+        let branches = setup.get_branches();
+
+        let first = LeastFreeSpaceCreatePolicy::new();
+        for _ in 0..20 {
+            assert_eq!(first.select_branch(&branches, Path::new("/file")).unwrap().path, branches[0].path);
+        }
+
+        let random = LeastFreeSpaceCreatePolicy::with_tiebreak(TieBreak::Random);
+        let mut counts: HashMap<_, usize> = HashMap::new();
+        for _ in 0..600 {
+            let selected = random.select_branch(&branches, Path::new("/file")).unwrap();
+            *counts.entry(selected.path.clone()).or_default() += 1;
+        }
+        assert!(!counts.contains_key(&branches[1].path));
+        for branch in [&branches[0], &branches[2]] {
+            let count = counts[&branch.path];
+            assert!((200..=400).contains(&count), "{:?} picked {} times", branch.path, count);
+        }
+    }
+}
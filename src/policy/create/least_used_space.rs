@@ -1,7 +1,6 @@
 use crate::branch::Branch;
 use crate::policy::error::PolicyError;
 use crate::policy::traits::CreatePolicy;
-use crate::policy::utils::DiskSpace;
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
@@ -37,7 +36,7 @@ impl CreatePolicy for LeastUsedSpaceCreatePolicy {
                 continue;
             }
             
-            match DiskSpace::for_path(&branch.path) {
+            match branch.disk_space() {
                 Ok(disk_space) => {
                     // Select branch with least used space
                     if disk_space.used < least_used_space {
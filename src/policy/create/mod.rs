@@ -1,19 +1,27 @@
+pub mod all;
 pub mod existing_path_first_found;
+pub mod existing_path_first_found_writable;
 pub mod existing_path_least_free_space;
 pub mod existing_path_most_free_space;
+pub mod existing_path_most_free_space_with_fallback;
 pub mod first_found;
 pub mod least_free_space;
 pub mod least_used_space;
 pub mod most_free_space;
 pub mod pfrd;
 pub mod random;
+pub mod random_free_space;
 
+pub use all::AllCreatePolicy;
 pub use existing_path_first_found::ExistingPathFirstFoundCreatePolicy;
+pub use existing_path_first_found_writable::ExistingPathFirstFoundWritableCreatePolicy;
 pub use existing_path_least_free_space::ExistingPathLeastFreeSpaceCreatePolicy;
 pub use existing_path_most_free_space::ExistingPathMostFreeSpaceCreatePolicy;
+pub use existing_path_most_free_space_with_fallback::ExistingPathMostFreeSpaceWithFallbackCreatePolicy;
 pub use first_found::FirstFoundCreatePolicy;
 pub use least_free_space::LeastFreeSpaceCreatePolicy;
 pub use least_used_space::LeastUsedSpaceCreatePolicy;
 pub use most_free_space::MostFreeSpaceCreatePolicy;
 pub use pfrd::ProportionalFillRandomDistributionCreatePolicy;
-pub use random::RandomCreatePolicy;
\ No newline at end of file
+pub use random::RandomCreatePolicy;
+pub use random_free_space::RandomFreeSpaceCreatePolicy;
\ No newline at end of file
@@ -1,16 +1,24 @@
 use crate::branch::Branch;
 use crate::policy::error::PolicyError;
 use crate::policy::traits::CreatePolicy;
-use crate::policy::utils::DiskSpace;
+use crate::policy::utils::TieBreak;
 use std::io;
 use std::path::Path;
 use std::sync::Arc;
 
-pub struct MostFreeSpaceCreatePolicy;
+pub struct MostFreeSpaceCreatePolicy {
+    tiebreak: TieBreak,
+}
 
 impl MostFreeSpaceCreatePolicy {
+    /// Break ties by taking the first tied branch
     pub fn new() -> Self {
-        Self
+        Self::with_tiebreak(TieBreak::First)
+    }
+    
+    /// Break ties between branches with equal free space as `tiebreak` says
+    pub fn with_tiebreak(tiebreak: TieBreak) -> Self {
+        Self { tiebreak }
     }
 }
 
@@ -31,7 +39,7 @@ impl CreatePolicy for MostFreeSpaceCreatePolicy {
             return Err(PolicyError::NoBranchesAvailable);
         }
         
-        let mut best_branch: Option<Arc<Branch>> = None;
+        let mut tied: Vec<Arc<Branch>> = Vec::new();
         let mut max_free_space = 0u64;
         
         for branch in branches {
@@ -39,12 +47,15 @@ impl CreatePolicy for MostFreeSpaceCreatePolicy {
                 continue;
             }
             
-            match DiskSpace::for_path(&branch.path) {
+            match branch.disk_space() {
                 Ok(disk_space) => {
                     tracing::debug!("Branch {:?} has {} bytes available", branch.path, disk_space.available);
                     if disk_space.available > max_free_space {
                         max_free_space = disk_space.available;
-                        best_branch = Some(branch.clone());
+                        tied.clear();
+                        tied.push(branch.clone());
+                    } else if disk_space.available == max_free_space && !tied.is_empty() {
+                        tied.push(branch.clone());
                     }
                 }
                 Err(e) => {
@@ -55,6 +66,7 @@ impl CreatePolicy for MostFreeSpaceCreatePolicy {
             }
         }
         
+        let best_branch = self.tiebreak.pick(&tied);
         if let Some(ref branch) = best_branch {
             tracing::info!("MFS policy selected branch {:?} with {} bytes free", branch.path, max_free_space);
         }
@@ -72,4 +84,36 @@ impl CreatePolicy for MostFreeSpaceCreatePolicy {
             }
         })
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::SpacePolicyTestSetup;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_random_tiebreak_spreads_across_equal_branches() {
+        // Branches 1 and 2 tie for the most free space
+        let setup = SpacePolicyTestSetup::new(10, 50, 50);
+        setup.setup_space();
+        let branches = setup.get_branches();
+
+        let first = MostFreeSpaceCreatePolicy::new();
+        for _ in 0..20 {
+            assert_eq!(first.select_branch(&branches, Path::new("/file")).unwrap().path, branches[1].path);
+        }
+
+        let random = MostFreeSpaceCreatePolicy::with_tiebreak(TieBreak::Random);
+        let mut counts: HashMap<_, usize> = HashMap::new();
+        for _ in 0..600 {
+            let selected = random.select_branch(&branches, Path::new("/file")).unwrap();
+            *counts.entry(selected.path.clone()).or_default() += 1;
+        }
+        assert!(!counts.contains_key(&branches[0].path));
+        for branch in &branches[1..] {
+            let count = counts[&branch.path];
+            assert!((200..=400).contains(&count), "{:?} picked {} times", branch.path, count);
+        }
+    }
+}
@@ -6,7 +6,8 @@ use std::path::Path;
 use std::sync::Arc;
 
 /// Proportional Fill Random Distribution (PFRD) create policy
-/// Selects branches randomly weighted by their available space
+/// Selects branches randomly weighted by their available space, multiplied
+/// by each branch's configured weight (1 unless set with `weight=N`)
 pub struct ProportionalFillRandomDistributionCreatePolicy;
 
 impl ProportionalFillRandomDistributionCreatePolicy {
@@ -33,7 +34,7 @@ impl CreatePolicy for ProportionalFillRandomDistributionCreatePolicy {
             .enumerate()
             .filter_map(|(idx, branch)| {
                 if branch.allows_create() {
-                    branch.free_space().ok().map(|space| (idx, space))
+                    branch.free_space().ok().map(|space| (idx, space.saturating_mul(branch.weight)))
                 } else {
                     None
                 }
@@ -53,7 +54,7 @@ impl CreatePolicy for ProportionalFillRandomDistributionCreatePolicy {
             return Ok(branches[idx].clone());
         }
 
-        // Extract weights (available space) for weighted random selection
+        // Extract weights (weighted available space) for weighted random selection
         let weights: Vec<u64> = available_branches.iter().map(|(_, space)| *space).collect();
         
         // Log available branches and their weights
@@ -69,7 +70,7 @@ impl CreatePolicy for ProportionalFillRandomDistributionCreatePolicy {
                 let branch_idx = available_branches[selected_idx].0;
                 
                 tracing::debug!(
-                    "PFRD selected branch at index {} with {} bytes free space",
+                    "PFRD selected branch at index {} with weight {}",
                     branch_idx,
                     available_branches[selected_idx].1
                 );
@@ -107,6 +108,40 @@ mod tests {
         assert!(Arc::ptr_eq(&result, &branch));
     }
 
+    #[test]
+    fn test_pfrd_branch_weight_scales_share() {
+        use crate::test_utils::SpacePolicyTestSetup;
+        
+        // Equal free space on both branches, the second weighted three times higher
+        let setup = SpacePolicyTestSetup::new(50, 50, 50);
+        setup.setup_space();
+        let branches: Vec<Arc<Branch>> = setup.branches[..2].iter()
+            .zip([1, 3])
+            .map(|((dir, _), weight)| {
+                Arc::new(Branch::new(dir.path().to_path_buf(), BranchMode::ReadWrite).with_weight(weight))
+            })
+            .collect();
+        for branch in &branches {
+            branch.refresh_disk_space().unwrap();
+        }
+        
+        let policy = ProportionalFillRandomDistributionCreatePolicy::new();
+        const CREATES: usize = 4000;
+        let heavy = (0..CREATES)
+            .filter(|_| {
+                let selected = policy.select_branch(&branches, Path::new("/test.txt")).unwrap();
+                Arc::ptr_eq(&selected, &branches[1])
+            })
+            .count();
+        
+        // Expect 3/4 of the files on the weighted branch
+        let expected = CREATES * 3 / 4;
+        assert!(
+            heavy.abs_diff(expected) < CREATES / 20,
+            "weighted branch got {} of {} creates", heavy, CREATES
+        );
+    }
+
     #[test]
     fn test_pfrd_no_writable_branches() {
         let dir1 = TempDir::new().unwrap();
@@ -0,0 +1,138 @@
+use crate::branch::Branch;
+use crate::policy::error::PolicyError;
+use crate::policy::traits::CreatePolicy;
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Random among branches with free space create policy
+///
+/// Picks uniformly like `rand`, but only among writable branches with more
+/// than `min_free_space` bytes available, so full branches stop receiving
+/// files without the free space skewing the choice as in `pfrd`.
+pub struct RandomFreeSpaceCreatePolicy {
+    min_free_space: u64,
+}
+
+impl RandomFreeSpaceCreatePolicy {
+    /// Skip only branches with no space left at all
+    pub fn new() -> Self {
+        Self::with_min_free_space(0)
+    }
+
+    /// Skip branches with `min_free_space` bytes available or less
+    pub fn with_min_free_space(min_free_space: u64) -> Self {
+        Self { min_free_space }
+    }
+}
+
+impl Default for RandomFreeSpaceCreatePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CreatePolicy for RandomFreeSpaceCreatePolicy {
+    fn name(&self) -> &'static str {
+        "msprand"
+    }
+
+    fn select_branch(
+        &self,
+        branches: &[Arc<Branch>],
+        _path: &Path,
+    ) -> Result<Arc<Branch>, PolicyError> {
+        let writable: Vec<&Arc<Branch>> = branches.iter().filter(|b| b.allows_create()).collect();
+        if writable.is_empty() {
+            return Err(if branches.is_empty() {
+                PolicyError::NoBranchesAvailable
+            } else {
+                PolicyError::ReadOnlyFilesystem
+            });
+        }
+
+        let eligible: Vec<&Arc<Branch>> = writable
+            .into_iter()
+            .filter(|branch| match branch.disk_space() {
+                Ok(space) => space.available > self.min_free_space,
+                Err(e) => {
+                    tracing::warn!("Failed to get disk space for {:?}: {}", branch.path, e);
+                    false
+                }
+            })
+            .collect();
+
+        tracing::debug!("msprand choosing among {} branches above {} bytes free", eligible.len(), self.min_free_space);
+        eligible
+            .choose(&mut thread_rng())
+            .map(|branch| Arc::clone(branch))
+            .ok_or(PolicyError::NoSpace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch::BranchMode;
+    use crate::test_utils::SpacePolicyTestSetup;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_spreads_across_branches_with_space() {
+        // Branch 0 is full; branches 1 and 2 differ in space but are picked alike
+        let setup = SpacePolicyTestSetup::new(0, 10, 90);
+        setup.setup_space();
+        let branches = setup.get_branches();
+        let policy = RandomFreeSpaceCreatePolicy::new();
+
+        let mut counts: HashMap<_, usize> = HashMap::new();
+        for _ in 0..600 {
+            let selected = policy.select_branch(&branches, Path::new("/file")).unwrap();
+            *counts.entry(selected.path.clone()).or_default() += 1;
+        }
+
+        assert!(!counts.contains_key(&branches[0].path));
+        // Uniform over two branches: 300 expected each, far outside pfrd's 1:9 split
+        for branch in &branches[1..] {
+            let count = counts[&branch.path];
+            assert!((200..=400).contains(&count), "{:?} picked {} times", branch.path, count);
+        }
+    }
+
+    #[test]
+    fn test_skips_branches_at_or_below_min_free_space() {
+        let setup = SpacePolicyTestSetup::new(5, 20, 50);
+        setup.setup_space();
+        let branches = setup.get_branches();
+
+        let policy = RandomFreeSpaceCreatePolicy::with_min_free_space(20 * 1024 * 1024);
+        for _ in 0..50 {
+            assert_eq!(policy.select_branch(&branches, Path::new("/file")).unwrap().path, branches[2].path);
+        }
+
+        let policy = RandomFreeSpaceCreatePolicy::with_min_free_space(50 * 1024 * 1024);
+        assert!(matches!(policy.select_branch(&branches, Path::new("/file")), Err(PolicyError::NoSpace)));
+    }
+
+    #[test]
+    fn test_readonly_branches_are_never_chosen() {
+        let setup = SpacePolicyTestSetup::new(90, 10, 50);
+        setup.setup_space();
+        let paths = setup.get_paths();
+        let branches = vec![
+            Arc::new(Branch::new(paths[0].clone(), BranchMode::ReadOnly)),
+            Arc::new(Branch::new(paths[1].clone(), BranchMode::ReadWrite)),
+        ];
+        let policy = RandomFreeSpaceCreatePolicy::new();
+
+        for _ in 0..50 {
+            assert_eq!(policy.select_branch(&branches, Path::new("/file")).unwrap().path, paths[1]);
+        }
+        assert!(matches!(
+            policy.select_branch(&branches[..1], Path::new("/file")),
+            Err(PolicyError::ReadOnlyFilesystem)
+        ));
+        assert!(matches!(policy.select_branch(&[], Path::new("/file")), Err(PolicyError::NoBranchesAvailable)));
+    }
+}
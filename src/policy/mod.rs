@@ -8,26 +8,34 @@ pub mod utils;
 // Re-export commonly used items
 pub use error::PolicyError;
 pub use traits::{ActionPolicy, CreatePolicy, SearchPolicy};
+pub use utils::TieBreak;
 
 // Re-export all policy implementations
 pub use action::AllActionPolicy;
 pub use action::existing_path_all::ExistingPathAllActionPolicy;
-// pub use action::existing_path_first_found::ExistingPathFirstFoundActionPolicy;
+pub use action::ExistingPathFirstFoundActionPolicy;
 
 pub use create::{
+    AllCreatePolicy,
     FirstFoundCreatePolicy,
     LeastFreeSpaceCreatePolicy,
     LeastUsedSpaceCreatePolicy,
     MostFreeSpaceCreatePolicy,
     RandomCreatePolicy,
+    RandomFreeSpaceCreatePolicy,
     ExistingPathFirstFoundCreatePolicy,
+    ExistingPathFirstFoundWritableCreatePolicy,
     ExistingPathMostFreeSpaceCreatePolicy,
+    ExistingPathMostFreeSpaceWithFallbackCreatePolicy,
     ExistingPathLeastFreeSpaceCreatePolicy,
     ProportionalFillRandomDistributionCreatePolicy,
 };
 
 pub use search::{
+    AllSearchPolicy,
+    ExistingPathNewestSearchPolicy,
     FirstFoundSearchPolicy,
+    RandomSearchPolicy,
 };
 
 /// Create a policy instance from its name
@@ -39,9 +47,41 @@ pub fn create_policy_from_name(name: &str) -> Option<Box<dyn CreatePolicy>> {
         "lus" => Some(Box::new(LeastUsedSpaceCreatePolicy::new())),
         "rand" => Some(Box::new(RandomCreatePolicy::new())),
         "epff" => Some(Box::new(ExistingPathFirstFoundCreatePolicy::new())),
+        "epff-writable" => Some(Box::new(ExistingPathFirstFoundWritableCreatePolicy::new())),
         "epmfs" => Some(Box::new(ExistingPathMostFreeSpaceCreatePolicy::new())),
+        "epmfs-or-mfs" => Some(Box::new(ExistingPathMostFreeSpaceWithFallbackCreatePolicy::new())),
         "eplfs" => Some(Box::new(ExistingPathLeastFreeSpaceCreatePolicy::new())),
         "pfrd" => Some(Box::new(ProportionalFillRandomDistributionCreatePolicy::new())),
+        "msprand" => Some(Box::new(RandomFreeSpaceCreatePolicy::new())),
         _ => None,
     }
-}
\ No newline at end of file
+}
+
+/// Create a func.mkdir policy from its name: any create policy, or `all`
+pub fn mkdir_policy_from_name(name: &str) -> Option<Box<dyn CreatePolicy>> {
+    match name {
+        "all" => Some(Box::new(AllCreatePolicy::new())),
+        _ => create_policy_from_name(name),
+    }
+}
+
+/// Create an action policy instance from its name
+pub fn action_policy_from_name(name: &str) -> Option<Box<dyn ActionPolicy>> {
+    match name {
+        "all" => Some(Box::new(AllActionPolicy::new())),
+        "epall" => Some(Box::new(ExistingPathAllActionPolicy::new())),
+        "epff" => Some(Box::new(ExistingPathFirstFoundActionPolicy::new())),
+        _ => None,
+    }
+}
+
+/// Create a search policy instance from its name
+pub fn search_policy_from_name(name: &str) -> Option<Box<dyn SearchPolicy>> {
+    match name {
+        "ff" => Some(Box::new(FirstFoundSearchPolicy::new())),
+        "newest" => Some(Box::new(search::newest::NewestSearchPolicy::new())),
+        "epnewest" => Some(Box::new(ExistingPathNewestSearchPolicy::new())),
+        "rand" => Some(Box::new(RandomSearchPolicy::new())),
+        _ => None,
+    }
+}
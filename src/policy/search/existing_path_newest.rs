@@ -0,0 +1,122 @@
+use crate::branch::Branch;
+use crate::policy::{PolicyError, SearchPolicy};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Existing path newest search policy - of the branches where the path
+/// exists, returns the one whose copy has the newest modification time
+///
+/// Unlike `newest`, the path itself is checked with lstat, so symlinks
+/// (including dangling ones) count as existing copies and are compared by
+/// their own mtime. Copies with equal mtimes resolve to the earliest branch.
+#[derive(Default)]
+pub struct ExistingPathNewestSearchPolicy;
+
+impl ExistingPathNewestSearchPolicy {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl SearchPolicy for ExistingPathNewestSearchPolicy {
+    fn name(&self) -> &'static str {
+        "epnewest"
+    }
+
+    fn search_branches(
+        &self,
+        branches: &[Arc<Branch>],
+        path: &Path,
+    ) -> Result<Vec<Arc<Branch>>, PolicyError> {
+        let mut newest: Option<(&Arc<Branch>, std::time::SystemTime)> = None;
+
+        for branch in branches {
+            // Existing path filter: skip branches without the path
+            let Ok(metadata) = branch.full_path(path).symlink_metadata() else {
+                continue;
+            };
+            let Ok(modified) = metadata.modified() else {
+                continue;
+            };
+            if newest.is_none_or(|(_, time)| modified > time) {
+                newest = Some((branch, modified));
+            }
+        }
+
+        match newest {
+            Some((branch, _)) => Ok(vec![Arc::clone(branch)]),
+            None => Err(PolicyError::NoBranchesAvailable),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch::BranchMode;
+    use filetime::FileTime;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup_test_branches() -> (Vec<TempDir>, Vec<Arc<Branch>>) {
+        let temp_dirs = vec![
+            TempDir::new().unwrap(),
+            TempDir::new().unwrap(),
+            TempDir::new().unwrap(),
+        ];
+
+        let branches = temp_dirs
+            .iter()
+            .map(|dir| Arc::new(Branch::new(dir.path().to_path_buf(), BranchMode::ReadWrite)))
+            .collect();
+
+        (temp_dirs, branches)
+    }
+
+    #[test]
+    fn test_epnewest_picks_newest_existing_copy() {
+        let (_temp_dirs, branches) = setup_test_branches();
+        let policy = ExistingPathNewestSearchPolicy::new();
+
+        for (branch, mtime) in [(&branches[0], 1_000), (&branches[2], 2_000)] {
+            let file_path = branch.full_path(Path::new("replica.txt"));
+            fs::write(&file_path, "data").unwrap();
+            filetime::set_file_mtime(&file_path, FileTime::from_unix_time(mtime, 0)).unwrap();
+        }
+
+        let result = policy.search_branches(&branches, Path::new("replica.txt")).unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].path, branches[2].path);
+    }
+
+    #[test]
+    fn test_epnewest_ties_and_epoch_mtimes() {
+        let (_temp_dirs, branches) = setup_test_branches();
+        let policy = ExistingPathNewestSearchPolicy::new();
+
+        // A copy stamped at the epoch still exists and is selectable
+        for branch in &branches[1..] {
+            let file_path = branch.full_path(Path::new("old.txt"));
+            fs::write(&file_path, "data").unwrap();
+            filetime::set_file_mtime(&file_path, FileTime::from_unix_time(0, 0)).unwrap();
+        }
+
+        let result = policy.search_branches(&branches, Path::new("old.txt")).unwrap();
+        assert_eq!(result[0].path, branches[1].path);
+    }
+
+    #[test]
+    fn test_epnewest_counts_dangling_symlinks() {
+        let (_temp_dirs, branches) = setup_test_branches();
+        let policy = ExistingPathNewestSearchPolicy::new();
+
+        std::os::unix::fs::symlink("/nonexistent/target", branches[1].full_path(Path::new("link"))).unwrap();
+
+        let result = policy.search_branches(&branches, Path::new("link")).unwrap();
+        assert_eq!(result[0].path, branches[1].path);
+        assert!(matches!(
+            policy.search_branches(&branches, Path::new("missing")),
+            Err(PolicyError::NoBranchesAvailable)
+        ));
+    }
+}
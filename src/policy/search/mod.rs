@@ -1,5 +1,10 @@
 pub mod all;
+pub mod existing_path_newest;
 pub mod first_found;
 pub mod newest;
+pub mod random;
 
-pub use first_found::FirstFoundSearchPolicy;
\ No newline at end of file
+pub use all::AllSearchPolicy;
+pub use existing_path_newest::ExistingPathNewestSearchPolicy;
+pub use first_found::FirstFoundSearchPolicy;
+pub use random::RandomSearchPolicy;
@@ -0,0 +1,88 @@
+use crate::branch::Branch;
+use crate::policy::{PolicyError, SearchPolicy};
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Random search policy - returns one randomly chosen branch containing the path
+///
+/// Used for `func.open=rand` to spread reads of replicated files across branches.
+#[derive(Default)]
+pub struct RandomSearchPolicy;
+
+impl RandomSearchPolicy {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl SearchPolicy for RandomSearchPolicy {
+    fn name(&self) -> &'static str {
+        "rand"
+    }
+
+    fn search_branches(
+        &self,
+        branches: &[Arc<Branch>],
+        path: &Path,
+    ) -> Result<Vec<Arc<Branch>>, PolicyError> {
+        let candidates: Vec<&Arc<Branch>> = branches
+            .iter()
+            .filter(|branch| branch.full_path(path).exists())
+            .collect();
+        
+        let mut rng = thread_rng();
+        candidates
+            .choose(&mut rng)
+            .map(|branch| vec![Arc::clone(branch)])
+            .ok_or(PolicyError::NoBranchesAvailable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch::BranchMode;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup_test_branches() -> (Vec<TempDir>, Vec<Arc<Branch>>) {
+        let temp_dirs = vec![
+            TempDir::new().unwrap(),
+            TempDir::new().unwrap(),
+            TempDir::new().unwrap(),
+        ];
+        
+        let branches = temp_dirs
+            .iter()
+            .map(|dir| Arc::new(Branch::new(dir.path().to_path_buf(), BranchMode::ReadWrite)))
+            .collect();
+            
+        (temp_dirs, branches)
+    }
+
+    #[test]
+    fn test_rand_only_returns_branches_with_path() {
+        let (_temp_dirs, branches) = setup_test_branches();
+        let policy = RandomSearchPolicy::new();
+        
+        fs::write(branches[0].full_path(Path::new("file.txt")), "a").unwrap();
+        fs::write(branches[2].full_path(Path::new("file.txt")), "c").unwrap();
+        
+        for _ in 0..50 {
+            let result = policy.search_branches(&branches, Path::new("file.txt")).unwrap();
+            assert_eq!(result.len(), 1);
+            assert_ne!(result[0].path, branches[1].path);
+        }
+    }
+    
+    #[test]
+    fn test_rand_returns_error_when_not_found() {
+        let (_temp_dirs, branches) = setup_test_branches();
+        let policy = RandomSearchPolicy::new();
+        
+        let result = policy.search_branches(&branches, Path::new("missing.txt"));
+        assert!(matches!(result, Err(PolicyError::NoBranchesAvailable)));
+    }
+}
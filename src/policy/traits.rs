@@ -17,6 +17,16 @@ pub trait CreatePolicy: Send + Sync {
     fn is_path_preserving(&self) -> bool {
         false // Default to false, override in path-preserving policies
     }
+    
+    /// Branches a new directory is made on; just the selected branch unless
+    /// the policy spreads directories across branches
+    fn select_branches(
+        &self,
+        branches: &[Arc<Branch>],
+        path: &Path,
+    ) -> Result<Vec<Arc<Branch>>, PolicyError> {
+        Ok(vec![self.select_branch(branches, path)?])
+    }
 }
 
 /// Action policies determine which branch instances to operate on for metadata changes
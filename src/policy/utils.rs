@@ -1,6 +1,27 @@
+use crate::branch::Branch;
+use rand::seq::SliceRandom;
 use std::fs;
 use std::io;
 use std::path::Path;
+use std::sync::Arc;
+
+/// How mfs and lfs choose among branches with exactly the same free space
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TieBreak {
+    #[default]
+    First,  // Earliest tied branch in pool order
+    Random, // Any tied branch, so equal disks share new files
+}
+
+impl TieBreak {
+    /// Pick one of `tied`, given in pool order
+    pub fn pick(self, tied: &[Arc<Branch>]) -> Option<Arc<Branch>> {
+        match self {
+            TieBreak::First => tied.first().cloned(),
+            TieBreak::Random => tied.choose(&mut rand::thread_rng()).cloned(),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DiskSpace {
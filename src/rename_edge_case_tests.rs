@@ -8,6 +8,7 @@ mod tests {
     use crate::branch::{Branch, BranchMode};
     use crate::config::create_config;
     use crate::policy::{AllActionPolicy, FirstFoundSearchPolicy, FirstFoundCreatePolicy};
+    use crate::errno::ToErrno;
     use crate::rename_ops::{RenameManager, RenameError};
     
     #[test]
@@ -42,6 +43,66 @@ mod tests {
         assert_eq!(content, "source content");
     }
     
+    fn two_branch_rename_manager() -> (TempDir, TempDir, RenameManager) {
+        let temp1 = TempDir::new().unwrap();
+        let temp2 = TempDir::new().unwrap();
+        let branches = vec![
+            Arc::new(Branch::new(temp1.path().to_path_buf(), BranchMode::ReadWrite)),
+            Arc::new(Branch::new(temp2.path().to_path_buf(), BranchMode::ReadWrite)),
+        ];
+        let rename_mgr = RenameManager::new(
+            branches,
+            Box::new(AllActionPolicy::new()),
+            Box::new(FirstFoundSearchPolicy),
+            Box::new(FirstFoundCreatePolicy::new()),
+            create_config(),
+        );
+        (temp1, temp2, rename_mgr)
+    }
+    
+    #[test]
+    fn test_rename_file_onto_directory_is_eisdir() {
+        let (temp1, temp2, rename_mgr) = two_branch_rename_manager();
+        
+        // The directory only exists on the other branch
+        fs::write(temp1.path().join("file.txt"), "data").unwrap();
+        fs::create_dir(temp2.path().join("target")).unwrap();
+        
+        let err = rename_mgr.rename(Path::new("file.txt"), Path::new("target")).unwrap_err();
+        assert!(matches!(err, RenameError::IsDirectory));
+        assert_eq!(err.errno(), 21);
+        assert!(temp1.path().join("file.txt").exists());
+        assert!(!temp1.path().join("target").exists());
+        assert!(temp2.path().join("target").is_dir());
+        
+        // And the other way round
+        fs::create_dir(temp1.path().join("dir")).unwrap();
+        let err = rename_mgr.rename(Path::new("dir"), Path::new("file.txt")).unwrap_err();
+        assert_eq!(err.errno(), 20);
+    }
+    
+    #[test]
+    fn test_rename_directory_onto_non_empty_directory() {
+        let (temp1, temp2, rename_mgr) = two_branch_rename_manager();
+        
+        // Empty on the first branch, but with an entry on the second
+        fs::create_dir(temp1.path().join("src")).unwrap();
+        fs::create_dir(temp1.path().join("dest")).unwrap();
+        fs::create_dir(temp2.path().join("dest")).unwrap();
+        fs::write(temp2.path().join("dest/child.txt"), "x").unwrap();
+        
+        let err = rename_mgr.rename(Path::new("src"), Path::new("dest")).unwrap_err();
+        assert!(matches!(err, RenameError::NotEmpty));
+        assert_eq!(err.errno(), 39);
+        assert!(temp1.path().join("src").is_dir());
+        
+        // Once empty everywhere the rename replaces it
+        fs::remove_file(temp2.path().join("dest/child.txt")).unwrap();
+        rename_mgr.rename(Path::new("src"), Path::new("dest")).unwrap();
+        assert!(!temp1.path().join("src").exists());
+        assert!(temp1.path().join("dest").is_dir());
+    }
+    
     #[test]
     fn test_rename_empty_directory() {
         let temp = TempDir::new().unwrap();
@@ -305,4 +366,46 @@ mod tests {
         let result = rename_mgr.rename(old_path, new_path);
         assert!(matches!(result, Err(RenameError::Policy(_))));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_rename_keeps_hard_links() {
+        use std::os::unix::fs::MetadataExt;
+        let (temp1, _temp2, rename_mgr) = two_branch_rename_manager();
+        
+        fs::write(temp1.path().join("a.txt"), "shared").unwrap();
+        fs::hard_link(temp1.path().join("a.txt"), temp1.path().join("b.txt")).unwrap();
+        
+        rename_mgr.rename(Path::new("a.txt"), Path::new("c.txt")).unwrap();
+        
+        let renamed = fs::metadata(temp1.path().join("c.txt")).unwrap();
+        let other = fs::metadata(temp1.path().join("b.txt")).unwrap();
+        assert_eq!(renamed.ino(), other.ino());
+        assert_eq!(renamed.nlink(), 2);
+        
+        // Writes through one name are still visible through the other
+        fs::write(temp1.path().join("c.txt"), "updated").unwrap();
+        assert_eq!(fs::read_to_string(temp1.path().join("b.txt")).unwrap(), "updated");
+    }
+    
+    #[test]
+    fn test_failed_rename_of_hard_linked_copy_is_exdev() {
+        use std::os::unix::fs::MetadataExt;
+        let (temp1, temp2, rename_mgr) = two_branch_rename_manager();
+        
+        fs::write(temp1.path().join("a.txt"), "one").unwrap();
+        fs::create_dir(temp1.path().join("d")).unwrap();
+        fs::write(temp2.path().join("a.txt"), "two").unwrap();
+        fs::hard_link(temp2.path().join("a.txt"), temp2.path().join("b.txt")).unwrap();
+        // The rename into d/ cannot succeed on the second branch
+        fs::write(temp2.path().join("d"), "not a directory").unwrap();
+        
+        let err = rename_mgr.rename(Path::new("a.txt"), Path::new("d/a.txt")).unwrap_err();
+        assert!(matches!(err, RenameError::CrossDevice));
+        assert_eq!(err.errno(), 18);
+        
+        // The first branch is rolled back and the linked copy is left intact
+        assert!(temp1.path().join("a.txt").exists());
+        assert!(!temp1.path().join("d/a.txt").exists());
+        assert_eq!(fs::metadata(temp2.path().join("a.txt")).unwrap().nlink(), 2);
+    }
+}
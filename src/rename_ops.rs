@@ -1,11 +1,11 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::fs;
 use std::io;
 use thiserror::Error;
 use tracing;
 
-use crate::branch::{Branch, BranchMode};
+use crate::branch::{Branch, BranchList, BranchMode};
 use crate::policy::{ActionPolicy, SearchPolicy, CreatePolicy, PolicyError};
 use crate::config::ConfigRef;
 use crate::fs_utils;
@@ -34,6 +34,15 @@ pub enum RenameError {
     #[error("Invalid path")]
     InvalidPath,
     
+    #[error("Destination is a directory")]
+    IsDirectory,
+    
+    #[error("Destination is not a directory")]
+    NotDirectory,
+    
+    #[error("Destination directory not empty")]
+    NotEmpty,
+    
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
     
@@ -42,20 +51,6 @@ pub enum RenameError {
 }
 
 impl RenameError {
-    pub fn to_errno(&self) -> i32 {
-        match self {
-            RenameError::NotFound => 2,           // ENOENT
-            RenameError::PermissionDenied => 13,  // EACCES
-            RenameError::ReadOnly => 30,          // EROFS
-            RenameError::NoSpace => 28,           // ENOSPC
-            RenameError::CrossDevice => 18,       // EXDEV
-            RenameError::DestinationExists => 17, // EEXIST
-            RenameError::InvalidPath => 22,       // EINVAL
-            RenameError::Io(e) => e.raw_os_error().unwrap_or(5), // EIO
-            RenameError::Policy(_) => 5,          // EIO
-        }
-    }
-    
     fn priority(&self) -> u32 {
         match self {
             RenameError::NotFound => 1,
@@ -65,8 +60,11 @@ impl RenameError {
             RenameError::CrossDevice => 5,
             RenameError::DestinationExists => 6,
             RenameError::InvalidPath => 7,
-            RenameError::Io(_) => 8,
-            RenameError::Policy(_) => 9,
+            RenameError::IsDirectory => 8,
+            RenameError::NotDirectory => 9,
+            RenameError::NotEmpty => 10,
+            RenameError::Io(_) => 11,
+            RenameError::Policy(_) => 12,
         }
     }
 }
@@ -89,7 +87,7 @@ fn io_error_to_rename_error(e: io::Error) -> RenameError {
 }
 
 pub struct RenameManager {
-    branches: Vec<Arc<Branch>>,
+    branches: BranchList,
     action_policy: Box<dyn ActionPolicy>,
     search_policy: Box<dyn SearchPolicy>,
     create_policy: Box<dyn CreatePolicy>,
@@ -98,14 +96,14 @@ pub struct RenameManager {
 
 impl RenameManager {
     pub fn new(
-        branches: Vec<Arc<Branch>>,
+        branches: impl Into<BranchList>,
         action_policy: Box<dyn ActionPolicy>,
         search_policy: Box<dyn SearchPolicy>,
         create_policy: Box<dyn CreatePolicy>,
         config: ConfigRef,
     ) -> Self {
         Self {
-            branches,
+            branches: branches.into(),
             action_policy,
             search_policy,
             create_policy,
@@ -113,14 +111,27 @@ impl RenameManager {
         }
     }
     
+    /// Whether renames follow the path-preserving strategy (unless ignorepponrename is set)
+    pub fn uses_path_preserving(&self) -> bool {
+        self.create_policy.is_path_preserving() && !self.config.read().ignore_path_preserving_on_rename
+    }
+    
+    /// Rename `old_path` to `new_path` on every branch holding the source
+    ///
+    /// Each branch is renamed in place with rename(2) and never copied, so
+    /// hard links between names on a branch survive. A source copy whose
+    /// rename fails is normally dropped once another branch succeeds, but a
+    /// hard-linked copy cannot be dropped without losing its other names:
+    /// the branches already renamed are rolled back and the rename fails
+    /// with EXDEV instead.
     pub fn rename(&self, old_path: &Path, new_path: &Path) -> Result<(), RenameError> {
         let _span = tracing::info_span!("rename::rename", old = ?old_path, new = ?new_path).entered();
         tracing::debug!("Starting rename operation");
         
+        self.check_destination(old_path, new_path)?;
+        
         // Determine which strategy to use
-        let config = self.config.read();
-        let use_path_preserving = self.create_policy.is_path_preserving() && 
-                                  !config.ignore_path_preserving_on_rename;
+        let use_path_preserving = self.uses_path_preserving();
         
         let strategy = if use_path_preserving { "path-preserving" } else { "create-path" };
         tracing::info!("Using {} rename strategy", strategy);
@@ -138,12 +149,52 @@ impl RenameManager {
         result
     }
     
+    /// POSIX checks against the union view of the destination
+    ///
+    /// Per-branch renames only see their own branch, so a file could replace
+    /// a directory that exists on another branch, or a directory could land
+    /// on one whose entries live elsewhere. A file onto a directory fails with
+    /// EISDIR, a directory onto a file with ENOTDIR, and a directory onto a
+    /// directory that is non-empty on any branch with ENOTEMPTY.
+    fn check_destination(&self, old_path: &Path, new_path: &Path) -> Result<(), RenameError> {
+        if old_path == new_path {
+            return Ok(());
+        }
+        
+        let first_found = |path: &Path| {
+            self.branches.snapshot().iter()
+                .find_map(|branch| fs::symlink_metadata(branch.full_path(path)).ok())
+        };
+        let (Some(source), Some(dest)) = (first_found(old_path), first_found(new_path)) else {
+            return Ok(());
+        };
+        
+        match (source.is_dir(), dest.is_dir()) {
+            (false, true) => Err(RenameError::IsDirectory),
+            (true, false) => Err(RenameError::NotDirectory),
+            (true, true) if !self.union_dir_is_empty(new_path) => Err(RenameError::NotEmpty),
+            _ => Ok(()),
+        }
+    }
+    
+    /// Whether a directory has no entries on any branch, ignoring whiteout markers
+    fn union_dir_is_empty(&self, path: &Path) -> bool {
+        self.branches.snapshot().iter().all(|branch| {
+            let Ok(entries) = fs::read_dir(branch.full_path(path)) else {
+                return true;
+            };
+            entries.flatten().all(|entry| {
+                entry.file_name().to_string_lossy().starts_with(crate::file_ops::WHITEOUT_PREFIX)
+            })
+        })
+    }
+    
     fn rename_preserve_path(&self, old_path: &Path, new_path: &Path) -> Result<(), RenameError> {
         let _span = tracing::debug_span!("rename::preserve_path", old = ?old_path, new = ?new_path).entered();
         tracing::debug!("Starting path-preserving rename");
         
         // 1. Find branches where source file exists using action policy
-        let source_branches = self.action_policy.select_branches(&self.branches, old_path)?;
+        let source_branches = self.action_policy.select_branches(&self.branches.snapshot(), old_path)?;
         if source_branches.is_empty() {
             return Err(RenameError::NotFound);
         }
@@ -151,9 +202,10 @@ impl RenameManager {
         let mut success = false;
         let mut to_remove = Vec::new();
         let mut last_error = None;
+        let mut renamed = Vec::new();
         
         // 2. For each branch in the pool
-        for branch in &self.branches {
+        for branch in self.branches.snapshot().iter() {
             let new_full_path = branch.full_path(new_path);
             
             // 3. If source doesn't exist on this branch, mark destination for removal
@@ -174,9 +226,14 @@ impl RenameManager {
                 Ok(()) => {
                     tracing::debug!("Rename successful on branch {:?}", branch.path);
                     success = true;
+                    renamed.push((old_full_path, new_full_path));
                 }
                 Err(e) => {
                     tracing::warn!("Rename failed on branch {:?}: {:?}", branch.path, e);
+                    if fs_utils::is_hard_linked(&old_full_path) {
+                        Self::rollback(&renamed);
+                        return Err(RenameError::CrossDevice);
+                    }
                     last_error = Some(io_error_to_rename_error(e));
                     to_remove.push(old_full_path);
                 }
@@ -188,6 +245,14 @@ impl RenameManager {
             return Err(last_error.unwrap_or(RenameError::CrossDevice));
         }
         
+        // A directory renamed on only some branches would split the subtree
+        if let Some(error) = last_error {
+            if Self::is_directory_on_any(&source_branches, old_path) {
+                Self::rollback(&renamed);
+                return Err(error);
+            }
+        }
+        
         // 6. Clean up marked files
         for path in to_remove {
             let _ = fs::remove_file(path);
@@ -201,7 +266,7 @@ impl RenameManager {
         tracing::debug!("Starting create-path rename");
         
         // 1. Find branches where source file exists using action policy
-        let source_branches = self.action_policy.select_branches(&self.branches, old_path)?;
+        let source_branches = self.action_policy.select_branches(&self.branches.snapshot(), old_path)?;
         if source_branches.is_empty() {
             return Err(RenameError::NotFound);
         }
@@ -209,15 +274,16 @@ impl RenameManager {
         // 2. Get target branches for new path's parent using search policy
         // Note: It's OK if parent doesn't exist yet - we'll create it
         let parent_path = new_path.parent().ok_or(RenameError::InvalidPath)?;
-        let target_branches = self.search_policy.search_branches(&self.branches, parent_path)
+        let target_branches = self.search_policy.search_branches(&self.branches.snapshot(), parent_path)
             .unwrap_or_else(|_| Vec::new());
         
         let mut any_success = false;
         let mut to_remove = Vec::new();
         let mut last_error = None;
+        let mut renamed = Vec::new();
         
         // 3. For each branch in the pool
-        for branch in &self.branches {
+        for branch in self.branches.snapshot().iter() {
             let new_full_path = branch.full_path(new_path);
             
             // 4. If source doesn't exist on this branch, mark destination for removal
@@ -252,7 +318,7 @@ impl RenameManager {
                         let mut cloned = false;
                         if let Some(parent) = new_path.parent() {
                             // Look for the parent directory on any branch
-                            for src_branch in &self.branches {
+                            for src_branch in self.branches.snapshot().iter() {
                                 if src_branch.full_path(parent).exists() {
                                     // Clone from this branch
                                     if fs_utils::ensure_parent_cloned(
@@ -289,8 +355,14 @@ impl RenameManager {
             match rename_result {
                 Ok(()) => {
                     any_success = true;
+                    renamed.push((old_full_path, new_full_path));
                 }
                 Err(e) => {
+                    if fs_utils::is_hard_linked(&old_full_path) {
+                        tracing::warn!("Rename of hard-linked {:?} failed: {:?}", old_full_path, e);
+                        Self::rollback(&renamed);
+                        return Err(RenameError::CrossDevice);
+                    }
                     last_error = Some(io_error_to_rename_error(e));
                     to_remove.push(old_full_path);
                 }
@@ -304,13 +376,39 @@ impl RenameManager {
             )));
         }
         
-        // 9. Clean up if any rename succeeded
+        // 9. A directory renamed on only some branches would leave the union
+        // half-renamed, so undo the branches that succeeded
+        if let Some(error) = last_error {
+            if Self::is_directory_on_any(&source_branches, old_path) {
+                Self::rollback(&renamed);
+                return Err(error);
+            }
+        }
+        
+        // 10. Clean up if any rename succeeded
         for path in to_remove {
             let _ = fs::remove_file(path);
         }
         
         Ok(())
     }
+    
+    fn is_directory_on_any(branches: &[Arc<Branch>], path: &Path) -> bool {
+        branches.iter().any(|b| b.full_path(path).is_dir())
+    }
+    
+    /// Undo per-branch renames after a partial failure, newest first
+    fn rollback(renamed: &[(PathBuf, PathBuf)]) {
+        for (old_full_path, new_full_path) in renamed.iter().rev() {
+            match fs::rename(new_full_path, old_full_path) {
+                Ok(()) => tracing::info!("Rolled back rename {:?} -> {:?}", new_full_path, old_full_path),
+                Err(e) => tracing::error!(
+                    "Failed to roll back rename {:?} -> {:?}: {:?}",
+                    new_full_path, old_full_path, e
+                ),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -500,4 +598,44 @@ mod tests {
         assert_eq!(content1, "content1");
         assert_eq!(content2, "content2");
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_rename_directory_rolls_back_on_partial_failure() {
+        let (branches, _temps) = setup_test_branches();
+        
+        // A subtree present on both branches
+        for (idx, branch) in branches.iter().enumerate() {
+            fs::create_dir_all(branch.path.join("src/nested")).unwrap();
+            fs::write(branch.path.join(format!("src/nested/file{}.txt", idx)), "data").unwrap();
+        }
+        // On branch 2 the destination is a non-empty directory, so its rename fails
+        fs::create_dir_all(branches[1].path.join("dst/blocker")).unwrap();
+        
+        let config = create_config();
+        let rename_mgr = RenameManager::new(
+            branches.clone(),
+            Box::new(AllActionPolicy::new()),
+            Box::new(FirstFoundSearchPolicy),
+            Box::new(FirstFoundCreatePolicy),
+            config,
+        );
+        
+        let result = rename_mgr.rename(Path::new("src"), Path::new("dst"));
+        assert!(result.is_err());
+        
+        // Every branch still has the original subtree and nothing moved to dst
+        assert!(branches[0].path.join("src/nested/file0.txt").exists());
+        assert!(branches[1].path.join("src/nested/file1.txt").exists());
+        assert!(!branches[0].path.join("dst").exists());
+        assert!(branches[1].path.join("dst/blocker").exists());
+        
+        // Once the blocker is gone the rename succeeds everywhere
+        fs::remove_dir(branches[1].path.join("dst/blocker")).unwrap();
+        fs::remove_dir(branches[1].path.join("dst")).unwrap();
+        rename_mgr.rename(Path::new("src"), Path::new("dst")).unwrap();
+        assert!(branches[0].path.join("dst/nested/file0.txt").exists());
+        assert!(branches[1].path.join("dst/nested/file1.txt").exists());
+        assert!(!branches[0].path.join("src").exists());
+        assert!(!branches[1].path.join("src").exists());
+    }
+}
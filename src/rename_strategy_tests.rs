@@ -254,6 +254,31 @@ mod tests {
         assert!(branches[0].path.join(new_path).exists());
     }
     
+    #[test]
+    fn test_ignorepponrename_option_flips_strategy() {
+        use crate::config_manager::ConfigManager;
+        
+        let (branches, _temps) = setup_test_environment();
+        let config = create_config();
+        let config_manager = ConfigManager::new(config.clone());
+        let rename_mgr = RenameManager::new(
+            branches,
+            Box::new(AllActionPolicy::new()),
+            Box::new(FirstFoundSearchPolicy),
+            Box::new(MockPathPreservingPolicy { path_preserving: true }),
+            config,
+        );
+        
+        assert_eq!(config_manager.get_option("ignorepponrename").unwrap(), "false");
+        assert!(rename_mgr.uses_path_preserving());
+        
+        config_manager.set_option("user.mergerfs.ignorepponrename", "true").unwrap();
+        assert!(!rename_mgr.uses_path_preserving());
+        
+        config_manager.set_option("ignorepponrename", "false").unwrap();
+        assert!(rename_mgr.uses_path_preserving());
+    }
+    
     #[test]
     fn test_rename_with_cross_device_error() {
         // This test would require mocking filesystem errors, which is complex
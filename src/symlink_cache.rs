@@ -0,0 +1,147 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use parking_lot::Mutex;
+use crate::policy::PolicyError;
+
+/// Default number of symlink targets kept when cache.symlinks is enabled
+pub const DEFAULT_SYMLINK_CACHE_CAPACITY: usize = 4096;
+
+struct CacheState {
+    targets: HashMap<u64, PathBuf>,
+    // Insertion order, used to evict the oldest entry when full
+    order: VecDeque<u64>,
+}
+
+/// Bounded cache of resolved symlink targets keyed by inode (cache.symlinks)
+pub struct SymlinkCache {
+    state: Mutex<CacheState>,
+    capacity: usize,
+}
+
+impl SymlinkCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                targets: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+            capacity: capacity.max(1),
+        }
+    }
+    
+    /// Return the cached target for `ino`, or resolve and cache it
+    pub fn get_or_insert_with<F>(&self, ino: u64, resolve: F) -> Result<PathBuf, PolicyError>
+    where
+        F: FnOnce() -> Result<PathBuf, PolicyError>,
+    {
+        if let Some(target) = self.state.lock().targets.get(&ino) {
+            tracing::debug!("symlink cache hit for inode {}", ino);
+            return Ok(target.clone());
+        }
+        
+        // Resolve without holding the lock
+        let target = resolve()?;
+        
+        let mut state = self.state.lock();
+        if state.targets.insert(ino, target.clone()).is_none() {
+            state.order.push_back(ino);
+            while state.order.len() > self.capacity {
+                if let Some(oldest) = state.order.pop_front() {
+                    state.targets.remove(&oldest);
+                }
+            }
+        }
+        
+        Ok(target)
+    }
+    
+    /// Drop the cached target for an inode
+    pub fn invalidate(&self, ino: u64) {
+        let mut state = self.state.lock();
+        if state.targets.remove(&ino).is_some() {
+            state.order.retain(|&cached| cached != ino);
+        }
+    }
+    
+    /// Drop every cached target
+    pub fn clear(&self) {
+        let mut state = self.state.lock();
+        state.targets.clear();
+        state.order.clear();
+    }
+    
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().targets.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::branch::{Branch, BranchMode};
+    use crate::file_ops::FileManager;
+    use crate::policy::FirstFoundCreatePolicy;
+    use std::cell::Cell;
+    use std::path::Path;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_repeated_readlink_hits_cache_and_rewrite_invalidates() {
+        let temp_dir = TempDir::new().unwrap();
+        let branch = Arc::new(Branch::new(temp_dir.path().to_path_buf(), BranchMode::ReadWrite));
+        let file_manager = FileManager::new(vec![branch.clone()], Box::new(FirstFoundCreatePolicy::new()));
+        
+        let link = Path::new("/link");
+        file_manager.create_symlink(link, Path::new("first-target")).unwrap();
+        
+        // Count how often the FileManager is actually consulted
+        let lookups = Cell::new(0);
+        let resolve = || {
+            lookups.set(lookups.get() + 1);
+            file_manager.read_symlink(link)
+        };
+        
+        let cache = SymlinkCache::new(16);
+        const INO: u64 = 42;
+        for _ in 0..10 {
+            assert_eq!(cache.get_or_insert_with(INO, resolve).unwrap(), PathBuf::from("first-target"));
+        }
+        assert_eq!(lookups.get(), 1);
+        
+        // Rewrite the symlink (unlink + symlink) and invalidate as the FUSE layer does
+        std::fs::remove_file(branch.full_path(link)).unwrap();
+        file_manager.create_symlink(link, Path::new("second-target")).unwrap();
+        cache.invalidate(INO);
+        
+        assert_eq!(cache.get_or_insert_with(INO, resolve).unwrap(), PathBuf::from("second-target"));
+        assert_eq!(lookups.get(), 2);
+    }
+
+    #[test]
+    fn test_cache_is_bounded() {
+        let cache = SymlinkCache::new(3);
+        for ino in 0..10u64 {
+            cache.get_or_insert_with(ino, || Ok(PathBuf::from(format!("t{}", ino)))).unwrap();
+        }
+        assert_eq!(cache.len(), 3);
+        
+        // Oldest entries were evicted, newest kept
+        let calls = Cell::new(0);
+        cache.get_or_insert_with(9, || { calls.set(calls.get() + 1); Ok(PathBuf::from("x")) }).unwrap();
+        cache.get_or_insert_with(0, || { calls.set(calls.get() + 1); Ok(PathBuf::from("t0")) }).unwrap();
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_errors_are_not_cached() {
+        let cache = SymlinkCache::new(4);
+        assert!(cache.get_or_insert_with(1, || Err(PolicyError::PathNotFound)).is_err());
+        assert_eq!(cache.len(), 0);
+        
+        cache.get_or_insert_with(1, || Ok(PathBuf::from("t"))).unwrap();
+        cache.invalidate(1);
+        assert_eq!(cache.len(), 0);
+    }
+}
@@ -26,30 +26,6 @@ pub enum XattrError {
     Io(#[from] std::io::Error),
 }
 
-impl XattrError {
-    pub fn errno(&self) -> i32 {
-        // Standard errno constants compatible with MUSL
-        const ENOATTR: i32 = 61;  // No such attribute (Linux)
-        const ENODATA: i32 = 61;  // No data available (alias for ENOATTR)
-        const EPERM: i32 = 1;     // Operation not permitted
-        const ENAMETOOLONG: i32 = 36; // File name too long
-        const E2BIG: i32 = 7;     // Argument list too long
-        const ENOTSUP: i32 = 95;  // Not supported
-        const EINVAL: i32 = 22;   // Invalid argument
-        const EIO: i32 = 5;       // I/O error
-        
-        match self {
-            XattrError::NotFound => ENOATTR,
-            XattrError::PermissionDenied => EPERM,
-            XattrError::NameTooLong => ENAMETOOLONG,
-            XattrError::ValueTooLarge => E2BIG,
-            XattrError::NotSupported => ENOTSUP,
-            XattrError::InvalidArgument => EINVAL,
-            XattrError::Io(_) => EIO,
-        }
-    }
-}
-
 #[derive(Debug, Clone, Copy)]
 pub enum XattrFlags {
     Create,  // XATTR_CREATE - fail if exists
@@ -1,8 +1,8 @@
 use super::{XattrError, XattrFlags, PolicyRV};
-use crate::branch::Branch;
-use crate::policy::{ActionPolicy, SearchPolicy};
+use crate::branch::BranchList;
+use crate::policy::{ActionPolicy, AllSearchPolicy, SearchPolicy};
+use std::collections::HashSet;
 use std::path::Path;
-use std::sync::Arc;
 use xattr;
 use tracing;
 
@@ -13,8 +13,16 @@ pub trait XattrOperations {
     fn remove_xattr(&self, path: &Path, name: &str) -> Result<(), XattrError>;
 }
 
+/// Extended attribute operations across the branches of the pool
+///
+/// Set and remove go to every branch their action policy selects, while
+/// get and list read one copy. The first branch returned by the getxattr
+/// policy is the authoritative copy: reads come from it, and when a set or
+/// remove succeeds on some branches but fails on that one, its error is
+/// the one reported. With the default ff search policy that is always the
+/// first branch holding the file, so reads don't jump between copies.
 pub struct XattrManager {
-    pub branches: Vec<Arc<Branch>>,
+    pub branches: BranchList,
     pub getxattr_policy: Box<dyn SearchPolicy>,
     pub setxattr_policy: Box<dyn ActionPolicy>,
     pub listxattr_policy: Box<dyn SearchPolicy>,
@@ -23,14 +31,14 @@ pub struct XattrManager {
 
 impl XattrManager {
     pub fn new(
-        branches: Vec<Arc<Branch>>,
+        branches: impl Into<BranchList>,
         getxattr_policy: Box<dyn SearchPolicy>,
         setxattr_policy: Box<dyn ActionPolicy>,
         listxattr_policy: Box<dyn SearchPolicy>,
         removexattr_policy: Box<dyn ActionPolicy>,
     ) -> Self {
         Self {
-            branches,
+            branches: branches.into(),
             getxattr_policy,
             setxattr_policy,
             listxattr_policy,
@@ -43,7 +51,7 @@ impl XattrManager {
         
         // Use search policy to find file
         tracing::debug!("Searching for file using getxattr policy");
-        let branches = match self.getxattr_policy.search_branches(&self.branches, path) {
+        let branches = match self.getxattr_policy.search_branches(&self.branches.snapshot(), path) {
             Ok(branches) => branches,
             Err(_) => return Err(XattrError::NotFound),
         };
@@ -74,7 +82,7 @@ impl XattrManager {
         }
         
         // Use action policy to get target branches
-        let branches = match self.setxattr_policy.select_branches(&self.branches, path) {
+        let branches = match self.setxattr_policy.select_branches(&self.branches.snapshot(), path) {
             Ok(branches) => branches,
             Err(_) => return Err(XattrError::NotFound),
         };
@@ -94,7 +102,7 @@ impl XattrManager {
     
     pub fn list_xattr(&self, path: &Path) -> Result<Vec<String>, XattrError> {
         // Use search policy to find file
-        let branches = match self.listxattr_policy.search_branches(&self.branches, path) {
+        let branches = match self.listxattr_policy.search_branches(&self.branches.snapshot(), path) {
             Ok(branches) => branches,
             Err(_) => return Err(XattrError::NotFound),
         };
@@ -108,6 +116,42 @@ impl XattrManager {
         self.list_xattr_from_path(&full_path)
     }
     
+    /// List the attribute names of every copy of `path`, each name once
+    ///
+    /// Used instead of `list_xattr` with xattr.union, so copies whose
+    /// attributes have diverged all show up. Names keep the order of the
+    /// first copy that has them, in branch order. A copy that can't be
+    /// listed is skipped; the first error is returned only if none can.
+    pub fn list_xattr_union(&self, path: &Path) -> Result<Vec<String>, XattrError> {
+        let branches = match AllSearchPolicy::new().search_branches(&self.branches.snapshot(), path) {
+            Ok(branches) => branches,
+            Err(_) => return Err(XattrError::NotFound),
+        };
+        
+        let mut names = Vec::new();
+        let mut seen = HashSet::new();
+        let mut first_error = None;
+        let mut listed = false;
+        for branch in &branches {
+            match self.list_xattr_from_path(&branch.full_path(path)) {
+                Ok(branch_names) => {
+                    listed = true;
+                    names.extend(branch_names.into_iter().filter(|name| seen.insert(name.clone())));
+                }
+                Err(e) => {
+                    tracing::debug!("Skipping xattrs of {:?} on {:?}: {:?}", path, branch.path, e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        
+        match (listed, first_error) {
+            (false, Some(e)) => Err(e),
+            (false, None) => Err(XattrError::NotFound),
+            (true, _) => Ok(names),
+        }
+    }
+    
     pub fn remove_xattr(&self, path: &Path, name: &str) -> Result<(), XattrError> {
         // Block removing mergerfs special attributes
         if name.starts_with("user.mergerfs.") {
@@ -115,7 +159,7 @@ impl XattrManager {
         }
         
         // Use action policy
-        let branches = match self.removexattr_policy.select_branches(&self.branches, path) {
+        let branches = match self.removexattr_policy.select_branches(&self.branches.snapshot(), path) {
             Ok(branches) => branches,
             Err(_) => return Err(XattrError::NotFound),
         };
@@ -223,7 +267,7 @@ impl XattrManager {
         
         // Mixed results - check if target branch had an error
         // Use getxattr policy to find the "authoritative" branch
-        if let Ok(branches) = self.getxattr_policy.search_branches(&self.branches, path) {
+        if let Ok(branches) = self.getxattr_policy.search_branches(&self.branches.snapshot(), path) {
             if let Some(target_branch) = branches.first() {
                 let target_path = target_branch.path.to_string_lossy().to_string();
                 
@@ -256,6 +300,7 @@ mod tests {
     use crate::policy::{FirstFoundSearchPolicy, AllActionPolicy};
     use tempfile::TempDir;
     use std::fs;
+    use std::sync::Arc;
     
     fn create_test_manager() -> (Vec<TempDir>, XattrManager) {
         let temp1 = TempDir::new().unwrap();
@@ -283,7 +328,7 @@ mod tests {
         
         // Create a test file
         let test_path = Path::new("test.txt");
-        let full_path = manager.branches[0].full_path(test_path);
+        let full_path = manager.branches.snapshot()[0].full_path(test_path);
         fs::write(&full_path, b"test content").unwrap();
         
         // Set an xattr
@@ -313,7 +358,7 @@ mod tests {
         
         // Create a test file
         let test_path = Path::new("test.txt");
-        let full_path = manager.branches[0].full_path(test_path);
+        let full_path = manager.branches.snapshot()[0].full_path(test_path);
         fs::write(&full_path, b"test content").unwrap();
         
         // Try to set a mergerfs special attribute
@@ -24,7 +24,7 @@ impl MergerfsXattrHandler {
     
     fn get_basepath(&self, path: &Path) -> Result<Vec<u8>, XattrError> {
         // Find which branch contains the file
-        for branch in &self.file_manager.branches {
+        for branch in self.file_manager.branches().iter() {
             let full_path = branch.full_path(path);
             if full_path.exists() {
                 return Ok(branch.path.to_string_lossy().as_bytes().to_vec());
@@ -40,7 +40,7 @@ impl MergerfsXattrHandler {
     
     fn get_fullpath(&self, path: &Path) -> Result<Vec<u8>, XattrError> {
         // Find the full path to the actual file
-        for branch in &self.file_manager.branches {
+        for branch in self.file_manager.branches().iter() {
             let full_path = branch.full_path(path);
             if full_path.exists() {
                 return Ok(full_path.to_string_lossy().as_bytes().to_vec());
@@ -53,7 +53,7 @@ impl MergerfsXattrHandler {
         let mut all_paths = Vec::new();
         let mut found_any = false;
         
-        for branch in &self.file_manager.branches {
+        for branch in self.file_manager.branches().iter() {
             let full_path = branch.full_path(path);
             if full_path.exists() {
                 if found_any {
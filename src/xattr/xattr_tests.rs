@@ -35,7 +35,7 @@ fn test_xattr_create_replace_flags() {
     
     // Create test file in first branch
     let test_path = Path::new("test.txt");
-    let full_path = manager.branches[0].full_path(test_path);
+    let full_path = manager.branches.snapshot()[0].full_path(test_path);
     fs::write(&full_path, b"test content").unwrap();
     
     let attr_name = "user.test_attr";
@@ -70,7 +70,7 @@ fn test_xattr_all_action_policy() {
     // Create test file in multiple branches
     let test_path = Path::new("test.txt");
     for i in 0..2 {  // Only writable branches
-        let full_path = manager.branches[i].full_path(test_path);
+        let full_path = manager.branches.snapshot()[i].full_path(test_path);
         fs::write(&full_path, format!("content{}", i)).unwrap();
     }
     
@@ -81,13 +81,13 @@ fn test_xattr_all_action_policy() {
     
     // Verify xattr exists on both writable branches
     for i in 0..2 {
-        let full_path = manager.branches[i].full_path(test_path);
+        let full_path = manager.branches.snapshot()[i].full_path(test_path);
         let value = xattr::get(&full_path, attr_name).unwrap().unwrap();
         assert_eq!(value, attr_value);
     }
     
     // Verify readonly branch doesn't have it
-    let readonly_path = manager.branches[2].full_path(test_path);
+    let readonly_path = manager.branches.snapshot()[2].full_path(test_path);
     assert!(!readonly_path.exists());
 }
 
@@ -125,7 +125,7 @@ fn test_xattr_multiple_attributes() {
     
     // Create test file
     let test_path = Path::new("test.txt");
-    let full_path = manager.branches[0].full_path(test_path);
+    let full_path = manager.branches.snapshot()[0].full_path(test_path);
     fs::write(&full_path, b"test content").unwrap();
     
     // Set multiple attributes
@@ -180,7 +180,7 @@ fn test_xattr_policy_rv_mixed_results() {
     
     // Create test file only in first branch
     let test_path = Path::new("test.txt");
-    let full_path = manager.branches[0].full_path(test_path);
+    let full_path = manager.branches.snapshot()[0].full_path(test_path);
     fs::write(&full_path, b"test content").unwrap();
     
     // Set xattr - should only succeed on first branch
@@ -193,7 +193,7 @@ fn test_xattr_policy_rv_mixed_results() {
     assert_eq!(value, attr_value);
     
     // Second branch shouldn't have the file or attribute
-    let full_path2 = manager.branches[1].full_path(test_path);
+    let full_path2 = manager.branches.snapshot()[1].full_path(test_path);
     assert!(!full_path2.exists());
 }
 
@@ -203,7 +203,7 @@ fn test_xattr_large_values() {
     
     // Create test file
     let test_path = Path::new("test.txt");
-    let full_path = manager.branches[0].full_path(test_path);
+    let full_path = manager.branches.snapshot()[0].full_path(test_path);
     fs::write(&full_path, b"test content").unwrap();
     
     // Test with moderately large attribute value (1KB)
@@ -231,7 +231,7 @@ fn test_xattr_empty_value() {
     
     // Create test file
     let test_path = Path::new("test.txt");
-    let full_path = manager.branches[0].full_path(test_path);
+    let full_path = manager.branches.snapshot()[0].full_path(test_path);
     fs::write(&full_path, b"test content").unwrap();
     
     // Set empty attribute value
@@ -245,4 +245,28 @@ fn test_xattr_empty_value() {
     // Should appear in list
     let attrs = manager.list_xattr(test_path).unwrap();
     assert!(attrs.contains(&attr_name.to_string()));
-}
\ No newline at end of file
+}
+#[test]
+fn test_xattr_union_lists_every_copy() {
+    let (_temps, manager) = create_test_manager_with_policies();
+    
+    // The same file on both writable branches, with diverged attributes
+    let test_path = Path::new("test.txt");
+    let branches = manager.branches.snapshot();
+    for (branch, names) in branches.iter().zip([["user.shared", "user.first"], ["user.second", "user.shared"]]) {
+        let full_path = branch.full_path(test_path);
+        fs::write(&full_path, b"test content").unwrap();
+        for name in names {
+            xattr::set(&full_path, name, b"value").unwrap();
+        }
+    }
+    
+    let mut first_only = manager.list_xattr(test_path).unwrap();
+    first_only.sort();
+    assert_eq!(first_only, vec!["user.first", "user.shared"]);
+    
+    let mut union = manager.list_xattr_union(test_path).unwrap();
+    union.sort();
+    assert_eq!(union, vec!["user.first", "user.second", "user.shared"]);
+    assert!(matches!(manager.list_xattr_union(Path::new("missing.txt")), Err(XattrError::NotFound)));
+}